@@ -0,0 +1,84 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion, SamplingMode, Throughput,
+};
+use ipa::{
+    ff::{Field, Fp32BitPrime, Gf32Bit, Gf3Bit, Gf5Bit},
+    secret_sharing::SharedValue,
+    test_fixture::{
+        prf_sharding::{group_by_user, test_feature_label_dot_product, test_prf_ipa},
+        TestWorld, TestWorldConfig, WorkloadConfig, WorkloadGenerator,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::runtime::Builder;
+
+const MAX_ROWS_PER_USER: usize = 50;
+const NUM_SATURATING_SUM_BITS: usize = 4;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(3)
+        .thread_name("helper-worker")
+        .enable_time()
+        .build()
+        .expect("Creating runtime failed");
+
+    let mut group = c.benchmark_group("prf_sharding");
+    group.sample_size(10);
+    group.sampling_mode(SamplingMode::Flat);
+
+    for query_size in [100usize, 1_000, 10_000] {
+        let mut rng = StdRng::seed_from_u64(query_size as u64);
+        let mut records = WorkloadGenerator::new(&mut rng, WorkloadConfig::uniform(5, 16, 10))
+            .generate(query_size);
+        group_by_user(&mut records);
+        let config = TestWorldConfig::default();
+
+        group.throughput(Throughput::Elements(query_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("attribution_and_capping_and_aggregation", query_size),
+            &records,
+            |b, records| {
+                b.to_async(&rt).iter(|| async {
+                    let world = TestWorld::new_with(config.clone());
+                    test_prf_ipa::<Gf5Bit, Gf3Bit, Fp32BitPrime>(
+                        &world,
+                        black_box(records),
+                        NUM_SATURATING_SUM_BITS,
+                        MAX_ROWS_PER_USER,
+                    )
+                    .await;
+                });
+            },
+        );
+
+        let records = records
+            .into_iter()
+            .map(|record| {
+                let feature_vector = if record.is_trigger_report {
+                    Gf32Bit::ZERO
+                } else {
+                    Gf32Bit::truncate_from(rng.gen::<u32>())
+                };
+                (record, feature_vector)
+            })
+            .collect::<Vec<_>>();
+        group.bench_with_input(
+            BenchmarkId::new("feature_label_dot_product", query_size),
+            &records,
+            |b, records| {
+                b.to_async(&rt).iter(|| async {
+                    let world = TestWorld::new_with(config.clone());
+                    test_feature_label_dot_product::<Gf32Bit, Fp32BitPrime>(
+                        &world,
+                        black_box(records),
+                    )
+                    .await;
+                });
+            },
+        );
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion, SamplingMode, Throughput,
+};
+use ipa::{
+    ff::{Fp32BitPrime, Gf5Bit, Gf8Bit},
+    helpers::GatewayConfig,
+    test_fixture::{
+        aggregation::test_sparse_aggregate, ipa::IpaSecurityModel, TestWorld, TestWorldConfig,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::runtime::Builder;
+
+const NUM_BUCKETS: u32 = 16;
+const MAX_CONTRIBUTION: u32 = 3;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(3)
+        .thread_name("helper-worker")
+        .enable_time()
+        .build()
+        .expect("Creating runtime failed");
+
+    let mut group = c.benchmark_group("sparse_aggregate");
+    group.sample_size(10);
+    group.sampling_mode(SamplingMode::Flat);
+
+    for query_size in [100usize, 1_000, 10_000] {
+        let mut rng = StdRng::seed_from_u64(query_size as u64);
+        let input = (0..query_size)
+            .map(|_| {
+                (
+                    rng.gen_range(0..NUM_BUCKETS),
+                    rng.gen_range(0..=MAX_CONTRIBUTION),
+                )
+            })
+            .collect::<Vec<_>>();
+        let config = TestWorldConfig {
+            gateway_config: GatewayConfig::new(query_size.clamp(16, 1024)),
+            ..TestWorldConfig::default()
+        };
+
+        group.throughput(Throughput::Elements(query_size as u64));
+        for mode in [IpaSecurityModel::SemiHonest, IpaSecurityModel::Malicious] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{mode:?}"), query_size),
+                &input,
+                |b, input| {
+                    b.to_async(&rt).iter(|| async {
+                        let world = TestWorld::new_with(config.clone());
+                        test_sparse_aggregate::<Gf5Bit, Gf8Bit, Fp32BitPrime>(
+                            &world,
+                            black_box(input),
+                            NUM_BUCKETS as usize,
+                            mode,
+                        )
+                        .await;
+                    });
+                },
+            );
+        }
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
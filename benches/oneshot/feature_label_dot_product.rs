@@ -0,0 +1,141 @@
+use std::{num::NonZeroUsize, time::Instant};
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use ipa::{
+    error::Error,
+    ff::{Field, Fp32BitPrime, Gf32Bit},
+    helpers::GatewayConfig,
+    secret_sharing::SharedValue,
+    test_fixture::{
+        ipa::IpaSecurityModel,
+        prf_sharding::{group_by_user, test_feature_label_dot_product},
+        TestWorld, TestWorldConfig, WorkloadConfig, WorkloadGenerator,
+    },
+};
+use rand::{random, rngs::StdRng, Rng, SeedableRng};
+use tokio::runtime::Builder;
+
+#[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+/// A benchmark for the feature-label dot product of the PRF-sharded protocols. Source events
+/// carry random 32-bit feature vectors and each person contributes the feature vector of at most
+/// one source event, so there are no breakdown keys or contribution cap to configure. It only
+/// supports the semi-honest security model.
+#[derive(Parser)]
+#[command(about, long_about = None)]
+struct Args {
+    /// The number of threads to use for running the protocol.
+    #[arg(short = 'j', long, default_value = "3")]
+    threads: usize,
+    /// The total number of records to process.
+    #[arg(short = 'n', long, default_value = "1000")]
+    query_size: usize,
+    /// The maximum number of records for each person, at most 64.
+    #[arg(short = 'u', long, default_value = "50")]
+    records_per_user: u32,
+    /// The random seed to use.
+    #[arg(short = 's', long)]
+    random_seed: Option<u64>,
+    /// The amount of active items to concurrently track.
+    #[arg(short = 'a', long)]
+    active_work: Option<NonZeroUsize>,
+    /// Desired security model. Only semi-honest is supported, because attribution works on
+    /// replicated shares of `Gf2` that malicious contexts do not provide.
+    #[arg(short = 'm', long, value_enum, default_value_t=IpaSecurityModel::SemiHonest)]
+    mode: IpaSecurityModel,
+    /// Needed for benches.
+    #[arg(long, hide = true)]
+    bench: bool,
+}
+
+impl Args {
+    fn active(&self) -> usize {
+        self.active_work
+            .map(NonZeroUsize::get)
+            .unwrap_or_else(|| self.query_size.clamp(16, 1024))
+    }
+
+    fn check_mode(&self) {
+        if let IpaSecurityModel::Malicious = self.mode {
+            Self::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    "the PRF-sharded protocols only support the semi-honest security model",
+                )
+                .exit();
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    type BenchField = Fp32BitPrime;
+
+    let _prep_time = Instant::now();
+    let config = TestWorldConfig {
+        gateway_config: GatewayConfig::new(args.active()),
+        ..TestWorldConfig::default()
+    };
+
+    let seed = args.random_seed.unwrap_or_else(|| random());
+    tracing::trace!(
+        "Using random seed: {seed} for {q} records",
+        q = args.query_size
+    );
+    // Every row of a user is processed under a step of its own, and there can't be more than 64.
+    assert!(
+        args.records_per_user <= 64,
+        "there can be at most 64 records for each person"
+    );
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut raw_data = WorkloadGenerator::new(
+        &mut rng,
+        WorkloadConfig::uniform(5, 20, args.records_per_user),
+    )
+    .generate(args.query_size);
+    group_by_user(&mut raw_data);
+    let raw_data = raw_data
+        .into_iter()
+        .map(|record| {
+            let feature_vector = if record.is_trigger_report {
+                Gf32Bit::ZERO
+            } else {
+                Gf32Bit::truncate_from(rng.gen::<u32>())
+            };
+            (record, feature_vector)
+        })
+        .collect::<Vec<_>>();
+
+    let world = TestWorld::new_with(config.clone());
+    tracing::trace!("Preparation complete in {:?}", _prep_time.elapsed());
+
+    let _protocol_time = Instant::now();
+    test_feature_label_dot_product::<Gf32Bit, BenchField>(&world, &raw_data).await;
+    tracing::trace!(
+        "Semi-honest feature-label dot product for {q} records took {t:?}",
+        q = args.query_size,
+        t = _protocol_time.elapsed()
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    #[cfg(feature = "dhat-heap")]
+    let _profiler = dhat::Profiler::new_heap();
+
+    let args = Args::parse();
+    args.check_mode();
+    let rt = Builder::new_multi_thread()
+        .worker_threads(args.threads)
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+    let task = rt.spawn(run(args));
+    rt.block_on(task)?
+}
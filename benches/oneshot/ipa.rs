@@ -10,7 +10,7 @@ use ipa::{
     helpers::{query::IpaQueryConfig, GatewayConfig},
     test_fixture::{
         ipa::{ipa_in_the_clear, test_ipa, IpaSecurityModel},
-        EventGenerator, EventGeneratorConfig, TestWorld, TestWorldConfig,
+        TestWorld, TestWorldConfig, WorkloadConfig, WorkloadGenerator,
     },
 };
 use rand::{random, rngs::StdRng, SeedableRng};
@@ -90,6 +90,8 @@ impl Args {
             attribution_window_seconds: self.attribution_window(),
             num_multi_bits: self.num_multi_bits,
             plaintext_match_keys: true,
+            offline_preprocessing: false,
+            gf2_sort: false,
         }
     }
 }
@@ -109,17 +111,15 @@ async fn run(args: Args) -> Result<(), Error> {
         q = args.query_size
     );
     let rng = StdRng::seed_from_u64(seed);
-    let raw_data = EventGenerator::with_config(
+    let raw_data = WorkloadGenerator::new(
         rng,
-        EventGeneratorConfig {
-            max_trigger_value: NonZeroU32::try_from(args.max_trigger_value).unwrap(),
-            max_breakdown_key: NonZeroU32::try_from(args.breakdown_keys).unwrap(),
-            max_events_per_user: NonZeroU32::try_from(args.records_per_user).unwrap(),
-            ..Default::default()
-        },
+        WorkloadConfig::uniform(
+            args.max_trigger_value,
+            args.breakdown_keys,
+            args.records_per_user,
+        ),
     )
-    .take(args.query_size)
-    .collect::<Vec<_>>();
+    .generate(args.query_size);
 
     let expected_results = ipa_in_the_clear(
         &raw_data,
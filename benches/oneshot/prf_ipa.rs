@@ -0,0 +1,168 @@
+use std::{num::NonZeroUsize, time::Instant};
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use ipa::{
+    error::Error,
+    ff::{Fp32BitPrime, Gf3Bit, Gf5Bit},
+    helpers::GatewayConfig,
+    secret_sharing::SharedValue,
+    test_fixture::{
+        ipa::IpaSecurityModel,
+        prf_sharding::{group_by_user, test_prf_ipa},
+        TestWorld, TestWorldConfig, WorkloadConfig, WorkloadGenerator,
+    },
+};
+use rand::{random, rngs::StdRng, SeedableRng};
+use tokio::runtime::Builder;
+
+#[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+// Aggregation one-hot encodes breakdown keys, under a step for each of their values, and there
+// can't be more than 64 of those.
+type BreakdownKey = Gf5Bit;
+type TriggerValue = Gf3Bit;
+
+/// A benchmark for attribution, capping and aggregation of the PRF-sharded IPA protocol, that is
+/// everything after the PRF of the match keys is revealed. It only supports the semi-honest
+/// security model.
+#[derive(Parser)]
+#[command(about, long_about = None)]
+struct Args {
+    /// The number of threads to use for running the protocol.
+    #[arg(short = 'j', long, default_value = "3")]
+    threads: usize,
+    /// The total number of records to process.
+    #[arg(short = 'n', long, default_value = "1000")]
+    query_size: usize,
+    /// The maximum number of records for each person.
+    #[arg(short = 'u', long, default_value = "50")]
+    records_per_user: u32,
+    /// The number of most recent records of each person that are processed.
+    #[arg(long, default_value = "50")]
+    max_rows_per_user: usize,
+    /// The contribution cap for each person. Must be a power of two greater than the largest
+    /// trigger value.
+    #[arg(short = 'c', long, default_value = "16")]
+    per_user_cap: u32,
+    /// The number of breakdown keys, at most 32. Breakdown keys have 5 bits, because aggregation
+    /// one-hot encodes them under a step for each of their values.
+    #[arg(short = 'b', long, default_value = "16", value_parser = clap::value_parser!(u32).range(1..=32))]
+    breakdown_keys: u32,
+    /// The maximum trigger value.
+    #[arg(short = 't', long, default_value = "5")]
+    max_trigger_value: u32,
+    /// The random seed to use.
+    #[arg(short = 's', long)]
+    random_seed: Option<u64>,
+    /// The amount of active items to concurrently track.
+    #[arg(short = 'a', long)]
+    active_work: Option<NonZeroUsize>,
+    /// Desired security model. Only semi-honest is supported, because attribution works on
+    /// replicated shares of `Gf2` that malicious contexts do not provide.
+    #[arg(short = 'm', long, value_enum, default_value_t=IpaSecurityModel::SemiHonest)]
+    mode: IpaSecurityModel,
+    /// Needed for benches.
+    #[arg(long, hide = true)]
+    bench: bool,
+}
+
+impl Args {
+    fn active(&self) -> usize {
+        self.active_work
+            .map(NonZeroUsize::get)
+            .unwrap_or_else(|| self.query_size.clamp(16, 1024))
+    }
+
+    fn check_mode(&self) {
+        if let IpaSecurityModel::Malicious = self.mode {
+            Self::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    "the PRF-sharded protocols only support the semi-honest security model",
+                )
+                .exit();
+        }
+    }
+
+    fn num_saturating_sum_bits(&self) -> usize {
+        assert!(
+            self.per_user_cap.is_power_of_two() && self.per_user_cap > 1 << TriggerValue::BITS,
+            "per-user cap must be a power of two greater than {}, got {}",
+            1 << TriggerValue::BITS,
+            self.per_user_cap
+        );
+        assert!(
+            self.max_trigger_value <= 1 << TriggerValue::BITS,
+            "trigger values must fit in {} bits",
+            TriggerValue::BITS
+        );
+        usize::try_from(self.per_user_cap.ilog2()).unwrap()
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    type BenchField = Fp32BitPrime;
+
+    let _prep_time = Instant::now();
+    let config = TestWorldConfig {
+        gateway_config: GatewayConfig::new(args.active()),
+        ..TestWorldConfig::default()
+    };
+
+    let seed = args.random_seed.unwrap_or_else(|| random());
+    tracing::trace!(
+        "Using random seed: {seed} for {q} records",
+        q = args.query_size
+    );
+    let rng = StdRng::seed_from_u64(seed);
+    let mut raw_data = WorkloadGenerator::new(
+        rng,
+        WorkloadConfig::uniform(
+            args.max_trigger_value,
+            args.breakdown_keys,
+            args.records_per_user,
+        ),
+    )
+    .generate(args.query_size);
+    group_by_user(&mut raw_data);
+
+    let world = TestWorld::new_with(config.clone());
+    tracing::trace!("Preparation complete in {:?}", _prep_time.elapsed());
+
+    let _protocol_time = Instant::now();
+    test_prf_ipa::<BreakdownKey, TriggerValue, BenchField>(
+        &world,
+        &raw_data,
+        args.num_saturating_sum_bits(),
+        args.max_rows_per_user,
+    )
+    .await;
+    tracing::trace!(
+        "Semi-honest PRF-sharded IPA for {q} records took {t:?}",
+        q = args.query_size,
+        t = _protocol_time.elapsed()
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    #[cfg(feature = "dhat-heap")]
+    let _profiler = dhat::Profiler::new_heap();
+
+    let args = Args::parse();
+    args.check_mode();
+    let rt = Builder::new_multi_thread()
+        .worker_threads(args.threads)
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+    let task = rt.spawn(run(args));
+    rt.block_on(task)?
+}
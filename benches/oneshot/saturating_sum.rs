@@ -0,0 +1,83 @@
+//! Computes the same number of saturating sums over scalar [`Gf2`] shares and over vectors of
+//! Gf2 lanes, where every record carries one sum per lane.
+use std::time::Instant;
+
+use futures::future::try_join_all;
+use ipa::{
+    ff::Gf2,
+    protocol::{boolean::saturating_sum::SaturatingSum, context::Context, RecordId},
+    secret_sharing::{
+        replicated::semi_honest::AdditiveShare as Replicated,
+        vector::{Gf2Vector, Gf2x256, Gf2x64},
+        BitDecomposed, SharedValue,
+    },
+    test_fixture::{Runner, TestWorld},
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    thread_rng, Rng,
+};
+
+const SUMS: usize = 1 << 14;
+const SUM_BITS: u32 = 8;
+const VALUE_BITS: u32 = 3;
+
+async fn saturating_sum<V>(world: &TestWorld) -> f32
+where
+    V: Gf2Vector,
+    Standard: Distribution<V>,
+{
+    let mut rng = thread_rng();
+    let inputs = (0..SUMS / V::LANES)
+        .map(|_| {
+            (
+                BitDecomposed::new((0..SUM_BITS).map(|_| rng.gen::<V>())),
+                BitDecomposed::new((0..VALUE_BITS).map(|_| rng.gen::<V>())),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    world
+        .semi_honest(inputs.into_iter(), |ctx, inputs| async move {
+            let ctx = ctx.set_total_records(inputs.len());
+            // all records fit into the active work window of the test world
+            #[allow(clippy::disallowed_methods)]
+            try_join_all(inputs.into_iter().enumerate().map(|(i, (sum, value))| {
+                let ctx = ctx.clone();
+                async move {
+                    SaturatingSum::new(sum, Replicated::ZERO)
+                        .add(ctx, RecordId::from(i), &value)
+                        .await
+                }
+            }))
+            .await
+            .unwrap()
+        })
+        .await;
+
+    start.elapsed().as_secs_f32()
+}
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 3)]
+async fn main() {
+    println!("saturating sum benchmark: {SUMS} sums of {SUM_BITS} bits");
+
+    let scalar = saturating_sum::<Gf2>(&TestWorld::default()).await;
+    println!("Gf2: {scalar}s");
+    for (name, duration) in [
+        (
+            "Gf2x64",
+            saturating_sum::<Gf2x64>(&TestWorld::default()).await,
+        ),
+        (
+            "Gf2x256",
+            saturating_sum::<Gf2x256>(&TestWorld::default()).await,
+        ),
+    ] {
+        println!(
+            "{name}: {duration}s, {speedup:.1}x speedup",
+            speedup = scalar / duration
+        );
+    }
+}
@@ -3,20 +3,30 @@ use std::time::Instant;
 use futures::stream::iter as stream_iter;
 use ipa::{
     error::Error,
-    ff::{Field, Fp32BitPrime, GaloisField},
+    ff::{Field, Fp32BitPrime, Gf2},
     helpers::GatewayConfig,
     protocol::{
-        context::{validator::SemiHonest as SemiHonestValidator, Validator},
-        sort::generate_permutation_opt::generate_permutation_opt,
+        context::Context,
+        sort::{
+            apply_sort::apply_sort_permutation,
+            generate_permutation::{
+                generate_permutation_and_reveal_shuffled, RevealedAndRandomPermutations,
+            },
+            quicksort::quicksort_and_reveal_shuffled,
+        },
         MatchKey,
     },
     secret_sharing::{
-        replicated::semi_honest::AdditiveShare as Replicated, IntoShares, SharedValue,
+        replicated::semi_honest::AdditiveShare as Replicated, BitDecomposed, IntoShares,
+        SharedValue,
     },
     test_fixture::{join3, Reconstruct, TestWorld, TestWorldConfig},
 };
 use rand::Rng;
 
+/// Compares the radix sort, which converts match key bits to a prime field, with the quicksort
+/// over boolean shares. Both generate a shuffled and revealed sort permutation that is then
+/// applied to the match keys.
 #[tokio::main(flavor = "multi_thread", worker_threads = 3)]
 async fn main() -> Result<(), Error> {
     const BATCHSIZE: usize = 100;
@@ -37,35 +47,60 @@ async fn main() -> Result<(), Error> {
         match_keys.push(rng.gen::<MatchKey>());
     }
 
+    let mut sorted_match_keys = match_keys.clone();
+    sorted_match_keys.sort_unstable();
+
     let [s0, s1, s2] = match_keys.iter().cloned().share_with(&mut rng);
+    let [b0, b1, b2] = match_keys
+        .iter()
+        .map(|mk| BitDecomposed::decompose(MatchKey::BITS, |i| Gf2::truncate_from(mk[i])))
+        .share_with(&mut rng);
 
     let start = Instant::now();
-    let [(v0, r0), (v1, r1), (v2, r2)]: [(SemiHonestValidator<'_, BenchField>, Vec<Replicated<_>>);
-        3] = join3(
-        generate_permutation_opt(ctx0, stream_iter(s0), NUM_MULTI_BITS, MatchKey::BITS),
-        generate_permutation_opt(ctx1, stream_iter(s1), NUM_MULTI_BITS, MatchKey::BITS),
-        generate_permutation_opt(ctx2, stream_iter(s2), NUM_MULTI_BITS, MatchKey::BITS),
+    let radix = join3(
+        generate_permutation_and_reveal_shuffled::<BenchField, _, _, _>(
+            ctx0.narrow("radix"),
+            stream_iter(s0.clone()),
+            NUM_MULTI_BITS,
+            MatchKey::BITS,
+        ),
+        generate_permutation_and_reveal_shuffled::<BenchField, _, _, _>(
+            ctx1.narrow("radix"),
+            stream_iter(s1.clone()),
+            NUM_MULTI_BITS,
+            MatchKey::BITS,
+        ),
+        generate_permutation_and_reveal_shuffled::<BenchField, _, _, _>(
+            ctx2.narrow("radix"),
+            stream_iter(s2.clone()),
+            NUM_MULTI_BITS,
+            MatchKey::BITS,
+        ),
     )
     .await;
-    let result = join3(v0.validate(r0), v1.validate(r1), v2.validate(r2)).await;
-
     let duration = start.elapsed().as_secs_f32();
-    println!("sort benchmark BATCHSIZE {BATCHSIZE} NUM_MULTI_BITS {NUM_MULTI_BITS} complete after {duration}s");
-
-    assert_eq!(result[0].len(), BATCHSIZE);
-    assert_eq!(result[1].len(), BATCHSIZE);
-    assert_eq!(result[2].len(), BATCHSIZE);
+    println!("radix sort benchmark BATCHSIZE {BATCHSIZE} NUM_MULTI_BITS {NUM_MULTI_BITS} complete after {duration}s");
 
-    let mut mpc_sorted_list: Vec<u128> = (0..BATCHSIZE).map(|i| i as u128).collect();
-    for (i, match_key) in match_keys.iter().enumerate() {
-        let index = [&result[0][i], &result[1][i], &result[2][i]].reconstruct();
-        mpc_sorted_list[index.as_u128() as usize] = match_key.as_u128();
-    }
+    let start = Instant::now();
+    let quicksort = join3(
+        quicksort_and_reveal_shuffled(ctx0.narrow("quicksort"), b0),
+        quicksort_and_reveal_shuffled(ctx1.narrow("quicksort"), b1),
+        quicksort_and_reveal_shuffled(ctx2.narrow("quicksort"), b2),
+    )
+    .await;
+    let duration = start.elapsed().as_secs_f32();
+    println!("Gf2 quicksort benchmark BATCHSIZE {BATCHSIZE} complete after {duration}s");
 
-    let mut sorted_match_keys = match_keys.clone();
-    sorted_match_keys.sort_unstable();
-    for i in 0..BATCHSIZE {
-        assert_eq!(sorted_match_keys[i].as_u128(), mpc_sorted_list[i]);
+    for (name, permutations) in [("radix", radix), ("quicksort", quicksort)] {
+        let [p0, p1, p2]: &[RevealedAndRandomPermutations; 3] = &permutations;
+        let step = format!("apply_{name}");
+        let sorted: [Vec<Replicated<MatchKey>>; 3] = join3(
+            apply_sort_permutation(ctx0.narrow(step.as_str()), s0.clone(), p0),
+            apply_sort_permutation(ctx1.narrow(step.as_str()), s1.clone(), p1),
+            apply_sort_permutation(ctx2.narrow(step.as_str()), s2.clone(), p2),
+        )
+        .await;
+        assert_eq!(sorted.reconstruct(), sorted_match_keys);
     }
 
     Ok(())
@@ -0,0 +1,123 @@
+use std::{num::NonZeroUsize, time::Instant};
+
+use clap::Parser;
+use ipa::{
+    error::Error,
+    ff::{Fp32BitPrime, Gf5Bit, Gf8Bit},
+    helpers::GatewayConfig,
+    test_fixture::{
+        aggregation::test_sparse_aggregate, ipa::IpaSecurityModel, TestWorld, TestWorldConfig,
+    },
+};
+use rand::{random, rngs::StdRng, Rng, SeedableRng};
+use tokio::runtime::Builder;
+
+#[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+/// A benchmark for the standalone sparse aggregation protocol. Every record is a contribution
+/// that has already been capped, with a uniformly random breakdown key and value.
+#[derive(Parser)]
+#[command(about, long_about = None)]
+struct Args {
+    /// The number of threads to use for running the protocol.
+    #[arg(short = 'j', long, default_value = "3")]
+    threads: usize,
+    /// The total number of records to process.
+    #[arg(short = 'n', long, default_value = "1000")]
+    query_size: usize,
+    /// The largest contribution of a record, at most 255.
+    #[arg(short = 'c', long, default_value = "3")]
+    per_user_cap: u32,
+    /// The number of breakdown keys, at most 32.
+    #[arg(short = 'b', long, default_value = "16")]
+    breakdown_keys: u32,
+    /// The random seed to use.
+    #[arg(short = 's', long)]
+    random_seed: Option<u64>,
+    /// The amount of active items to concurrently track.
+    #[arg(short = 'a', long)]
+    active_work: Option<NonZeroUsize>,
+    /// Desired security model for the aggregation protocol
+    #[arg(short = 'm', long, value_enum, default_value_t=IpaSecurityModel::Malicious)]
+    mode: IpaSecurityModel,
+    /// Needed for benches.
+    #[arg(long, hide = true)]
+    bench: bool,
+}
+
+impl Args {
+    fn active(&self) -> usize {
+        self.active_work
+            .map(NonZeroUsize::get)
+            .unwrap_or_else(|| self.query_size.clamp(16, 1024))
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    type BenchField = Fp32BitPrime;
+
+    let _prep_time = Instant::now();
+    let config = TestWorldConfig {
+        gateway_config: GatewayConfig::new(args.active()),
+        ..TestWorldConfig::default()
+    };
+
+    let seed = args.random_seed.unwrap_or_else(|| random());
+    tracing::trace!(
+        "Using random seed: {seed} for {q} records",
+        q = args.query_size
+    );
+    assert!(
+        args.per_user_cap < 1 << 8 && args.breakdown_keys <= 1 << 5,
+        "contributions must fit in 8 bits and breakdown keys in 5 bits"
+    );
+    let mut rng = StdRng::seed_from_u64(seed);
+    let input = (0..args.query_size)
+        .map(|_| {
+            (
+                rng.gen_range(0..args.breakdown_keys),
+                rng.gen_range(0..=args.per_user_cap),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let world = TestWorld::new_with(config.clone());
+    tracing::trace!("Preparation complete in {:?}", _prep_time.elapsed());
+
+    let _protocol_time = Instant::now();
+    test_sparse_aggregate::<Gf5Bit, Gf8Bit, BenchField>(
+        &world,
+        &input,
+        usize::try_from(args.breakdown_keys).unwrap(),
+        args.mode,
+    )
+    .await;
+    tracing::trace!(
+        "{m:?} sparse aggregation for {q} records took {t:?}",
+        m = args.mode,
+        q = args.query_size,
+        t = _protocol_time.elapsed()
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    #[cfg(feature = "dhat-heap")]
+    let _profiler = dhat::Profiler::new_heap();
+
+    let args = Args::parse();
+    let rt = Builder::new_multi_thread()
+        .worker_threads(args.threads)
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+    let task = rt.spawn(run(args));
+    rt.block_on(task)?
+}
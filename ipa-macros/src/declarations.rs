@@ -0,0 +1,171 @@
+// Parses the narrowing relationships declared on `#[derive(Step)]` enums.
+//
+//     #[derive(Step)]
+//     pub(crate) enum Step {
+//         #[step(child = crate::protocol::step::BitOpStep)]
+//         AddBits,                                      // narrowed by `BitOpStep` after this step
+//         #[step(child = crate::protocol::context::validator::Step)]
+//         #[step(child = UserNthRowStep)]
+//         BinaryValidator,
+//     }
+//
+// `child` may also be set on the enum itself, in which case it applies to all variants. A variant
+// marked `#[step(transparent)]` has the same children as the step it was narrowed from; this is
+// what `MaliciousProtocol` of the validator does, as the protocol running inside the validator
+// keeps narrowing with the steps of whoever created it.
+//
+// Child paths are ordinary Rust paths, resolved in the scope of the enum that declares them. The
+// steps narrowed from the root gate are declared the same way, on the `Compact` gate itself.
+
+use syn::{Attribute, Meta, Variant};
+
+/// Number of steps generated for a `#[dynamic]` variant.
+pub(crate) const DYNAMIC_STEPS: usize = 64;
+
+/// Maximum number of steps of a `#[dynamic(N)]` variant.
+pub(crate) const MAX_DYNAMIC_STEPS: usize = 1024;
+
+/// Returns the number of steps of a `#[dynamic]` or `#[dynamic(N)]` variant, or `None` if the
+/// variant is not dynamic.
+pub(crate) fn dynamic_steps(variant: &Variant) -> Result<Option<usize>, syn::Error> {
+    let Some(attr) = variant.attrs.iter().find(|x| x.path().is_ident("dynamic")) else {
+        return Ok(None);
+    };
+    let count = match &attr.meta {
+        Meta::Path(_) => DYNAMIC_STEPS,
+        Meta::List(list) => list.parse_args::<syn::LitInt>()?.base10_parse()?,
+        Meta::NameValue(_) => {
+            return Err(syn::Error::new_spanned(attr, "expected `#[dynamic(N)]`"))
+        }
+    };
+    if count == 0 || count > MAX_DYNAMIC_STEPS {
+        return Err(syn::Error::new_spanned(
+            attr,
+            format!("dynamic steps must have between 1 and {MAX_DYNAMIC_STEPS} steps"),
+        ));
+    }
+    Ok(Some(count))
+}
+
+/// The contents of `#[step(...)]` attributes on an enum or one of its variants.
+#[derive(Default)]
+pub(crate) struct StepAttributes {
+    pub transparent: bool,
+    pub children: Vec<syn::Path>,
+}
+
+pub(crate) fn parse_step_attributes(attrs: &[Attribute]) -> Result<StepAttributes, syn::Error> {
+    let mut result = StepAttributes::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("step")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("transparent") {
+                result.transparent = true;
+            } else if meta.path.is_ident("child") {
+                result.children.push(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("expected `transparent` or `child = <path>`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(result)
+}
+
+/// A variant of a step enum and the steps it narrows to.
+pub(crate) struct StepVariant<'a> {
+    pub variant: &'a Variant,
+    /// Number of steps of a `#[dynamic]` variant, `None` for static ones.
+    pub dynamic: Option<usize>,
+    pub transparent: bool,
+    pub children: Vec<syn::Path>,
+}
+
+impl StepVariant<'_> {
+    /// Number of narrowed steps this variant produces.
+    pub fn len(&self) -> usize {
+        self.dynamic.unwrap_or(1)
+    }
+}
+
+/// Parses the variants of a step enum together with the children declared on them and on the
+/// enum.
+pub(crate) fn step_variants<'a>(
+    attrs: &[Attribute],
+    data: &'a syn::DataEnum,
+) -> Result<Vec<StepVariant<'a>>, syn::Error> {
+    let enum_attrs = parse_step_attributes(attrs)?;
+    if enum_attrs.transparent {
+        let attr = attrs.iter().find(|a| a.path().is_ident("step")).unwrap();
+        return Err(syn::Error::new_spanned(
+            attr,
+            "`transparent` can only be set on enum variants",
+        ));
+    }
+
+    data.variants
+        .iter()
+        .map(|variant| {
+            let attrs = parse_step_attributes(&variant.attrs)?;
+            let mut children = enum_attrs.children.clone();
+            children.extend(attrs.children);
+            Ok(StepVariant {
+                variant,
+                dynamic: dynamic_steps(variant)?,
+                transparent: attrs.transparent,
+                children,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, DeriveInput};
+
+    use super::step_variants;
+
+    #[test]
+    fn parses_declarations() {
+        let item: DeriveInput = parse_quote! {
+            #[derive(Step)]
+            #[step(child = crate::protocol::basics::mul::malicious::Step)]
+            enum Step {
+                #[dynamic(8)]
+                #[step(child = BitStep)]
+                Row(usize),
+                #[step(transparent)]
+                Validator,
+            }
+        };
+        let syn::Data::Enum(data) = &item.data else {
+            unreachable!()
+        };
+        let variants = step_variants(&item.attrs, data).unwrap();
+        let mul: syn::Path = parse_quote!(crate::protocol::basics::mul::malicious::Step);
+        let bit: syn::Path = parse_quote!(BitStep);
+
+        assert_eq!(variants[0].variant.ident, "Row");
+        assert_eq!(variants[0].len(), 8);
+        assert!(!variants[0].transparent);
+        assert_eq!(variants[0].children, vec![mul.clone(), bit]);
+
+        assert_eq!(variants[1].dynamic, None);
+        assert!(variants[1].transparent);
+        assert_eq!(variants[1].children, vec![mul]);
+    }
+
+    #[test]
+    fn rejects_unknown_attributes() {
+        let item: DeriveInput = parse_quote! {
+            #[derive(Step)]
+            #[step(root)]
+            enum Step {
+                Row,
+            }
+        };
+        let syn::Data::Enum(data) = &item.data else {
+            unreachable!()
+        };
+        assert!(step_variants(&item.attrs, data).is_err());
+    }
+}
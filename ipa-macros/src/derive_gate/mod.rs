@@ -2,13 +2,15 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, DeriveInput};
 
-use crate::parser::{group_by_modules, ipa_state_transition_map};
-
-/// Generate a state transition graph and the corresponding `AsRef<str>`
-/// and `deserialize()` implementations for `Compact` gate.
+use crate::declarations::parse_step_attributes;
 
+/// Generate the list of step enums that the `Compact` gate narrows to from its root state.
+///
+/// These are declared with `#[step(child = ...)]` on the gate, like the children of any other step.
+/// Every other transition is declared by the step enums themselves; see `derive_step` for how
+/// `Compact` uses the declarations.
 pub fn expand(item: TokenStream) -> TokenStream {
-    // `item` is the `struct Compact(u16)` in AST
+    // `item` is the `struct Compact(u32)` in AST
     let ast = parse_macro_input!(item as DeriveInput);
     let gate = &ast.ident;
     match &ast.data {
@@ -16,60 +18,25 @@ pub fn expand(item: TokenStream) -> TokenStream {
         _ => panic!("derive Gate expects a struct"),
     }
 
-    let mut expanded = quote!(
-        impl crate::protocol::step::Step for #gate {}
-    );
-
-    let steps = ipa_state_transition_map();
-    let grouped_steps = group_by_modules(&steps);
-    let mut reverse_map = Vec::new();
-    let mut deserialize_map = Vec::new();
-
-    for (_, steps) in grouped_steps {
-        // generate the reverse map for `impl AsRef<str> for Compact`
-        // this is used to convert a state ID to a string representation of the state.
-        reverse_map.extend(steps.iter().map(|s| {
-            let path = &s.path;
-            let state_id = s.id;
-            quote!(
-                #state_id => #path,
-            )
-        }));
-
-        deserialize_map.extend(steps.iter().map(|s| {
-            let path = &s.path;
-            let state_id = s.id;
-            quote!(
-                #path => #state_id,
-            )
-        }));
-    }
-
-    expanded.extend(quote!(
-        impl AsRef<str> for #gate {
-            fn as_ref(&self) -> &str {
-                match self.0 {
-                    #(#reverse_map)*
-                    _ => static_reverse_state_map(self.0),
-                }
-            }
+    let attrs = match parse_step_attributes(&ast.attrs) {
+        Ok(attrs) if attrs.transparent => {
+            return syn::Error::new_spanned(gate, "the root gate cannot be transparent")
+                .to_compile_error()
+                .into();
         }
-    ));
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let roots = attrs.children;
 
-    // replace `u16` with the type acquired from the AST
-    expanded.extend(
-        quote!(
-            impl Compact {
-                pub fn deserialize(s: &str) -> Compact {
-                    Self(match s {
-                        #(#deserialize_map)*
-                        _ => static_deserialize_state_map(s),
-                    })
-                }
-            }
-        )
-        .into_iter(),
-    );
+    quote!(
+        impl crate::protocol::step::Step for #gate {}
 
-    expanded.into()
+        impl #gate {
+            /// Step enums that are narrowed from the root gate.
+            const ROOTS: &'static [crate::protocol::step::CompactStepsFn] =
+                &[#(<#roots as crate::protocol::step::CompactStep>::steps),*];
+        }
+    )
+    .into()
 }
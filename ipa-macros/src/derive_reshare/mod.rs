@@ -0,0 +1,114 @@
+// Procedural macro to derive the `Reshare` trait for a struct of secret shares.
+//
+// Every field is reshared in parallel, under a step of its own, and the struct is built back from
+// the reshared fields. For example:
+//
+//     #[derive(Reshare)]
+//     struct Row<F: Field> {
+//         value: Replicated<F>,
+//         bits: BitDecomposed<Replicated<Gf2>>,
+//     }
+//
+// expands to
+//
+//     #[async_trait]
+//     impl<F: Field, C> Reshare<C, RecordId> for Row<F>
+//     where
+//         C: Context,
+//         Replicated<F>: Reshare<C, RecordId> + Send + Sync,
+//         BitDecomposed<Replicated<Gf2>>: Reshare<C, RecordId> + Send + Sync,
+//     {
+//         async fn reshare<'fut>(&self, ctx: C, record_id: RecordId, to_helper: Role) -> ... {
+//             let (value, bits) = try_join!(
+//                 self.value.reshare(ctx.narrow(&ReshareFieldStep::from(0)), record_id, to_helper),
+//                 self.bits.reshare(ctx.narrow(&ReshareFieldStep::from(1)), record_id, to_helper),
+//             )?;
+//             Ok(Self { value, bits })
+//         }
+//     }
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, DeriveInput};
+
+/// The number of steps of `ReshareFieldStep`, which bounds the number of fields.
+const MAX_FIELDS: usize = 16;
+
+pub fn expand(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let fields = match &ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return TokenStream::from(
+                syn::Error::new_spanned(ast, "ipa_macros::Reshare expects a struct with named fields")
+                    .to_compile_error(),
+            );
+        }
+    };
+
+    if fields.len() > MAX_FIELDS {
+        return TokenStream::from(
+            syn::Error::new_spanned(
+                ast,
+                format!("ipa_macros::Reshare supports at most {MAX_FIELDS} fields"),
+            )
+            .to_compile_error(),
+        );
+    }
+
+    let ident = &ast.ident;
+    let ctx = format_ident!("__C");
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let mut generics = ast.generics.clone();
+    generics.params.push(parse_quote!(#ctx));
+    let where_clause = generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(#ctx: crate::protocol::context::Context));
+    for field in fields {
+        let ty = &field.ty;
+        where_clause.predicates.push(parse_quote!(
+            #ty: crate::protocol::basics::Reshare<#ctx, crate::protocol::RecordId> + Send + Sync
+        ));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let names = fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let indices = 0..names.len();
+
+    quote!(
+        #[async_trait::async_trait]
+        impl #impl_generics crate::protocol::basics::Reshare<#ctx, crate::protocol::RecordId>
+            for #ident #ty_generics #where_clause
+        {
+            async fn reshare<'fut>(
+                &self,
+                ctx: #ctx,
+                record_id: crate::protocol::RecordId,
+                to_helper: crate::helpers::Role,
+            ) -> Result<Self, crate::error::Error>
+            where
+                #ctx: 'fut,
+            {
+                let (#(#names,)*) = futures::try_join!(
+                    #(
+                        self.#names.reshare(
+                            ctx.narrow(&crate::protocol::basics::ReshareFieldStep::from(#indices)),
+                            record_id,
+                            to_helper,
+                        ),
+                    )*
+                )?;
+                Ok(Self { #(#names),* })
+            }
+        }
+    )
+    .into()
+}
@@ -1,51 +1,39 @@
 // Procedural macro to derive the Step and StepNarrow traits and generate a memory-efficient gate.
 //
-// The goal is to generate a state transition graph and the corresponding `StepNarrow` implementations
-// for the IPA protocol. This macro assumes that a complete IPA steps file exists in the repo at the
-// location specified as `STEPS_FILE`. The steps file can be generated by running `collect_steps.py`.
+// The goal is to let the `Compact` gate check every narrow against the narrowing relationships
+// declared with `#[step(...)]` attributes on the step enums (see `declarations.rs` for the syntax).
+// Each step enum describes its own steps and the enums they narrow to, so the declarations are
+// assembled from the types alone and no macro needs to see more than the item it is applied to:
 //
-// The steps file contains a list of narrowed steps, where each line represents a hierarchy of narrowed
-// steps delimited by "/". For example, the following lines represent a hierarchy of narrowed steps:
-//
-//     RootStep                                => 0
-//     RootStep/StepA::A1                      => 1
-//     RootStep/StepA::A1/StepB::B1            => 2
-//     RootStep/StepA::A1/StepB::B2            => 3
-//     RootStep/StepC::C1                      => 4
-//     RootStep/StepC::C1/StepD::D1            => 5
-//     RootStep/StepC::C1/StepD::D1/StepA::A2  => 6
-//     RootStep/StepC::C2                      => 7
-//
-// From these lines, we want to generate StepNarrow implementations for each step.
+//     impl CompactStep for StepA {
+//         fn steps() -> &'static CompactSteps {
+//             static STEPS: CompactSteps = CompactSteps {
+//                 names: &["a1", "a2"],
+//                 children: &[&[<StepB as CompactStep>::steps], &[]],
+//                 transparent: &[false, false],
+//             };
+//             &STEPS
+//         }
+//     }
 //
 //     impl StepNarrow<StepA> for Compact {
 //         fn narrow(&self, step: &StepA) -> Self {
-//             Self(match (self.0, step.as_ref()) {
-//                 (0, "A1") => 1,
-//                 (5, "A2") => 6,
-//                 _ => panic!("invalid state transition"),
-//             })
-//         }
-//     }
-//     impl StepNarrow<StepB> for Compact {
-//         fn narrow(&self, step: &StepB) -> Self {
-//             Self(match (self.0, step.as_ref()) {
-//                 (1, "B1") => 2,
-//                 (1, "B2") => 3,
-//                 _ => panic!("invalid state transition"),
-//             })
+//             let index: u16 = match step {
+//                 StepA::A1 => 0,
+//                 StepA::A2 => 1,
+//             };
+//             self.narrow_to(<StepA as CompactStep>::steps(), index)
 //         }
 //     }
-//     ...
+//
+// `Compact` numbers its states when they are first narrowed to, and panics if the step it narrows
+// to is not declared as a child of the current one.
 
 use proc_macro::TokenStream;
 use quote::{__private::TokenStream as TokenStream2, format_ident, quote};
 use syn::{parse_macro_input, DeriveInput};
 
-use crate::{
-    parser::{group_by_modules, ipa_state_transition_map, StepMetaData},
-    tree::Node,
-};
+use crate::declarations::{dynamic_steps, step_variants, StepVariant};
 
 trait CaseStyle {
     fn to_snake_case(&self) -> String;
@@ -97,10 +85,19 @@ pub fn expand(input: TokenStream) -> TokenStream {
 
     // implement `AsRef<str>`
     extend_or_error!(out, impl_as_ref(&ident, &data));
-    // implement `StepNarrow<T>` if `compact-gate` feature is enabled. we need the if
-    // statement here to avoid a compile error when `collect_steps.py` is run.
+    let variants = match step_variants(&ast.attrs, data) {
+        Ok(variants) => variants,
+        Err(e) => {
+            out.extend(e.to_compile_error());
+            return out.into();
+        }
+    };
+    // make sure that the declared children are steps
+    out.extend(assert_declared_children(&variants));
+    // implement `CompactStep` and `StepNarrow<T>` if `compact-gate` feature is enabled.
+    // `Descriptive` gate implements `StepNarrow` for all steps instead.
     if cfg!(feature = "compact-gate") {
-        extend_or_error!(out, impl_step_narrow(&ident, &data));
+        out.extend(impl_step_narrow(ident, &variants));
     }
 
     out.into()
@@ -113,19 +110,19 @@ fn impl_as_ref(ident: &syn::Ident, data: &syn::DataEnum) -> Result<TokenStream2,
     let mut const_arrays = Vec::new();
     let mut arms = Vec::new();
 
-    data.variants.iter().for_each(|v| {
+    for v in &data.variants {
         let ident = &v.ident;
         let ident_snake_case = ident.to_string().to_snake_case();
         let ident_upper_case = ident_snake_case.to_uppercase();
 
-        if is_dynamic_step(v) {
-            // create an array of 64 strings and use the variant index as array index
-            let steps = (0..64)
+        if let Some(count) = dynamic_steps(v)? {
+            // create an array of strings and use the variant index as array index
+            let steps = (0..count)
                 .map(|i| format!("{}{}", ident_snake_case, i))
                 .collect::<Vec<_>>();
             let steps_array_ident = format_ident!("{}_DYNAMIC_STEP", ident_upper_case);
             const_arrays.extend(quote!(
-                const #steps_array_ident: [&str; 64] = [#(#steps),*];
+                const #steps_array_ident: [&str; #count] = [#(#steps),*];
             ));
             arms.extend(quote!(
                 Self::#ident(i) => #steps_array_ident[usize::try_from(*i).unwrap()],
@@ -136,7 +133,7 @@ fn impl_as_ref(ident: &syn::Ident, data: &syn::DataEnum) -> Result<TokenStream2,
                 Self::#ident => #ident_snake_case,
             ));
         }
-    });
+    }
 
     Ok(quote!(
         impl AsRef<str> for #ident {
@@ -150,134 +147,75 @@ fn impl_as_ref(ident: &syn::Ident, data: &syn::DataEnum) -> Result<TokenStream2,
     ))
 }
 
-/// Build a state transition map for the enum variants, and use it to generate
-/// a `StepNarrow` implementation.
-fn impl_step_narrow(ident: &syn::Ident, data: &syn::DataEnum) -> Result<TokenStream2, syn::Error> {
-    // get a list of IPA protocol steps from `steps.txt` that match the enum
-    let meta = match get_meta_data_for(&ident, &data) {
-        Ok(steps) => steps,
-        Err(e) => return Err(e),
-    };
-
-    // generate match arms for each state transition
-    let mut states = Vec::new();
-    states.extend(meta.iter().map(|s| {
-        let new_state = &s.name;
-        let new_state_id = s.id;
-        let previous_state_id = s.get_parent().unwrap().id;
-        quote!(
-            (#previous_state_id, #new_state) => #new_state_id,
-        )
-    }));
-
-    let panic = quote!(panic!(
-        "Invalid state transition: {} -> {}",
-        self.0,
-        step.as_ref(),
-    ));
-
-    let narrow_impl = quote!(
-        Self(match (self.0, step.as_ref()) {
-            #(#states)*
-            _ => #panic,
-        })
-    );
+/// `Compact` gate only sees the declared children through `CompactStep`, so here we check that
+/// they name step types in scope of this enum. This is done for all gates, so a broken declaration
+/// does not go unnoticed until a compact build.
+fn assert_declared_children(variants: &[StepVariant]) -> TokenStream2 {
+    let children = variants.iter().flat_map(|v| &v.children).collect::<Vec<_>>();
+    if children.is_empty() {
+        return TokenStream2::new();
+    }
 
-    Ok(quote!(
-        #[cfg(feature = "compact-gate")]
-        impl crate::protocol::step::StepNarrow<#ident> for crate::protocol::step::Gate {
-            fn narrow(&self, step: &#ident) -> Self {
-                #narrow_impl
-            }
-        }
-    ))
+    quote!(
+        const _: fn() = || {
+            fn is_step<S: crate::protocol::step::Step + ?Sized>() {}
+            #(is_step::<#children>();)*
+        };
+    )
 }
 
-/// Returns a list of IPA protocol steps from `steps.txt` that match the enum
-/// variants in given `data`.
-fn get_meta_data_for(
-    ident: &syn::Ident,
-    data: &syn::DataEnum,
-) -> Result<Vec<Node<StepMetaData>>, syn::Error> {
-    // Create lists of steps grouped by modules from `steps.txt`.
-    let steps = ipa_state_transition_map();
-    let grouped_steps = group_by_modules(&steps);
-
-    // Create a list of all enum variant names in the given enum `data`.
-    // If a step is narrowed, they will be present in `steps` vec.
-    // The dynamic steps are synthetically generated here to cover all
-    // subsets in `steps.txt`.
-    let variant_names = data
-        .variants
-        .iter()
-        .flat_map(|v| {
-            if is_dynamic_step(v) {
-                (0..64)
-                    .map(|i| format!("{}{}", v.ident.to_string().to_snake_case(), i))
-                    .collect::<Vec<_>>()
-            } else {
-                vec![v.ident.to_string().to_snake_case()]
-            }
-        })
-        .collect::<Vec<_>>();
+/// Generate the `CompactStep` implementation that describes the steps of the enum, and a
+/// `StepNarrow` implementation that narrows `Compact` gate to a step, identified by its index in
+/// the enum.
+fn impl_step_narrow(ident: &syn::Ident, variants: &[StepVariant]) -> TokenStream2 {
+    // steps are numbered in the order of variants; dynamic variants take as many numbers as they
+    // have steps.
+    let mut arms = Vec::new();
+    let mut names = Vec::new();
+    let mut children = Vec::new();
+    let mut transparent = Vec::new();
+    let mut offset = 0_u16;
+    for v in variants {
+        let variant = &v.variant.ident;
+        let name = variant.to_string().to_snake_case();
+        if v.dynamic.is_some() {
+            arms.push(quote!(
+                #ident::#variant(i) => #offset + u16::try_from(*i).unwrap(),
+            ));
+            names.extend((0..v.len()).map(|i| format!("{name}{i}")));
+        } else {
+            arms.push(quote!(#ident::#variant => #offset,));
+            names.push(name);
+        }
+        offset += u16::try_from(v.len()).unwrap();
 
-    // If there are no variants in the enum, return an empty vector. The
-    // caller will need to handle this case properly.
-    if variant_names.is_empty() {
-        return Ok(Vec::new());
+        let steps = &v.children;
+        let step_children =
+            quote!(&[#(<#steps as crate::protocol::step::CompactStep>::steps),*]);
+        children.extend(std::iter::repeat(step_children).take(v.len()));
+        transparent.extend(std::iter::repeat(v.transparent).take(v.len()));
     }
 
-    // Here, we try to find the enum we are expanding from `grouped_steps`.
-    // Note that there could be multiple enums with the same name, and the
-    // proc-macro does not know the module name it is being used in, so we
-    // can't simply use the enum name as a key to look up.
-    // Instead, we check whether all steps in `grouped_steps[i]` exist in
-    // `variant_names`. If `S ⊆ variant_names where S ∈ grouped_steps`,
-    // then we have found our enum. If there are more than one enum that
-    // satisfies this condition, we are in trouble...
-    let mut target_steps = Vec::new();
-    for (_, steps) in grouped_steps {
-        if steps.iter().all(|s| {
-            s.module.ends_with(ident.to_string().as_str()) && variant_names.contains(&s.name)
-        }) {
-            target_steps.push(steps);
+    quote!(
+        impl crate::protocol::step::CompactStep for #ident {
+            fn steps() -> &'static crate::protocol::step::CompactSteps {
+                static STEPS: crate::protocol::step::CompactSteps =
+                    crate::protocol::step::CompactSteps {
+                        names: &[#(#names),*],
+                        children: &[#(#children),*],
+                        transparent: &[#(#transparent),*],
+                    };
+                &STEPS
+            }
         }
-    }
 
-    match target_steps.len() {
-        0 => {
-            // If we get here, we have not found the enum in `steps.txt`. It's
-            // likely that the enum is newly added but `steps.txt` is not updated,
-            // the code where the step is narrowed is no longer executed, or the
-            // step is used in tests only.
-            Err(syn::Error::new_spanned(
-                ident,
-                "ipa_macros::step expects an enum with variants that match the steps in \
-            steps.txt. If you've made a change to steps, make sure to run `collect_steps.py` \
-            and replace steps.txt with the output. If the step is not a part of the protocol \
-            yet, you can temporarily hide the step or the module containing the step with \
-            `#[cfg(feature = \"descriptive-gate\")]`.",
-            ))
-        }
-        1 => {
-            Ok(target_steps[0]
-                .iter()
-                .map(|s|
-                    // we want to retain the references to the parents, so we use `upgrade()`
-                    s.upgrade())
-                .collect::<Vec<_>>())
+        impl crate::protocol::step::StepNarrow<#ident> for crate::protocol::step::Gate {
+            fn narrow(&self, step: &#ident) -> Self {
+                let index: u16 = match step {
+                    #(#arms)*
+                };
+                self.narrow_to(<#ident as crate::protocol::step::CompactStep>::steps(), index)
+            }
         }
-        _ => Err(syn::Error::new_spanned(
-            ident,
-            format!(
-                "ipa_macros::step found multiple enums that have the same name and \
-            contain at least one variant with the same name. Consider renaming the \
-            enum/variant to avoid this conflict.",
-            ),
-        )),
-    }
-}
-
-fn is_dynamic_step(variant: &syn::Variant) -> bool {
-    variant.attrs.iter().any(|x| x.path().is_ident("dynamic"))
+    )
 }
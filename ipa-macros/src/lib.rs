@@ -1,15 +1,20 @@
+mod declarations;
 mod derive_gate;
+mod derive_reshare;
 mod derive_step;
-mod parser;
-mod tree;
 use proc_macro::TokenStream;
 
-#[proc_macro_derive(Gate)]
+#[proc_macro_derive(Gate, attributes(step))]
 pub fn derive_gate(item: TokenStream) -> TokenStream {
     derive_gate::expand(item)
 }
 
-#[proc_macro_derive(Step, attributes(dynamic))]
+#[proc_macro_derive(Step, attributes(dynamic, step))]
 pub fn derive_step(input: TokenStream) -> TokenStream {
     derive_step::expand(input)
 }
+
+#[proc_macro_derive(Reshare)]
+pub fn derive_reshare(input: TokenStream) -> TokenStream {
+    derive_reshare::expand(input)
+}
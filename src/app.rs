@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::{
     helpers::{
         query::{QueryConfig, QueryInput},
@@ -6,8 +8,8 @@ use crate::{
     hpke::{KeyPair, KeyRegistry},
     protocol::QueryId,
     query::{
-        NewQueryError, QueryCompletionError, QueryInputError, QueryProcessor, QueryStatus,
-        QueryStatusError,
+        AdmissionConfig, NewQueryError, QueryCompletionError, QueryInputError, QueryProcessor,
+        QueryStatus, QueryStatusError,
     },
     sync::Arc,
 };
@@ -33,7 +35,24 @@ impl Setup {
     pub fn with_key_registry(
         key_registry: KeyRegistry<KeyPair>,
     ) -> (Self, TransportCallbacks<TransportImpl>) {
-        let query_processor = Arc::new(QueryProcessor::new(key_registry));
+        Self::with_config(key_registry, AdmissionConfig::default(), None)
+    }
+
+    /// Same as [`with_key_registry`], but also lets the caller configure the limits applied to
+    /// new queries, and a directory to capture the traffic of every query to.
+    ///
+    /// [`with_key_registry`]: Self::with_key_registry
+    #[must_use]
+    pub fn with_config(
+        key_registry: KeyRegistry<KeyPair>,
+        admission_config: AdmissionConfig,
+        capture_dir: Option<PathBuf>,
+    ) -> (Self, TransportCallbacks<TransportImpl>) {
+        let mut query_processor = QueryProcessor::new(key_registry, admission_config);
+        if let Some(dir) = capture_dir {
+            query_processor = query_processor.with_capture_dir(dir);
+        }
+        let query_processor = Arc::new(query_processor);
         let this = Self {
             query_processor: Arc::clone(&query_processor),
         };
@@ -74,7 +93,7 @@ impl Setup {
             }),
             query_status: Box::new(move |_transport: TransportImpl, query_id| {
                 let processor = Arc::clone(&sqp);
-                Box::pin(async move { processor.query_status(query_id) })
+                Box::pin(async move { processor.query_status_with_progress(query_id) })
             }),
             complete_query: Box::new(move |_transport: TransportImpl, query_id| {
                 let processor = Arc::clone(&cqp);
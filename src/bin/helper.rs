@@ -1,9 +1,10 @@
 use std::{
     fs,
-    net::TcpListener,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
     os::fd::{FromRawFd, RawFd},
     path::{Path, PathBuf},
     process,
+    time::Duration,
 };
 
 use clap::{self, Parser, Subcommand};
@@ -12,10 +13,18 @@ use ipa::{
     cli::{
         client_config_setup, keygen, test_setup, ConfGenArgs, KeygenArgs, TestSetupArgs, Verbosity,
     },
-    config::{hpke_registry, HpkeServerConfig, NetworkConfig, ServerConfig, TlsConfig},
+    config::{
+        hpke_registry, HpkeServerConfig, NetworkConfig, QueryRateLimitConfig, ServerConfig,
+        TlsConfig,
+    },
     error::BoxError,
     helpers::HelperIdentity,
-    net::{ClientIdentity, HttpTransport, MpcHelperClient},
+    net::{
+        health::{self, PeerStatus},
+        ClientIdentity, HttpTransport, MpcHelperClient,
+    },
+    query::AdmissionConfig,
+    telemetry::prometheus,
     AppSetup,
 };
 use tracing::{error, info};
@@ -88,6 +97,33 @@ struct ServerArgs {
     /// Private key for decrypting match keys
     #[arg(long, requires = "mk_public_key")]
     mk_private_key: Option<PathBuf>,
+
+    /// Memory budget for running queries, in megabytes. Queries that are estimated to need more
+    /// memory than what is left are rejected. Unlimited if not set.
+    #[arg(long)]
+    max_query_memory_mb: Option<u64>,
+
+    /// Maximum number of queries a report collector may run at the same time. Report collectors
+    /// are told apart by their client certificate, or by their address without one.
+    #[arg(long, default_value = "1")]
+    max_running_queries_per_collector: usize,
+
+    /// Number of query creation requests a report collector may issue back to back
+    #[arg(long, default_value = "10")]
+    query_rate_limit_burst: u32,
+
+    /// Number of seconds it takes for a report collector to regain one query creation request
+    #[arg(long, default_value = "6")]
+    query_rate_limit_refill_secs: u64,
+
+    /// Port to serve Prometheus metrics on, at `/metrics`. Metrics are not exported if not set.
+    #[arg(long)]
+    admin_port: Option<u16>,
+
+    /// Directory to capture the traffic of every query to, for replaying failed queries offline.
+    /// Captures contain this helper's input and secrets, so keep them as safe as its keys.
+    #[arg(long)]
+    capture_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -95,6 +131,8 @@ enum HelperCommand {
     Confgen(ConfGenArgs),
     Keygen(KeygenArgs),
     TestSetup(TestSetupArgs),
+    /// Check that this helper's keys and certificate are usable and that it can reach its peers
+    CheckNetwork(ServerArgs),
 }
 
 fn read_utf8_bytes(path: &Path) -> Result<Vec<u8>, BoxError> {
@@ -103,18 +141,26 @@ fn read_utf8_bytes(path: &Path) -> Result<Vec<u8>, BoxError> {
         .into_bytes())
 }
 
-async fn server(args: ServerArgs) -> Result<(), BoxError> {
+/// Everything a helper needs to know about itself and its peers, built from the command line.
+struct HelperConfig {
+    identity: HelperIdentity,
+    server: ServerConfig,
+    network: NetworkConfig,
+    clients: [MpcHelperClient; 3],
+}
+
+fn helper_config(args: &ServerArgs) -> Result<HelperConfig, BoxError> {
     let my_identity = HelperIdentity::try_from(args.identity.expect("enforced by clap")).unwrap();
 
-    let (identity, server_tls) = match (args.tls_cert, args.tls_key) {
+    let (identity, server_tls) = match (&args.tls_cert, &args.tls_key) {
         (Some(cert), Some(key_file)) => {
-            let key = read_utf8_bytes(&key_file)?;
-            let certs = read_utf8_bytes(&cert)?;
+            let key = read_utf8_bytes(key_file)?;
+            let certs = read_utf8_bytes(cert)?;
             (
                 ClientIdentity::from_pks8(&certs, &key)?,
                 Some(TlsConfig::File {
-                    certificate_file: cert,
-                    private_key_file: key_file,
+                    certificate_file: cert.clone(),
+                    private_key_file: key_file.clone(),
                 }),
             )
         }
@@ -124,20 +170,23 @@ async fn server(args: ServerArgs) -> Result<(), BoxError> {
 
     let mk_encryption = args
         .mk_public_key
-        .zip(args.mk_private_key)
+        .clone()
+        .zip(args.mk_private_key.clone())
         .map(|(pk_path, sk_path)| HpkeServerConfig::File {
             public_key_file: pk_path,
             private_key_file: sk_path,
         });
 
-    let key_registry = hpke_registry(mk_encryption.as_ref()).await?;
-    let (setup, callbacks) = AppSetup::with_key_registry(key_registry);
-
     let server_config = ServerConfig {
         port: args.port,
         disable_https: args.disable_https,
         tls: server_tls,
         hpke_config: mk_encryption,
+        rate_limit: QueryRateLimitConfig {
+            refill_interval: Duration::from_secs(args.query_rate_limit_refill_secs),
+            burst: args.query_rate_limit_burst,
+            max_running_queries: args.max_running_queries_per_collector,
+        },
     };
 
     let scheme = if args.disable_https {
@@ -150,6 +199,40 @@ async fn server(args: ServerArgs) -> Result<(), BoxError> {
         .override_scheme(&scheme);
     let clients = MpcHelperClient::from_conf(&network_config, identity);
 
+    Ok(HelperConfig {
+        identity: my_identity,
+        server: server_config,
+        network: network_config,
+        clients,
+    })
+}
+
+async fn server(args: ServerArgs) -> Result<(), BoxError> {
+    let HelperConfig {
+        identity: my_identity,
+        server: server_config,
+        network: network_config,
+        clients,
+    } = helper_config(&args)?;
+
+    let _metrics = if let Some(port) = args.admin_port {
+        let handle = prometheus::install_recorder()?;
+        Some(prometheus::serve(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)), handle).await)
+    } else {
+        None
+    };
+
+    let key_registry = hpke_registry(server_config.hpke_config.as_ref()).await?;
+    let (setup, callbacks) = AppSetup::with_config(
+        key_registry,
+        AdmissionConfig {
+            max_memory_bytes: args
+                .max_query_memory_mb
+                .map(|mb| mb.saturating_mul(1 << 20)),
+        },
+        args.capture_dir.clone(),
+    );
+
     let (transport, server) = HttpTransport::new(
         my_identity,
         server_config,
@@ -189,6 +272,40 @@ async fn server(args: ServerArgs) -> Result<(), BoxError> {
     Ok(())
 }
 
+/// Runs the same checks as the `/readyz` and `/peers` endpoints without starting the server.
+async fn check_network(args: ServerArgs) -> Result<(), BoxError> {
+    let config = helper_config(&args)?;
+
+    let readiness = health::readiness(&config.server).await;
+    for check in &readiness.checks {
+        let status = if check.ok { "ok" } else { "FAILED" };
+        println!("{:<12} {status:<8} {}", check.name, check.detail);
+    }
+
+    let peers = health::probe_peers(config.identity, &config.clients, &config.network).await;
+    for peer in &peers {
+        let status = if peer.is_healthy() { "ok" } else { "FAILED" };
+        let mut detail = match (peer.round_trip_ms, &peer.error) {
+            (Some(ms), _) => format!("{} responded in {ms}ms", peer.url),
+            (None, Some(e)) => format!("{}: {e}", peer.url),
+            (None, None) => peer.url.clone(),
+        };
+        if let Some(secs) = peer.certificate_expires_in_secs {
+            detail.push_str(&format!(", certificate expires in {secs}s"));
+        }
+        println!(
+            "{:<12} {status:<8} {detail}",
+            format!("{:?}", peer.identity)
+        );
+    }
+
+    if readiness.ready && peers.iter().all(PeerStatus::is_healthy) {
+        Ok(())
+    } else {
+        Err("network check failed".into())
+    }
+}
+
 #[tokio::main]
 pub async fn main() {
     let args = Args::parse();
@@ -199,6 +316,7 @@ pub async fn main() {
         Some(HelperCommand::Keygen(args)) => keygen(&args),
         Some(HelperCommand::TestSetup(args)) => test_setup(args),
         Some(HelperCommand::Confgen(args)) => client_config_setup(args),
+        Some(HelperCommand::CheckNetwork(args)) => check_network(args).await,
     };
 
     if let Err(e) = res {
@@ -7,12 +7,11 @@ use std::{
 
 use clap::Parser;
 use ipa::cli::Verbosity;
-use rand::{rngs::StdRng, SeedableRng};
 use tracing::{debug, error, info};
 
-use crate::{gen_events::generate_events, sample::Sample};
-
-const DEFAULT_EVENT_GEN_COUNT: u32 = 100_000;
+use crate::gen_events::GenEventsArgs;
+#[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+use crate::simulate::{print_report, simulate, SimulateArgs};
 
 #[derive(Debug, Parser)]
 pub struct CommonArgs {
@@ -61,37 +60,13 @@ pub struct Args {
 #[clap(name = "command")]
 pub enum Command {
     #[command(about = "Generate synthetic events.")]
-    GenEvents {
-        #[arg(
-            short,
-            long,
-            default_value = "1",
-            help = "Multiply the number of events generated by the scale factor. For example, --scale-factor=100 generates 10,000,000 synthetic events."
-        )]
-        scale_factor: u32,
-
-        #[arg(
-            short,
-            long,
-            help = "Random generator seed. Setting the seed allows reproduction of the synthetic data exactly."
-        )]
-        random_seed: Option<u64>,
-
-        #[arg(
-            short,
-            long,
-            default_value = "0",
-            help = "Simulate ads created in this epoch. Impressions and conversions for a given ad may happen in the next epoch."
-        )]
-        epoch: u8,
-
-        #[arg(
-            short,
-            long,
-            help = "Configuration file containing distributions data."
-        )]
-        config_file: PathBuf,
-    },
+    GenEvents(GenEventsArgs),
+
+    #[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+    #[command(
+        about = "Estimate the latency and bandwidth of a query by running it on a simulated network."
+    )]
+    Simulate(SimulateArgs),
 }
 
 impl Command {
@@ -99,68 +74,54 @@ impl Command {
         info!("Command {:?}", self);
 
         match self {
-            Self::GenEvents {
-                scale_factor,
-                random_seed,
-                epoch,
-                config_file,
-            } => {
-                Command::gen_events(common, *scale_factor, random_seed, *epoch, config_file);
-            }
+            Self::GenEvents(args) => Command::gen_events(common, args),
+            #[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+            Self::Simulate(args) => Command::simulate(common, args),
         }
     }
 
-    fn gen_events(
-        common: &CommonArgs,
-        scale_factor: u32,
-        random_seed: &Option<u64>,
-        epoch: u8,
-        config_file: &Path,
-    ) {
-        let mut input = Command::get_input(&Some(config_file.to_path_buf())).unwrap_or_else(|e| {
-            error!("Failed to open the input file. {}", e);
+    /// `simulate` runs the helpers in this process, which sets up logging and metrics the way
+    /// tests do.
+    pub fn sets_up_logging(&self) -> bool {
+        #[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+        return matches!(self, Self::Simulate(_));
+        #[cfg(not(all(feature = "test-fixture", feature = "in-memory-infra")))]
+        false
+    }
+
+    #[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+    fn simulate(common: &CommonArgs, args: &SimulateArgs) {
+        let mut out = common.get_output().unwrap_or_else(|e| {
+            error!("Failed to open the output file. {}", e);
+            process::exit(1);
+        });
+
+        let report = simulate(args);
+        print_report(args, &report, &mut out).unwrap_or_else(|e| {
+            error!("Failed to write the report. {}", e);
             process::exit(1);
         });
+    }
 
+    fn gen_events(common: &CommonArgs, args: &GenEventsArgs) {
         let mut out = common.get_output().unwrap_or_else(|e| {
             error!("Failed to open the output file. {}", e);
             process::exit(1);
         });
 
-        info!(
-            "scale: {}, seed: {:?}, epoch: {}",
-            scale_factor, random_seed, epoch
-        );
-        debug!(
-            "Total number of events to generate: {}",
-            DEFAULT_EVENT_GEN_COUNT * scale_factor
-        );
-
-        let config = serde_json::from_reader(&mut input).unwrap();
-        let sample = Sample::new(&config);
-
-        let mut rng = random_seed.map_or(StdRng::from_entropy(), StdRng::seed_from_u64);
-
-        let (s_count, t_count) = generate_events(
-            &sample,
-            DEFAULT_EVENT_GEN_COUNT * scale_factor,
-            epoch,
-            &mut rng,
-            &mut out,
-        );
-
-        info!("{} source events generated", s_count);
-        info!("{} trigger events generated", t_count);
-        info!(
-            "trigger/source ratio: {}",
-            f64::from(t_count) / f64::from(s_count)
-        );
-    }
+        debug!("Total number of events to generate: {}", args.count());
+        let records = args.generate().unwrap_or_else(|e| {
+            error!("Failed to read the configuration file. {}", e);
+            process::exit(1);
+        });
 
-    fn get_input(path: &Option<PathBuf>) -> Result<Box<dyn io::Read>, io::Error> {
-        match path {
-            Some(ref path) => File::open(path).map(|f| Box::new(f) as Box<dyn io::Read>),
-            None => Ok(Box::new(io::stdin())),
-        }
+        let triggers = records.iter().filter(|r| r.is_trigger_report).count();
+        info!("{} source events generated", records.len() - triggers);
+        info!("{} trigger events generated", triggers);
+
+        args.write(&records, &mut out).unwrap_or_else(|e| {
+            error!("Failed to write the events. {}", e);
+            process::exit(1);
+        });
     }
 }
@@ -1,13 +1,12 @@
 mod cmd;
-mod config;
 mod gen_events;
-mod models;
-mod sample;
+#[cfg(all(feature = "test-fixture", feature = "in-memory-infra"))]
+mod simulate;
 
 use clap::Parser;
 
 fn main() {
     let args = cmd::Args::parse();
-    let _handle = args.common.logging.setup_logging();
+    let _handle = (!args.cmd.sets_up_logging()).then(|| args.common.logging.setup_logging());
     args.cmd.dispatch(&args.common);
 }
@@ -0,0 +1,278 @@
+use std::{
+    io,
+    num::{NonZeroU32, NonZeroU64},
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum};
+use comfy_table::Table;
+use ipa::{
+    ff::{Fp32BitPrime, GaloisField, Gf3Bit, Gf5Bit, Gf8Bit},
+    helpers::{query::IpaQueryConfig, GatewayConfig},
+    secret_sharing::SharedValue,
+    test_fixture::{
+        aggregation::test_sparse_aggregate,
+        ipa::{ipa_in_the_clear, test_ipa, IpaSecurityModel},
+        prf_sharding::{group_by_user, test_prf_ipa},
+        simulate::{NetworkProfile, SimulationReport, Simulator},
+        TestWorldConfig, WorkloadConfig, WorkloadGenerator,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::runtime::Builder;
+
+// The same fields as the oneshot benchmarks of these protocols.
+type BreakdownKey = Gf5Bit;
+type TriggerValue = Gf3Bit;
+type ContributionValue = Gf8Bit;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SimulatedQuery {
+    SemiHonestIpa,
+    MaliciousIpa,
+    SemiHonestSparseAggregate,
+    MaliciousSparseAggregate,
+    /// Attribution, capping and aggregation of the PRF-sharded IPA, which is semi-honest only.
+    SemiHonestPrfIpa,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    #[arg(long, value_enum, default_value_t = SimulatedQuery::MaliciousIpa)]
+    query: SimulatedQuery,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value = "1000",
+        help = "Number of records in the query."
+    )]
+    query_size: usize,
+
+    #[arg(
+        long,
+        default_value = "50",
+        help = "Round trip time between helpers, in milliseconds."
+    )]
+    rtt: u64,
+
+    #[arg(
+        long,
+        default_value = "1000",
+        help = "Bandwidth of each helper's uplink, in megabits per second."
+    )]
+    bandwidth: NonZeroU64,
+
+    #[arg(
+        long,
+        default_value = "3",
+        help = "The contribution cap for each person. PRF-sharded IPA needs a power of two greater than 8."
+    )]
+    per_user_cap: u32,
+
+    #[arg(long, default_value = "16", help = "The number of breakdown keys.")]
+    breakdown_keys: NonZeroU32,
+
+    #[arg(long, default_value = "5", help = "The maximum trigger value.")]
+    max_trigger_value: NonZeroU32,
+
+    #[arg(
+        long,
+        default_value = "50",
+        help = "The maximum number of records for each person."
+    )]
+    records_per_user: NonZeroU32,
+
+    #[arg(
+        short,
+        long,
+        help = "Random generator seed. Setting the seed allows reproduction of the synthetic data exactly."
+    )]
+    random_seed: Option<u64>,
+}
+
+impl SimulateArgs {
+    fn profile(&self) -> NetworkProfile {
+        NetworkProfile {
+            rtt: Duration::from_millis(self.rtt),
+            bandwidth: self
+                .bandwidth
+                .saturating_mul(NonZeroU64::new(125_000).unwrap()),
+        }
+    }
+
+    fn rng(&self) -> StdRng {
+        self.random_seed
+            .map_or(StdRng::from_entropy(), StdRng::seed_from_u64)
+    }
+
+    fn workload(&self) -> WorkloadGenerator<StdRng> {
+        WorkloadGenerator::new(
+            self.rng(),
+            WorkloadConfig::uniform(
+                self.max_trigger_value.get(),
+                self.breakdown_keys.get(),
+                self.records_per_user.get(),
+            ),
+        )
+    }
+
+    fn ipa_config(&self) -> IpaQueryConfig {
+        IpaQueryConfig {
+            per_user_credit_cap: self.per_user_cap,
+            max_breakdown_key: self.breakdown_keys.get(),
+            plaintext_match_keys: true,
+            ..IpaQueryConfig::default()
+        }
+    }
+}
+
+/// Runs the query on a simulated network and returns its network usage. Rounds are counted from
+/// the dependencies between messages, so this takes as long as the computation does.
+///
+/// ## Panics
+/// If the arguments don't fit the fields the query runs with, or the query fails.
+pub fn simulate(args: &SimulateArgs) -> SimulationReport {
+    let rt = Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(async {
+        let simulator = Simulator::new(TestWorldConfig {
+            gateway_config: GatewayConfig::new(args.query_size.clamp(16, 1024)),
+            ..TestWorldConfig::default()
+        });
+        match args.query {
+            SimulatedQuery::SemiHonestIpa => {
+                run_ipa(&simulator, args, IpaSecurityModel::SemiHonest).await;
+            }
+            SimulatedQuery::MaliciousIpa => {
+                run_ipa(&simulator, args, IpaSecurityModel::Malicious).await;
+            }
+            SimulatedQuery::SemiHonestSparseAggregate => {
+                run_sparse_aggregate(&simulator, args, IpaSecurityModel::SemiHonest).await;
+            }
+            SimulatedQuery::MaliciousSparseAggregate => {
+                run_sparse_aggregate(&simulator, args, IpaSecurityModel::Malicious).await;
+            }
+            SimulatedQuery::SemiHonestPrfIpa => run_prf_ipa(&simulator, args).await,
+        }
+        simulator.report()
+    })
+}
+
+async fn run_ipa(simulator: &Simulator, args: &SimulateArgs, security_model: IpaSecurityModel) {
+    let records = args.workload().generate(args.query_size);
+    let config = args.ipa_config();
+    let expected = ipa_in_the_clear(
+        &records,
+        config.per_user_credit_cap,
+        config.attribution_window_seconds,
+        config.max_breakdown_key,
+    );
+    test_ipa::<Fp32BitPrime>(
+        simulator.world(),
+        &records,
+        &expected,
+        config,
+        security_model,
+    )
+    .await;
+}
+
+async fn run_sparse_aggregate(
+    simulator: &Simulator,
+    args: &SimulateArgs,
+    security_model: IpaSecurityModel,
+) {
+    assert!(
+        args.per_user_cap < 1 << ContributionValue::BITS
+            && args.breakdown_keys.get() <= 1 << BreakdownKey::BITS,
+        "contributions must fit in {} bits and breakdown keys in {} bits",
+        ContributionValue::BITS,
+        BreakdownKey::BITS
+    );
+    let mut rng = args.rng();
+    let input = (0..args.query_size)
+        .map(|_| {
+            (
+                rng.gen_range(0..args.breakdown_keys.get()),
+                rng.gen_range(0..=args.per_user_cap),
+            )
+        })
+        .collect::<Vec<_>>();
+    test_sparse_aggregate::<BreakdownKey, ContributionValue, Fp32BitPrime>(
+        simulator.world(),
+        &input,
+        usize::try_from(args.breakdown_keys.get()).unwrap(),
+        security_model,
+    )
+    .await;
+}
+
+async fn run_prf_ipa(simulator: &Simulator, args: &SimulateArgs) {
+    assert!(
+        args.per_user_cap.is_power_of_two() && args.per_user_cap > 1 << TriggerValue::BITS,
+        "per-user cap must be a power of two greater than {}, got {}",
+        1 << TriggerValue::BITS,
+        args.per_user_cap
+    );
+    assert!(
+        args.max_trigger_value.get() <= 1 << TriggerValue::BITS
+            && args.breakdown_keys.get() <= 1 << BreakdownKey::BITS,
+        "trigger values must fit in {} bits and breakdown keys in {} bits",
+        TriggerValue::BITS,
+        BreakdownKey::BITS
+    );
+    let mut records = args.workload().generate(args.query_size);
+    group_by_user(&mut records);
+    test_prf_ipa::<BreakdownKey, TriggerValue, Fp32BitPrime>(
+        simulator.world(),
+        &records,
+        usize::try_from(args.per_user_cap.ilog2()).unwrap(),
+        usize::try_from(args.records_per_user.get()).unwrap(),
+    )
+    .await;
+}
+
+/// Writes the estimated time of each top-level step of the query and of the entire query.
+pub fn print_report(
+    args: &SimulateArgs,
+    report: &SimulationReport,
+    out: &mut dyn io::Write,
+) -> Result<(), io::Error> {
+    let profile = args.profile();
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Step",
+        "Records sent",
+        "Bytes sent",
+        "Rounds",
+        "Estimated time",
+    ]);
+    for step in &report.steps {
+        table.add_row(vec![
+            step.step.clone(),
+            step.records_sent.to_string(),
+            step.bytes_sent.to_string(),
+            step.rounds.to_string(),
+            format!("{:.3?}", step.estimate(&profile)),
+        ]);
+    }
+    table.add_row(vec![
+        "Total".to_string(),
+        report
+            .steps
+            .iter()
+            .map(|step| step.records_sent)
+            .sum::<u64>()
+            .to_string(),
+        report.bytes_sent().to_string(),
+        report.rounds().to_string(),
+        format!("{:.3?}", report.estimate(&profile)),
+    ]);
+
+    writeln!(
+        out,
+        "{:?} for {} records, RTT {}ms, {}Mbps per helper",
+        args.query, args.query_size, args.rtt, args.bandwidth
+    )?;
+    writeln!(out, "{table}")
+}
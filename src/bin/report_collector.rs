@@ -27,7 +27,7 @@ use ipa::{
     report::{KeyIdentifier, DEFAULT_KEY_ID},
     test_fixture::{
         ipa::{ipa_in_the_clear, IpaSecurityModel, TestRawDataRecord},
-        EventGenerator, EventGeneratorConfig,
+        ReportFilter, WorkloadConfig, WorkloadGenerator,
     },
 };
 use rand::{distributions::Alphanumeric, rngs::StdRng, thread_rng, Rng};
@@ -59,6 +59,10 @@ struct Args {
     #[arg(long, value_name = "FILE")]
     output_file: Option<PathBuf>,
 
+    /// Directory to save the result shares received from each helper to, as they are downloaded
+    #[arg(long, value_name = "DIR")]
+    shares_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     action: ReportCollectorCommand,
 }
@@ -99,12 +103,62 @@ enum ReportCollectorCommand {
         seed: Option<u64>,
 
         #[clap(flatten)]
-        gen_args: EventGeneratorConfig,
+        gen_args: GenIpaInputsArgs,
     },
     /// Apply differential privacy noise to IPA inputs
     ApplyDpNoise(ApplyDpArgs),
 }
 
+/// Bounds of the uniformly distributed inputs `gen-ipa-inputs` generates.
+#[derive(Debug, clap::Args)]
+struct GenIpaInputsArgs {
+    #[arg(long, default_value = "1000000000000")]
+    max_user_id: u64,
+    #[arg(long, default_value = "5")]
+    max_trigger_value: u32,
+    #[arg(long, default_value = "20")]
+    max_breakdown_key: u32,
+    #[arg(long, default_value = "10")]
+    max_events_per_user: u32,
+    /// Indicates the types of reports that will appear in the output. Possible values
+    /// are: only impressions, only conversions or both.
+    #[arg(value_enum, long, default_value_t = ReportFilter::All)]
+    report_filter: ReportFilter,
+    /// Probability that a conversion can be attributed to someone. The others have no match key.
+    #[arg(long, required_if_eq("report_filter", "TriggerOnly"), default_value = "0.02", value_parser = validate_probability)]
+    conversion_probability: Option<f64>,
+}
+
+impl From<GenIpaInputsArgs> for WorkloadConfig {
+    fn from(args: GenIpaInputsArgs) -> Self {
+        let ephemeral_conversion_rate = match args.report_filter {
+            ReportFilter::TriggerOnly => 1.0 - args.conversion_probability.unwrap_or(1.0),
+            ReportFilter::All | ReportFilter::SourceOnly => 0.0,
+        };
+        WorkloadConfig {
+            ephemeral_conversion_rate,
+            report_filter: args.report_filter,
+            ..WorkloadConfig::uniform(
+                args.max_trigger_value,
+                args.max_breakdown_key,
+                args.max_events_per_user,
+            )
+            .with_max_match_key(args.max_user_id)
+        }
+    }
+}
+
+fn validate_probability(value: &str) -> Result<f64, String> {
+    let v = value
+        .parse::<f64>()
+        .map_err(|e| format!("{e} not a float number"))?;
+    if (0.0..=1.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err(format!("probability must be between 0.0 and 1.0, got {v}"))
+    }
+}
+
 #[derive(Debug, clap::Args)]
 struct GenInputArgs {
     /// Maximum records per user
@@ -162,12 +216,12 @@ fn gen_inputs(
     count: u32,
     seed: Option<u64>,
     output_file: Option<PathBuf>,
-    args: EventGeneratorConfig,
+    args: GenIpaInputsArgs,
 ) -> io::Result<()> {
     let rng = seed
         .map(StdRng::seed_from_u64)
         .unwrap_or_else(|| StdRng::from_entropy());
-    let event_gen = EventGenerator::with_config(rng, args).take(count as usize);
+    let event_gen = WorkloadGenerator::new(rng, args.into()).generate(count as usize);
     let mut writer: Box<dyn Write> = if let Some(path) = output_file {
         Box::new(OpenOptions::new().write(true).create_new(true).open(path)?)
     } else {
@@ -265,6 +319,7 @@ async fn ipa(
         query_id,
         ipa_query_config,
         key_registries.init_from(network),
+        args.shares_dir.as_deref(),
     )
     .await;
 
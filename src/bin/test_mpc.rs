@@ -8,7 +8,7 @@ use ipa::{
         playbook::{make_clients, secure_mul, validate, InputSource},
         Verbosity,
     },
-    ff::{Field, FieldType, Fp31, Fp32BitPrime, Serializable},
+    ff::{Field, FieldType, Fp31, Fp32BitPrime, Fp61BitPrime, Serializable},
     helpers::query::{QueryConfig, QueryType::TestMultiply},
     net::MpcHelperClient,
     secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
@@ -120,5 +120,6 @@ async fn multiply(args: &Args, helper_clients: &[MpcHelperClient; 3]) {
     match args.input.field {
         FieldType::Fp31 => multiply_in_field::<Fp31>(&args, helper_clients).await,
         FieldType::Fp32BitPrime => multiply_in_field::<Fp32BitPrime>(&args, helper_clients).await,
+        FieldType::Fp61BitPrime => multiply_in_field::<Fp61BitPrime>(&args, helper_clients).await,
     };
 }
@@ -2,6 +2,7 @@
 use std::{
     cmp::min,
     iter::zip,
+    path::Path,
     time::{Duration, Instant},
 };
 
@@ -13,7 +14,7 @@ use tokio::time::sleep;
 use typenum::Unsigned;
 
 use crate::{
-    cli::IpaQueryResult,
+    cli::{playbook::download_results, IpaQueryResult},
     ff::{PrimeField, Serializable},
     helpers::{
         query::{IpaQueryConfig, QueryInput, QuerySize},
@@ -23,14 +24,76 @@ use crate::{
     ipa_test_input,
     net::MpcHelperClient,
     protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey, QueryId},
-    query::QueryStatus,
+    query::{QueryProgress, QueryStatus},
     report::{KeyIdentifier, Report},
     secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
-    test_fixture::{input::GenericReportTestInput, ipa::TestRawDataRecord, Reconstruct},
+    test_fixture::{
+        input::GenericReportTestInput, ipa::TestRawDataRecord, workload::encrypt_reports,
+    },
 };
 
+/// Renders `progress` as a single line, for example
+/// `[#####---------------]  25% (3/12 steps) sort/keys, 1024 records`.
+fn progress_bar(progress: &QueryProgress) -> String {
+    const WIDTH: u8 = 20;
+    let steps = match progress.estimated_total_steps {
+        Some(total) => format!("{}/{total} steps", progress.completed_steps),
+        None => format!("{} steps", progress.completed_steps),
+    };
+    let bar = progress.fraction().map_or_else(String::new, |fraction| {
+        // fraction is within [0, 1], so the conversions are lossless
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let filled = (fraction * f64::from(WIDTH)).round() as usize;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let percent = (fraction * 100.0).round() as usize;
+        format!(
+            "[{}{}] {percent:>3}% ",
+            "#".repeat(filled),
+            "-".repeat(usize::from(WIDTH) - filled)
+        )
+    });
+    format!(
+        "{bar}({steps}) {}, {} records",
+        progress.current_step.as_deref().unwrap_or("starting"),
+        progress.records_processed
+    )
+}
+
+/// Polls helpers until all of them report the query as completed, showing the progress of the
+/// query on stderr in the meantime.
+async fn wait_for_completion(clients: &[MpcHelperClient; 3], query_id: QueryId) {
+    let mut delay = Duration::from_millis(125);
+    loop {
+        let statuses = try_join_all(clients.iter().map(|client| client.query_status(query_id)))
+            .await
+            .unwrap();
+        if statuses
+            .iter()
+            .all(|(status, _)| *status == QueryStatus::Completed)
+        {
+            eprintln!();
+            break;
+        }
+
+        // The slowest helper determines how far along the query is.
+        if let Some(progress) = statuses
+            .into_iter()
+            .filter_map(|(_, progress)| progress)
+            .min_by_key(|progress| progress.completed_steps)
+        {
+            eprint!("\r{}", progress_bar(&progress));
+        }
+
+        sleep(delay).await;
+        delay = min(Duration::from_secs(5), delay * 2);
+        // TODO: Add a timeout of some sort, e.g. if the query does not make progress for a while.
+    }
+}
+
 /// Semi-honest IPA protocol.
-/// Returns aggregated values per breakdown key represented as index in the returned vector
+/// Returns aggregated values per breakdown key represented as index in the returned vector.
+/// If `shares_dir` is set, result shares received from helpers are saved there, see
+/// [`download_results`].
 #[allow(clippy::missing_panics_doc)]
 pub async fn playbook_ipa<F, MK, BK, KR>(
     records: &[TestRawDataRecord],
@@ -38,6 +101,7 @@ pub async fn playbook_ipa<F, MK, BK, KR>(
     query_id: QueryId,
     query_config: IpaQueryConfig,
     encryption: Option<(KeyIdentifier, [&KR; 3])>,
+    shares_dir: Option<&Path>,
 ) -> IpaQueryResult
 where
     F: PrimeField + IntoShares<AdditiveShare<F>>,
@@ -52,22 +116,8 @@ where
 
     if !query_config.plaintext_match_keys {
         if let Some((key_id, key_registries)) = encryption {
-            const ESTIMATED_AVERAGE_REPORT_SIZE: usize = 80; // TODO: confirm/adjust
-            for buffer in &mut buffers {
-                buffer.reserve(query_size * ESTIMATED_AVERAGE_REPORT_SIZE);
-            }
-
             let mut rng = StdRng::from_entropy();
-            let shares: [Vec<Report<_, _, _>>; 3] = records.iter().cloned().share();
-            zip(&mut buffers, shares).zip(key_registries).for_each(
-                |((buf, shares), key_registry)| {
-                    for share in shares {
-                        share
-                            .delimited_encrypt_to(key_id, key_registry, &mut rng, buf)
-                            .unwrap();
-                    }
-                },
-            );
+            buffers = encrypt_reports(records, key_id, key_registries, &mut rng).unwrap();
         } else {
             panic!("match key encryption was requested, but one or more helpers is missing a public key")
         }
@@ -114,34 +164,13 @@ where
     .await
     .unwrap();
 
-    let mut delay = Duration::from_millis(125);
-    loop {
-        if try_join_all(clients.iter().map(|client| client.query_status(query_id)))
-            .await
-            .unwrap()
-            .into_iter()
-            .all(|status| status == QueryStatus::Completed)
-        {
-            break;
-        }
-
-        sleep(delay).await;
-        delay = min(Duration::from_secs(5), delay * 2);
-        // TODO: Add a timeout of some sort. Possibly, add some sort of progress indicator to
-        // the status API so we can check whether the query is making progress.
-    }
+    wait_for_completion(clients, query_id).await;
 
     // wait until helpers have processed the query and get the results from them
-    let results: [_; 3] = try_join_all(clients.iter().map(|client| client.query_results(query_id)))
+    let results: Vec<F> = download_results(clients, query_id, shares_dir)
         .await
-        .unwrap()
-        .try_into()
         .unwrap();
 
-    let results: Vec<F> = results
-        .map(|bytes| AdditiveShare::<F>::from_byte_slice(&bytes).collect::<Vec<_>>())
-        .reconstruct();
-
     let lat = mpc_time.elapsed();
     tracing::info!("Running IPA for {query_size:?} records took {t:?}", t = lat);
     let mut breakdowns = vec![0; usize::try_from(query_config.max_breakdown_key).unwrap()];
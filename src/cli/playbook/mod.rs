@@ -3,18 +3,25 @@ mod ipa;
 mod multiply;
 
 use core::fmt::Debug;
-use std::{fs, path::Path, time::Duration};
+use std::{fs, iter::zip, path::Path, time::Duration};
 
 use comfy_table::{Cell, Color, Table};
+use futures::{future::try_join3, stream::select_all, TryStreamExt};
 use hyper::http::uri::Scheme;
 pub use input::InputSource;
 pub use multiply::secure_mul;
-use tokio::time::sleep;
+use tokio::{fs::File, io::AsyncWriteExt, time::sleep};
+use typenum::Unsigned;
 
 pub use self::ipa::playbook_ipa;
 use crate::{
     config::{ClientConfig, NetworkConfig, PeerConfig},
+    error::BoxError,
+    ff::Serializable,
     net::{ClientIdentity, MpcHelperClient},
+    protocol::QueryId,
+    secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, SharedValue},
+    test_fixture::Reconstruct,
 };
 
 pub fn validate<'a, I, S>(expected: I, actual: I)
@@ -100,3 +107,76 @@ async fn clients_ready(clients: &[MpcHelperClient; 3]) -> bool {
         && clients[1].echo("").await.is_ok()
         && clients[2].echo("").await.is_ok()
 }
+
+/// Waits for the query to complete and reconstructs its results from the shares returned by all
+/// helpers.
+///
+/// Shares are reconstructed as soon as all three helpers have sent them, so only the bytes that
+/// one helper sent ahead of the others are kept in memory. If `shares_dir` is set, shares received
+/// from helper `N` are also written to `<shares_dir>/helperN.shares` as they arrive.
+///
+/// ## Errors
+/// If any of the helpers fails to return the results, if writing to `shares_dir` fails or if the
+/// helpers return different numbers of shares.
+///
+/// ## Panics
+/// If the shares returned by the helpers are not consistent.
+pub async fn download_results<V>(
+    clients: &[MpcHelperClient; 3],
+    query_id: QueryId,
+    shares_dir: Option<&Path>,
+) -> Result<Vec<V>, BoxError>
+where
+    V: SharedValue,
+    Replicated<V>: Serializable,
+{
+    let share_size = <Replicated<V> as Serializable>::Size::USIZE;
+    let mut files = [None, None, None];
+    if let Some(dir) = shares_dir {
+        for (i, file) in files.iter_mut().enumerate() {
+            *file = Some(File::create(dir.join(format!("helper{}.shares", i + 1))).await?);
+        }
+    }
+
+    let (h1, h2, h3) = try_join3(
+        clients[0].query_results(query_id),
+        clients[1].query_results(query_id),
+        clients[2].query_results(query_id),
+    )
+    .await?;
+    let mut chunks = select_all(
+        [h1, h2, h3]
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunks)| Box::pin(chunks.map_ok(move |chunk| (i, chunk)))),
+    );
+
+    let mut pending: [Vec<u8>; 3] = Default::default();
+    let mut results = Vec::new();
+    while let Some((i, chunk)) = chunks.try_next().await? {
+        if let Some(file) = files[i].as_mut() {
+            file.write_all(&chunk).await?;
+        }
+        pending[i].extend_from_slice(&chunk);
+
+        let ready = pending.iter().map(Vec::len).min().unwrap() / share_size * share_size;
+        if ready > 0 {
+            let [s0, s1, s2] = pending
+                .each_ref()
+                .map(|p| Replicated::<V>::from_byte_slice(&p[..ready]));
+            results.extend(zip(s0, zip(s1, s2)).map(|(s0, (s1, s2))| [s0, s1, s2].reconstruct()));
+            for p in &mut pending {
+                p.drain(..ready);
+            }
+        }
+    }
+
+    for file in files.iter_mut().flatten() {
+        file.flush().await?;
+    }
+    if pending.iter().any(|p| !p.is_empty()) {
+        return Err("helpers returned different numbers of result shares".into());
+    }
+
+    Ok(results)
+}
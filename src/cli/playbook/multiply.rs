@@ -7,12 +7,12 @@ use generic_array::{ArrayLength, GenericArray};
 use typenum::Unsigned;
 
 use crate::{
+    cli::playbook::download_results,
     ff::{Field, Serializable},
     helpers::{query::QueryInput, BodyStream},
     net::MpcHelperClient,
     protocol::QueryId,
     secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, IntoShares},
-    test_fixture::Reconstruct,
 };
 
 /// Secure multiplication. Each input must be a valid tuple of field values.
@@ -65,14 +65,5 @@ where
     .unwrap();
 
     // wait until helpers have processed the query and get the results from them
-    let results: [_; 3] = try_join_all(clients.iter().map(|client| client.query_results(query_id)))
-        .await
-        .unwrap()
-        .try_into()
-        .unwrap();
-
-    // expect replicated shares to be sent back
-    results
-        .map(|bytes| Replicated::<F>::from_byte_slice(&bytes).collect::<Vec<_>>())
-        .reconstruct()
+    download_results(clients, query_id, None).await.unwrap()
 }
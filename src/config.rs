@@ -271,6 +271,35 @@ pub struct ServerConfig {
 
     /// Configuration needed for encrypting and decrypting match keys
     pub hpke_config: Option<HpkeServerConfig>,
+
+    /// Limits on query creation requests from report collectors
+    pub rate_limit: QueryRateLimitConfig,
+}
+
+/// Limits on how often report collectors may ask a helper to create new queries.
+///
+/// Each collector gets a token bucket holding up to `burst` requests that is refilled with one
+/// token every `refill_interval`. Helpers are identified by their TLS client certificate, other
+/// collectors by the address they connect from.
+#[derive(Clone, Debug)]
+pub struct QueryRateLimitConfig {
+    /// Maximum number of queries a single collector may have running. A query counts until this
+    /// helper no longer knows about it, i.e. until its results are collected.
+    pub max_running_queries: usize,
+    /// Number of query creation requests a collector may issue back to back.
+    pub burst: u32,
+    /// Time it takes to regain one request after the burst is exhausted.
+    pub refill_interval: Duration,
+}
+
+impl Default for QueryRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_running_queries: 1,
+            burst: 10,
+            refill_interval: Duration::from_secs(6),
+        }
+    }
 }
 
 pub trait HyperClientConfigurator {
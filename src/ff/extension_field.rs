@@ -0,0 +1,270 @@
+use std::fmt::{Debug, Formatter};
+
+use generic_array::GenericArray;
+use typenum::U8;
+
+use super::{Field, Fp32BitPrime, PrimeField, Serializable};
+use crate::secret_sharing::SharedValue;
+
+/// Quadratic extension of [`Fp32BitPrime`], `GF(p^2) = GF(p)[i] / (i^2 + 1)`.
+///
+/// `p = 2^32 - 5` is congruent to 3 modulo 4, so `-1` is not a quadratic residue and `i^2 + 1` is
+/// irreducible. Elements are stored as `a + b*i`; the low 32 bits of the storage hold `a` and the
+/// high 32 bits hold `b`.
+///
+/// This field is used to carry the `r * x` values of malicious shares over `Fp32BitPrime`, which
+/// brings the probability of an additive attack going undetected down from `2^-32` to `2^-64`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fp32BitPrimeExt {
+    re: Fp32BitPrime,
+    im: Fp32BitPrime,
+}
+
+impl Fp32BitPrimeExt {
+    #[must_use]
+    pub const fn new(re: Fp32BitPrime, im: Fp32BitPrime) -> Self {
+        Self { re, im }
+    }
+}
+
+impl From<Fp32BitPrime> for Fp32BitPrimeExt {
+    fn from(re: Fp32BitPrime) -> Self {
+        Self::new(re, Fp32BitPrime::ZERO)
+    }
+}
+
+impl SharedValue for Fp32BitPrimeExt {
+    type Storage = u64;
+    const BITS: u32 = 64;
+    const ZERO: Self = Self::new(Fp32BitPrime::ZERO, Fp32BitPrime::ZERO);
+}
+
+impl Field for Fp32BitPrimeExt {
+    const ONE: Self = Self::new(Fp32BitPrime::ONE, Fp32BitPrime::ZERO);
+
+    fn as_u128(&self) -> u128 {
+        u128::from(u64::from(*self))
+    }
+
+    /// An infallible conversion from `u128` to this type. Unlike [`TryFrom`], the input is split
+    /// into two 64-bit halves, each reduced modulo `p` into one coefficient. This keeps the bias
+    /// of values drawn from PRSS as small as it is for [`Fp32BitPrime`].
+    #[allow(clippy::cast_possible_truncation)]
+    fn truncate_from<T: Into<u128>>(v: T) -> Self {
+        let v = v.into();
+        Self::new(
+            Fp32BitPrime::truncate_from(v as u64),
+            Fp32BitPrime::truncate_from((v >> 64) as u64),
+        )
+    }
+}
+
+impl From<Fp32BitPrimeExt> for u64 {
+    fn from(v: Fp32BitPrimeExt) -> Self {
+        u64::from(u32::from(v.im)) << 32 | u64::from(u32::from(v.re))
+    }
+}
+
+impl TryFrom<u128> for Fp32BitPrimeExt {
+    type Error = crate::error::Error;
+
+    fn try_from(v: u128) -> Result<Self, Self::Error> {
+        let p = u128::from(Fp32BitPrime::PRIME);
+        let (re, im) = (v & u128::from(u32::MAX), v >> 32);
+        if re < p && im < p {
+            Ok(Self::new(
+                Fp32BitPrime::truncate_from(re),
+                Fp32BitPrime::truncate_from(im),
+            ))
+        } else {
+            Err(crate::error::Error::FieldValueTruncation(format!(
+                "{v} is not a valid element of GF(p^2), p={p}"
+            )))
+        }
+    }
+}
+
+impl Serializable for Fp32BitPrimeExt {
+    type Size = U8;
+
+    fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+        buf.copy_from_slice(&u64::from(*self).to_le_bytes());
+    }
+
+    /// Each coefficient is reduced modulo `p`, so that any 8 bytes received from a peer
+    /// deserialize to some element instead of causing a panic.
+    fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
+        let v = u64::from_le_bytes((*buf).into());
+        Self::new(
+            Fp32BitPrime::truncate_from(v & u64::from(u32::MAX)),
+            Fp32BitPrime::truncate_from(v >> 32),
+        )
+    }
+}
+
+impl std::ops::Add for Fp32BitPrimeExt {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl std::ops::AddAssign for Fp32BitPrimeExt {
+    #[allow(clippy::assign_op_pattern)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Neg for Fp32BitPrimeExt {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl std::ops::Sub for Fp32BitPrimeExt {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl std::ops::SubAssign for Fp32BitPrimeExt {
+    #[allow(clippy::assign_op_pattern)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::ops::Mul for Fp32BitPrimeExt {
+    type Output = Self;
+
+    /// `(a + bi)(c + di) = (ac - bd) + (ad + bc)i`
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl std::ops::MulAssign for Fp32BitPrimeExt {
+    #[allow(clippy::assign_op_pattern)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl rand::distributions::Distribution<Fp32BitPrimeExt> for rand::distributions::Standard {
+    fn sample<R: crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> Fp32BitPrimeExt {
+        Fp32BitPrimeExt::new(rng.gen(), rng.gen())
+    }
+}
+
+impl Debug for Fp32BitPrimeExt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({}+{}i)_mod{}",
+            u32::from(self.re),
+            u32::from(self.im),
+            Fp32BitPrime::PRIME
+        )
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use generic_array::GenericArray;
+    use proptest::proptest;
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+
+    fn ext(re: u32, im: u32) -> Fp32BitPrimeExt {
+        Fp32BitPrimeExt::new(
+            Fp32BitPrime::truncate_from(re),
+            Fp32BitPrime::truncate_from(im),
+        )
+    }
+
+    #[test]
+    fn i_squared_is_minus_one() {
+        let i = ext(0, 1);
+        assert_eq!(-Fp32BitPrimeExt::ONE, i * i);
+    }
+
+    #[test]
+    fn embeds_base_field() {
+        let mut rng = thread_rng();
+        let (a, b) = (rng.gen::<Fp32BitPrime>(), rng.gen::<Fp32BitPrime>());
+        assert_eq!(
+            Fp32BitPrimeExt::from(a * b),
+            Fp32BitPrimeExt::from(a) * Fp32BitPrimeExt::from(b)
+        );
+        assert_eq!(
+            Fp32BitPrimeExt::from(a + b),
+            Fp32BitPrimeExt::from(a) + Fp32BitPrimeExt::from(b)
+        );
+    }
+
+    #[test]
+    fn mul() {
+        // (3 + 2i)(1 + 4i) = 3 + 12i + 2i + 8i^2 = -5 + 14i
+        assert_eq!(ext(Fp32BitPrime::PRIME - 5, 14), ext(3, 2) * ext(1, 4));
+    }
+
+    #[test]
+    fn try_from_rejects_non_canonical() {
+        let p = u128::from(Fp32BitPrime::PRIME);
+        assert!(Fp32BitPrimeExt::try_from(p).is_err());
+        assert!(Fp32BitPrimeExt::try_from(p << 32).is_err());
+        assert!(Fp32BitPrimeExt::try_from(1 << 64).is_err());
+        assert_eq!(ext(1, 2), Fp32BitPrimeExt::try_from(2 << 32 | 1).unwrap());
+    }
+
+    #[test]
+    fn deserialize_reduces_non_canonical() {
+        let p = u64::from(Fp32BitPrime::PRIME);
+        for (v, expected) in [
+            (p, ext(0, 0)),
+            (p << 32 | (p + 1), ext(1, 0)),
+            (u64::MAX, ext(4, 4)),
+        ] {
+            let buf = GenericArray::from(v.to_le_bytes());
+            assert_eq!(expected, Fp32BitPrimeExt::deserialize(&buf));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn serde(re in 0..Fp32BitPrime::PRIME, im in 0..Fp32BitPrime::PRIME) {
+            let v = ext(re, im);
+            let mut buf = GenericArray::default();
+            v.serialize(&mut buf);
+
+            assert_eq!(v, Fp32BitPrimeExt::deserialize(&buf));
+        }
+
+        #[test]
+        fn inverse_exists(re in 1..Fp32BitPrime::PRIME, im in 0..Fp32BitPrime::PRIME) {
+            // x^(p^2 - 1) = 1 for any non-zero x, which fails if `i^2 + 1` has a root in GF(p)
+            let x = ext(re, im);
+            let mut acc = Fp32BitPrimeExt::ONE;
+            let mut base = x;
+            let mut exp = u64::from(Fp32BitPrime::PRIME).pow(2) - 1;
+            while exp > 0 {
+                if exp & 1 == 1 {
+                    acc *= base;
+                }
+                base *= base;
+                exp >>= 1;
+            }
+            assert_eq!(Fp32BitPrimeExt::ONE, acc);
+        }
+    }
+}
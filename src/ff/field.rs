@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use typenum::{U1, U4};
+use typenum::{U1, U4, U8};
 
 use crate::{
     error,
@@ -15,6 +15,10 @@ impl Block for u32 {
     type Size = U4;
 }
 
+impl Block for u64 {
+    type Size = U8;
+}
+
 pub trait Field: SharedValue + TryFrom<u128, Error = error::Error> + Into<Self::Storage> {
     /// Multiplicative identity element
     const ONE: Self;
@@ -36,4 +40,5 @@ pub enum FieldType {
     #[cfg(any(test, feature = "weak-field"))]
     Fp31,
     Fp32BitPrime,
+    Fp61BitPrime,
 }
@@ -2,18 +2,20 @@
 //
 // This is where we store arithmetic shared secret data models.
 
+mod extension_field;
 mod field;
 mod galois_field;
 mod prime_field;
 
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
+pub use extension_field::Fp32BitPrimeExt;
 pub use field::{Field, FieldType};
 pub use galois_field::{GaloisField, Gf2, Gf32Bit, Gf3Bit, Gf40Bit, Gf5Bit, Gf8Bit};
 use generic_array::{ArrayLength, GenericArray};
 #[cfg(any(test, feature = "weak-field"))]
 pub use prime_field::Fp31;
-pub use prime_field::{Fp32BitPrime, PrimeField};
+pub use prime_field::{Fp32BitPrime, Fp61BitPrime, PrimeField};
 
 #[derive(Debug, thiserror::Error, PartialEq, Eq)]
 pub enum Error {
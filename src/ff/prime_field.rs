@@ -3,10 +3,12 @@ use generic_array::GenericArray;
 use super::Field;
 use crate::{
     ff::Serializable,
-    secret_sharing::{Block, SharedValue},
+    secret_sharing::{replicated::malicious::ExtendableField, Block, SharedValue},
 };
 
-pub trait PrimeField: Field {
+/// Every prime field can be used in malicious protocols, so it must name the field that carries
+/// the MACs of its values.
+pub trait PrimeField: Field + ExtendableField {
     type PrimeInteger: Into<u128>;
 
     const PRIME: Self::PrimeInteger;
@@ -20,16 +22,55 @@ impl<F: PrimeField> Serializable for F {
         buf.copy_from_slice(raw);
     }
 
+    /// The value is reduced modulo `p`, so that any bytes received from a peer deserialize to
+    /// some element instead of causing a panic.
     fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
         let mut buf_to = [0u8; 16];
         buf_to[..buf.len()].copy_from_slice(buf);
 
-        Self::try_from(u128::from_le_bytes(buf_to)).unwrap()
+        Self::truncate_from(u128::from_le_bytes(buf_to))
     }
 }
 
 macro_rules! field_impl {
     ( $field:ident, $store:ty, $bits:expr, $prime:expr ) => {
+        field_impl! { @common $field, $store, $bits, $prime }
+
+        impl std::ops::Mul for $field {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                debug_assert!(u32::try_from(Self::PRIME).is_ok());
+                let c = u64::from;
+                // TODO(mt) - constant time?
+                #[allow(clippy::cast_possible_truncation)]
+                Self(((c(self.0) * c(rhs.0)) % c(Self::PRIME)) as <Self as SharedValue>::Storage)
+            }
+        }
+    };
+    // Fields with a Mersenne prime modulus `2^bits - 1` reduce products with shifts and additions
+    // instead of a division.
+    ( $field:ident, $store:ty, $bits:expr, $prime:expr, mersenne ) => {
+        field_impl! { @common $field, $store, $bits, $prime }
+
+        impl std::ops::Mul for $field {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                debug_assert_eq!(u128::from(Self::PRIME), (1 << Self::BITS) - 1);
+                let p = u128::from(Self::PRIME);
+                let prod = u128::from(self.0) * u128::from(rhs.0);
+                // 2^bits = 1 (mod p), so the high part of the product can be folded into the
+                // low part. Two folds bring the value below `p + 2`.
+                let r = (prod & p) + (prod >> Self::BITS);
+                let r = (r & p) + (r >> Self::BITS);
+                let r = if r >= p { r - p } else { r };
+                #[allow(clippy::cast_possible_truncation)]
+                Self(r as <Self as SharedValue>::Storage)
+            }
+        }
+    };
+    ( @common $field:ident, $store:ty, $bits:expr, $prime:expr ) => {
         use super::*;
         use crate::ff::FieldType;
 
@@ -113,18 +154,6 @@ macro_rules! field_impl {
             }
         }
 
-        impl std::ops::Mul for $field {
-            type Output = Self;
-
-            fn mul(self, rhs: Self) -> Self::Output {
-                debug_assert!(u32::try_from(Self::PRIME).is_ok());
-                let c = u64::from;
-                // TODO(mt) - constant time?
-                #[allow(clippy::cast_possible_truncation)]
-                Self(((c(self.0) * c(rhs.0)) % c(Self::PRIME)) as <Self as SharedValue>::Storage)
-            }
-        }
-
         impl std::ops::MulAssign for $field {
             #[allow(clippy::assign_op_pattern)]
             fn mul_assign(&mut self, rhs: Self) {
@@ -297,6 +326,53 @@ mod fp32bit {
     }
 }
 
+mod fp61bit {
+    field_impl! { Fp61BitPrime, u64, 61, (1 << 61) - 1, mersenne }
+
+    #[cfg(all(test, unit_test))]
+    mod specialized_tests {
+        use generic_array::GenericArray;
+        use proptest::proptest;
+
+        use super::*;
+        use crate::ff::Serializable;
+
+        #[test]
+        fn sixty_one_bit_prime() {
+            let x = Fp61BitPrime::truncate_from(Fp61BitPrime::PRIME - 1);
+            let y = Fp61BitPrime::truncate_from(Fp61BitPrime::PRIME - 2);
+
+            assert_eq!(x - y, Fp61BitPrime::ONE);
+            assert_eq!(y - x, Fp61BitPrime::truncate_from(Fp61BitPrime::PRIME - 1));
+            assert_eq!(y + x, Fp61BitPrime::truncate_from(Fp61BitPrime::PRIME - 3));
+            assert_eq!(x * y, Fp61BitPrime::truncate_from(2_u32));
+            assert_eq!(x * x, Fp61BitPrime::ONE);
+        }
+
+        #[test]
+        fn deserialize_reduces_non_canonical() {
+            // `2^64 - 1 = 8p + 7`
+            let buf = GenericArray::from([0xff; 8]);
+            assert_eq!(
+                Fp61BitPrime::truncate_from(7_u32),
+                Fp61BitPrime::deserialize(&buf)
+            );
+        }
+
+        proptest! {
+            #[test]
+            fn mersenne_reduction(a in 0..Fp61BitPrime::PRIME, b in 0..Fp61BitPrime::PRIME) {
+                let expected = u128::from(a) * u128::from(b) % u128::from(Fp61BitPrime::PRIME);
+                assert_eq!(
+                    expected,
+                    (Fp61BitPrime::truncate_from(a) * Fp61BitPrime::truncate_from(b)).as_u128()
+                );
+            }
+        }
+    }
+}
+
 #[cfg(any(test, feature = "weak-field"))]
 pub use fp31::Fp31;
 pub use fp32bit::Fp32BitPrime;
+pub use fp61bit::Fp61BitPrime;
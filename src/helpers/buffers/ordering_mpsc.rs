@@ -4,23 +4,25 @@ use std::{
     fmt::{Debug, Formatter},
     num::NonZeroUsize,
     pin::Pin,
-    sync::atomic::Ordering::{AcqRel, Acquire},
     task::{Context, Poll},
 };
 
 use bitvec::{bitvec, vec::BitVec};
 use futures::{FutureExt, Stream};
 use generic_array::GenericArray;
-use tokio::sync::{
-    mpsc::{self, Receiver, Sender},
-    Notify,
-};
 use typenum::Unsigned;
 
 use crate::{
     ff::Serializable,
     helpers::{Error, Message},
-    sync::{atomic::AtomicUsize, Arc},
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering::{AcqRel, Acquire},
+        },
+        mpsc::{self, Receiver, Sender},
+        Arc, Notify,
+    },
 };
 
 pub struct OrderingMpscReceiver<M: Message> {
@@ -164,12 +166,9 @@ impl<M: Message> OrderingMpscReceiver<M> {
     /// Take the next set of values.  This will not resolve until `min_count` values are available.
     pub async fn take_next(&mut self, min_count: usize) -> Option<Vec<u8>> {
         // Read all values that are available before trying to return anything.
-        while let Some(read) = self.rx.recv().now_or_never() {
-            if let Some((index, msg)) = read {
-                self.insert(index, &msg);
-            } else {
-                return None;
-            }
+        // If the channel is closed, values that were already received can still be taken.
+        while let Some(Some((index, msg))) = self.rx.recv().now_or_never() {
+            self.insert(index, &msg);
         }
         loop {
             let output = self.take(min_count);
@@ -265,8 +264,14 @@ impl OrderingMpscEnd {
 
     /// Block asynchronously until the end reaches `until`.
     async fn block(&self, until: usize) {
-        while until >= self.get() {
-            self.notify.notified().await;
+        loop {
+            // Register for notifications before checking, or an `incr` that happens
+            // in between would go unnoticed.
+            let notified = self.notify.notified();
+            if until < self.get() {
+                break;
+            }
+            notified.await;
         }
     }
 }
@@ -409,6 +414,18 @@ mod unit {
         assert!(rx.take_next(1).now_or_never().unwrap().is_none());
     }
 
+    /// ... but values that can be read are still delivered before the receiver reports that.
+    #[tokio::test]
+    async fn drop_after_contiguous() {
+        let (tx, mut rx) = ordering_mpsc("test", NonZeroUsize::new(3).unwrap());
+        tx.send_test(0).await;
+        tx.send_test(1).await;
+        mem::drop(tx);
+        let buf = rx.take_next(1).now_or_never().unwrap().unwrap();
+        assert_eq!(buf.len(), 2 * FP32BIT_SIZE);
+        assert!(rx.take_next(1).now_or_never().unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn gap() {
         let (tx, mut rx) = ordering_mpsc("test", NonZeroUsize::new(3).unwrap());
@@ -479,9 +496,34 @@ mod unit {
 
 #[cfg(all(test, feature = "shuttle"))]
 mod concurrency {
-    use shuttle::{check_random, future::block_on};
+    use std::num::NonZeroUsize;
 
-    use crate::helpers::buffers::ordering_mpsc::fixture::{shuffle_indices, shuffled_send_recv};
+    use futures::future::try_join_all;
+    use generic_array::GenericArray;
+    use shuttle::{
+        check_random,
+        future::{block_on, spawn},
+    };
+
+    use crate::{
+        ff::{Field, Fp32BitPrime, Serializable},
+        helpers::buffers::ordering_mpsc::{
+            fixture::{shuffle_indices, shuffled_send_recv, TestSender, FP32BIT_SIZE},
+            ordering_mpsc,
+        },
+        test_executor::explore,
+    };
+
+    /// Check that `buf` holds the values that `send_test` produces for `indices`.
+    fn assert_values(buf: &[u8], indices: std::ops::Range<usize>) {
+        assert_eq!(buf.len(), indices.len() * FP32BIT_SIZE);
+        for (i, chunk) in indices.zip(buf.chunks(FP32BIT_SIZE)) {
+            assert_eq!(
+                Fp32BitPrime::deserialize(GenericArray::from_slice(chunk)),
+                Fp32BitPrime::truncate_from(u128::try_from(i).unwrap())
+            );
+        }
+    }
 
     #[test]
     fn shuffle() {
@@ -494,6 +536,93 @@ mod concurrency {
             1000,
         );
     }
+
+    /// Several writers, each with its own clone of the sender, send interleaved indices
+    /// while the reader concurrently drains the channel. The channel is much smaller
+    /// than the number of messages, so writers regularly wait for the reader to move the end.
+    #[test]
+    fn concurrent_writers() {
+        const WRITERS: usize = 3;
+        const COUNT: usize = 12;
+
+        explore(|| async {
+            let (tx, mut rx) = ordering_mpsc("test", NonZeroUsize::new(4).unwrap());
+            let writers = (0..WRITERS)
+                .map(|w| {
+                    let tx = tx.clone();
+                    spawn(async move {
+                        for i in (w..COUNT).step_by(WRITERS) {
+                            tx.send_test(i).await;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            drop(tx);
+
+            let mut buf = Vec::new();
+            while let Some(chunk) = rx.take_next(1).await {
+                buf.extend(chunk);
+            }
+            try_join_all(writers).await.unwrap();
+            assert_values(&buf, 0..COUNT);
+        });
+    }
+
+    /// A send for the first index past the end of the channel only completes once
+    /// the reader has taken the values ahead of it.
+    #[test]
+    fn send_at_capacity() {
+        const CAPACITY: usize = 4;
+
+        explore(|| async {
+            let (tx, mut rx) = ordering_mpsc("test", NonZeroUsize::new(CAPACITY).unwrap());
+            let past_end = spawn({
+                let tx = tx.clone();
+                async move { tx.send_test(CAPACITY).await }
+            });
+            let in_range = spawn(async move {
+                for i in (0..CAPACITY).rev() {
+                    tx.send_test(i).await;
+                }
+            });
+
+            let first = rx.take_next(CAPACITY).await.unwrap();
+            assert_values(&first, 0..CAPACITY);
+            let second = rx.take_next(1).await.unwrap();
+            assert_values(&second, CAPACITY..CAPACITY + 1);
+
+            past_end.await.unwrap();
+            in_range.await.unwrap();
+            assert!(rx.take_next(1).await.is_none());
+        });
+    }
+
+    /// Dropping every sender while the reader waits closes the channel, even if there
+    /// is a gap that means some of the received values can never be read.
+    #[test]
+    fn close_during_pending_read() {
+        explore(|| async {
+            let (tx, mut rx) = ordering_mpsc("test", NonZeroUsize::new(4).unwrap());
+            let reader = spawn(async move {
+                let mut buf = Vec::new();
+                while let Some(chunk) = rx.take_next(1).await {
+                    buf.extend(chunk);
+                }
+                buf
+            });
+            let writers = [0, 2]
+                .into_iter()
+                .map(|i| {
+                    let tx = tx.clone();
+                    spawn(async move { tx.send_test(i).await })
+                })
+                .collect::<Vec<_>>();
+            drop(tx);
+
+            try_join_all(writers).await.unwrap();
+            assert_values(&reader.await.unwrap(), 0..1);
+        });
+    }
 }
 
 #[cfg(all(test, unit_test))]
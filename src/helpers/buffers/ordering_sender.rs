@@ -655,3 +655,174 @@ mod test {
         });
     }
 }
+
+#[cfg(all(test, feature = "shuttle"))]
+mod concurrency {
+    use std::num::NonZeroUsize;
+
+    use futures::{future::try_join_all, StreamExt};
+    use generic_array::GenericArray;
+    use shuttle::future::{spawn, JoinHandle};
+    use typenum::Unsigned;
+
+    use super::OrderingSender;
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, Serializable},
+        sync::Arc,
+        test_executor::explore,
+    };
+
+    fn sender(write_size: usize, spare: usize) -> Arc<OrderingSender> {
+        Arc::new(OrderingSender::new(
+            NonZeroUsize::new(write_size).unwrap(),
+            NonZeroUsize::new(spare).unwrap(),
+        ))
+    }
+
+    fn value<M: Field>(i: usize) -> M {
+        M::truncate_from(u128::try_from(i).unwrap())
+    }
+
+    /// Spawn a task that sends `value(i)` for each of `indices`, in that order.
+    fn spawn_writer<M: Field>(sender: &Arc<OrderingSender>, indices: Vec<usize>) -> JoinHandle<()> {
+        let sender = Arc::clone(sender);
+        spawn(async move {
+            for i in indices {
+                sender.send(i, value::<M>(i)).await;
+            }
+        })
+    }
+
+    /// Spawn a task that closes the sender at `i`.
+    fn spawn_close(sender: &Arc<OrderingSender>, i: usize) -> JoinHandle<()> {
+        let sender = Arc::clone(sender);
+        spawn(async move { sender.close(i).await })
+    }
+
+    /// Spawn a task that reads the sender as a stream until it is closed.
+    fn spawn_reader(sender: &Arc<OrderingSender>) -> JoinHandle<Vec<Vec<u8>>> {
+        let sender = Arc::clone(sender);
+        spawn(async move { sender.as_stream().collect::<Vec<_>>().await })
+    }
+
+    /// Check that the stream produced `value(i)` for each `i` in `0..count`, in order,
+    /// and never produced more than `limit` bytes at a time.
+    fn assert_stream<M: Field>(chunks: &[Vec<u8>], count: usize, limit: usize) {
+        let sz = <M as Serializable>::Size::USIZE;
+        assert!(chunks.iter().all(|c| !c.is_empty() && c.len() <= limit));
+        let buf = chunks.concat();
+        assert_eq!(buf.len(), count * sz);
+        for (i, b) in buf.chunks(sz).enumerate() {
+            assert_eq!(M::deserialize(GenericArray::from_slice(b)), value::<M>(i));
+        }
+    }
+
+    /// Every message is sent from its own task, in reverse order, while the buffer
+    /// is only big enough for a few of them.
+    #[test]
+    fn out_of_order_sends() {
+        const COUNT: usize = 8;
+        const WRITE_SIZE: usize = 3;
+        const SPARE: usize = 2;
+
+        explore(|| async {
+            let sender = sender(WRITE_SIZE, SPARE);
+            let writers = (0..COUNT)
+                .rev()
+                .map(|i| spawn_writer::<Fp31>(&sender, vec![i]))
+                .collect::<Vec<_>>();
+            let close = spawn_close(&sender, COUNT);
+            let chunks = spawn_reader(&sender).await.unwrap();
+
+            try_join_all(writers).await.unwrap();
+            close.await.unwrap();
+            assert_stream::<Fp31>(&chunks, COUNT, WRITE_SIZE + SPARE);
+        });
+    }
+
+    /// Writers that each own a strided set of indices race each other and the reader.
+    #[test]
+    fn concurrent_writers() {
+        const WRITERS: usize = 3;
+        const COUNT: usize = 9;
+        const WRITE_SIZE: usize = 8;
+        const SPARE: usize = 5;
+
+        explore(|| async {
+            let sender = sender(WRITE_SIZE, SPARE);
+            let reader = spawn_reader(&sender);
+            let writers = (0..WRITERS)
+                .map(|w| {
+                    spawn_writer::<Fp32BitPrime>(&sender, (w..COUNT).step_by(WRITERS).collect())
+                })
+                .collect::<Vec<_>>();
+            try_join_all(writers).await.unwrap();
+            sender.close(COUNT).await;
+
+            let chunks = reader.await.unwrap();
+            assert_stream::<Fp32BitPrime>(&chunks, COUNT, WRITE_SIZE + SPARE);
+        });
+    }
+
+    /// A reader that is waiting for the buffer to fill is woken by `close` and gets
+    /// whatever is left, even though that is less than the write size.
+    #[test]
+    fn close_during_pending_read() {
+        const COUNT: usize = 2;
+
+        explore(|| async {
+            let sender = sender(16, 2);
+            let reader = spawn_reader(&sender);
+            let writer = spawn_writer::<Fp31>(&sender, (0..COUNT).collect());
+            let close = spawn_close(&sender, COUNT);
+
+            writer.await.unwrap();
+            close.await.unwrap();
+            assert_stream::<Fp31>(&reader.await.unwrap(), COUNT, COUNT);
+        });
+    }
+
+    /// Closing before anything is written ends the stream without producing anything.
+    #[test]
+    fn close_empty_during_pending_read() {
+        explore(|| async {
+            let sender = sender(16, 2);
+            let reader = spawn_reader(&sender);
+            spawn_close(&sender, 0).await.unwrap();
+            assert!(reader.await.unwrap().is_empty());
+        });
+    }
+
+    /// The largest message the spare capacity allows, with the smallest write size.
+    /// Every message makes data available to the stream, but only one fits in the
+    /// buffer, so each of the others has to wait for the reader to make room.
+    #[test]
+    fn minimal_spare() {
+        const COUNT: usize = 6;
+        const SZ: usize = <Fp32BitPrime as Serializable>::Size::USIZE;
+
+        explore(|| async {
+            let sender = sender(1, SZ + 1);
+            let writers = (0..COUNT)
+                .rev()
+                .map(|i| spawn_writer::<Fp32BitPrime>(&sender, vec![i]))
+                .collect::<Vec<_>>();
+            let reader = spawn_reader(&sender);
+            try_join_all(writers).await.unwrap();
+            sender.close(COUNT).await;
+
+            assert_stream::<Fp32BitPrime>(&reader.await.unwrap(), COUNT, 1 + SZ + 1);
+        });
+    }
+
+    /// A message that is no smaller than the spare capacity could deadlock, so
+    /// the sender refuses to write it.
+    #[test]
+    #[should_panic(expected = "expect message size 4 to be less than spare 4")]
+    fn spare_too_small() {
+        explore(|| async {
+            let sender = sender(8, 4);
+            sender.send(0, value::<Fp32BitPrime>(0)).await;
+        });
+    }
+}
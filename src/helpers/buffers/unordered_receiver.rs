@@ -511,3 +511,195 @@ mod test {
         });
     }
 }
+
+#[cfg(all(test, feature = "shuttle"))]
+mod concurrency {
+    use std::num::NonZeroUsize;
+
+    use futures::{
+        channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+        future::try_join_all,
+        stream::poll_fn,
+        Stream,
+    };
+    use generic_array::GenericArray;
+    use shuttle::future::{spawn, yield_now, JoinHandle};
+    use typenum::Unsigned;
+
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, Serializable},
+        helpers::{
+            buffers::{unordered_receiver::UnorderedReceiver, OrderingSender},
+            Error,
+        },
+        protocol::RecordId,
+        sync::Arc,
+        test_executor::explore,
+    };
+
+    /// Small enough for reads to regularly overflow the tracked wakers.
+    const CAPACITY: usize = 3;
+
+    fn value<M: Field>(i: usize) -> M {
+        M::truncate_from(u128::try_from(i).unwrap())
+    }
+
+    fn encode<M: Field>(count: usize) -> Vec<u8> {
+        let sz = <M as Serializable>::Size::USIZE;
+        let mut buf = vec![0; count * sz];
+        for (i, b) in buf.chunks_mut(sz).enumerate() {
+            value::<M>(i).serialize(GenericArray::from_mut_slice(b));
+        }
+        buf
+    }
+
+    type ChannelReceiver = UnorderedReceiver<UnboundedReceiver<Vec<u8>>, Vec<u8>>;
+
+    /// Make a receiver that is fed by a channel, so that data arrives while reads are pending.
+    fn receiver() -> (UnboundedSender<Vec<u8>>, ChannelReceiver) {
+        let (tx, rx) = unbounded();
+        let recv = UnorderedReceiver::new(Box::pin(rx), NonZeroUsize::new(CAPACITY).unwrap());
+        (tx, recv)
+    }
+
+    /// Spawn a task that feeds `buf` to the receiver in chunks of the given sizes,
+    /// yielding after each, then closes the stream.
+    fn spawn_feed(
+        tx: UnboundedSender<Vec<u8>>,
+        mut buf: Vec<u8>,
+        chunk_sizes: &'static [usize],
+    ) -> JoinHandle<()> {
+        spawn(async move {
+            for &sz in chunk_sizes.iter().cycle() {
+                if buf.is_empty() {
+                    break;
+                }
+                let rest = buf.split_off(sz.min(buf.len()));
+                tx.unbounded_send(buf).unwrap();
+                buf = rest;
+                yield_now().await;
+            }
+        })
+    }
+
+    /// Spawn a task for each index, in the given order, that reads `value(i)` from the receiver.
+    fn spawn_readers<M, S, I>(
+        recv: &UnorderedReceiver<S, Vec<u8>>,
+        indices: I,
+    ) -> Vec<JoinHandle<()>>
+    where
+        M: Field,
+        S: Stream<Item = Vec<u8>> + Send + 'static,
+        I: IntoIterator<Item = usize>,
+    {
+        indices
+            .into_iter()
+            .map(|i| {
+                let recv = recv.clone();
+                spawn(async move {
+                    let m: M = recv.recv(i).await.unwrap();
+                    assert_eq!(m, value::<M>(i));
+                })
+            })
+            .collect()
+    }
+
+    /// Reads are started furthest into the future first, while data trickles in
+    /// with chunks that don't line up with the reads.
+    #[test]
+    fn out_of_order_reads() {
+        const COUNT: usize = 10;
+
+        explore(|| async {
+            let (tx, recv) = receiver();
+            let readers = spawn_readers::<Fp31, _, _>(&recv, (0..COUNT).rev());
+            spawn_feed(tx, encode::<Fp31>(COUNT), &[3, 1, 4])
+                .await
+                .unwrap();
+            try_join_all(readers).await.unwrap();
+        });
+    }
+
+    /// Every byte arrives on its own, so every message is assembled from spare data.
+    #[test]
+    fn byte_by_byte() {
+        const COUNT: usize = 4;
+
+        explore(|| async {
+            let (tx, recv) = receiver();
+            let feed = spawn_feed(tx, encode::<Fp32BitPrime>(COUNT), &[1]);
+            let readers = spawn_readers::<Fp32BitPrime, _, _>(&recv, [2, 0, 3, 1]);
+            try_join_all(readers).await.unwrap();
+            feed.await.unwrap();
+        });
+    }
+
+    /// A read that is waiting for data when the stream ends fails, even if part of the
+    /// message it is waiting for has been received.
+    #[test]
+    fn close_during_pending_read() {
+        const COUNT: usize = 2;
+        const SZ: usize = <Fp32BitPrime as Serializable>::Size::USIZE;
+
+        explore(|| async {
+            let (tx, recv) = receiver();
+            let last = spawn({
+                let recv = recv.clone();
+                async move {
+                    let res = recv.recv::<Fp32BitPrime, _>(COUNT).await;
+                    assert!(matches!(
+                        res,
+                        Err(Error::EndOfStream { record_id }) if record_id == RecordId::from(COUNT)
+                    ));
+                }
+            });
+            let readers = spawn_readers::<Fp32BitPrime, _, _>(&recv, 0..COUNT);
+
+            let mut buf = encode::<Fp32BitPrime>(COUNT + 1);
+            buf.truncate(COUNT * SZ + SZ / 2);
+            spawn_feed(tx, buf, &[SZ]).await.unwrap();
+
+            try_join_all(readers).await.unwrap();
+            last.await.unwrap();
+        });
+    }
+
+    /// The whole buffer layer: concurrent writers race to send to an `OrderingSender`,
+    /// and concurrent readers take the messages out of an `UnorderedReceiver` that reads
+    /// from it, also out of order.
+    #[test]
+    fn ordering_sender_to_unordered_receiver() {
+        const WRITERS: usize = 2;
+        const COUNT: usize = 8;
+
+        explore(|| async {
+            let sender = Arc::new(OrderingSender::new(
+                NonZeroUsize::new(8).unwrap(),
+                NonZeroUsize::new(5).unwrap(),
+            ));
+            let recv = UnorderedReceiver::new(
+                Box::pin(poll_fn({
+                    let sender = Arc::clone(&sender);
+                    move |cx| sender.take_next(cx)
+                })),
+                NonZeroUsize::new(CAPACITY).unwrap(),
+            );
+
+            let readers = spawn_readers::<Fp32BitPrime, _, _>(&recv, (0..COUNT).rev());
+            let writers = (0..WRITERS)
+                .map(|w| {
+                    let sender = Arc::clone(&sender);
+                    spawn(async move {
+                        for i in (w..COUNT).step_by(WRITERS) {
+                            sender.send(i, value::<Fp32BitPrime>(i)).await;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            sender.close(COUNT).await;
+
+            try_join_all(writers).await.unwrap();
+            try_join_all(readers).await.unwrap();
+        });
+    }
+}
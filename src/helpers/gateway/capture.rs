@@ -0,0 +1,514 @@
+//! Record of everything a helper saw while running a query.
+//!
+//! A gateway created with [`Gateway::with_capture`] writes every message it sends or receives to a
+//! [`Capture`], together with the query it runs, the seed it negotiated PRSS with and its input.
+//! That is enough to run this helper's side of the query again without its peers, see
+//! [`crate::query::replay`].
+//!
+//! A capture is a file with one JSON object per line. Messages are written in the order they were
+//! sent or received, which is not the order of their record ids. Received messages are captured
+//! as they arrive from the peer, whether or not the helper reads them.
+//!
+//! [`Gateway::with_capture`]: crate::helpers::Gateway::with_capture
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll},
+    thread::{self, JoinHandle},
+};
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::{
+    helpers::{query::PrepareQuery, ChannelId, Role},
+    protocol::{step::Gate, RecordId},
+    sync::{mpsc, Arc},
+};
+
+/// Seed of the random number generator a helper negotiates PRSS with.
+pub type PrssSeed = [u8; 32];
+
+/// Messages on one channel, indexed by record id.
+pub type ChannelRecords = BTreeMap<RecordId, Vec<u8>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("line {line} of the capture is malformed: {source}")]
+    Malformed {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("the capture has no {0}")]
+    Missing(&'static str),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Query {
+        role: Role,
+        prepare: PrepareQuery,
+    },
+    PrssSeed {
+        #[serde(with = "hex")]
+        seed: PrssSeed,
+    },
+    Input {
+        #[serde(with = "hex")]
+        data: Vec<u8>,
+    },
+    Sent {
+        peer: Role,
+        gate: String,
+        record_id: RecordId,
+        #[serde(with = "hex")]
+        data: Vec<u8>,
+    },
+    Received {
+        peer: Role,
+        gate: String,
+        record_id: RecordId,
+        #[serde(with = "hex")]
+        data: Vec<u8>,
+    },
+}
+
+/// What the writer thread is asked to do.
+enum Message {
+    Write(Event),
+    /// Flush the sink and tell the sender once everything before this message is written.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Writes a capture. Failing to write does not fail the query: the first error is logged and
+/// everything after it is dropped.
+///
+/// Events are written to the sink by a thread of their own, so that helpers never block on it.
+/// Dropping the capture waits for that thread to write and flush everything recorded before, and
+/// so does [`Capture::flush`], for captures that are still shared with a gateway.
+pub struct Capture {
+    events: Option<mpsc::UnboundedSender<Message>>,
+    writer: Option<JoinHandle<()>>,
+    /// Shared with the writer, which is a thread rather than a task.
+    failed: std::sync::Arc<AtomicBool>,
+}
+
+impl Capture {
+    /// Creates a capture that is written to `sink`.
+    ///
+    /// ## Panics
+    /// If the thread that writes to `sink` can't be started.
+    pub fn new<W: Write + Send + 'static>(sink: W) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let failed = std::sync::Arc::new(AtomicBool::new(false));
+        let writer = thread::Builder::new()
+            .name("capture".to_owned())
+            .spawn({
+                let failed = std::sync::Arc::clone(&failed);
+                move || write_events(sink, rx, &failed)
+            })
+            .expect("can start the capture writer thread");
+        Self {
+            events: Some(tx),
+            writer: Some(writer),
+            failed,
+        }
+    }
+
+    /// Creates a capture that is written to the file at `path`.
+    ///
+    /// ## Errors
+    /// If the file can't be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Records the query this helper runs, and its role in it.
+    pub fn record_query(&self, role: Role, prepare: &PrepareQuery) {
+        self.write(Event::Query {
+            role,
+            prepare: prepare.clone(),
+        });
+    }
+
+    pub fn record_prss_seed(&self, seed: &PrssSeed) {
+        self.write(Event::PrssSeed { seed: *seed });
+    }
+
+    /// Records the next chunk of the query input.
+    pub fn record_input(&self, data: &[u8]) {
+        self.write(Event::Input {
+            data: data.to_vec(),
+        });
+    }
+
+    pub(super) fn record_sent(&self, channel_id: &ChannelId, record_id: RecordId, data: &[u8]) {
+        self.write(Event::Sent {
+            peer: channel_id.role,
+            gate: channel_id.gate.as_ref().to_owned(),
+            record_id,
+            data: data.to_vec(),
+        });
+    }
+
+    pub(super) fn record_received(&self, channel_id: &ChannelId, record_id: RecordId, data: &[u8]) {
+        self.write(Event::Received {
+            peer: channel_id.role,
+            gate: channel_id.gate.as_ref().to_owned(),
+            record_id,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Waits until everything recorded so far is written to the sink and flushed. Returns
+    /// immediately if writing the capture failed.
+    ///
+    /// ## Panics
+    /// Never: the sender of events is only taken when the capture is dropped.
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        // The writer only stops early if it failed, and then there is nothing to wait for.
+        if self
+            .events
+            .as_ref()
+            .unwrap()
+            .send(Message::Flush(tx))
+            .is_ok()
+        {
+            let _ = rx.await;
+        }
+    }
+
+    fn write(&self, event: Event) {
+        if self.failed.load(Ordering::Relaxed) {
+            return;
+        }
+        // The writer only stops early if it failed, and then the event is dropped anyway.
+        let _ = self.events.as_ref().unwrap().send(Message::Write(event));
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        drop(self.events.take());
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                tracing::warn!("capture writer panicked, the capture is incomplete");
+            }
+        }
+    }
+}
+
+/// Writes `events` to `sink` until the capture is dropped. The sink is flushed whenever there are
+/// no more events to write, or when asked to.
+fn write_events<W: Write>(
+    mut sink: W,
+    mut events: mpsc::UnboundedReceiver<Message>,
+    failed: &AtomicBool,
+) {
+    fn write_message<W: Write>(sink: &mut W, message: Message) -> io::Result<()> {
+        match message {
+            Message::Write(event) => {
+                serde_json::to_writer(&mut *sink, &event)?;
+                sink.write_all(b"\n")
+            }
+            Message::Flush(done) => {
+                sink.flush()?;
+                let _ = done.send(());
+                Ok(())
+            }
+        }
+    }
+
+    let mut write_all = || -> io::Result<()> {
+        while let Some(message) = events.blocking_recv() {
+            write_message(&mut sink, message)?;
+            while let Ok(message) = events.try_recv() {
+                write_message(&mut sink, message)?;
+            }
+            sink.flush()?;
+        }
+        Ok(())
+    };
+    if let Err(e) = write_all() {
+        failed.store(true, Ordering::Relaxed);
+        tracing::warn!("failed to write capture, it will be incomplete: {e}");
+    }
+}
+
+/// Stream of the data a helper receives from a peer, that records every message in it to a
+/// capture as it arrives.
+pub(super) struct CapturedStream<S> {
+    inner: S,
+    received: Option<ReceivedMessages>,
+}
+
+struct ReceivedMessages {
+    capture: Arc<Capture>,
+    channel_id: ChannelId,
+    message_size: usize,
+    /// Start of a message that the peer sent in the next chunk.
+    pending: Vec<u8>,
+    next_record: usize,
+}
+
+impl<S> CapturedStream<S> {
+    /// Records messages of `message_size` bytes that arrive on `channel_id` to `capture`, if
+    /// there is one.
+    pub fn new(
+        inner: S,
+        capture: Option<&Arc<Capture>>,
+        channel_id: &ChannelId,
+        message_size: usize,
+    ) -> Self {
+        Self {
+            inner,
+            received: capture.map(|capture| ReceivedMessages {
+                capture: Arc::clone(capture),
+                channel_id: channel_id.clone(),
+                message_size,
+                pending: Vec::new(),
+                next_record: 0,
+            }),
+        }
+    }
+}
+
+impl ReceivedMessages {
+    fn record(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        let mut messages = self.pending.chunks_exact(self.message_size);
+        for message in &mut messages {
+            self.capture.record_received(
+                &self.channel_id,
+                RecordId::from(self.next_record),
+                message,
+            );
+            self.next_record += 1;
+        }
+        let rest = messages.remainder().len();
+        self.pending.drain(..self.pending.len() - rest);
+    }
+}
+
+impl<S: Stream<Item = Vec<u8>> + Unpin> Stream for CapturedStream<S> {
+    type Item = Vec<u8>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+        let chunk = this.inner.poll_next_unpin(cx);
+        if let (Poll::Ready(Some(chunk)), Some(received)) = (&chunk, &mut this.received) {
+            received.record(chunk);
+        }
+        chunk
+    }
+}
+
+/// A capture, read back.
+#[derive(Debug)]
+pub struct Recording {
+    pub role: Role,
+    pub prepare: PrepareQuery,
+    pub prss_seed: PrssSeed,
+    /// Query input as this helper received it.
+    pub input: Vec<u8>,
+    /// Messages this helper sent, per channel to a peer.
+    pub sent: HashMap<ChannelId, ChannelRecords>,
+    /// Messages this helper received, per channel from a peer.
+    pub received: HashMap<ChannelId, ChannelRecords>,
+}
+
+impl Recording {
+    /// Reads the capture written to the file at `path`.
+    ///
+    /// ## Errors
+    /// If the file can't be read or does not contain a capture.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, CaptureError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a capture from `reader`.
+    ///
+    /// ## Errors
+    /// If `reader` fails or does not contain a capture.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, CaptureError> {
+        let mut query = None;
+        let mut prss_seed = None;
+        let mut input = Vec::new();
+        let mut sent = HashMap::<_, ChannelRecords>::new();
+        let mut received = HashMap::<_, ChannelRecords>::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let event = serde_json::from_str(&line).map_err(|source| CaptureError::Malformed {
+                line: i + 1,
+                source,
+            })?;
+            match event {
+                Event::Query { role, prepare } => query = Some((role, prepare)),
+                Event::PrssSeed { seed } => prss_seed = Some(seed),
+                Event::Input { data } => input.extend(data),
+                Event::Sent {
+                    peer,
+                    gate,
+                    record_id,
+                    data,
+                } => {
+                    let channel_id = ChannelId::new(peer, Gate::from(gate.as_str()));
+                    sent.entry(channel_id).or_default().insert(record_id, data);
+                }
+                Event::Received {
+                    peer,
+                    gate,
+                    record_id,
+                    data,
+                } => {
+                    let channel_id = ChannelId::new(peer, Gate::from(gate.as_str()));
+                    received
+                        .entry(channel_id)
+                        .or_default()
+                        .insert(record_id, data);
+                }
+            }
+        }
+
+        let (role, prepare) = query.ok_or(CaptureError::Missing("query"))?;
+        Ok(Self {
+            role,
+            prepare,
+            prss_seed: prss_seed.ok_or(CaptureError::Missing("PRSS seed"))?,
+            input,
+            sent,
+            received,
+        })
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use futures::{executor::block_on, stream, StreamExt};
+
+    use super::{Capture, CaptureError, CapturedStream, Recording};
+    use crate::{
+        ff::FieldType,
+        helpers::{
+            query::{PrepareQuery, QueryConfig, QueryType},
+            ChannelId, HelperIdentity, Role, RoleAssignment,
+        },
+        protocol::{
+            step::{Gate, StepNarrow},
+            QueryId, RecordId,
+        },
+    };
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn prepare() -> PrepareQuery {
+        PrepareQuery {
+            query_id: QueryId,
+            config: QueryConfig::new(QueryType::TestMultiply, FieldType::Fp31, 1).unwrap(),
+            roles: RoleAssignment::new(HelperIdentity::make_three()),
+        }
+    }
+
+    #[test]
+    fn read_back() {
+        let buffer = Buffer::default();
+        let capture = Capture::new(buffer.clone());
+        let channel_id = ChannelId::new(Role::H3, Gate::default().narrow("step"));
+        capture.record_query(Role::H2, &prepare());
+        capture.record_prss_seed(&[7; 32]);
+        capture.record_input(&[1, 2]);
+        capture.record_sent(&channel_id, RecordId::from(1), &[5]);
+        capture.record_received(&channel_id, RecordId::from(1), &[6]);
+        capture.record_sent(&channel_id, RecordId::from(0), &[4]);
+        capture.record_input(&[3]);
+        drop(capture);
+
+        let recording = Recording::from_reader(buffer.0.lock().unwrap().as_slice()).unwrap();
+        assert_eq!(Role::H2, recording.role);
+        assert_eq!(prepare(), recording.prepare);
+        assert_eq!([7; 32], recording.prss_seed);
+        assert_eq!(vec![1, 2, 3], recording.input);
+        assert_eq!(
+            vec![vec![4], vec![5]],
+            recording.sent[&channel_id]
+                .values()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(RecordId::from(1), vec![6])],
+            recording.received[&channel_id]
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn received_across_chunks() {
+        let buffer = Buffer::default();
+        let capture = Arc::new(Capture::new(buffer.clone()));
+        let channel_id = ChannelId::new(Role::H1, Gate::default().narrow("step"));
+        capture.record_query(Role::H2, &prepare());
+        capture.record_prss_seed(&[7; 32]);
+        let chunks = stream::iter(vec![vec![1, 2, 3], vec![4, 5], vec![6]]);
+        let received = CapturedStream::new(chunks, Some(&capture), &channel_id, 2);
+        assert_eq!(3, block_on(received.count()));
+        drop(capture);
+
+        let recording = Recording::from_reader(buffer.0.lock().unwrap().as_slice()).unwrap();
+        assert_eq!(
+            vec![
+                (RecordId::from(0), vec![1, 2]),
+                (RecordId::from(1), vec![3, 4]),
+                (RecordId::from(2), vec![5, 6]),
+            ],
+            recording.received[&channel_id]
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn incomplete() {
+        let buffer = Buffer::default();
+        Capture::new(buffer.clone()).record_query(Role::H1, &prepare());
+
+        let r = Recording::from_reader(buffer.0.lock().unwrap().as_slice());
+        assert!(matches!(r, Err(CaptureError::Missing("PRSS seed"))));
+    }
+
+    #[test]
+    fn malformed() {
+        let r = Recording::from_reader(&b"{\"event\":\"prss_seed\"}\nnot json\n"[..]);
+        assert!(matches!(r, Err(CaptureError::Malformed { line: 1, .. })));
+    }
+}
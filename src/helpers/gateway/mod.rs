@@ -1,16 +1,24 @@
+pub mod capture;
 mod receive;
+#[cfg(any(test, feature = "test-fixture"))]
+pub mod rounds;
 mod send;
 mod transport;
 
 use std::{fmt::Debug, num::NonZeroUsize};
 
+#[cfg(any(test, feature = "test-fixture"))]
+use rounds::RoundCounter;
 pub use send::SendingEnd;
+#[cfg(any(test, feature = "test-fixture"))]
+pub use send::{SendAction, SendHook};
 #[cfg(all(feature = "shuttle", test))]
 use shuttle::future as tokio;
 
 use crate::{
     helpers::{
         gateway::{
+            capture::Capture,
             receive::{GatewayReceivers, ReceivingEnd as ReceivingEndBase},
             send::GatewaySenders,
             transport::RoleResolvingTransport,
@@ -18,6 +26,8 @@ use crate::{
         ChannelId, Message, Role, RoleAssignment, TotalRecords, Transport,
     },
     protocol::QueryId,
+    query::ProgressTracker,
+    sync::Arc,
 };
 
 /// Alias for the currently configured transport.
@@ -43,6 +53,7 @@ pub struct Gateway<T: Transport = TransportImpl> {
     transport: RoleResolvingTransport<T>,
     senders: GatewaySenders,
     receivers: GatewayReceivers<T>,
+    progress: Arc<ProgressTracker>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -70,9 +81,47 @@ impl<T: Transport> Gateway<T> {
             },
             senders: GatewaySenders::default(),
             receivers: GatewayReceivers::default(),
+            progress: Arc::default(),
         }
     }
 
+    /// Writes every message this gateway sends or receives to `capture`.
+    #[must_use]
+    pub fn with_capture(mut self, capture: Arc<Capture>) -> Self {
+        self.senders.capture = Some(Arc::clone(&capture));
+        self.receivers.capture = Some(capture);
+        self
+    }
+
+    /// The capture this gateway writes to, if any.
+    #[must_use]
+    pub fn capture(&self) -> Option<&Arc<Capture>> {
+        self.receivers.capture.as_ref()
+    }
+
+    /// Records the depth of every message this gateway sends or receives in `counter`. The
+    /// gateways of all helpers must share the counter.
+    #[cfg(any(test, feature = "test-fixture"))]
+    #[must_use]
+    pub fn with_round_counter(mut self, counter: Arc<RoundCounter>) -> Self {
+        let rounds = rounds::HelperRounds {
+            role: self.role(),
+            counter,
+        };
+        self.senders.rounds = Some(rounds.clone());
+        self.receivers.rounds = Some(rounds);
+        self
+    }
+
+    /// Passes every message this gateway sends through `hook`, to simulate a helper that deviates
+    /// from the protocol.
+    #[cfg(any(test, feature = "test-fixture"))]
+    #[must_use]
+    pub fn with_send_hook(mut self, hook: Arc<dyn SendHook>) -> Self {
+        self.senders.send_hook = Some(hook);
+        self
+    }
+
     #[must_use]
     pub fn role(&self) -> Role {
         self.transport.role()
@@ -83,6 +132,12 @@ impl<T: Transport> Gateway<T> {
         &self.config
     }
 
+    /// Progress of the query this gateway serves, based on the channels opened so far.
+    #[must_use]
+    pub fn progress(&self) -> &Arc<ProgressTracker> {
+        &self.progress
+    }
+
     ///
     /// ## Panics
     /// If there is a failure connecting via HTTP
@@ -92,10 +147,15 @@ impl<T: Transport> Gateway<T> {
         channel_id: &ChannelId,
         total_records: TotalRecords,
     ) -> SendingEnd<M> {
-        let (tx, maybe_stream) =
-            self.senders
-                .get_or_create::<M>(channel_id, self.config.active_work(), total_records);
+        let (tx, maybe_stream) = self.senders.get_or_create::<M>(
+            channel_id,
+            self.role(),
+            self.config.active_work(),
+            total_records,
+            &self.progress,
+        );
         if let Some(stream) = maybe_stream {
+            self.progress.enter(&channel_id.gate);
             tokio::spawn({
                 let channel_id = channel_id.clone();
                 let transport = self.transport.clone();
@@ -114,11 +174,17 @@ impl<T: Transport> Gateway<T> {
 
     #[must_use]
     pub fn get_receiver<M: Message>(&self, channel_id: &ChannelId) -> ReceivingEndBase<T, M> {
-        ReceivingEndBase::new(
+        let receiving_end = ReceivingEndBase::new(
             channel_id.clone(),
-            self.receivers
-                .get_or_create(channel_id, || self.transport.receive(channel_id)),
-        )
+            self.receivers.get_or_create(channel_id, || {
+                self.transport
+                    .receive::<M>(channel_id, self.receivers.capture.as_ref())
+            }),
+        );
+        #[cfg(any(test, feature = "test-fixture"))]
+        let receiving_end = receiving_end.with_rounds(self.receivers.rounds.clone());
+
+        receiving_end
     }
 }
 
@@ -1,39 +1,54 @@
 use std::marker::PhantomData;
 
 use dashmap::{mapref::entry::Entry, DashMap};
-use futures::Stream;
 
+#[cfg(any(test, feature = "test-fixture"))]
+use crate::helpers::gateway::rounds::HelperRounds;
 use crate::{
-    helpers::{buffers::UnorderedReceiver, ChannelId, Error, Message, Transport},
+    helpers::{
+        buffers::UnorderedReceiver,
+        gateway::capture::{Capture, CapturedStream},
+        ChannelId, Error, Message, Transport,
+    },
     protocol::RecordId,
+    sync::Arc,
 };
 
 /// Receiving end end of the gateway channel.
 pub struct ReceivingEnd<T: Transport, M: Message> {
     channel_id: ChannelId,
     unordered_rx: UR<T>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    rounds: Option<HelperRounds>,
     _phantom: PhantomData<M>,
 }
 
 /// Receiving channels, indexed by (role, step).
 pub(super) struct GatewayReceivers<T: Transport> {
     inner: DashMap<ChannelId, UR<T>>,
+    pub(super) capture: Option<Arc<Capture>>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    pub(super) rounds: Option<HelperRounds>,
 }
 
-pub(super) type UR<T> = UnorderedReceiver<
-    <T as Transport>::RecordsStream,
-    <<T as Transport>::RecordsStream as Stream>::Item,
->;
+pub(super) type UR<T> = UnorderedReceiver<CapturedStream<<T as Transport>::RecordsStream>, Vec<u8>>;
 
 impl<T: Transport, M: Message> ReceivingEnd<T, M> {
     pub(super) fn new(channel_id: ChannelId, rx: UR<T>) -> Self {
         Self {
             channel_id,
             unordered_rx: rx,
+            #[cfg(any(test, feature = "test-fixture"))]
+            rounds: None,
             _phantom: PhantomData,
         }
     }
 
+    #[cfg(any(test, feature = "test-fixture"))]
+    pub(super) fn with_rounds(self, rounds: Option<HelperRounds>) -> Self {
+        Self { rounds, ..self }
+    }
+
     /// Receive message associated with the given record id. This method does not return until
     /// message is actually received and deserialized.
     ///
@@ -44,14 +59,21 @@ impl<T: Transport, M: Message> ReceivingEnd<T, M> {
     /// This will panic if message size does not fit into 8 bytes and it somehow got serialized
     /// and sent to this helper.
     pub async fn receive(&self, record_id: RecordId) -> Result<M, Error> {
-        self.unordered_rx
+        let msg = self
+            .unordered_rx
             .recv::<M, _>(record_id)
             .await
             .map_err(|e| Error::ReceiveError {
                 source: self.channel_id.role,
                 step: self.channel_id.gate.to_string(),
                 inner: Box::new(e),
-            })
+            })?;
+        #[cfg(any(test, feature = "test-fixture"))]
+        if let Some(rounds) = &self.rounds {
+            rounds.record_received(&self.channel_id, record_id);
+        }
+
+        Ok(msg)
     }
 }
 
@@ -59,6 +81,9 @@ impl<T: Transport> Default for GatewayReceivers<T> {
     fn default() -> Self {
         Self {
             inner: DashMap::default(),
+            capture: None,
+            #[cfg(any(test, feature = "test-fixture"))]
+            rounds: None,
         }
     }
 }
@@ -0,0 +1,146 @@
+//! Counts the communication rounds of a query from the dependencies between its messages.
+//!
+//! Every message is given a depth when it is sent: one more than the deepest message its sender
+//! has received for the same record, or zero if there is none. Messages of a gate that are `n`
+//! deep can't be sent before `n` messages have gone back and forth, one after another, so the
+//! depths on the gates of a step give the number of rounds on its critical path.
+//!
+//! Records are tracked separately, so that records processed at the same time don't add to each
+//! other's depth. A message that depends on messages for other records is only as deep as its own
+//! record, which is exact as long as all records go through the same rounds, as they do in IPA.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    helpers::{ChannelId, Role},
+    protocol::{step::Gate, RecordId},
+    sync::{Arc, Mutex},
+};
+
+/// Depths of the messages the helpers of one query send to each other. It is shared by their
+/// gateways, see [`Gateway::with_round_counter`].
+///
+/// [`Gateway::with_round_counter`]: crate::helpers::Gateway::with_round_counter
+#[derive(Default)]
+pub struct RoundCounter {
+    inner: Mutex<Depths>,
+}
+
+#[derive(Default)]
+struct Depths {
+    /// Depth of the next message a helper sends for a record.
+    next: HashMap<(Role, RecordId), u64>,
+    /// Depth of the messages sent but not received yet, by sender, receiver, gate and record.
+    in_flight: HashMap<(Role, Role, Gate, RecordId), u64>,
+    /// Depths of the messages sent on a gate.
+    gates: HashMap<Gate, Range<u64>>,
+}
+
+impl RoundCounter {
+    /// Rounds that the messages on each gate were sent in. The range starts at the depth of the
+    /// shallowest message and ends after the deepest one.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn gate_rounds(&self) -> Vec<(Gate, Range<u64>)> {
+        let depths = self.inner.lock().unwrap();
+        depths
+            .gates
+            .iter()
+            .map(|(gate, rounds)| (gate.clone(), rounds.clone()))
+            .collect()
+    }
+
+    /// Number of rounds on the critical path of everything that was sent so far.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn rounds(&self) -> u64 {
+        let depths = self.inner.lock().unwrap();
+        depths.gates.values().map(|r| r.end).max().unwrap_or(0)
+    }
+}
+
+/// A [`RoundCounter`] as seen by one of the helpers.
+#[derive(Clone)]
+pub(super) struct HelperRounds {
+    pub role: Role,
+    pub counter: Arc<RoundCounter>,
+}
+
+impl HelperRounds {
+    pub fn record_sent(&self, channel_id: &ChannelId, record_id: RecordId) {
+        let mut depths = self.counter.inner.lock().unwrap();
+        let depth = depths
+            .next
+            .get(&(self.role, record_id))
+            .copied()
+            .unwrap_or_default();
+        depths.in_flight.insert(
+            (
+                self.role,
+                channel_id.role,
+                channel_id.gate.clone(),
+                record_id,
+            ),
+            depth,
+        );
+        let rounds = depths
+            .gates
+            .entry(channel_id.gate.clone())
+            .or_insert(depth..depth + 1);
+        rounds.start = rounds.start.min(depth);
+        rounds.end = rounds.end.max(depth + 1);
+    }
+
+    pub fn record_received(&self, channel_id: &ChannelId, record_id: RecordId) {
+        let mut depths = self.counter.inner.lock().unwrap();
+        let key = (
+            channel_id.role,
+            self.role,
+            channel_id.gate.clone(),
+            record_id,
+        );
+        if let Some(depth) = depths.in_flight.remove(&key) {
+            let next = depths.next.entry((self.role, record_id)).or_default();
+            *next = (*next).max(depth + 1);
+        }
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::{HelperRounds, RoundCounter};
+    use crate::{
+        helpers::{ChannelId, Role},
+        protocol::{step::Gate, RecordId},
+        sync::Arc,
+    };
+
+    #[test]
+    fn depth_follows_records() {
+        let counter = Arc::new(RoundCounter::default());
+        let [h1, h2, _] = (*Role::all()).map(|role| HelperRounds {
+            role,
+            counter: Arc::clone(&counter),
+        });
+        let channel = |role, gate| ChannelId::new(role, Gate::from(gate));
+
+        // two messages for record 0 one after another, record 1 in parallel
+        h1.record_sent(&channel(Role::H2, "a"), RecordId::from(0));
+        h1.record_sent(&channel(Role::H2, "a"), RecordId::from(1));
+        h2.record_received(&channel(Role::H1, "a"), RecordId::from(0));
+        h2.record_sent(&channel(Role::H1, "b"), RecordId::from(0));
+        h2.record_sent(&channel(Role::H1, "b"), RecordId::from(1));
+
+        let mut rounds = counter.gate_rounds();
+        rounds.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        assert_eq!(
+            vec![(Gate::from("a"), 0..1), (Gate::from("b"), 0..2)],
+            rounds
+        );
+        assert_eq!(2, counter.rounds());
+    }
+}
@@ -7,15 +7,22 @@ use std::{
 
 use dashmap::{mapref::entry::Entry, DashMap};
 use futures::Stream;
+use generic_array::GenericArray;
 use typenum::Unsigned;
 
+#[cfg(any(test, feature = "test-fixture"))]
+use crate::helpers::gateway::rounds::HelperRounds;
 use crate::{
-    helpers::{buffers::OrderingSender, ChannelId, Error, Message, Role, TotalRecords},
+    helpers::{
+        buffers::OrderingSender, gateway::capture::Capture, ChannelId, Error, Message, Role,
+        TotalRecords,
+    },
     protocol::RecordId,
+    query::ProgressTracker,
     sync::Arc,
     telemetry::{
         labels::{ROLE, STEP},
-        metrics::{BYTES_SENT, RECORDS_SENT},
+        metrics::{BYTES_SENT, RECORDS_SENT, STEP_BYTES_SENT},
     },
 };
 
@@ -27,28 +34,86 @@ pub struct SendingEnd<M: Message> {
     _phantom: PhantomData<M>,
 }
 
+/// Intercepts messages before a gateway sends them. Test fixtures use it to make a helper deviate
+/// from the protocol.
+#[cfg(any(test, feature = "test-fixture"))]
+pub trait SendHook: Send + Sync {
+    /// Called with the serialized message for `record_id` before it is sent over `channel_id`,
+    /// which carries `total_records`. Returns what the gateway sends instead.
+    fn on_send(
+        &self,
+        channel_id: &ChannelId,
+        record_id: RecordId,
+        total_records: TotalRecords,
+        data: &[u8],
+    ) -> SendAction;
+}
+
+/// What a gateway does with a message after a [`SendHook`] has seen it.
+#[cfg(any(test, feature = "test-fixture"))]
+#[derive(Debug)]
+pub enum SendAction {
+    /// Sends these serialized messages, each for its record. They may belong to other records
+    /// than the one the hook was called with, and the list may be empty if the hook holds the
+    /// message back. Every record must eventually be sent exactly once, unless the stream ends.
+    Send(Vec<(RecordId, Vec<u8>)>),
+    /// Ends the stream before the record the hook was called with, as if the helper stopped
+    /// sending. Its peer sees the end of the stream instead of the message, and the messages for
+    /// later records are discarded. The stream ends once all the earlier records are sent, so
+    /// sends of later records must not be waiting for them at that point.
+    End,
+}
+
 /// Sending channels, indexed by (role, step).
 #[derive(Default)]
 pub(super) struct GatewaySenders {
     inner: DashMap<ChannelId, Arc<GatewaySender>>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    pub(super) send_hook: Option<Arc<dyn SendHook>>,
+    pub(super) capture: Option<Arc<Capture>>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    pub(super) rounds: Option<HelperRounds>,
 }
 
 pub(super) struct GatewaySender {
     channel_id: ChannelId,
     ordering_tx: OrderingSender,
     total_records: TotalRecords,
+    progress: Arc<ProgressTracker>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    send_hook: Option<Arc<dyn SendHook>>,
+    /// The record before which a [`SendHook`] ended this stream.
+    #[cfg(any(test, feature = "test-fixture"))]
+    ended_at: crate::sync::Mutex<Option<RecordId>>,
+    capture: Option<Arc<Capture>>,
+    #[cfg(any(test, feature = "test-fixture"))]
+    rounds: Option<HelperRounds>,
 }
 
 pub(super) struct GatewaySendStream {
     inner: Arc<GatewaySender>,
+    sender_role: Role,
 }
 
 impl GatewaySender {
-    fn new(channel_id: ChannelId, tx: OrderingSender, total_records: TotalRecords) -> Self {
+    fn new(
+        channel_id: ChannelId,
+        tx: OrderingSender,
+        total_records: TotalRecords,
+        progress: Arc<ProgressTracker>,
+    ) -> Self {
         Self {
             channel_id,
             ordering_tx: tx,
             total_records,
+            progress,
+            #[cfg(any(test, feature = "test-fixture"))]
+            send_hook: None,
+            #[cfg(any(test, feature = "test-fixture"))]
+            ended_at: crate::sync::Mutex::new(None),
+            capture: None,
+            #[cfg(any(test, feature = "test-fixture"))]
+            rounds: None,
         }
     }
 
@@ -67,15 +132,60 @@ impl GatewaySender {
             }
         }
 
+        #[cfg(any(test, feature = "test-fixture"))]
+        if let Some(hook) = &self.send_hook {
+            self.send_hooked(hook.as_ref(), record_id, msg).await;
+            return Ok(());
+        }
+
+        self.write(record_id, msg).await;
+
+        Ok(())
+    }
+
+    async fn write<M: Message>(&self, record_id: RecordId, msg: M) {
+        if let Some(capture) = &self.capture {
+            let mut data = GenericArray::default();
+            msg.serialize(&mut data);
+            capture.record_sent(&self.channel_id, record_id, &data);
+        }
+        #[cfg(any(test, feature = "test-fixture"))]
+        if let Some(rounds) = &self.rounds {
+            rounds.record_sent(&self.channel_id, record_id);
+        }
+
         // TODO: make OrderingSender::send fallible
         // TODO: test channel close
         let i = usize::from(record_id);
         self.ordering_tx.send(i, msg).await;
+        self.progress
+            .record_sent(i, self.total_records.is_last(record_id));
         if self.total_records.is_last(record_id) {
             self.ordering_tx.close(i + 1).await;
         }
+    }
 
-        Ok(())
+    #[cfg(any(test, feature = "test-fixture"))]
+    async fn send_hooked<M: Message>(&self, hook: &dyn SendHook, record_id: RecordId, msg: M) {
+        if matches!(*self.ended_at.lock().unwrap(), Some(end) if record_id >= end) {
+            return;
+        }
+
+        let mut data = GenericArray::default();
+        msg.serialize(&mut data);
+        match hook.on_send(&self.channel_id, record_id, self.total_records, &data) {
+            SendAction::Send(mut messages) => {
+                messages.sort_by_key(|&(record_id, _)| record_id);
+                for (record_id, data) in messages {
+                    self.write(record_id, M::deserialize(GenericArray::from_slice(&data)))
+                        .await;
+                }
+            }
+            SendAction::End => {
+                *self.ended_at.lock().unwrap() = Some(record_id);
+                self.ordering_tx.close(usize::from(record_id)).await;
+            }
+        }
     }
 }
 
@@ -120,8 +230,10 @@ impl GatewaySenders {
     pub(crate) fn get_or_create<M: Message>(
         &self,
         channel_id: &ChannelId,
+        sender_role: Role,
         capacity: NonZeroUsize,
         total_records: TotalRecords, // TODO track children for indeterminate senders
+        progress: &Arc<ProgressTracker>,
     ) -> (Arc<GatewaySender>, Option<GatewaySendStream>) {
         assert!(
             total_records.is_specified(),
@@ -149,16 +261,27 @@ impl GatewaySenders {
                         .expect("capacity should not overflow")
                 };
 
-                let sender = Arc::new(GatewaySender::new(
-                    channel_id.clone(),
-                    OrderingSender::new(write_size, SPARE.unwrap()),
-                    total_records,
-                ));
+                let sender = Arc::new(GatewaySender {
+                    #[cfg(any(test, feature = "test-fixture"))]
+                    send_hook: self.send_hook.clone(),
+                    #[cfg(any(test, feature = "test-fixture"))]
+                    rounds: self.rounds.clone(),
+                    capture: self.capture.clone(),
+                    ..GatewaySender::new(
+                        channel_id.clone(),
+                        OrderingSender::new(write_size, SPARE.unwrap()),
+                        total_records,
+                        Arc::clone(progress),
+                    )
+                });
                 entry.insert(Arc::clone(&sender));
 
                 (
                     Arc::clone(&sender),
-                    Some(GatewaySendStream { inner: sender }),
+                    Some(GatewaySendStream {
+                        inner: sender,
+                        sender_role,
+                    }),
                 )
             }
         }
@@ -169,6 +292,17 @@ impl Stream for GatewaySendStream {
     type Item = Vec<u8>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::get_mut(self).inner.ordering_tx.take_next(cx)
+        let this = Pin::get_mut(self);
+        let next = this.inner.ordering_tx.take_next(cx);
+        if let Poll::Ready(Some(batch)) = &next {
+            // one sample per flushed batch, recording every message would be too expensive
+            #[allow(clippy::cast_precision_loss)]
+            let len = batch.len() as f64;
+            metrics::histogram!(STEP_BYTES_SENT, len,
+                STEP => this.inner.channel_id.gate.as_ref().to_string(),
+                ROLE => this.sender_role.as_static_str()
+            );
+        }
+        next
     }
 }
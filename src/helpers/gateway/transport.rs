@@ -1,10 +1,17 @@
+use typenum::Unsigned;
+
 use crate::{
     helpers::{
         buffers::UnorderedReceiver,
-        gateway::{receive::UR, send::GatewaySendStream},
-        ChannelId, GatewayConfig, Role, RoleAssignment, RouteId, Transport,
+        gateway::{
+            capture::{Capture, CapturedStream},
+            receive::UR,
+            send::GatewaySendStream,
+        },
+        ChannelId, GatewayConfig, Message, Role, RoleAssignment, RouteId, Transport,
     },
     protocol::QueryId,
+    sync::Arc,
 };
 
 /// Transport adapter that resolves [`Role`] -> [`HelperIdentity`] mapping. As gateways created
@@ -41,7 +48,13 @@ impl<T: Transport> RoleResolvingTransport<T> {
             .await
     }
 
-    pub(crate) fn receive(&self, channel_id: &ChannelId) -> UR<T> {
+    /// Opens the channel to receive messages of type `M` from a peer. Every message that arrives
+    /// on it is recorded to `capture`, if there is one.
+    pub(crate) fn receive<M: Message>(
+        &self,
+        channel_id: &ChannelId,
+        capture: Option<&Arc<Capture>>,
+    ) -> UR<T> {
         let peer = self.roles.identity(channel_id.role);
         assert_ne!(
             peer,
@@ -50,10 +63,13 @@ impl<T: Transport> RoleResolvingTransport<T> {
         );
 
         UnorderedReceiver::new(
-            Box::pin(
+            Box::pin(CapturedStream::new(
                 self.inner
                     .receive(peer, (self.query_id, channel_id.gate.clone())),
-            ),
+                capture,
+                channel_id,
+                M::Size::USIZE,
+            )),
             self.config.active_work(),
         )
     }
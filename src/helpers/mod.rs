@@ -17,7 +17,9 @@ pub use buffers::OrderingSender;
 pub use error::{Error, Result};
 // TODO: this type should only be available within infra. Right now several infra modules
 // are exposed at the root level. That makes it impossible to have a proper hierarchy here.
-pub use gateway::{Gateway, TransportError, TransportImpl};
+pub use gateway::{capture, Gateway, TransportError, TransportImpl};
+#[cfg(any(test, feature = "test-fixture"))]
+pub use gateway::{rounds::RoundCounter, SendAction, SendHook};
 pub use gateway::{GatewayConfig, ReceivingEnd, SendingEnd};
 use generic_array::GenericArray;
 pub use prss_protocol::negotiate as negotiate_prss;
@@ -29,7 +31,7 @@ pub use transport::{
     StepBinding, StreamCollection, StreamKey, Transport, WrappedBoxBodyStream,
 };
 #[cfg(feature = "in-memory-infra")]
-pub use transport::{InMemoryNetwork, InMemoryTransport};
+pub use transport::{InMemoryNetwork, InMemoryTransport, LinkConfig, NetworkFaults};
 use typenum::{Unsigned, U8};
 use x25519_dalek::PublicKey;
 
@@ -5,7 +5,7 @@ use crate::{
     protocol::QueryId,
     query::{
         NewQueryError, PrepareQueryError, ProtocolResult, QueryCompletionError, QueryInputError,
-        QueryStatus, QueryStatusError,
+        QueryProgress, QueryStatus, QueryStatusError,
     },
 };
 
@@ -56,9 +56,9 @@ callbacks! {
     (QueryInputCallback, QueryInputResult):
         async fn(T, QueryInput) -> Result<(), QueryInputError>;
 
-    /// Called by clients to retrieve query status.
+    /// Called by clients to retrieve query status and, if the query is running, its progress.
     (QueryStatusCallback, QueryStatusResult):
-        async fn(T, QueryId) -> Result<QueryStatus, QueryStatusError>;
+        async fn(T, QueryId) -> Result<(QueryStatus, Option<QueryProgress>), QueryStatusError>;
 
     /// Called by clients to drive query to completion and retrieve results.
     (CompleteQueryCallback, CompleteQueryResult):
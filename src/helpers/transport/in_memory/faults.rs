@@ -0,0 +1,542 @@
+//! Fault injection for the in-memory transport.
+//!
+//! [`NetworkFaults`] makes [`InMemoryNetwork`] behave more like a WAN: each directed link between
+//! two helpers may have its own latency, bandwidth and jitter, streams may be cut short and one
+//! of the helpers may crash once it reaches a given step. Only record streams are affected;
+//! queries are still delivered instantly.
+//!
+//! Random delays are drawn from generators seeded with [`NetworkFaults::new`] seed and the link
+//! and gate of each stream, so the same seed produces the same delays. With a paused tokio clock
+//! and a single-threaded runtime, the entire run is reproducible.
+//!
+//! Streams may overtake each other and chunks may be split, but the bytes within a stream always
+//! arrive in the order they were sent, as they would over a TCP connection. The gateway does not
+//! expect anything else.
+//!
+//! This uses tokio timers, so it can't be used with shuttle.
+//!
+//! [`InMemoryNetwork`]: super::InMemoryNetwork
+
+use std::{collections::HashMap, num::NonZeroU64, time::Duration};
+
+use futures::StreamExt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use tokio::time::{sleep, sleep_until, Instant};
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::transport::InMemoryStream;
+use crate::{
+    helpers::HelperIdentity,
+    protocol::step::Gate,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, Notify,
+    },
+};
+
+/// Number of chunks that can be on a link on behalf of a single stream. Once there are this many,
+/// the sender has to wait for the receiver to take some of them, as with a TCP window.
+const IN_FLIGHT_CHUNKS: usize = 16;
+
+/// Conditions of a directed link between two helpers.
+#[derive(Clone, Debug, Default)]
+pub struct LinkConfig {
+    /// Time it takes a chunk to reach the peer once it has been put on the link.
+    pub latency: Duration,
+    /// Bytes per second that can be put on the link, shared by all the streams that use it.
+    /// If `None`, the bandwidth is unlimited.
+    pub bandwidth: Option<NonZeroU64>,
+    /// Upper bound of the random delay added to every chunk and to the opening of every stream.
+    /// Streams that are opened close to each other may arrive out of order.
+    pub jitter: Duration,
+    /// Randomly splits chunks in two, so that messages may arrive partially.
+    pub split_chunks: bool,
+}
+
+/// Seedable description of the faults injected into an [`InMemoryNetwork`].
+///
+/// [`InMemoryNetwork`]: super::InMemoryNetwork
+#[derive(Clone, Debug, Default)]
+pub struct NetworkFaults {
+    seed: u64,
+    default_link: LinkConfig,
+    links: HashMap<(HelperIdentity, HelperIdentity), LinkConfig>,
+    dropped_streams: Vec<(String, usize)>,
+    crash: Option<(HelperIdentity, String)>,
+}
+
+impl NetworkFaults {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the conditions of all links that are not configured with [`Self::with_link`].
+    #[must_use]
+    pub fn with_all_links(mut self, config: LinkConfig) -> Self {
+        self.default_link = config;
+        self
+    }
+
+    /// Sets the conditions of the link from `from` to `to`. The link in the opposite direction
+    /// is not affected.
+    #[must_use]
+    pub fn with_link(
+        mut self,
+        from: HelperIdentity,
+        to: HelperIdentity,
+        config: LinkConfig,
+    ) -> Self {
+        self.links.insert((from, to), config);
+        self
+    }
+
+    /// Ends the streams on the gates that contain `gate` after `after_chunks` chunks, as if the
+    /// connection was lost. Receivers see the end of the stream before all the records arrive.
+    #[must_use]
+    pub fn drop_stream(mut self, gate: &str, after_chunks: usize) -> Self {
+        self.dropped_streams.push((gate.to_owned(), after_chunks));
+        self
+    }
+
+    /// Crashes `helper` when it opens a stream on a gate that contains `gate`. The streams it
+    /// sends end, including the ones it opens later, and the streams sent to it are discarded.
+    /// Peers that wait for a stream that the crashed helper never opens wait forever, as there are
+    /// no timeouts.
+    #[must_use]
+    pub fn crash(mut self, helper: HelperIdentity, gate: &str) -> Self {
+        self.crash = Some((helper, gate.to_owned()));
+        self
+    }
+
+    fn link(&self, from: HelperIdentity, to: HelperIdentity) -> &LinkConfig {
+        self.links.get(&(from, to)).unwrap_or(&self.default_link)
+    }
+}
+
+/// State of a link while the network is running.
+struct Link {
+    config: LinkConfig,
+    /// The time when all the chunks sent on this link so far have been put on it.
+    busy_until: Mutex<Instant>,
+}
+
+impl Link {
+    /// Returns the time when a chunk of `len` bytes sent now arrives at the peer.
+    fn deliver_at(&self, len: usize, rng: &mut StdRng) -> Instant {
+        let now = Instant::now();
+        let sent = match self.config.bandwidth {
+            Some(bandwidth) => {
+                let mut busy_until = self.busy_until.lock().unwrap();
+                *busy_until = (*busy_until).max(now) + transfer_time(len, bandwidth);
+                *busy_until
+            }
+            None => now,
+        };
+        sent + self.config.latency + jitter(rng, self.config.jitter)
+    }
+}
+
+fn transfer_time(len: usize, bandwidth: NonZeroU64) -> Duration {
+    let nanos = u128::try_from(len).unwrap() * 1_000_000_000 / u128::from(bandwidth.get());
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+fn jitter(rng: &mut StdRng, max: Duration) -> Duration {
+    if max.is_zero() {
+        Duration::ZERO
+    } else {
+        rng.gen_range(Duration::ZERO..=max)
+    }
+}
+
+/// [`NetworkFaults`] applied to the streams of a running network.
+pub(super) struct Faults {
+    config: NetworkFaults,
+    links: HashMap<(HelperIdentity, HelperIdentity), Link>,
+    crashed: AtomicBool,
+    /// Wakes up the streams waiting for their next chunk once a helper has crashed.
+    crash_notify: Notify,
+}
+
+impl Faults {
+    pub fn new(config: NetworkFaults) -> Self {
+        let identities = HelperIdentity::make_three();
+        let now = Instant::now();
+        let links = identities
+            .iter()
+            .flat_map(|&from| identities.iter().map(move |&to| (from, to)))
+            .filter(|(from, to)| from != to)
+            .map(|(from, to)| {
+                let link = Link {
+                    config: config.link(from, to).clone(),
+                    busy_until: Mutex::new(now),
+                };
+                ((from, to), link)
+            })
+            .collect();
+
+        Self {
+            config,
+            links,
+            crashed: AtomicBool::new(false),
+            crash_notify: Notify::new(),
+        }
+    }
+
+    fn is_crashed(&self, helper: HelperIdentity) -> bool {
+        self.crashed.load(Ordering::Acquire)
+            && matches!(&self.config.crash, Some((h, _)) if *h == helper)
+    }
+
+    /// Returns the generator of random delays for the stream from `from` to `to` on `gate`. Its
+    /// seed is derived from the network seed with a hash that doesn't change between Rust
+    /// versions or platforms, so a seed reproduces the same delays anywhere.
+    fn rng(&self, from: HelperIdentity, to: HelperIdentity, gate: &Gate) -> StdRng {
+        let seed = Sha256::new()
+            .chain_update(self.config.seed.to_le_bytes())
+            .chain_update([u8::from(from), u8::from(to)])
+            .chain_update(gate.as_ref())
+            .finalize();
+        StdRng::from_seed(seed.into())
+    }
+
+    /// Makes `stream` go through the faulty link from `from` to `to`, which may take time and
+    /// crash the sender if it reached the crash step.
+    pub async fn wrap(
+        self: &Arc<Self>,
+        from: HelperIdentity,
+        to: HelperIdentity,
+        gate: &Gate,
+        stream: InMemoryStream,
+    ) -> InMemoryStream {
+        if matches!(&self.config.crash, Some((helper, g)) if *helper == from && gate.as_ref().contains(g.as_str()))
+        {
+            tracing::warn!("{from:?} crashes at {gate:?}");
+            self.crashed.store(true, Ordering::Release);
+            self.crash_notify.notify_waiters();
+        }
+
+        let mut rng = self.rng(from, to, gate);
+        let link = &self.links[&(from, to)];
+        sleep(jitter(&mut rng, link.config.jitter)).await;
+
+        let drop_after = self
+            .config
+            .dropped_streams
+            .iter()
+            .find(|(g, _)| gate.as_ref().contains(g.as_str()))
+            .map(|&(_, after_chunks)| after_chunks);
+        let (tx, rx) = mpsc::channel(IN_FLIGHT_CHUNKS);
+        tokio::spawn(Arc::clone(self).forward(from, to, stream, tx, drop_after, rng));
+
+        InMemoryStream::wrap(ReceiverStream::new(rx).then(|(at, chunk)| async move {
+            sleep_until(at).await;
+            chunk
+        }))
+    }
+
+    /// Puts the chunks of `stream` on the link with the time they arrive at the peer. Once the
+    /// stream is cut, the rest of it is discarded, so that the sender is not blocked.
+    async fn forward(
+        self: Arc<Self>,
+        from: HelperIdentity,
+        to: HelperIdentity,
+        mut stream: InMemoryStream,
+        tx: mpsc::Sender<(Instant, Vec<u8>)>,
+        drop_after: Option<usize>,
+        mut rng: StdRng,
+    ) {
+        let link = &self.links[&(from, to)];
+        let mut tx = Some(tx);
+        let mut last = Instant::now();
+        let mut chunks = 0;
+        loop {
+            // created before checking for a crash, so that a crash right after the check still
+            // wakes it up
+            let crashed = self.crash_notify.notified();
+            if drop_after.map_or(false, |n| chunks >= n)
+                || self.is_crashed(from)
+                || self.is_crashed(to)
+            {
+                tx = None;
+            }
+
+            let Some(sender) = &tx else {
+                if stream.next().await.is_none() {
+                    break;
+                }
+                continue;
+            };
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                () = crashed => continue,
+            };
+            let Some(mut chunk) = chunk else {
+                break;
+            };
+            chunks += 1;
+
+            let mut pieces = Vec::with_capacity(2);
+            if link.config.split_chunks && chunk.len() > 1 && rng.gen() {
+                let tail = chunk.split_off(rng.gen_range(1..chunk.len()));
+                pieces.extend([chunk, tail]);
+            } else {
+                pieces.push(chunk);
+            }
+            for piece in pieces {
+                let Ok(permit) = sender.reserve().await else {
+                    // the receiver is gone, nobody is interested in the rest of the stream
+                    return;
+                };
+                last = last.max(link.deliver_at(piece.len(), &mut rng));
+                permit.send((last, piece));
+            }
+        }
+    }
+}
+
+/// These run on a paused tokio clock, which needs `tokio/test-util` from `test-fixture`.
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{
+        iter::{repeat, zip},
+        num::NonZeroU64,
+        time::Duration,
+    };
+
+    use futures::StreamExt;
+    use tokio::time::Instant;
+
+    use super::{LinkConfig, NetworkFaults};
+    use crate::{
+        error::Error,
+        ff::{Field, Fp31},
+        helpers::{
+            transport::in_memory::InMemoryNetwork, Error as HelperError, GatewayConfig,
+            HelperIdentity, RouteId, Transport, TransportCallbacks,
+        },
+        protocol::{basics::SecureMul, context::Context, step::Gate, QueryId, RecordId},
+        secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig},
+    };
+
+    fn network(faults: NetworkFaults) -> InMemoryNetwork {
+        InMemoryNetwork::with_faults(
+            [
+                TransportCallbacks::default(),
+                TransportCallbacks::default(),
+                TransportCallbacks::default(),
+            ],
+            faults,
+        )
+    }
+
+    /// Sends `chunks` from helper 1 to helper 2 and returns them as they arrive, with the time
+    /// it took for each of them.
+    async fn transfer(
+        network: &InMemoryNetwork,
+        gate: &str,
+        chunks: Vec<Vec<u8>>,
+    ) -> Vec<(Duration, Vec<u8>)> {
+        let start = Instant::now();
+        let gate = Gate::from(gate);
+        network
+            .transport(HelperIdentity::ONE)
+            .send(
+                HelperIdentity::TWO,
+                (RouteId::Records, QueryId, gate.clone()),
+                futures::stream::iter(chunks),
+            )
+            .await
+            .unwrap();
+        network
+            .transport(HelperIdentity::TWO)
+            .receive(HelperIdentity::ONE, (QueryId, gate))
+            .map(|chunk| (start.elapsed(), chunk))
+            .collect()
+            .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency_and_bandwidth() {
+        let link = LinkConfig {
+            latency: Duration::from_millis(50),
+            bandwidth: NonZeroU64::new(1000),
+            ..LinkConfig::default()
+        };
+        let network = network(NetworkFaults::new(0).with_link(
+            HelperIdentity::ONE,
+            HelperIdentity::TWO,
+            link,
+        ));
+
+        let received = transfer(&network, "delayed", vec![vec![0; 100], vec![1; 200]]).await;
+        assert_eq!(
+            vec![
+                (Duration::from_millis(150), vec![0; 100]),
+                (Duration::from_millis(350), vec![1; 200]),
+            ],
+            received
+        );
+
+        // the other direction is not affected
+        let start = Instant::now();
+        let gate = Gate::from("instant");
+        network
+            .transport(HelperIdentity::TWO)
+            .send(
+                HelperIdentity::ONE,
+                (RouteId::Records, QueryId, gate.clone()),
+                futures::stream::iter(vec![vec![2; 100]]),
+            )
+            .await
+            .unwrap();
+        let received = network
+            .transport(HelperIdentity::ONE)
+            .receive(HelperIdentity::TWO, (QueryId, gate))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(vec![vec![2; 100]], received);
+        assert_eq!(Duration::ZERO, start.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_is_reproducible() {
+        let faults = NetworkFaults::new(42).with_all_links(LinkConfig {
+            jitter: Duration::from_millis(100),
+            split_chunks: true,
+            ..LinkConfig::default()
+        });
+        let chunks = (0..10_u8).map(|i| vec![i; 10]).collect::<Vec<_>>();
+
+        let first = transfer(&network(faults.clone()), "jitter", chunks.clone()).await;
+        let second = transfer(&network(faults), "jitter", chunks.clone()).await;
+        assert_eq!(first, second);
+        assert!(first.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert!(first.iter().any(|(elapsed, _)| !elapsed.is_zero()));
+        assert_eq!(
+            chunks.concat(),
+            first
+                .into_iter()
+                .flat_map(|(_, chunk)| chunk)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drop_stream() {
+        let network = network(NetworkFaults::new(0).drop_stream("dropped", 2));
+        let chunks = (0..5_u8).map(|i| vec![i]).collect::<Vec<_>>();
+
+        let received = transfer(&network, "dropped", chunks.clone()).await;
+        assert_eq!(
+            vec![vec![0], vec![1]],
+            received.into_iter().map(|(_, c)| c).collect::<Vec<_>>()
+        );
+        let received = transfer(&network, "other", chunks.clone()).await;
+        assert_eq!(
+            chunks,
+            received.into_iter().map(|(_, c)| c).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crash() {
+        let network = network(NetworkFaults::new(0).crash(HelperIdentity::ONE, "crash"));
+        let chunks = vec![vec![1, 2, 3]];
+
+        let received = transfer(&network, "before", chunks.clone()).await;
+        assert_eq!(1, received.len());
+        let received = transfer(&network, "crash", chunks.clone()).await;
+        assert!(received.is_empty());
+        let received = transfer(&network, "after", chunks).await;
+        assert!(received.is_empty());
+    }
+
+    /// Multiplies `count` pairs of values in a world with the given faults.
+    async fn multiply(
+        config: TestWorldConfig,
+        count: usize,
+    ) -> (Vec<Fp31>, [Result<Vec<Replicated<Fp31>>, Error>; 3]) {
+        let a = (0..count).map(|i| Fp31::truncate_from(u128::try_from(i).unwrap()));
+        let b = a.clone().rev();
+        let expected = zip(a.clone(), b.clone()).map(|(a, b)| a * b).collect();
+        let world = TestWorld::new_with(config);
+        let results = world
+            .semi_honest((a, b), |ctx, (a, b): (Vec<_>, Vec<_>)| async move {
+                let ctx = ctx.narrow("mul").set_total_records(a.len());
+                ctx
+                    .try_join(
+                        zip(repeat(ctx.clone()), zip(a, b)).enumerate().map(
+                            |(i, (ctx, (a, b)))| async move {
+                                a.multiply(&b, ctx, RecordId::from(i)).await
+                            },
+                        ),
+                    )
+                    .await
+            })
+            .await;
+        (expected, results)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn multiply_over_wan() {
+        const COUNT: usize = 200;
+        let faults = NetworkFaults::new(7).with_all_links(LinkConfig {
+            latency: Duration::from_millis(40),
+            bandwidth: NonZeroU64::new(50),
+            jitter: Duration::from_millis(20),
+            split_chunks: true,
+        });
+        let config = TestWorldConfig {
+            gateway_config: GatewayConfig::new(4),
+            ..TestWorldConfig::default()
+        }
+        .with_network_faults(faults);
+
+        let start = Instant::now();
+        let (expected, results) = multiply(config, COUNT).await;
+        assert_eq!(expected, results.map(Result::unwrap).reconstruct());
+        // every helper sends one byte per multiplication to its peer
+        assert!(start.elapsed() >= Duration::from_secs(4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_stream_fails_query() {
+        let config = TestWorldConfig::default()
+            .with_network_faults(NetworkFaults::new(0).drop_stream("mul", 0));
+        let (_, results) = multiply(config, 10).await;
+        for result in results {
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::InfraError(HelperError::ReceiveError { .. }))
+                ),
+                "{result:?}"
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crash_fails_query() {
+        let config = TestWorldConfig::default()
+            .with_network_faults(NetworkFaults::new(0).crash(HelperIdentity::TWO, "mul"));
+        let (_, [h1, h2, h3]) = multiply(config, 10).await;
+        // H1 only receives from H3, which is alive
+        assert!(h1.is_ok());
+        assert!(
+            matches!(h2, Err(Error::InfraError(HelperError::ReceiveError { .. }))),
+            "{h2:?}"
+        );
+        assert!(
+            matches!(h3, Err(Error::InfraError(HelperError::ReceiveError { .. }))),
+            "{h3:?}"
+        );
+    }
+}
@@ -1,7 +1,10 @@
+mod faults;
 mod transport;
 
+pub use faults::{LinkConfig, NetworkFaults};
 pub use transport::Setup;
 
+use self::faults::Faults;
 use crate::{
     helpers::{HelperIdentity, TransportCallbacks},
     sync::{Arc, Weak},
@@ -29,8 +32,32 @@ impl Default for InMemoryNetwork {
 impl InMemoryNetwork {
     #[must_use]
     pub fn new(callbacks: [TransportCallbacks<InMemoryTransport>; 3]) -> Self {
+        Self::start(callbacks, None)
+    }
+
+    /// Creates a network that delays, cuts or crashes the record streams as described by
+    /// `faults`. See [`NetworkFaults`] for details.
+    #[must_use]
+    pub fn with_faults(
+        callbacks: [TransportCallbacks<InMemoryTransport>; 3],
+        faults: NetworkFaults,
+    ) -> Self {
+        Self::start(callbacks, Some(Faults::new(faults)))
+    }
+
+    fn start(
+        callbacks: [TransportCallbacks<InMemoryTransport>; 3],
+        faults: Option<Faults>,
+    ) -> Self {
+        let faults = faults.map(Arc::new);
         let [mut first, mut second, mut third]: [_; 3] =
-            HelperIdentity::make_three().map(Setup::new);
+            HelperIdentity::make_three().map(|identity| {
+                let setup = Setup::new(identity);
+                match &faults {
+                    Some(faults) => setup.with_faults(Arc::clone(faults)),
+                    None => setup,
+                }
+            });
 
         first.connect(&mut second);
         second.connect(&mut third);
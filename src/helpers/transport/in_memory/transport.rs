@@ -21,6 +21,7 @@ use shuttle::future as tokio;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::Instrument;
 
+use super::faults::Faults;
 use crate::{
     error::BoxError,
     helpers::{
@@ -58,15 +59,21 @@ pub struct InMemoryTransport {
     identity: HelperIdentity,
     connections: HashMap<HelperIdentity, ConnectionTx>,
     record_streams: StreamCollection<InMemoryStream>,
+    faults: Option<Arc<Faults>>,
 }
 
 impl InMemoryTransport {
     #[must_use]
-    fn new(identity: HelperIdentity, connections: HashMap<HelperIdentity, ConnectionTx>) -> Self {
+    fn new(
+        identity: HelperIdentity,
+        connections: HashMap<HelperIdentity, ConnectionTx>,
+        faults: Option<Arc<Faults>>,
+    ) -> Self {
         Self {
             identity,
             connections,
             record_streams: StreamCollection::default(),
+            faults,
         }
     }
 
@@ -178,13 +185,15 @@ impl Transport for Weak<InMemoryTransport> {
         let channel = this.get_channel(dest);
         let addr = Addr::from_route(this.identity, route);
         let (ack_tx, ack_rx) = oneshot::channel();
+        let mut stream = InMemoryStream::wrap(data);
+        if let (RouteId::Records, Some(faults), Some(gate)) = (addr.route, &this.faults, &addr.gate)
+        {
+            stream = faults.wrap(this.identity, dest, gate, stream).await;
+        }
 
-        channel
-            .send((addr, InMemoryStream::wrap(data), ack_tx))
-            .await
-            .map_err(|_e| {
-                io::Error::new::<String>(io::ErrorKind::ConnectionAborted, "channel closed".into())
-            })?;
+        channel.send((addr, stream, ack_tx)).await.map_err(|_e| {
+            io::Error::new::<String>(io::ErrorKind::ConnectionAborted, "channel closed".into())
+        })?;
 
         ack_rx
             .await
@@ -219,7 +228,7 @@ impl InMemoryStream {
         Self::from_iter(std::iter::empty())
     }
 
-    fn wrap<S: Stream<Item = StreamItem> + Send + 'static>(value: S) -> Self {
+    pub(super) fn wrap<S: Stream<Item = StreamItem> + Send + 'static>(value: S) -> Self {
         Self {
             inner: Box::pin(value),
         }
@@ -319,6 +328,7 @@ pub struct Setup {
     tx: ConnectionTx,
     rx: ConnectionRx,
     connections: HashMap<HelperIdentity, ConnectionTx>,
+    faults: Option<Arc<Faults>>,
 }
 
 impl Setup {
@@ -330,9 +340,17 @@ impl Setup {
             tx,
             rx,
             connections: HashMap::default(),
+            faults: None,
         }
     }
 
+    /// Makes the streams sent by this helper go through faulty links.
+    #[must_use]
+    pub(super) fn with_faults(mut self, faults: Arc<Faults>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
     /// Establishes a link between this helper and another one
     ///
     /// ## Panics
@@ -352,7 +370,11 @@ impl Setup {
         self,
         callbacks: TransportCallbacks<Weak<InMemoryTransport>>,
     ) -> (ConnectionTx, Arc<InMemoryTransport>) {
-        let transport = Arc::new(InMemoryTransport::new(self.identity, self.connections));
+        let transport = Arc::new(InMemoryTransport::new(
+            self.identity,
+            self.connections,
+            self.faults,
+        ));
         transport.listen(callbacks, self.rx);
 
         (self.tx, transport)
@@ -252,6 +252,20 @@ pub struct IpaQueryConfig {
     #[cfg_attr(feature = "clap", arg(long))]
     #[serde(default)]
     pub plaintext_match_keys: bool,
+
+    /// If true, helpers generate the random values that IPA needs, but that do not depend on the
+    /// inputs, while the inputs are being uploaded. This cuts the latency after the upload
+    /// completes.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub offline_preprocessing: bool,
+
+    /// If true, IPA sorts the input by match key with a comparison sort over boolean shares,
+    /// instead of the radix sort that converts match key bits to the prime field first.
+    /// `num_multi_bits` only applies to the radix sort.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub gf2_sort: bool,
 }
 
 impl Default for IpaQueryConfig {
@@ -262,6 +276,8 @@ impl Default for IpaQueryConfig {
             attribution_window_seconds: None,
             num_multi_bits: 3,
             plaintext_match_keys: false,
+            offline_preprocessing: false,
+            gf2_sort: false,
         }
     }
 }
@@ -285,6 +301,8 @@ impl IpaQueryConfig {
             ),
             num_multi_bits,
             plaintext_match_keys: false,
+            offline_preprocessing: false,
+            gf2_sort: false,
         }
     }
 
@@ -304,10 +322,58 @@ impl IpaQueryConfig {
             attribution_window_seconds: None,
             num_multi_bits,
             plaintext_match_keys: false,
+            offline_preprocessing: false,
+            gf2_sort: false,
+        }
+    }
+}
+
+/// Configuration of the PRF-sharded IPA protocol, which processes the events of each user
+/// together once the PRF of their match key is revealed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct PrfShardedIpaQueryConfig {
+    /// The contribution of each user is capped at `2^num_saturating_sum_bits`.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "5"))]
+    pub num_saturating_sum_bits: u32,
+
+    /// Users with more rows than this only have their most recent rows processed. The oldest
+    /// rows of these users are dropped before attribution. Must be at most
+    /// [`PrfShardedIpaQueryConfig::MAX_ROWS_PER_USER`].
+    #[cfg_attr(feature = "clap", arg(long, default_value = "50"))]
+    pub max_rows_per_user: u32,
+
+    /// If set, helpers add dummy users under fresh PRF values before the PRF is revealed, so that
+    /// the number of users with each number of events, up to `max_rows_per_user`, is
+    /// differentially private with this epsilon. If not set, helpers learn the exact number of
+    /// events of every user.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub padding_epsilon: Option<f64>,
+
+    /// The delta of the dummy user padding. Only used if `padding_epsilon` is set.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "1e-6"))]
+    pub padding_delta: f64,
+}
+
+impl Default for PrfShardedIpaQueryConfig {
+    fn default() -> Self {
+        Self {
+            num_saturating_sum_bits: 5,
+            max_rows_per_user: 50,
+            padding_epsilon: None,
+            padding_delta: 1e-6,
         }
     }
 }
 
+impl PrfShardedIpaQueryConfig {
+    /// The largest supported value of `max_rows_per_user`. Every row of a user is processed under
+    /// a step of its own, and the number of these steps must be bounded.
+    pub const MAX_ROWS_PER_USER: u32 = 64;
+}
+
 #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 #[serde(try_from = "u32")] // Tell serde to deserialize data into an int and then try to convert it into a valie contributuion bit size
 pub struct ContributionBits(u32);
@@ -332,6 +398,12 @@ impl Default for ContributionBits {
     }
 }
 
+impl From<ContributionBits> for u32 {
+    fn from(value: ContributionBits) -> Self {
+        value.0
+    }
+}
+
 impl std::fmt::Display for ContributionBits {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.0)
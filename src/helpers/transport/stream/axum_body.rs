@@ -8,7 +8,7 @@ use futures::{Stream, TryStreamExt};
 use hyper::Body;
 use pin_project::pin_project;
 
-use crate::error::BoxError;
+use crate::{error::BoxError, helpers::BytesStream};
 
 type AxumInner = futures::stream::MapErr<BodyStream, fn(axum::Error) -> crate::error::BoxError>;
 
@@ -24,6 +24,11 @@ impl WrappedAxumBodyStream {
         Self::new_internal(inner)
     }
 
+    /// Wraps a stream of chunks, e.g. one that inspects another body stream.
+    pub fn from_stream<S: BytesStream + 'static>(inner: S) -> Self {
+        Self::from_body(Body::wrap_stream(inner))
+    }
+
     pub(super) fn new_internal(inner: BodyStream) -> Self {
         Self(inner.map_err(axum::Error::into_inner as fn(axum::Error) -> BoxError))
     }
@@ -5,11 +5,17 @@ use std::{
 
 use futures::Stream;
 
-use crate::helpers::transport::stream::BoxBytesStream;
+use crate::helpers::transport::stream::{BoxBytesStream, BytesStream};
 
 pub struct WrappedBoxBodyStream(BoxBytesStream);
 
 impl WrappedBoxBodyStream {
+    /// Boxes any stream of chunks.
+    #[must_use]
+    pub fn from_stream<S: BytesStream + 'static>(inner: S) -> Self {
+        Self(Box::pin(inner))
+    }
+
     /// Wrap an axum body stream, returning an instance of `crate::helpers::BodyStream`.
     #[cfg(all(feature = "in-memory-infra", feature = "web-app"))]
     #[must_use]
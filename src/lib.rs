@@ -42,16 +42,96 @@ extern crate shuttle_crate as shuttle;
 #[cfg(all(feature = "shuttle", test))]
 pub(crate) mod sync {
     pub use shuttle::sync::{Arc, Mutex, MutexGuard, Once, Weak};
+    /// Shuttle has no asynchronous channels. Tokio's only interact with the executor through
+    /// wakers, so Shuttle still decides which task runs once they become ready, but it can't
+    /// preempt a task inside of them.
+    pub use tokio::sync::mpsc;
+
+    pub use self::notify::Notify;
     pub mod atomic {
-        pub use shuttle::sync::atomic::{AtomicUsize, Ordering};
+        pub use shuttle::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    }
+
+    mod notify {
+        use std::{
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll, Waker},
+        };
+
+        use shuttle::sync::Mutex;
+
+        /// A model of [`tokio::sync::Notify`] built on Shuttle's mutex, so that Shuttle can
+        /// preempt tasks while they register for and send notifications. Only
+        /// [`notify_waiters`] is supported.
+        ///
+        /// [`notify_waiters`]: Notify::notify_waiters
+        pub struct Notify {
+            state: Mutex<State>,
+        }
+
+        #[derive(Default)]
+        struct State {
+            /// The number of calls to `notify_waiters` so far.
+            generation: usize,
+            waiters: Vec<Waker>,
+        }
+
+        impl Notify {
+            pub fn new() -> Self {
+                Self {
+                    state: Mutex::new(State::default()),
+                }
+            }
+
+            /// Like Tokio's, the returned future is woken by any call to [`notify_waiters`]
+            /// made after it was created, even if it hasn't been polled yet.
+            ///
+            /// [`notify_waiters`]: Notify::notify_waiters
+            pub fn notified(&self) -> Notified<'_> {
+                Notified {
+                    notify: self,
+                    generation: self.state.lock().unwrap().generation,
+                }
+            }
+
+            pub fn notify_waiters(&self) {
+                let mut state = self.state.lock().unwrap();
+                state.generation += 1;
+                for w in state.waiters.drain(..) {
+                    w.wake();
+                }
+            }
+        }
+
+        pub struct Notified<'a> {
+            notify: &'a Notify,
+            generation: usize,
+        }
+
+        impl Future for Notified<'_> {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                let mut state = self.notify.state.lock().unwrap();
+                if state.generation == self.generation {
+                    state.waiters.push(cx.waker().clone());
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            }
+        }
     }
 }
 
 #[cfg(not(all(feature = "shuttle", test)))]
 pub(crate) mod sync {
     pub use std::sync::{Arc, Mutex, MutexGuard, Once, Weak};
+
+    pub use tokio::sync::{mpsc, Notify};
     pub mod atomic {
-        pub use std::sync::atomic::{AtomicUsize, Ordering};
+        pub use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     }
 }
 
@@ -97,6 +177,22 @@ pub(crate) mod test_executor {
     {
         shuttle::check_random(move || shuttle::future::block_on(f()), ITER);
     }
+
+    /// Explore the schedules of `f` with both the random and the PCT scheduler. The latter is
+    /// better at finding bugs that only show up after a few specific preemptions.
+    pub fn explore<F, Fut>(f: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()>,
+    {
+        const ITER: usize = 100;
+        const PCT_DEPTH: usize = 3;
+
+        let f = std::sync::Arc::new(f);
+        let g = std::sync::Arc::clone(&f);
+        shuttle::check_random(move || shuttle::future::block_on(g()), ITER);
+        shuttle::check_pct(move || shuttle::future::block_on(f()), ITER, PCT_DEPTH);
+    }
 }
 
 #[cfg(all(test, unit_test, not(feature = "shuttle")))]
@@ -6,6 +6,7 @@ use std::{
     iter::repeat,
     pin::Pin,
     task::{ready, Context, Poll},
+    time::Duration,
 };
 
 use axum::http::uri::{self, Parts, Scheme};
@@ -101,6 +102,19 @@ impl<'a> ResponseFromEndpoint<'a> {
         self.inner.status()
     }
 
+    /// Returns the delay requested by the server via `Retry-After` header, if any. Only the
+    /// delay-seconds form of the header is supported.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.inner
+            .headers()
+            .get(hyper::header::RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+            .map(Duration::from_secs)
+    }
+
     pub fn into_body(self) -> Body {
         self.inner.into_body()
     }
@@ -371,7 +385,7 @@ impl MpcHelperClient {
         Ok(self.request(req))
     }
 
-    /// Retrieve the status of a query.
+    /// Retrieve the status of a query and, if it is running, its progress.
     ///
     /// ## Errors
     /// If the request has illegal arguments, or fails to deliver to helper
@@ -379,16 +393,22 @@ impl MpcHelperClient {
     pub async fn query_status(
         &self,
         query_id: QueryId,
-    ) -> Result<crate::query::QueryStatus, Error> {
+    ) -> Result<
+        (
+            crate::query::QueryStatus,
+            Option<crate::query::QueryProgress>,
+        ),
+        Error,
+    > {
         let req = http_serde::query::status::Request::new(query_id);
         let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
 
         let resp = self.request(req).await?;
         if resp.status().is_success() {
             let body_bytes = body::to_bytes(resp.into_body()).await?;
-            let http_serde::query::status::ResponseBody { status } =
+            let http_serde::query::status::ResponseBody { status, progress } =
                 serde_json::from_slice(&body_bytes)?;
-            Ok(status)
+            Ok((status, progress))
         } else {
             Err(Error::from_failed_resp(resp).await)
         }
@@ -397,16 +417,22 @@ impl MpcHelperClient {
     /// Wait for completion of the query and pull the results of this query. This is a blocking
     /// API so it is not supposed to be used outside of CLI context.
     ///
+    /// Results are returned as a stream of chunks of serialized shares, in the order they arrive
+    /// from the helper. Chunk boundaries are not aligned with share boundaries.
+    ///
     /// ## Errors
     /// If the request has illegal arguments, or fails to deliver to helper
     #[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
-    pub async fn query_results(&self, query_id: QueryId) -> Result<body::Bytes, Error> {
+    pub async fn query_results(
+        &self,
+        query_id: QueryId,
+    ) -> Result<impl Stream<Item = Result<body::Bytes, Error>> + Send, Error> {
         let req = http_serde::query::results::Request::new(query_id);
         let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
 
         let resp = self.request(req).await?;
         if resp.status().is_success() {
-            Ok(body::to_bytes(resp.into_body()).await?)
+            Ok(resp.into_body().map(|chunk| chunk.map_err(Error::from)))
         } else {
             Err(Error::from_failed_resp(resp).await)
         }
@@ -431,7 +457,10 @@ pub(crate) mod tests {
         task::Poll,
     };
 
-    use futures::stream::{once, poll_immediate};
+    use futures::{
+        stream::{once, poll_immediate},
+        TryStreamExt,
+    };
 
     use super::*;
     use crate::{
@@ -674,10 +703,19 @@ pub(crate) mod tests {
             ..Default::default()
         };
         let results = test_query_command(
-            |client| async move { client.query_results(expected_query_id).await.unwrap() },
+            |client| async move {
+                let chunks = client
+                    .query_results(expected_query_id)
+                    .await
+                    .unwrap()
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap();
+                chunks.concat()
+            },
             cb,
         )
         .await;
-        assert_eq!(results.to_vec(), expected_results.into_bytes());
+        assert_eq!(results, expected_results.into_bytes());
     }
 }
@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
 };
 
@@ -59,6 +61,13 @@ pub enum Error {
     },
     #[error("{error}")]
     Application { code: StatusCode, error: BoxError },
+    /// The request was rejected because the helper is overloaded or the caller exceeded its
+    /// request quota. Callers may try again after `retry_after`.
+    #[error("too many requests, retry after {}s: {reason}", .retry_after.as_secs())]
+    TooManyRequests {
+        retry_after: Duration,
+        reason: BoxError,
+    },
 }
 
 impl Error {
@@ -71,21 +80,44 @@ impl Error {
     /// Extracts the body from the response to use as error message.
     /// Because the body is in a future, cannot use [`From`] trait
     ///
+    /// `429 Too Many Requests` responses that carry a `Retry-After` header are converted to
+    /// [`Error::TooManyRequests`], so callers can tell when it makes sense to try again.
+    ///
     /// # Panics
     /// If the response is not a failure (4xx/5xx status)
     pub async fn from_failed_resp(resp: ResponseFromEndpoint<'_>) -> Self {
         let status = resp.status();
         assert!(status.is_client_error() || status.is_server_error()); // must be failure
+        let retry_after = (status == StatusCode::TOO_MANY_REQUESTS)
+            .then(|| resp.retry_after())
+            .flatten();
         let (endpoint, body) = resp.into_parts();
         hyper::body::to_bytes(body)
             .await
-            .map_or_else(Into::into, |reason_bytes| Error::FailedHttpRequest {
-                dest: endpoint.to_string(),
-                status,
-                reason: String::from_utf8_lossy(&reason_bytes).to_string(),
+            .map_or_else(Into::into, |reason_bytes| {
+                let reason = String::from_utf8_lossy(&reason_bytes).to_string();
+                match retry_after {
+                    Some(retry_after) => Error::TooManyRequests {
+                        retry_after,
+                        reason: reason.into(),
+                    },
+                    None => Error::FailedHttpRequest {
+                        dest: endpoint.to_string(),
+                        status,
+                        reason,
+                    },
+                }
             })
     }
 
+    #[must_use]
+    pub fn too_many_requests<E: Into<BoxError>>(retry_after: Duration, reason: E) -> Self {
+        Self::TooManyRequests {
+            retry_after,
+            reason: reason.into(),
+        }
+    }
+
     #[must_use]
     pub fn application<E: Into<BoxError>>(code: StatusCode, error: E) -> Self {
         Self::Application {
@@ -134,6 +166,17 @@ impl From<axum::extract::rejection::PathRejection> for Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
+        if let Self::TooManyRequests { retry_after, .. } = self {
+            // round up, so clients never come back before they are allowed to
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, secs.to_string())],
+                self.to_string(),
+            )
+                .into_response();
+        }
+
         let status_code = match self {
             Self::BadQueryString(_) | Self::BadPathString(_) | Self::MissingHeader(_) => {
                 StatusCode::UNPROCESSABLE_ENTITY
@@ -155,6 +198,7 @@ impl IntoResponse for Error {
             | Self::MissingExtension(_) => StatusCode::INTERNAL_SERVER_ERROR,
 
             Self::Application { code, .. } => code,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
         };
 
         (status_code, self.to_string()).into_response()
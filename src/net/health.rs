@@ -0,0 +1,210 @@
+//! Diagnostics that let orchestration decide whether a helper is able to serve queries.
+//!
+//! The same checks back the `/readyz` and `/peers` endpoints and the `helper check-network`
+//! command, so an operator sees identical results from inside and outside the helper.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::future::join;
+use serde::{Deserialize, Serialize};
+use tokio_rustls::rustls::Certificate;
+
+use crate::{
+    config::{hpke_registry, NetworkConfig, ServerConfig},
+    error::BoxError,
+    helpers::HelperIdentity,
+    net::{server::certificate_and_key, MpcHelperClient},
+};
+
+/// How long to wait for a peer to respond before declaring it unreachable.
+const PEER_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of a single readiness check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new<N: Into<String>, D: Into<String>>(name: N, result: Result<D, BoxError>) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail.into()),
+            Err(e) => (false, e.to_string()),
+        };
+        Self {
+            name: name.into(),
+            ok,
+            detail,
+        }
+    }
+}
+
+/// Response body of `GET /readyz`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Connectivity from this helper to one of its peers. Response body of `GET /peers` is a list
+/// of these, one per peer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub identity: HelperIdentity,
+    pub url: String,
+    pub reachable: bool,
+    /// Time it took to complete a request to the peer, if it responded.
+    pub round_trip_ms: Option<u64>,
+    /// Number of seconds until the peer's certificate, as configured in `network.toml`, expires.
+    /// Negative if it has already expired, `None` if the peer is not configured with a certificate.
+    pub certificate_expires_in_secs: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl PeerStatus {
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.reachable
+            && self
+                .certificate_expires_in_secs
+                .map_or(true, |secs| secs > 0)
+    }
+}
+
+/// Verifies that match key encryption keys can be loaded and that the TLS certificate of this
+/// helper is usable.
+pub async fn readiness(server: &ServerConfig) -> Readiness {
+    let keys = Check::new(
+        "hpke_keys",
+        match &server.hpke_config {
+            None => Ok("match key encryption is not configured".to_owned()),
+            Some(config) => hpke_registry(Some(config))
+                .await
+                .map(|_| "match key encryption keys loaded".to_owned()),
+        },
+    );
+    let tls = Check::new(
+        "tls",
+        if server.disable_https {
+            Ok("HTTPS is disabled".to_owned())
+        } else {
+            tls_status(server).await
+        },
+    );
+
+    let checks = vec![keys, tls];
+    Readiness {
+        ready: checks.iter().all(|c| c.ok),
+        checks,
+    }
+}
+
+async fn tls_status(server: &ServerConfig) -> Result<String, BoxError> {
+    let (certs, _key) = certificate_and_key(server).await?;
+    let cert = certs.first().ok_or("no certificate found")?;
+    let expires_in = certificate_expires_in(cert)?;
+    if expires_in <= 0 {
+        Err(format!("certificate expired {}s ago", -expires_in).into())
+    } else {
+        Ok(format!("certificate expires in {expires_in}s"))
+    }
+}
+
+/// Returns the number of seconds until `cert` expires. Negative values mean the certificate
+/// has already expired.
+///
+/// ## Errors
+/// If the certificate cannot be parsed.
+pub fn certificate_expires_in(cert: &Certificate) -> Result<i64, BoxError> {
+    let (_, cert) = x509_parser::parse_x509_certificate(&cert.0)
+        .map_err(|e| format!("failed to parse certificate: {e}"))?;
+    let now = i64::try_from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())?;
+    Ok(cert.validity().not_after.timestamp() - now)
+}
+
+/// Checks that helper `me` can reach the other two helpers in `network` using `clients`.
+pub async fn probe_peers(
+    me: HelperIdentity,
+    clients: &[MpcHelperClient; 3],
+    network: &NetworkConfig,
+) -> Vec<PeerStatus> {
+    let [left, right] = me.others();
+    let (left, right) = join(
+        probe_peer(left, clients, network),
+        probe_peer(right, clients, network),
+    )
+    .await;
+    vec![left, right]
+}
+
+async fn probe_peer(
+    peer: HelperIdentity,
+    clients: &[MpcHelperClient; 3],
+    network: &NetworkConfig,
+) -> PeerStatus {
+    let config = &network.peers()[peer];
+    let certificate_expires_in_secs = config
+        .certificate
+        .as_ref()
+        .and_then(|cert| certificate_expires_in(cert).ok());
+
+    let start = Instant::now();
+    let (reachable, round_trip_ms, error) =
+        match tokio::time::timeout(PEER_PROBE_TIMEOUT, clients[peer].echo("ping")).await {
+            Ok(Ok(_)) => (
+                true,
+                Some(u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)),
+                None,
+            ),
+            Ok(Err(e)) => (false, None, Some(e.to_string())),
+            Err(_) => (
+                false,
+                None,
+                Some(format!(
+                    "no response within {}s",
+                    PEER_PROBE_TIMEOUT.as_secs()
+                )),
+            ),
+        };
+
+    PeerStatus {
+        identity: peer,
+        url: config.url.to_string(),
+        reachable,
+        round_trip_ms,
+        certificate_expires_in_secs,
+        error,
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+    use crate::net::test::{TestConfigBuilder, TEST_CERTS_DER};
+
+    #[test]
+    fn test_certificate_is_valid() {
+        let cert = Certificate(TEST_CERTS_DER[0].clone());
+        assert!(certificate_expires_in(&cert).unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn ready_with_test_config() {
+        let conf = TestConfigBuilder::default().build();
+        for server in &conf.servers {
+            let readiness = readiness(server).await;
+            assert!(readiness.ready, "{readiness:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn not_ready_with_broken_tls() {
+        let mut server = TestConfigBuilder::default().build().servers[0].clone();
+        server.tls = None;
+        let readiness = readiness(&server).await;
+        assert!(!readiness.ready);
+        assert!(readiness.checks.iter().any(|c| c.name == "tls" && !c.ok));
+    }
+}
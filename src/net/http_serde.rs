@@ -79,6 +79,12 @@ pub mod echo {
     pub const AXUM_PATH: &str = "/echo";
 }
 
+pub mod health {
+    pub const HEALTHZ_AXUM_PATH: &str = "/healthz";
+    pub const READYZ_AXUM_PATH: &str = "/readyz";
+    pub const PEERS_AXUM_PATH: &str = "/peers";
+}
+
 pub mod query {
     use std::fmt::{Display, Formatter};
 
@@ -172,6 +178,14 @@ pub mod query {
                         write!(f, "&plaintext_match_keys=true")?;
                     }
 
+                    if config.offline_preprocessing {
+                        write!(f, "&offline_preprocessing=true")?;
+                    }
+
+                    if config.gf2_sort {
+                        write!(f, "&gf2_sort=true")?;
+                    }
+
                     if let Some(window) = config.attribution_window_seconds {
                         write!(f, "&attribution_window_seconds={}", window.get())?;
                     }
@@ -484,7 +498,11 @@ pub mod query {
         use axum::extract::{FromRequest, Path, RequestParts};
         use serde::{Deserialize, Serialize};
 
-        use crate::{net::Error, protocol::QueryId, query::QueryStatus};
+        use crate::{
+            net::Error,
+            protocol::QueryId,
+            query::{QueryProgress, QueryStatus},
+        };
 
         #[derive(Debug, Clone)]
         pub struct Request {
@@ -529,6 +547,9 @@ pub mod query {
         #[derive(Clone, Debug, Serialize, Deserialize)]
         pub struct ResponseBody {
             pub status: QueryStatus,
+            /// Only reported while the query is running.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub progress: Option<QueryProgress>,
         }
 
         pub const AXUM_PATH: &str = "/:query_id";
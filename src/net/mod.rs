@@ -1,5 +1,6 @@
 mod client;
 mod error;
+pub mod health;
 mod http_serde;
 mod server;
 #[cfg(all(test, not(feature = "shuttle")))]
@@ -0,0 +1,104 @@
+use axum::{routing::get, Extension, Json, Router};
+use hyper::StatusCode;
+
+use crate::{
+    config::{NetworkConfig, ServerConfig},
+    helpers::Transport,
+    net::{
+        health::{self, PeerStatus, Readiness},
+        http_serde, HttpTransport,
+    },
+    sync::Arc,
+};
+
+/// Everything the diagnostic endpoints need to know about this helper.
+pub struct HealthState {
+    pub transport: Arc<HttpTransport>,
+    pub server_config: ServerConfig,
+    pub network_config: NetworkConfig,
+}
+
+/// Responds as long as the process is up and serving requests.
+#[allow(clippy::unused_async)] // needs to be async for axum handler
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn readyz(state: Extension<Arc<HealthState>>) -> (StatusCode, Json<Readiness>) {
+    let readiness = health::readiness(&state.server_config).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+async fn peers(state: Extension<Arc<HealthState>>) -> (StatusCode, Json<Vec<PeerStatus>>) {
+    let peers = health::probe_peers(
+        Transport::identity(&state.transport),
+        state.transport.clients(),
+        &state.network_config,
+    )
+    .await;
+    let status = if peers.iter().all(PeerStatus::is_healthy) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(peers))
+}
+
+pub fn router(state: Arc<HealthState>) -> Router {
+    Router::new()
+        .route(http_serde::health::HEALTHZ_AXUM_PATH, get(healthz))
+        .route(http_serde::health::READYZ_AXUM_PATH, get(readyz))
+        .route(http_serde::health::PEERS_AXUM_PATH, get(peers))
+        .layer(Extension(state))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use hyper::{Body, Request};
+
+    use super::*;
+    use crate::net::test::TestServer;
+
+    async fn get_status(server: &TestServer, path: &str) -> (StatusCode, hyper::body::Bytes) {
+        let req = Request::get(format!("http://localhost{path}"))
+            .body(Body::empty())
+            .unwrap();
+        let resp = server.server.handle_req(req).await;
+        let status = resp.status();
+        (
+            status,
+            hyper::body::to_bytes(resp.into_body()).await.unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn healthz_and_readyz() {
+        let server = TestServer::builder().build().await;
+
+        let (status, body) = get_status(&server, http_serde::health::HEALTHZ_AXUM_PATH).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(&b"ok"[..], &body[..]);
+
+        let (status, body) = get_status(&server, http_serde::health::READYZ_AXUM_PATH).await;
+        assert_eq!(StatusCode::OK, status);
+        let readiness: Readiness = serde_json::from_slice(&body).unwrap();
+        assert!(readiness.ready);
+    }
+
+    #[tokio::test]
+    async fn peers_reports_each_peer() {
+        // Test server only runs one helper, peers are configured but not listening
+        let server = TestServer::builder().disable_https().build().await;
+        let (_, body) = get_status(&server, http_serde::health::PEERS_AXUM_PATH).await;
+        let peers: Vec<PeerStatus> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(2, peers.len());
+        assert!(peers
+            .iter()
+            .all(|p| p.identity != Transport::identity(&server.transport)));
+    }
+}
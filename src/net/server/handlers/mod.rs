@@ -1,18 +1,25 @@
 mod echo;
+mod health;
 mod query;
 
+pub use health::HealthState;
+
 use axum::Router;
 
 use crate::{
-    net::{http_serde, HttpTransport},
+    net::{http_serde, server::rate_limit::RateLimiter, HttpTransport},
     sync::Arc,
 };
 
-pub fn router(transport: Arc<HttpTransport>) -> Router {
-    echo::router().nest(
+pub fn router(
+    transport: Arc<HttpTransport>,
+    rate_limiter: Arc<RateLimiter>,
+    health: Arc<HealthState>,
+) -> Router {
+    echo::router().merge(health::router(health)).nest(
         http_serde::query::BASE_AXUM_PATH,
         Router::new()
-            .merge(query::query_router(Arc::clone(&transport)))
+            .merge(query::query_router(Arc::clone(&transport), rate_limiter))
             .merge(query::h2h_router(transport)),
     )
 }
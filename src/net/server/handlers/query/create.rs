@@ -3,30 +3,48 @@ use hyper::StatusCode;
 
 use crate::{
     helpers::Transport,
-    net::{http_serde, Error, HttpTransport},
+    net::{
+        http_serde,
+        server::rate_limit::{Collector, QueryRateLimitLayer, RateLimiter},
+        Error, HttpTransport,
+    },
     query::NewQueryError,
     sync::Arc,
 };
 
 /// Takes details from the HTTP request and creates a `[TransportCommand]::CreateQuery` that is sent
-/// to the [`HttpTransport`].
+/// to the [`HttpTransport`], unless the collector that sent it already has as many queries running
+/// as it may.
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    rate_limiter: Extension<Arc<RateLimiter>>,
+    Extension(collector): Extension<Collector>,
     req: http_serde::query::create::Request,
 ) -> Result<Json<http_serde::query::create::ResponseBody>, Error> {
     let transport = Transport::clone_ref(&*transport);
+    let slot = rate_limiter.reserve_query(collector)?;
+
     match transport.receive_query(req.query_config).await {
-        Ok(query_id) => Ok(Json(http_serde::query::create::ResponseBody { query_id })),
+        Ok(query_id) => {
+            slot.started(query_id);
+            Ok(Json(http_serde::query::create::ResponseBody { query_id }))
+        }
         Err(err @ NewQueryError::State { .. }) => {
             Err(Error::application(StatusCode::CONFLICT, err))
         }
+        Err(NewQueryError::Rejected(err)) => match err.retry_after() {
+            Some(retry_after) => Err(Error::too_many_requests(retry_after, err)),
+            None => Err(Error::application(StatusCode::PAYLOAD_TOO_LARGE, err)),
+        },
         Err(err) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, err)),
     }
 }
 
-pub fn router(transport: Arc<HttpTransport>) -> Router {
+pub fn router(transport: Arc<HttpTransport>, rate_limiter: Arc<RateLimiter>) -> Router {
     Router::new()
         .route(http_serde::query::create::AXUM_PATH, post(handler))
+        .layer(QueryRateLimitLayer::new(Arc::clone(&rate_limiter)))
+        .layer(Extension(rate_limiter))
         .layer(Extension(transport))
 }
 
@@ -42,7 +60,7 @@ mod tests {
 
     use super::*;
     use crate::{
-        ff::FieldType,
+        ff::{FieldType, Fp31},
         helpers::{
             query::{IpaQueryConfig, QueryConfig, QueryType, SparseAggregateQueryConfig},
             TransportCallbacks,
@@ -52,6 +70,8 @@ mod tests {
             test::TestServer,
         },
         protocol::QueryId,
+        query::{AdmissionError, ProtocolResult},
+        secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
     };
 
     async fn create_test(expected_query_config: QueryConfig) {
@@ -95,6 +115,8 @@ mod tests {
                     attribution_window_seconds: None,
                     num_multi_bits: 3,
                     plaintext_match_keys: true,
+                    offline_preprocessing: false,
+                    gf2_sort: false,
                 }),
                 FieldType::Fp32BitPrime,
                 1,
@@ -115,6 +137,8 @@ mod tests {
                 attribution_window_seconds: NonZeroU32::new(86_400),
                 num_multi_bits: 3,
                 plaintext_match_keys: true,
+                offline_preprocessing: true,
+                gf2_sort: true,
             }),
         })
         .await;
@@ -142,6 +166,63 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn rejected_query_is_429() {
+        let cb = TransportCallbacks {
+            receive_query: Box::new(move |_transport, _query_config| {
+                Box::pin(ready(Err(NewQueryError::Rejected(
+                    AdmissionError::InsufficientMemory {
+                        required: 2,
+                        available: 1,
+                    },
+                ))))
+            }),
+            ..Default::default()
+        };
+        let TestServer { server, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::create::Request::new(
+            QueryConfig::new(QueryType::TestMultiply, FieldType::Fp31, 1).unwrap(),
+        )
+        .try_into_http_request(Scheme::HTTP, Authority::from_static("localhost"))
+        .unwrap();
+        let resp = server.handle_req(req).await;
+
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert!(resp.headers().contains_key(hyper::header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn limits_running_queries() {
+        let cb = TransportCallbacks {
+            receive_query: Box::new(|_transport, _query_config| Box::pin(ready(Ok(QueryId)))),
+            complete_query: Box::new(|_transport, _query_id| {
+                let results: Box<dyn ProtocolResult> = Box::new(Vec::<Replicated<Fp31>>::new());
+                Box::pin(ready(Ok(results)))
+            }),
+            ..Default::default()
+        };
+        let TestServer { server, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let create = || {
+            server.handle_req(
+                http_serde::query::create::Request::new(
+                    QueryConfig::new(QueryType::TestMultiply, FieldType::Fp31, 1).unwrap(),
+                )
+                .try_into_http_request(Scheme::HTTP, Authority::from_static("localhost"))
+                .unwrap(),
+            )
+        };
+
+        assert_eq!(StatusCode::OK, create().await.status());
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, create().await.status());
+
+        // collecting the results completes the query
+        let results = http_serde::query::results::Request::new(QueryId)
+            .try_into_http_request(Scheme::HTTP, Authority::from_static("localhost"))
+            .unwrap();
+        assert_eq!(StatusCode::OK, server.handle_req(results).await.status());
+        assert_eq!(StatusCode::OK, create().await.status());
+    }
+
     struct OverrideReq {
         field_type: String,
         query_type_params: String,
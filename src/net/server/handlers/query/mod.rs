@@ -19,7 +19,10 @@ use hyper::{http::request, Request, StatusCode};
 use tower::{layer::layer_fn, Service};
 
 use crate::{
-    net::{server::ClientIdentity, HttpTransport},
+    net::{
+        server::{rate_limit::RateLimiter, ClientIdentity},
+        HttpTransport,
+    },
     sync::Arc,
 };
 
@@ -28,12 +31,18 @@ use crate::{
 /// In principle, this web service could be backed by either an HTTP-interconnected helper network or
 /// an in-memory helper network. These are the APIs used by external callers (report collectors) to
 /// examine attribution results.
-pub fn query_router(transport: Arc<HttpTransport>) -> Router {
+///
+/// Query creation is subject to the limits enforced by `rate_limiter`, which is told when queries
+/// complete.
+pub fn query_router(transport: Arc<HttpTransport>, rate_limiter: Arc<RateLimiter>) -> Router {
     Router::new()
-        .merge(create::router(Arc::clone(&transport)))
+        .merge(create::router(
+            Arc::clone(&transport),
+            Arc::clone(&rate_limiter),
+        ))
         .merge(input::router(Arc::clone(&transport)))
         .merge(status::router(Arc::clone(&transport)))
-        .merge(results::router(transport))
+        .merge(results::router(transport, rate_limiter))
 }
 
 /// Construct router for helper-to-helper communications
@@ -1,29 +1,54 @@
-use std::sync::Arc;
+use std::{convert::Infallible, num::NonZeroUsize, sync::Arc};
 
-use axum::{routing::get, Extension, Router};
-use hyper::StatusCode;
+use axum::{body::StreamBody, routing::get, Extension, Router};
+use futures::{stream, Stream};
+use hyper::{body::Bytes, StatusCode};
 
 use crate::{
     helpers::Transport,
-    net::{http_serde, server::Error, HttpTransport},
+    net::{
+        http_serde,
+        server::{rate_limit::RateLimiter, Error},
+        HttpTransport,
+    },
+    query::QueryCompletionError,
 };
 
+/// Number of result rows serialized into a single chunk of the response body.
+const ROWS_PER_CHUNK: Option<NonZeroUsize> = NonZeroUsize::new(4096);
+
 /// Handles the completion of the query by blocking the sender until query is completed.
+///
+/// Results are serialized lazily and sent as a chunked body, so neither side needs to hold the
+/// entire serialized result in memory.
+///
+/// Once the query is completed, this helper forgets about it, so it stops counting towards the
+/// running queries of the collector that created it.
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    rate_limiter: Extension<Arc<RateLimiter>>,
     req: http_serde::query::results::Request,
-) -> Result<Vec<u8>, Error> {
-    // TODO: we may be able to stream the response
+) -> Result<StreamBody<impl Stream<Item = Result<Bytes, Infallible>>>, Error> {
     let transport = Transport::clone_ref(&*transport);
+    let completion = rate_limiter.complete_on_drop(req.query_id);
     match transport.complete_query(req.query_id).await {
-        Ok(result) => Ok(result.into_bytes()),
+        Ok(result) => Ok(StreamBody::new(stream::iter(
+            result
+                .into_chunks(ROWS_PER_CHUNK.unwrap())
+                .map(|chunk| Ok(Bytes::from(chunk))),
+        ))),
+        Err(e @ QueryCompletionError::StateError { .. }) => {
+            completion.still_running();
+            Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
         Err(e) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e)),
     }
 }
 
-pub fn router(transport: Arc<HttpTransport>) -> Router {
+pub fn router(transport: Arc<HttpTransport>, rate_limiter: Arc<RateLimiter>) -> Router {
     Router::new()
         .route(http_serde::query::results::AXUM_PATH, get(handler))
+        .layer(Extension(rate_limiter))
         .layer(Extension(transport))
 }
 
@@ -31,11 +56,12 @@ pub fn router(transport: Arc<HttpTransport>) -> Router {
 mod tests {
     use std::future::ready;
 
-    use axum::http::Request;
+    use axum::{http::Request, response::IntoResponse};
     use hyper::StatusCode;
 
     use super::*;
     use crate::{
+        config::QueryRateLimitConfig,
         ff::Fp31,
         helpers::TransportCallbacks,
         net::{
@@ -65,7 +91,13 @@ mod tests {
         };
         let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
         let req = http_serde::query::results::Request::new(QueryId);
-        let results = handler(Extension(transport), req.clone()).await.unwrap();
+        let rate_limiter = Arc::new(RateLimiter::new(QueryRateLimitConfig::default()));
+        let results = handler(Extension(transport), Extension(rate_limiter), req.clone())
+            .await
+            .unwrap();
+        let results = hyper::body::to_bytes(results.into_response().into_body())
+            .await
+            .unwrap();
         assert_eq!(results, expected_results.into_bytes());
     }
 
@@ -14,7 +14,10 @@ async fn handler(
 ) -> Result<Json<status::ResponseBody>, Error> {
     let transport = Transport::clone_ref(&*transport);
     match transport.query_status(req.query_id).await {
-        Ok(state) => Ok(Json(status::ResponseBody { status: state })),
+        Ok((state, progress)) => Ok(Json(status::ResponseBody {
+            status: state,
+            progress,
+        })),
         Err(e) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e)),
     }
 }
@@ -41,17 +44,26 @@ mod tests {
             test::TestServer,
         },
         protocol::QueryId,
-        query::QueryStatus,
+        query::{QueryProgress, QueryStatus},
     };
 
     #[tokio::test]
     async fn status_test() {
         let expected_status = QueryStatus::Running;
+        let expected_progress = QueryProgress {
+            completed_steps: 3,
+            estimated_total_steps: Some(10),
+            current_step: Some("after_convert_all_bits/perform_user_capping".to_owned()),
+            records_processed: 42,
+        };
         let expected_query_id = QueryId;
         let cb = TransportCallbacks {
-            query_status: Box::new(move |_transport, query_id| {
-                assert_eq!(query_id, expected_query_id);
-                Box::pin(ready(Ok(expected_status)))
+            query_status: Box::new({
+                let progress = expected_progress.clone();
+                move |_transport, query_id| {
+                    assert_eq!(query_id, expected_query_id);
+                    Box::pin(ready(Ok((expected_status, Some(progress.clone())))))
+                }
             }),
             ..Default::default()
         };
@@ -59,8 +71,9 @@ mod tests {
         let req = http_serde::query::status::Request::new(QueryId);
         let response = handler(Extension(transport), req.clone()).await.unwrap();
 
-        let Json(http_serde::query::status::ResponseBody { status }) = response;
+        let Json(http_serde::query::status::ResponseBody { status, progress }) = response;
         assert_eq!(status, expected_status);
+        assert_eq!(progress, Some(expected_progress));
     }
 
     struct OverrideReq {
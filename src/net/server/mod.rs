@@ -1,4 +1,5 @@
 mod handlers;
+mod rate_limit;
 
 use std::{
     borrow::Cow,
@@ -13,8 +14,8 @@ use ::tokio::{
     io::{AsyncRead, AsyncWrite},
 };
 use axum::{
+    extract::connect_info::IntoMakeServiceWithConnectInfo,
     response::{IntoResponse, Response},
-    routing::IntoMakeService,
     Router,
 };
 use axum_server::{
@@ -48,7 +49,10 @@ use crate::{
     config::{NetworkConfig, ServerConfig, TlsConfig},
     error::BoxError,
     helpers::HelperIdentity,
-    net::{Error, HttpTransport},
+    net::{
+        server::{handlers::HealthState, rate_limit::RateLimiter},
+        Error, HttpTransport,
+    },
     sync::Arc,
     task::JoinHandle,
     telemetry::metrics::{web::RequestProtocolVersion, REQUESTS_RECEIVED},
@@ -81,6 +85,8 @@ pub struct MpcHelperServer {
     transport: Arc<HttpTransport>,
     config: ServerConfig,
     network_config: NetworkConfig,
+    rate_limiter: Arc<RateLimiter>,
+    health: Arc<HealthState>,
 }
 
 impl MpcHelperServer {
@@ -89,15 +95,27 @@ impl MpcHelperServer {
         config: ServerConfig,
         network_config: NetworkConfig,
     ) -> Self {
+        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit.clone()));
+        let health = Arc::new(HealthState {
+            transport: Arc::clone(&transport),
+            server_config: config.clone(),
+            network_config: network_config.clone(),
+        });
         MpcHelperServer {
             transport,
             config,
             network_config,
+            rate_limiter,
+            health,
         }
     }
 
     fn router(&self) -> Router {
-        handlers::router(Arc::clone(&self.transport))
+        handlers::router(
+            Arc::clone(&self.transport),
+            Arc::clone(&self.rate_limiter),
+            Arc::clone(&self.health),
+        )
     }
 
     #[cfg(all(test, unit_test))]
@@ -147,14 +165,14 @@ impl MpcHelperServer {
             (true, Some(listener)) => {
                 let svc = svc
                     .layer(layer_fn(SetClientIdentityFromHeader::new))
-                    .into_make_service();
+                    .into_make_service_with_connect_info::<SocketAddr>();
                 spawn_server(axum_server::from_tcp(listener), handle.clone(), svc).await
             }
             (true, None) => {
                 let addr = SocketAddr::new(BIND_ADDRESS.into(), self.config.port.unwrap_or(0));
                 let svc = svc
                     .layer(layer_fn(SetClientIdentityFromHeader::new))
-                    .into_make_service();
+                    .into_make_service_with_connect_info::<SocketAddr>();
                 spawn_server(axum_server::bind(addr), handle.clone(), svc).await
             }
             (false, Some(listener)) => {
@@ -166,7 +184,7 @@ impl MpcHelperServer {
                         ClientCertRecognizingAcceptor::new(a, self.network_config.clone())
                     }),
                     handle.clone(),
-                    svc.into_make_service(),
+                    svc.into_make_service_with_connect_info::<SocketAddr>(),
                 )
                 .await
             }
@@ -180,7 +198,7 @@ impl MpcHelperServer {
                         ClientCertRecognizingAcceptor::new(a, self.network_config.clone())
                     }),
                     handle.clone(),
-                    svc.into_make_service(),
+                    svc.into_make_service_with_connect_info::<SocketAddr>(),
                 )
                 .await
             }
@@ -215,12 +233,12 @@ impl MpcHelperServer {
 async fn spawn_server<A>(
     server: Server<A>,
     handle: Handle,
-    svc: IntoMakeService<Router>,
+    svc: IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
 ) -> JoinHandle<()>
 where
     A: Accept<
             AddrStream,
-            <IntoMakeService<Router> as MakeServiceRef<
+            <IntoMakeServiceWithConnectInfo<Router, SocketAddr> as MakeServiceRef<
                 AddrStream,
                 hyper::Request<hyper::Body>,
             >>::Service,
@@ -245,7 +263,7 @@ where
     })
 }
 
-async fn certificate_and_key(
+pub(super) async fn certificate_and_key(
     config: &ServerConfig,
 ) -> Result<(Vec<Certificate>, PrivateKey), BoxError> {
     let (cert, key) = match &config.tls {
@@ -0,0 +1,500 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::{IpAddr, SocketAddr},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::ConnectInfo,
+    response::{IntoResponse, Response},
+};
+use futures::{
+    future::{ready, Either, Ready},
+    FutureExt,
+};
+use hyper::Request;
+use tower::{Layer, Service};
+
+use crate::{
+    config::QueryRateLimitConfig,
+    helpers::HelperIdentity,
+    net::{server::ClientIdentity, Error},
+    protocol::QueryId,
+    sync::{Arc, Mutex},
+};
+
+/// How long a report collector is asked to wait before creating another query while it is at its
+/// limit of running queries. Queries take minutes to hours, so there is no point in retrying sooner.
+const QUERY_LIMIT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How often the limiter forgets collectors that are idle.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Identifies the report collector that issued a request, for the purpose of rate limiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Collector {
+    /// The caller presented a certificate that belongs to one of the helpers.
+    Helper(HelperIdentity),
+    /// Other callers are told apart by the address they connect from.
+    Address(IpAddr),
+    /// The request did not come through a connection, e.g. when the router is called directly.
+    Unknown,
+}
+
+impl<B> From<&Request<B>> for Collector {
+    fn from(req: &Request<B>) -> Self {
+        if let Some(id) = req.extensions().get::<ClientIdentity>() {
+            return Self::Helper(**id);
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map_or(Self::Unknown, |ConnectInfo(addr)| Self::Address(addr.ip()))
+    }
+}
+
+struct CollectorState {
+    tokens: u32,
+    last_refill: Instant,
+    /// Query creation requests that are being processed.
+    pending: usize,
+    /// Queries this collector created that are still running.
+    running: usize,
+}
+
+impl CollectorState {
+    fn new(config: &QueryRateLimitConfig) -> Self {
+        Self {
+            tokens: config.burst,
+            last_refill: Instant::now(),
+            pending: 0,
+            running: 0,
+        }
+    }
+
+    /// Adds the tokens earned since the last refill to the bucket.
+    fn refill(&mut self, config: &QueryRateLimitConfig, now: Instant) {
+        let (interval, burst) = (config.refill_interval, config.burst);
+        if interval.is_zero() {
+            self.tokens = burst;
+            return;
+        }
+        let elapsed = now.duration_since(self.last_refill);
+        let refills = u32::try_from(elapsed.as_nanos() / interval.as_nanos()).unwrap_or(u32::MAX);
+        if refills > 0 {
+            self.tokens = self.tokens.saturating_add(refills).min(burst);
+            self.last_refill = if self.tokens == burst {
+                now
+            } else {
+                self.last_refill + interval * refills
+            };
+        }
+    }
+
+    /// An idle collector is in the same state as one that was never seen, so it can be
+    /// forgotten.
+    fn is_idle(&self, config: &QueryRateLimitConfig) -> bool {
+        self.pending == 0 && self.running == 0 && self.tokens == config.burst
+    }
+}
+
+struct LimiterState {
+    collectors: HashMap<Collector, CollectorState>,
+    /// The collector that created each running query.
+    queries: HashMap<QueryId, Collector>,
+    last_prune: Instant,
+}
+
+/// Per-collector token buckets and running queries, shared by all connections.
+///
+/// Queries count towards the limit of the collector that created them until they are completed,
+/// which is when this helper forgets about them.
+pub struct RateLimiter {
+    config: QueryRateLimitConfig,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: QueryRateLimitConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(LimiterState {
+                collectors: HashMap::new(),
+                queries: HashMap::new(),
+                last_prune: Instant::now(),
+            }),
+        }
+    }
+
+    fn with_state<T>(&self, collector: Collector, f: impl FnOnce(&mut CollectorState) -> T) -> T {
+        let mut state = self.state.lock().unwrap();
+        let collector = state
+            .collectors
+            .entry(collector)
+            .or_insert_with(|| CollectorState::new(&self.config));
+        f(collector)
+    }
+
+    /// Forgets the collectors that are idle.
+    fn prune(&self, state: &mut LimiterState, now: Instant) {
+        state.collectors.retain(|_, collector| {
+            collector.refill(&self.config, now);
+            !collector.is_idle(&self.config)
+        });
+        state.last_prune = now;
+    }
+
+    /// Takes one token from `collector`'s bucket.
+    ///
+    /// ## Errors
+    /// If the collector exhausted its quota. The error carries the time after which a retry may
+    /// succeed.
+    fn acquire(&self, collector: Collector) -> Result<(), Error> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        if now.duration_since(state.last_prune) >= PRUNE_INTERVAL {
+            self.prune(&mut state, now);
+        }
+
+        let state = state
+            .collectors
+            .entry(collector)
+            .or_insert_with(|| CollectorState::new(&self.config));
+        state.refill(&self.config, now);
+
+        let interval = self.config.refill_interval;
+        if state.tokens == 0 && !interval.is_zero() {
+            let retry_after = interval.saturating_sub(now.duration_since(state.last_refill));
+            return Err(Error::too_many_requests(
+                retry_after.max(Duration::from_secs(1)),
+                "query creation rate limit exceeded",
+            ));
+        }
+
+        state.tokens = state.tokens.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Stops counting `query_id` towards the limit of the collector that created it, because this
+    /// helper no longer knows about it.
+    pub fn finished(&self, query_id: QueryId) {
+        let mut state = self.state.lock().unwrap();
+        let Some(collector) = state.queries.remove(&query_id) else {
+            return;
+        };
+        if let Entry::Occupied(mut entry) = state.collectors.entry(collector) {
+            let collector = entry.get_mut();
+            collector.running -= 1;
+            collector.refill(&self.config, Instant::now());
+            if collector.is_idle(&self.config) {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Returns a guard that calls [`finished`] for `query_id` when it is dropped, unless the
+    /// query turns out to be [`still_running`].
+    ///
+    /// [`finished`]: RateLimiter::finished
+    /// [`still_running`]: QueryCompletion::still_running
+    pub fn complete_on_drop(self: &Arc<Self>, query_id: QueryId) -> QueryCompletion {
+        QueryCompletion {
+            limiter: Some(Arc::clone(self)),
+            query_id,
+        }
+    }
+
+    /// Reserves room for one more query of `collector`.
+    ///
+    /// ## Errors
+    /// If the collector already has as many queries running, or being created, as it may.
+    pub fn reserve_query(self: &Arc<Self>, collector: Collector) -> Result<QuerySlot, Error> {
+        let limit = self.config.max_running_queries;
+        self.with_state(collector, |state| {
+            let running = state.pending + state.running;
+            if running >= limit {
+                return Err(Error::too_many_requests(
+                    QUERY_LIMIT_RETRY_AFTER,
+                    format!("{running} queries are already running, the limit is {limit}"),
+                ));
+            }
+            state.pending += 1;
+            Ok(())
+        })?;
+
+        Ok(QuerySlot {
+            limiter: Arc::clone(self),
+            collector,
+        })
+    }
+}
+
+/// Room for a query that is being created. If the query is not [`started`], the room is released
+/// when the slot is dropped.
+///
+/// [`started`]: QuerySlot::started
+pub struct QuerySlot {
+    limiter: Arc<RateLimiter>,
+    collector: Collector,
+}
+
+impl QuerySlot {
+    /// Counts `query_id` towards the collector's limit until it is [`finished`].
+    ///
+    /// [`finished`]: RateLimiter::finished
+    pub fn started(self, query_id: QueryId) {
+        self.limiter.finished(query_id);
+        let mut state = self.limiter.state.lock().unwrap();
+        state.queries.insert(query_id, self.collector);
+        state
+            .collectors
+            .entry(self.collector)
+            .or_insert_with(|| CollectorState::new(&self.limiter.config))
+            .running += 1;
+    }
+}
+
+impl Drop for QuerySlot {
+    fn drop(&mut self) {
+        self.limiter
+            .with_state(self.collector, |state| state.pending -= 1);
+    }
+}
+
+/// Marks a query [`finished`] when dropped, which covers both the query completing and the
+/// request that awaits its completion going away, as this helper forgets about the query either
+/// way.
+///
+/// [`finished`]: RateLimiter::finished
+pub struct QueryCompletion {
+    limiter: Option<Arc<RateLimiter>>,
+    query_id: QueryId,
+}
+
+impl QueryCompletion {
+    /// Keeps counting the query, because it could not be completed yet.
+    pub fn still_running(mut self) {
+        self.limiter = None;
+    }
+}
+
+impl Drop for QueryCompletion {
+    fn drop(&mut self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.finished(self.query_id);
+        }
+    }
+}
+
+/// [`Layer`] that applies the [`QueryRateLimitConfig`] request rate to the wrapped service.
+#[derive(Clone)]
+pub struct QueryRateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl QueryRateLimitLayer {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for QueryRateLimitLayer {
+    type Service = QueryRateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        QueryRateLimit {
+            inner,
+            limiter: Arc::clone(&self.limiter),
+        }
+    }
+}
+
+/// Returns HTTP 429 Too Many Requests with a `Retry-After` header if the collector that sent the
+/// request exceeded its rate limit. Otherwise, passes the [`Collector`] on to the handler as a
+/// request extension, so that it can apply the limit on running queries.
+#[derive(Clone)]
+pub struct QueryRateLimit<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<B, S> Service<Request<B>> for QueryRateLimit<S>
+where
+    S: Service<Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let collector = Collector::from(&req);
+        match self.limiter.acquire(collector) {
+            Ok(()) => {
+                req.extensions_mut().insert(collector);
+                self.inner.call(req).left_future()
+            }
+            Err(e) => ready(Ok(e.into_response())).right_future(),
+        }
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use hyper::StatusCode;
+
+    use super::*;
+
+    fn limiter(
+        max_running_queries: usize,
+        burst: u32,
+        refill_interval: Duration,
+    ) -> Arc<RateLimiter> {
+        Arc::new(RateLimiter::new(QueryRateLimitConfig {
+            max_running_queries,
+            burst,
+            refill_interval,
+        }))
+    }
+
+    fn collector(last_octet: u8) -> Collector {
+        Collector::Address(Ipv4Addr::new(10, 0, 0, last_octet).into())
+    }
+
+    #[test]
+    fn identifies_collectors() {
+        let mut req = Request::new(());
+        assert_eq!(Collector::Unknown, Collector::from(&req));
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))));
+        assert_eq!(collector(1), Collector::from(&req));
+        req.extensions_mut()
+            .insert(ClientIdentity(HelperIdentity::TWO));
+        assert_eq!(
+            Collector::Helper(HelperIdentity::TWO),
+            Collector::from(&req)
+        );
+    }
+
+    #[test]
+    fn limits_running_queries() {
+        let limiter = limiter(1, 10, Duration::from_secs(1));
+        let slot = limiter.reserve_query(collector(1)).unwrap();
+        assert!(matches!(
+            limiter.reserve_query(collector(1)),
+            Err(Error::TooManyRequests { .. })
+        ));
+
+        // the query keeps counting after it was created, until it finishes
+        slot.started(QueryId);
+        assert!(limiter.reserve_query(collector(1)).is_err());
+        limiter.finished(QueryId);
+        drop(limiter.reserve_query(collector(1)).unwrap());
+
+        // a query that fails to be created releases its slot
+        drop(limiter.reserve_query(collector(1)).unwrap());
+        limiter.reserve_query(collector(1)).unwrap();
+    }
+
+    #[test]
+    fn completion_guard() {
+        let limiter = limiter(1, 10, Duration::from_secs(1));
+        limiter
+            .reserve_query(collector(1))
+            .unwrap()
+            .started(QueryId);
+
+        limiter.complete_on_drop(QueryId).still_running();
+        assert!(limiter.reserve_query(collector(1)).is_err());
+
+        drop(limiter.complete_on_drop(QueryId));
+        limiter.reserve_query(collector(1)).unwrap();
+    }
+
+    #[test]
+    fn prunes_idle_collectors() {
+        let limiter = limiter(1, 1, Duration::from_millis(1));
+        limiter.acquire(collector(1)).unwrap();
+        limiter.acquire(collector(2)).unwrap();
+        limiter
+            .reserve_query(collector(2))
+            .unwrap()
+            .started(QueryId);
+        std::thread::sleep(Duration::from_millis(2));
+
+        // collectors with running queries are kept until the queries finish
+        let mut state = limiter.state.lock().unwrap();
+        limiter.prune(&mut state, Instant::now());
+        assert_eq!(
+            vec![collector(2)],
+            state.collectors.keys().copied().collect::<Vec<_>>()
+        );
+        drop(state);
+
+        limiter.finished(QueryId);
+        assert!(limiter.state.lock().unwrap().collectors.is_empty());
+    }
+
+    #[test]
+    fn limits_collectors_independently() {
+        let limiter = limiter(1, 1, Duration::from_secs(3600));
+        limiter.acquire(collector(1)).unwrap();
+        limiter
+            .reserve_query(collector(1))
+            .unwrap()
+            .started(QueryId);
+        assert!(limiter.acquire(collector(1)).is_err());
+        assert!(limiter.reserve_query(collector(1)).is_err());
+
+        limiter.acquire(collector(2)).unwrap();
+        limiter
+            .reserve_query(collector(2))
+            .unwrap()
+            .started(QueryId);
+        assert!(limiter.acquire(collector(2)).is_err());
+        assert!(limiter.reserve_query(collector(2)).is_err());
+
+        let helper = Collector::Helper(HelperIdentity::ONE);
+        limiter.acquire(helper).unwrap();
+        limiter.reserve_query(helper).unwrap();
+    }
+
+    #[test]
+    fn limits_rate() {
+        let limiter = limiter(10, 2, Duration::from_secs(3600));
+        limiter.acquire(collector(1)).unwrap();
+        limiter.acquire(collector(1)).unwrap();
+        let Err(Error::TooManyRequests { retry_after, .. }) = limiter.acquire(collector(1)) else {
+            panic!("third request must be rejected");
+        };
+        assert!(retry_after > Duration::from_secs(3500));
+    }
+
+    #[test]
+    fn refills() {
+        let limiter = limiter(10, 1, Duration::from_millis(1));
+        for _ in 0..3 {
+            limiter.acquire(collector(1)).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn rejection_is_429() {
+        let resp = Error::too_many_requests(Duration::from_millis(1500), "busy").into_response();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert_eq!(
+            "2",
+            resp.headers()
+                .get(hyper::header::RETRY_AFTER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+    }
+}
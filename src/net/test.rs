@@ -20,8 +20,8 @@ use tokio_rustls::rustls::Certificate;
 
 use crate::{
     config::{
-        ClientConfig, HpkeClientConfig, HpkeServerConfig, NetworkConfig, PeerConfig, ServerConfig,
-        TlsConfig,
+        ClientConfig, HpkeClientConfig, HpkeServerConfig, NetworkConfig, PeerConfig,
+        QueryRateLimitConfig, ServerConfig, TlsConfig,
     },
     helpers::{HelperIdentity, TransportCallbacks},
     hpke::{Deserializable as _, IpaPublicKey},
@@ -71,6 +71,7 @@ fn server_config_insecure_http(port: u16, matchkey_encryption: bool) -> ServerCo
         disable_https: true,
         tls: None,
         hpke_config: get_dummy_matchkey_encryption_info(matchkey_encryption),
+        rate_limit: QueryRateLimitConfig::default(),
     }
 }
 
@@ -89,6 +90,7 @@ pub fn server_config_https(
             private_key: String::from_utf8(private_key.to_owned()).unwrap(),
         }),
         hpke_config: get_dummy_matchkey_encryption_info(matchkey_encryption),
+        rate_limit: QueryRateLimitConfig::default(),
     }
 }
 
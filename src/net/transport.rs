@@ -63,6 +63,12 @@ impl HttpTransport {
         })
     }
 
+    /// Clients this helper uses to talk to every helper in the network, including itself.
+    #[must_use]
+    pub fn clients(&self) -> &[MpcHelperClient; 3] {
+        &self.clients
+    }
+
     pub fn receive_query(self: Arc<Self>, req: QueryConfig) -> ReceiveQueryResult {
         (Arc::clone(&self).callbacks.receive_query)(self, req)
     }
@@ -189,7 +195,10 @@ impl Transport for Arc<HttpTransport> {
 mod tests {
     use std::{iter::zip, net::TcpListener, task::Poll};
 
-    use futures::stream::{poll_immediate, StreamExt};
+    use futures::{
+        stream::{poll_immediate, StreamExt},
+        TryStreamExt,
+    };
     use futures_util::future::{join_all, try_join_all};
     use generic_array::GenericArray;
     use once_cell::sync::Lazy;
@@ -353,7 +362,14 @@ mod tests {
         try_join_all(handle_resps).await.unwrap();
 
         let result: [_; 3] = join_all(clients.clone().map(|client| async move {
-            let r = client.query_results(query_id).await.unwrap();
+            let r = client
+                .query_results(query_id)
+                .await
+                .unwrap()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+                .concat();
             AdditiveShare::<Fp31>::from_byte_slice(&r).collect::<Vec<_>>()
         }))
         .await
@@ -3,6 +3,7 @@ mod input;
 use futures::{stream::iter as stream_iter, Stream, TryStreamExt};
 use futures_util::StreamExt;
 pub use input::SparseAggregateInputRow;
+use ipa_macros::Step;
 
 use super::{context::Context, sort::bitwise_to_onehot, step::BitOpStep, RecordId};
 use crate::{
@@ -24,34 +25,24 @@ use crate::{
     seq_join::seq_join,
 };
 
-// TODO: Use `#[derive(Step)]` once the protocol is implemented and the bench test is enabled.
-//       Once that is done, run `collect_steps.py` to generate `steps.txt` that includes these steps.
-
+#[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = AggregationStep)]
     Validator,
+}
+
+#[derive(Step)]
+pub(crate) enum AggregationStep {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ConvertValueBits,
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ConvertBreakdownKeyBits,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputeEqualityChecks,
+    #[step(child = crate::protocol::step::BitOpStep)]
     CheckTimesValue,
 }
-impl crate::protocol::step::Step for Step {}
-impl AsRef<str> for Step {
-    fn as_ref(&self) -> &str {
-        match self {
-            Step::Validator => "validator",
-            Step::ConvertValueBits => "convert_value_bits",
-            Step::ConvertBreakdownKeyBits => "convert_breakdown_key_bits",
-            Step::ComputeEqualityChecks => "convert_equality_key_bits",
-            Step::CheckTimesValue => "check_times_values",
-        }
-    }
-}
-#[cfg(feature = "compact-gate")]
-impl super::step::StepNarrow<Step> for crate::protocol::step::Compact {
-    fn narrow(&self, _step: &Step) -> Self {
-        unimplemented!("compact gate is not supported in unit tests")
-    }
-}
 
 /// Binary-share aggregation protocol for a sparse breakdown key vector input.
 /// It takes a tuple of two vectors, `contribution_values` and `breakdown_keys`,
@@ -91,12 +82,12 @@ where
     // convert the input from `[Z2]^u` into `[Zp]^u`
     let (converted_value_bits, converted_breakdown_key_bits) = (
         upgrade_bit_shares(
-            &ctx.narrow(&Step::ConvertValueBits),
+            &ctx.narrow(&AggregationStep::ConvertValueBits),
             contributions,
             CV::BITS,
         ),
         upgrade_bit_shares(
-            &ctx.narrow(&Step::ConvertBreakdownKeyBits),
+            &ctx.narrow(&AggregationStep::ConvertBreakdownKeyBits),
             breakdowns,
             BK::BITS,
         ),
@@ -133,7 +124,7 @@ where
     S: LinearSecretSharing<F> + BasicProtocols<C, F> + Serializable + 'static,
     for<'a> &'a S: LinearRefOps<'a, S, F>,
 {
-    let equality_check_ctx = ctx.narrow(&Step::ComputeEqualityChecks);
+    let equality_check_ctx = ctx.narrow(&AggregationStep::ComputeEqualityChecks);
 
     // Generate N streams for each bucket specified by the `num_buckets`.
     // A stream is pipeline of contribution values multiplied by the "equality bit". An equality
@@ -176,7 +167,7 @@ where
     S: LinearSecretSharing<F> + BasicProtocols<C, F> + Serializable + 'static,
     for<'a> &'a S: LinearRefOps<'a, S, F>,
 {
-    let check_times_value_ctx = ctx.narrow(&Step::CheckTimesValue);
+    let check_times_value_ctx = ctx.narrow(&AggregationStep::CheckTimesValue);
 
     ctx.try_join(
         check_bits
@@ -215,11 +206,7 @@ where
         })
     });
 
-    convert_bits(
-        ctx.narrow(&Step::ConvertValueBits),
-        stream_iter(gf2_bits),
-        0..num_bits,
-    )
+    convert_bits(ctx.clone(), stream_iter(gf2_bits), 0..num_bits)
 }
 
 #[cfg(all(test, unit_test))]
@@ -227,9 +214,15 @@ mod tests {
     use super::sparse_aggregate;
     use crate::{
         ff::{Field, Fp32BitPrime, GaloisField, Gf3Bit, Gf8Bit},
+        helpers::GatewayConfig,
         protocol::aggregation::SparseAggregateInputRow,
         secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, SharedValue},
-        test_fixture::{Reconstruct, Runner, TestWorld},
+        test_fixture::{
+            cost::{self, Cost},
+            ipa::IpaSecurityModel,
+            simulate::Simulator,
+            Reconstruct, Runner, TestWorld, TestWorldConfig,
+        },
     };
 
     fn create_input_vec<BK, CV>(
@@ -311,4 +304,64 @@ mod tests {
             .reconstruct();
         assert_eq!(result, EXPECTED);
     }
+
+    #[tokio::test]
+    pub async fn cost_model() {
+        type BK = Gf3Bit;
+        type CV = Gf8Bit;
+
+        const ROWS: usize = 20;
+        const NUM_BUCKETS: usize = 6;
+
+        let input = (0..ROWS).map(|i| {
+            let i = u128::try_from(i).unwrap();
+            (
+                BK::truncate_from(i % u128::try_from(NUM_BUCKETS).unwrap()),
+                CV::truncate_from(i),
+            )
+        });
+
+        for security in [IpaSecurityModel::SemiHonest, IpaSecurityModel::Malicious] {
+            let simulator = Simulator::new(TestWorldConfig {
+                gateway_config: GatewayConfig::new(ROWS),
+                ..Default::default()
+            });
+            let world = simulator.world();
+            match security {
+                IpaSecurityModel::SemiHonest => {
+                    world
+                        .semi_honest(input.clone(), |ctx, shares| async move {
+                            sparse_aggregate::<_, _, _, Fp32BitPrime, CV, BK>(
+                                ctx,
+                                &create_input_vec(&shares),
+                                NUM_BUCKETS,
+                            )
+                            .await
+                            .unwrap()
+                        })
+                        .await;
+                }
+                IpaSecurityModel::Malicious => {
+                    world
+                        .malicious(input.clone(), |ctx, shares| async move {
+                            sparse_aggregate::<_, _, _, Fp32BitPrime, CV, BK>(
+                                ctx,
+                                &create_input_vec(&shares),
+                                NUM_BUCKETS,
+                            )
+                            .await
+                            .unwrap()
+                        })
+                        .await;
+                }
+            }
+
+            let model = cost::sparse_aggregate::<Fp32BitPrime, CV, BK>(ROWS, NUM_BUCKETS, security);
+            let measured = Cost::measured(&simulator);
+            assert!(
+                model.is_close_to(&measured, 0.1),
+                "{security:?}: expected {model:?}, measured {measured:?}"
+            );
+        }
+    }
 }
@@ -142,6 +142,7 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     ActiveBitTimesStopBit,
 }
 
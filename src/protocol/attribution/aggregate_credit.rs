@@ -143,8 +143,11 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputeEqualityChecks,
+    #[step(child = crate::protocol::step::BitOpStep)]
     CheckTimesCredit,
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ModConvBreakdownKeyBits,
 }
 
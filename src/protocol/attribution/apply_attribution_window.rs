@@ -13,7 +13,10 @@ use crate::{
     error::Error,
     ff::{Field, PrimeField},
     protocol::{
-        boolean::{greater_than_constant, random_bits_generator::RandomBitsGenerator},
+        boolean::{
+            greater_than_constant,
+            random_bits_generator::{RandomBitsGenerator, RandomBitsPool},
+        },
         context::{Context, UpgradedContext},
         BasicProtocols, RecordId,
     },
@@ -24,6 +27,9 @@ use crate::{
 /// events that are outside the window will be replaced with 0, hence will not be attributed to
 /// corresponding source events in the later aggregation stages.
 ///
+/// Comparisons against the window draw their random bits from `random_bits`, when given. The
+/// pool must have been generated by [`random_bits`] with the same `ctx`.
+///
 /// # Errors
 /// Fails if sub-protocols fails.
 #[tracing::instrument(name = "apply_window", skip_all)]
@@ -32,6 +38,7 @@ pub async fn apply_attribution_window<F, C, S>(
     input: &[ApplyAttributionWindowInputRow<F, S>],
     stop_bits: &[S],
     attribution_window_seconds: Option<NonZeroU32>,
+    random_bits: Option<RandomBitsPool<F, S>>,
 ) -> Result<Vec<ApplyAttributionWindowOutputRow<F, S>>, Error>
 where
     F: PrimeField,
@@ -47,6 +54,7 @@ where
             input,
             &mut t_deltas,
             attribution_window_seconds.get(),
+            random_bits,
         )
         .await?;
 
@@ -78,6 +86,21 @@ where
     }
 }
 
+/// Generates the random bits that [`apply_attribution_window`] needs to compare `count` records
+/// against the window, before its inputs are known.
+///
+/// # Errors
+/// If generating the bits fails.
+pub async fn random_bits<F, C, S>(ctx: C, count: usize) -> Result<RandomBitsPool<F, S>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    RandomBitsPool::generate(ctx.narrow(&Step::RandomBitsForBitDecomposition), count).await
+}
+
 /// Computes time deltas from each trigger event to its nearest matching source event.
 ///
 /// # Errors
@@ -146,6 +169,7 @@ async fn zero_out_expired_trigger_values<F, C, S>(
     input: &[ApplyAttributionWindowInputRow<F, S>],
     time_delta: &mut [S],
     cap: u32,
+    random_bits: Option<RandomBitsPool<F, S>>,
 ) -> Result<Vec<(S, S)>, Error>
 where
     F: PrimeField,
@@ -154,8 +178,11 @@ where
     for<'a> &'a S: LinearRefOps<'a, S, F>,
 {
     let ctx = ctx.set_total_records(input.len());
-    let random_bits_generator =
-        RandomBitsGenerator::new(ctx.narrow(&Step::RandomBitsForBitDecomposition));
+    let rbg_ctx = ctx.narrow(&Step::RandomBitsForBitDecomposition);
+    let random_bits_generator = match random_bits {
+        Some(pool) => RandomBitsGenerator::with_pool(rbg_ctx, pool),
+        None => RandomBitsGenerator::new(rbg_ctx),
+    };
     let rbg = &random_bits_generator;
     let cmp_ctx = ctx.narrow(&Step::TimeDeltaLessThanCap);
     let mul_ctx = ctx.narrow(&Step::CompareBitTimesTriggerValue);
@@ -187,9 +214,14 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     InitializeTimeDelta,
+    #[step(child = crate::protocol::boolean::random_bits_generator::FallbackStep)]
+    #[step(child = crate::protocol::boolean::solved_bits::Step)]
     RandomBitsForBitDecomposition,
+    #[step(child = crate::protocol::boolean::comparison::GreaterThanConstantStep)]
     TimeDeltaLessThanCap,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     CompareBitTimesTriggerValue,
 }
 
@@ -262,7 +294,7 @@ mod tests {
                     let (itb, hb): (Vec<_>, Vec<_>) = input.iter().map(|x| (x.is_trigger_report.clone(), x.helper_bit.clone())).unzip();
                     let stop_bits = compute_stop_bits(ctx.clone(), &itb, &hb).await.unwrap().collect::<Vec<_>>();
 
-                    apply_attribution_window(ctx, &input, &stop_bits, ATTRIBUTION_WINDOW)
+                    apply_attribution_window(ctx, &input, &stop_bits, ATTRIBUTION_WINDOW, None)
                         .await
                         .unwrap()
                 },
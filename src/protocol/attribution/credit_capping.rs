@@ -1,6 +1,7 @@
 use std::iter::{repeat, zip};
 
 use futures::{
+    future::try_join,
     stream::{iter, once},
     StreamExt, TryStreamExt,
 };
@@ -12,16 +13,22 @@ use crate::{
     ff::{Field, PrimeField},
     protocol::{
         basics::{if_else, SecureMul},
-        boolean::{greater_than_constant, random_bits_generator::RandomBitsGenerator},
+        boolean::{
+            greater_than_constant,
+            random_bits_generator::{RandomBitsGenerator, RandomBitsPool},
+        },
         context::{Context, UpgradedContext},
         BasicProtocols, RecordId,
     },
-    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
+    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps, SecretSharing},
     seq_join::seq_join,
 };
 
 /// User-level credit capping protocol.
 ///
+/// Comparisons against the cap draw their random bits from `random_bits`, when given. The pools
+/// must have been generated by [`random_bits`] with the same `ctx`.
+///
 /// ## Errors
 /// Fails if the multiplication protocol fails, or if the `cap` is larger than
 /// 1/2 of the prime number.
@@ -30,6 +37,7 @@ pub async fn credit_capping<F, C, S>(
     ctx: C,
     input: &[CreditCappingInputRow<F, S>],
     cap: u32,
+    random_bits: Option<CappingRandomBits<F, S>>,
 ) -> Result<Vec<S>, Error>
 where
     F: PrimeField,
@@ -43,6 +51,9 @@ where
             .collect::<Vec<_>>());
     }
     let input_len = input.len();
+    let (report_level_bits, user_level_bits) = random_bits.map_or((None, None), |bits| {
+        (Some(bits.report_level), Some(bits.user_level))
+    });
 
     if (u128::from(cap) * 2) >= F::PRIME.into() {
         return Err(crate::error::Error::InvalidQueryParameter(format!(
@@ -80,7 +91,8 @@ where
     // reversed prefix-OR of the `is_credit_larger_than_cap` step, then we can apply the cap to
     // all rows that precede the most recent row with a value larger than the cap.
     //
-    let capped_credits = report_level_capping(ctx.clone(), &original_credits, cap).await?;
+    let capped_credits =
+        report_level_capping(ctx.clone(), &original_credits, cap, report_level_bits).await?;
 
     //
     // Step 3. Compute user-level reversed prefix-sums
@@ -94,7 +106,8 @@ where
     // `exceeds_cap_bits` = 1 if `prefix_summed_credits` > `cap`
     //
     let exceeds_cap_bits =
-        is_credit_larger_than_cap(ctx.clone(), &prefix_summed_credits, cap).await?;
+        is_credit_larger_than_cap(ctx.clone(), &prefix_summed_credits, cap, user_level_bits)
+            .await?;
 
     //
     // Step 5. Compute the reversed prefix-OR of `exceeds_cap_bits`
@@ -237,6 +250,7 @@ async fn report_level_capping<F, C, S>(
     ctx: C,
     original_credits: &[S],
     cap: u32,
+    random_bits: Option<RandomBitsPool<F, S>>,
 ) -> Result<Vec<S>, Error>
 where
     F: PrimeField,
@@ -246,9 +260,13 @@ where
 {
     let share_of_cap = S::share_known_value(&ctx, F::truncate_from(cap));
     let cap_ref = &share_of_cap;
-    let exceeds_cap_bits =
-        is_credit_larger_than_cap(ctx.narrow(&Step::ReportLevelCapping), original_credits, cap)
-            .await?;
+    let exceeds_cap_bits = is_credit_larger_than_cap(
+        ctx.narrow(&Step::ReportLevelCapping),
+        original_credits,
+        cap,
+        random_bits,
+    )
+    .await?;
 
     let if_else_ctx = ctx
         .narrow(&Step::IfReportCreditExceedsCapOrElse)
@@ -288,10 +306,46 @@ where
     Ok(credits)
 }
 
+/// Random bits for both comparisons against the cap in [`credit_capping`]: of every report, and
+/// of the sum of credits of every user.
+#[derive(Debug)]
+pub struct CappingRandomBits<F: Field, S: SecretSharing<F>> {
+    report_level: RandomBitsPool<F, S>,
+    user_level: RandomBitsPool<F, S>,
+}
+
+/// Generates the random bits that [`credit_capping`] needs for `count` records, before its inputs
+/// are known.
+///
+/// ## Errors
+/// If generating the bits fails.
+pub async fn random_bits<F, C, S>(ctx: C, count: usize) -> Result<CappingRandomBits<F, S>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    let (report_level, user_level) = try_join(
+        RandomBitsPool::generate(
+            ctx.narrow(&Step::ReportLevelCapping)
+                .narrow(&Step::RandomBitsForComparison),
+            count,
+        ),
+        RandomBitsPool::generate(ctx.narrow(&Step::RandomBitsForComparison), count),
+    )
+    .await?;
+    Ok(CappingRandomBits {
+        report_level,
+        user_level,
+    })
+}
+
 async fn is_credit_larger_than_cap<F, C, S>(
     ctx: C,
     prefix_summed_credits: &[S],
     cap: u32,
+    random_bits: Option<RandomBitsPool<F, S>>,
 ) -> Result<Vec<S>, Error>
 where
     F: PrimeField,
@@ -301,8 +355,11 @@ where
 {
     let ctx_ref = &ctx;
     let ctx = ctx.set_total_records(prefix_summed_credits.len());
-    let random_bits_generator =
-        RandomBitsGenerator::new(ctx.narrow(&Step::RandomBitsForComparison));
+    let rbg_ctx = ctx.narrow(&Step::RandomBitsForComparison);
+    let random_bits_generator = match random_bits {
+        Some(pool) => RandomBitsGenerator::with_pool(rbg_ctx, pool),
+        None => RandomBitsGenerator::new(rbg_ctx),
+    };
     let rbg = &random_bits_generator;
 
     ctx_ref
@@ -447,15 +504,26 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     MaskSourceCredits,
+    #[step(child = Step)]
     ReportLevelCapping,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IfReportCreditExceedsCapOrElse,
+    #[step(child = crate::protocol::boolean::random_bits_generator::FallbackStep)]
+    #[step(child = crate::protocol::boolean::solved_bits::Step)]
     RandomBitsForComparison,
+    #[step(child = crate::protocol::boolean::comparison::GreaterThanConstantStep)]
     IsCapLessThanCurrentContribution,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IfCurrentExceedsCapOrElse,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IfNextExceedsCapOrElse,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IfNextEventHasSameMatchKeyOrElse,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     PrefixOrTimesHelperBit,
+    #[step(child = crate::protocol::attribution::InteractionPatternStep)]
     PrefixOrCompareBits,
 }
 
@@ -484,7 +552,7 @@ mod tests {
                 |ctx, input: Vec<CreditCappingInputRow<Fp32BitPrime, Replicated<_>>>| async move {
                     let validator = ctx.validator(); // We're not running validation for this in this case.
                     let ctx = validator.context();
-                    credit_capping(ctx, &input, cap).await.unwrap()
+                    credit_capping(ctx, &input, cap, None).await.unwrap()
                 },
             )
             .await
@@ -140,7 +140,7 @@ impl<F: Field, T: LinearSecretSharing<F>> CreditCappingInputRow<F, T> {
 
 // `Resharable` trait of the `AttributionResharableStep` is only used for testing.
 // For these steps that are not executed as a part of the main protocols, we can't
-// use `#[derive(Step)]` since the steps do not appear in `steps.txt`. Hide these
+// use `#[derive(Step)]` since they are not reachable from the root gate. Hide these
 // steps behind `test` and manually implement AsRef<str> and `NoCommsStep` for them.
 #[cfg(test)]
 pub(crate) enum AttributionResharableStep {
@@ -13,8 +13,10 @@ use futures::{
 use ipa_macros::Step;
 
 use self::{
-    accumulate_credit::accumulate_credit, aggregate_credit::aggregate_credit,
-    apply_attribution_window::apply_attribution_window, credit_capping::credit_capping,
+    accumulate_credit::accumulate_credit,
+    aggregate_credit::aggregate_credit,
+    apply_attribution_window::apply_attribution_window,
+    credit_capping::{credit_capping, CappingRandomBits},
     input::ApplyAttributionWindowInputRow,
 };
 use crate::{
@@ -23,7 +25,9 @@ use crate::{
     helpers::query::IpaQueryConfig,
     protocol::{
         basics::SecureMul,
-        boolean::{bitwise_equal::bitwise_equal_gf2, or::or},
+        boolean::{
+            bitwise_equal::bitwise_equal_gf2, or::or, random_bits_generator::RandomBitsPool,
+        },
         context::{Context, UpgradableContext, UpgradedContext, Validator},
         ipa::{ArithmeticallySharedIPAInputs, BinarySharedIPAInputs},
         modulus_conversion::convert_bits,
@@ -35,11 +39,78 @@ use crate::{
             malicious::{DowngradeMalicious, ExtendableField},
             semi_honest::AdditiveShare as Replicated,
         },
-        Linear as LinearSecretSharing, LinearRefOps,
+        Linear as LinearSecretSharing, LinearRefOps, SecretSharing,
     },
     seq_join::assert_send,
 };
 
+/// Random bits for the comparisons in attribution, generated before the inputs are known.
+///
+/// The default value has no bits, which makes attribution generate them as it goes.
+#[derive(Debug)]
+pub struct AttributionRandomBits<F: Field, S: SecretSharing<F>> {
+    attribution_window: Option<RandomBitsPool<F, S>>,
+    credit_capping: Option<CappingRandomBits<F, S>>,
+}
+
+impl<F: Field, S: SecretSharing<F>> Default for AttributionRandomBits<F, S> {
+    fn default() -> Self {
+        Self {
+            attribution_window: None,
+            credit_capping: None,
+        }
+    }
+}
+
+impl<F, S> AttributionRandomBits<F, S>
+where
+    F: PrimeField,
+    S: LinearSecretSharing<F>,
+{
+    /// Generates the bits that [`secure_attribution`] needs for `count` rows with `config`.
+    /// `m_ctx` must be the context of the validator that is given to [`secure_attribution`].
+    ///
+    /// # Errors
+    /// If generating the bits fails.
+    pub async fn generate<C>(m_ctx: C, count: usize, config: &IpaQueryConfig) -> Result<Self, Error>
+    where
+        C: UpgradedContext<F, Share = S>,
+        S: BasicProtocols<C, F>,
+        for<'a> &'a S: LinearRefOps<'a, S, F>,
+    {
+        let attribution_window = async {
+            match config.attribution_window_seconds {
+                Some(_) => apply_attribution_window::random_bits(
+                    m_ctx.narrow(&AttributionStep::ApplyAttributionWindow),
+                    count,
+                )
+                .await
+                .map(Some),
+                None => Ok(None),
+            }
+        };
+        // Capping at one does not compare anything.
+        let credit_capping = async {
+            if config.per_user_credit_cap == 1 {
+                Ok(None)
+            } else {
+                credit_capping::random_bits(
+                    m_ctx.narrow(&AttributionStep::PerformUserCapping),
+                    count,
+                )
+                .await
+                .map(Some)
+            }
+        };
+        let (attribution_window, credit_capping) =
+            try_join(attribution_window, credit_capping).await?;
+        Ok(Self {
+            attribution_window,
+            credit_capping,
+        })
+    }
+}
+
 /// Performs a set of attribution protocols on the sorted IPA input.
 ///
 /// # Errors
@@ -48,6 +119,7 @@ use crate::{
 pub async fn secure_attribution<V, VB, C, S, SB, F>(
     validator: V,
     binary_validator: VB,
+    random_bits: AttributionRandomBits<F, S>,
     arithmetically_shared_values: Vec<ArithmeticallySharedIPAInputs<F, S>>,
     binary_shared_values: Vec<BinarySharedIPAInputs<SB>>,
     config: IpaQueryConfig,
@@ -130,6 +202,7 @@ where
         &attribution_input_rows,
         &stop_bits,
         config.attribution_window_seconds,
+        random_bits.attribution_window,
     )
     .await?;
 
@@ -146,6 +219,7 @@ where
         m_ctx.narrow(&AttributionStep::PerformUserCapping),
         &accumulated_credits,
         config.per_user_credit_cap,
+        random_bits.credit_capping,
     )
     .await?;
 
@@ -163,9 +237,16 @@ where
 
 #[derive(Step)]
 pub(crate) enum AttributionStep {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ConvertHelperBits,
+    #[step(child = InteractionPatternStep)]
+    #[step(child = crate::protocol::attribution::apply_attribution_window::Step)]
     ApplyAttributionWindow,
+    #[step(child = InteractionPatternStep)]
+    #[step(child = crate::protocol::attribution::accumulate_credit::Step)]
     AccumulateCredit,
+    #[step(child = InteractionPatternStep)]
+    #[step(child = crate::protocol::attribution::credit_capping::Step)]
     PerformUserCapping,
 }
 
@@ -429,16 +510,22 @@ where
 #[derive(Step)]
 #[allow(clippy::enum_variant_names)]
 pub(in crate::protocol) enum Step {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     CurrentStopBitTimesSuccessorCredit,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     CurrentStopBitTimesSuccessorStopBit,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     CurrentCreditOrCreditUpdate,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputeHelperBits,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     ComputeStopBits,
 }
 
 #[derive(Step)]
 pub(crate) enum InteractionPatternStep {
     #[dynamic]
+    #[step(child = Step)]
     Depth(usize),
 }
 
@@ -14,6 +14,7 @@ use crate::{
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     MultiplyWithR,
     RevealR,
 }
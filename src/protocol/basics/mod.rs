@@ -1,7 +1,7 @@
 pub mod check_zero;
 mod if_else;
 pub(crate) mod mul;
-mod reshare;
+pub(crate) mod reshare;
 mod reveal;
 mod share_known_value;
 pub mod sum_of_product;
@@ -10,14 +10,15 @@ pub use check_zero::check_zero;
 pub use if_else::if_else;
 pub use mul::{MultiplyZeroPositions, SecureMul, ZeroPositions};
 pub use reshare::Reshare;
+pub(crate) use reshare::ReshareFieldStep;
 pub use reveal::Reveal;
 pub use share_known_value::ShareKnownValue;
 pub use sum_of_product::SumOfProducts;
 
 use crate::{
-    ff::Field,
     protocol::{
         context::{Context, UpgradedMaliciousContext},
+        prss::FromPrss,
         RecordId,
     },
     secret_sharing::{
@@ -39,7 +40,7 @@ pub trait BasicProtocols<C: Context, V: SharedValue>:
 {
 }
 
-impl<C: Context, F: Field> BasicProtocols<C, F> for AdditiveShare<F> {}
+impl<C: Context, V: SharedValue + FromPrss> BasicProtocols<C, V> for AdditiveShare<V> {}
 
 impl<'a, F: ExtendableField> BasicProtocols<UpgradedMaliciousContext<'a, F>, F>
     for MaliciousAdditiveShare<F>
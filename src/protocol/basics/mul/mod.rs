@@ -2,14 +2,17 @@ use async_trait::async_trait;
 
 use crate::{
     error::Error,
-    ff::Field,
     protocol::{
         context::{Context, UpgradedMaliciousContext},
+        prss::FromPrss,
         RecordId,
     },
-    secret_sharing::replicated::{
-        malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
-        semi_honest::AdditiveShare as Replicated,
+    secret_sharing::{
+        replicated::{
+            malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        SharedValue,
     },
 };
 
@@ -52,7 +55,7 @@ use {malicious::multiply as malicious_mul, semi_honest::multiply as semi_honest_
 
 /// Implement secure multiplication for semi-honest contexts with replicated secret sharing.
 #[async_trait]
-impl<C: Context, F: Field> SecureMul<C> for Replicated<F> {
+impl<C: Context, F: SharedValue + FromPrss> SecureMul<C> for Replicated<F> {
     async fn multiply_sparse<'fut>(
         &self,
         rhs: &Self,
@@ -1,15 +1,15 @@
 use crate::{
     error::Error,
-    ff::Field,
     helpers::Direction,
     protocol::{
         basics::{mul::sparse::MultiplyWork, MultiplyZeroPositions},
         context::Context,
-        prss::SharedRandomness,
+        prss::{FromPrss, SharedRandomness},
         RecordId,
     },
-    secret_sharing::replicated::{
-        semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing,
+    secret_sharing::{
+        replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+        SharedValue,
     },
 };
 
@@ -23,6 +23,9 @@ use crate::{
 ///
 /// The `zeros_at` argument indicates where there are known zeros in the inputs.
 ///
+/// `F` does not have to be a field. Any shared value with a distributive multiplication works,
+/// including vectors of Gf2 values that are multiplied lane by lane.
+///
 /// ## Errors
 /// Lots of things may go wrong here, from timeouts to bad output. They will be signalled
 /// back via the error response
@@ -35,7 +38,7 @@ pub async fn multiply<C, F>(
 ) -> Result<Replicated<F>, Error>
 where
     C: Context,
-    F: Field,
+    F: SharedValue + FromPrss,
 {
     let role = ctx.role();
     let [need_to_recv, need_to_send, need_random_right] = zeros.work_for(role);
@@ -43,7 +46,7 @@ where
     zeros.1.check(role, "b", b);
 
     // Shared randomness used to mask the values that are sent.
-    let (s0, s1) = ctx.prss().generate_fields(record_id);
+    let (s0, s1) = ctx.prss().generate::<F, _>(record_id);
 
     let mut rhs = a.right() * b.right();
     if need_to_send {
@@ -1,5 +1,6 @@
 use crate::{
-    ff::Field, helpers::Role, secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
+    helpers::Role,
+    secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, SharedValue},
 };
 
 /// A description of a replicated secret sharing, with zero values at known positions.
@@ -105,7 +106,7 @@ impl ZeroPositions {
     /// # Panics
     /// When the input value includes a non-zero value in a position marked as having a zero.
     #[cfg_attr(not(debug_assertions), allow(unused_variables))]
-    pub fn check<F: Field>(self, role: Role, which: &str, v: &Replicated<F>) {
+    pub fn check<V: SharedValue>(self, role: Role, which: &str, v: &Replicated<V>) {
         #[cfg(debug_assertions)]
         {
             use crate::{
@@ -115,14 +116,14 @@ impl ZeroPositions {
             let flags = <[bool; 3]>::from(self);
             if flags[role as usize] {
                 assert_eq!(
-                    F::ZERO,
+                    V::ZERO,
                     v.left(),
                     "expected a zero on the left for input {which}"
                 );
             }
             if flags[role.peer(Right) as usize] {
                 assert_eq!(
-                    F::ZERO,
+                    V::ZERO,
                     v.right(),
                     "expected a zero on the right for input {which}"
                 );
@@ -3,18 +3,15 @@ use std::iter::{repeat, zip};
 use async_trait::async_trait;
 use embed_doc_image::embed_doc_image;
 use futures::future::try_join;
+use ipa_macros::Step;
 
 use crate::{
     error::Error,
-    ff::Field,
     helpers::{Direction, Role},
     protocol::{
         context::{Context, UpgradedMaliciousContext},
-        prss::SharedRandomness,
-        sort::{
-            apply_sort::shuffle::InnerVectorElementStep,
-            ReshareStep::{RandomnessForValidation, ReshareRx},
-        },
+        prss::{FromPrss, SharedRandomness},
+        sort::ReshareStep::{RandomnessForValidation, ReshareRx},
         NoRecord, RecordBinding, RecordId,
     },
     secret_sharing::{
@@ -23,9 +20,40 @@ use crate::{
             semi_honest::AdditiveShare as Replicated,
             ReplicatedSecretSharing,
         },
-        BitDecomposed,
+        BitDecomposed, SharedValue,
     },
 };
+
+#[derive(Step)]
+#[step(child = crate::protocol::sort::ReshareStep)]
+pub(crate) enum InnerVectorElementStep {
+    #[dynamic]
+    #[step(child = crate::protocol::ipa::IPAInputRowResharableStep)]
+    Elem(usize),
+}
+
+impl From<usize> for InnerVectorElementStep {
+    fn from(v: usize) -> Self {
+        Self::Elem(v)
+    }
+}
+
+/// Step for one field of a struct that derives [`Reshare`](ipa_macros::Reshare).
+/// Such structs can have at most 16 fields.
+#[derive(Step)]
+#[step(child = crate::protocol::sort::ReshareStep)]
+#[step(child = InnerVectorElementStep)]
+pub(crate) enum ReshareFieldStep {
+    #[dynamic(16)]
+    Field(usize),
+}
+
+impl From<usize> for ReshareFieldStep {
+    fn from(v: usize) -> Self {
+        Self::Field(v)
+    }
+}
+
 #[embed_doc_image("reshare", "images/sort/reshare.png")]
 /// Trait for reshare protocol to renew shares of a secret value for all 3 helpers.
 ///
@@ -58,7 +86,7 @@ pub trait Reshare<C: Context, B: RecordBinding>: Sized {
 /// This implements semi-honest reshare algorithm of "Efficient Secure Three-Party Sorting Protocol with an Honest Majority" at communication cost of 2R.
 /// Input: Pi-1 and Pi+1 know their secret shares
 /// Output: At the end of the protocol, all 3 helpers receive their shares of a new, random secret sharing of the secret value
-impl<C: Context, F: Field> Reshare<C, RecordId> for Replicated<F> {
+impl<C: Context, F: SharedValue + FromPrss> Reshare<C, RecordId> for Replicated<F> {
     async fn reshare<'fut>(
         &self,
         ctx: C,
@@ -68,7 +96,7 @@ impl<C: Context, F: Field> Reshare<C, RecordId> for Replicated<F> {
     where
         C: 'fut,
     {
-        let r = ctx.prss().generate_fields(record_id);
+        let r = ctx.prss().generate::<F, _>(record_id);
 
         // `to_helper.left` calculates part1 = (self.0 + self.1) - r1 and sends part1 to `to_helper.right`
         // This is same as (a1 + a2) - r2 in the diagram
@@ -390,10 +418,11 @@ mod tests {
 
         #[tokio::test]
         async fn fp32bit_reshare_validation_fail() {
-            const PERTURBATIONS: [(Fp32BitPrime, Fp32BitPrime); 3] = [
-                (Fp32BitPrime::ONE, Fp32BitPrime::ONE),
-                (Fp32BitPrime::ONE, Fp32BitPrime::ZERO),
-                (Fp32BitPrime::ZERO, Fp32BitPrime::ONE),
+            type ExtendedField = <Fp32BitPrime as ExtendableField>::ExtendedField;
+            const PERTURBATIONS: [(Fp32BitPrime, ExtendedField); 3] = [
+                (Fp32BitPrime::ONE, ExtendedField::ONE),
+                (Fp32BitPrime::ONE, ExtendedField::ZERO),
+                (Fp32BitPrime::ZERO, ExtendedField::ONE),
             ];
             malicious_validation_fail_helper::<Fp32BitPrime>(&PERTURBATIONS).await;
         }
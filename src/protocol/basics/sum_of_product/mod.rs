@@ -2,14 +2,17 @@ use async_trait::async_trait;
 
 use crate::{
     error::Error,
-    ff::Field,
     protocol::{
         context::{Context, UpgradedMaliciousContext},
+        prss::FromPrss,
         RecordId,
     },
-    secret_sharing::replicated::{
-        malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
-        semi_honest::AdditiveShare as Replicated,
+    secret_sharing::{
+        replicated::{
+            malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        SharedValue,
     },
 };
 
@@ -29,7 +32,7 @@ pub trait SumOfProducts<C: Context>: Sized {
 }
 
 #[async_trait]
-impl<C: Context, F: Field> SumOfProducts<C> for Replicated<F> {
+impl<C: Context, F: SharedValue + FromPrss> SumOfProducts<C> for Replicated<F> {
     async fn sum_of_products<'fut>(
         ctx: C,
         record_id: RecordId,
@@ -1,10 +1,14 @@
 use crate::{
     error::Error,
-    ff::Field,
     helpers::Direction,
-    protocol::{context::Context, prss::SharedRandomness, RecordId},
-    secret_sharing::replicated::{
-        semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing,
+    protocol::{
+        context::Context,
+        prss::{FromPrss, SharedRandomness},
+        RecordId,
+    },
+    secret_sharing::{
+        replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+        SharedValue,
     },
 };
 
@@ -27,14 +31,14 @@ pub async fn sum_of_products<C, F>(
 ) -> Result<Replicated<F>, Error>
 where
     C: Context,
-    F: Field,
+    F: SharedValue + FromPrss,
 {
     assert_eq!(a.len(), b.len());
     let vec_len = a.len();
 
     // generate shared randomness.
     let prss = ctx.prss();
-    let (s0, s1): (F, F) = prss.generate_fields(record_id);
+    let (s0, s1): (F, F) = prss.generate(record_id);
     let role = ctx.role();
 
     // compute the value (d_i) we want to send to the right helper (i+1)
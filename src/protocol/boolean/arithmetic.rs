@@ -0,0 +1,956 @@
+use std::iter::{repeat, zip};
+
+#[cfg(feature = "descriptive-gate")]
+use futures::future::try_join;
+#[cfg(feature = "descriptive-gate")]
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    protocol::{context::Context, step::BitOpStep, BasicProtocols, RecordId},
+    secret_sharing::{
+        vector::Gf2Vector, BitDecomposed, Linear as LinearSecretSharing, LinearRefOps,
+    },
+};
+
+// Integer arithmetic on bitwise shares over `Gf2`.
+//
+// Integers are unsigned, and are represented least significant bit first. XOR is addition in
+// `Gf2`, so it is free, and every AND costs one multiplication. Shares may also be over any
+// `Gf2Vector`, in which case every lane holds an independent integer and all lanes are processed
+// with the same number of multiplications.
+//
+// The circuits that narrow `Step` are only available with descriptive gates: no protocol uses
+// them yet, so there is no step they could be narrowed from in the compact gate.
+//
+// The latency of these circuits is dominated by their multiplicative depth, because every round
+// of multiplications is a round trip between helpers. Additions can therefore be computed with
+// either of two circuits, see `Adder`.
+
+/// The circuit used to propagate carries in additions and subtractions of `n`-bit integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Adder {
+    /// Computes one carry after another. Needs `n` multiplications in `n` rounds.
+    RippleCarry,
+    /// Kogge-Stone parallel prefix adder. Needs `O(n log n)` multiplications in
+    /// `1 + ⌈log2(n)⌉` rounds.
+    #[cfg(feature = "descriptive-gate")]
+    ParallelPrefix,
+}
+
+/// What to do with results that do not fit into the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Discard the bits that do not fit, computing the result modulo `2^n`.
+    Wrap,
+    /// Clamp the result to the largest (or, for subtraction, to the smallest) `n`-bit value.
+    /// Costs one more round of `n` multiplications.
+    #[cfg(feature = "descriptive-gate")]
+    Saturate,
+}
+
+#[cfg(feature = "descriptive-gate")]
+#[derive(Step)]
+pub(crate) enum Step {
+    Generate,
+    Propagate,
+    Saturate,
+    PartialProducts,
+    Reduce,
+}
+
+/// Computes `x + y` and the carry out of the most significant bit.
+///
+/// The sum has as many bits as `x`. `y` may have fewer bits than `x`, in which case it is padded
+/// with zeroes, but this saves nothing: the carries still need to be computed for every bit.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+///
+/// # Panics
+/// If `y` has more bits than `x`.
+pub async fn add_with_carry<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    y: &[S],
+) -> Result<(BitDecomposed<S>, S), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert!(y.len() <= x.len(), "y must not be wider than x");
+    let y = pad(y, x.len(), &S::ZERO);
+    let (sum, carry) = add_bits(ctx, record_id, adder, x, &y, false, true).await?;
+    Ok((sum, carry.unwrap()))
+}
+
+/// Computes `x + y`, with the same number of bits as `x`.
+///
+/// When saturating, the result is `2^n - 1` whenever `x + y` does not fit into `n` bits.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+///
+/// # Panics
+/// If `y` has more bits than `x`.
+pub async fn add<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    overflow: Overflow,
+    x: &[S],
+    y: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert!(y.len() <= x.len(), "y must not be wider than x");
+    let y = pad(y, x.len(), &S::ZERO);
+    match overflow {
+        Overflow::Wrap => Ok(add_bits(ctx, record_id, adder, x, &y, false, false)
+            .await?
+            .0),
+        #[cfg(feature = "descriptive-gate")]
+        Overflow::Saturate => {
+            let (sum, carry) = add_bits(ctx.clone(), record_id, adder, x, &y, false, true).await?;
+            let carry = carry.unwrap();
+            // `s ∨ c = s ⊕ c ⊕ (s ∧ c)` sets every bit when there is a carry out.
+            let ctx = ctx.narrow(&Step::Saturate);
+            let sum = ctx
+                .parallel_join(
+                    sum.iter().enumerate().map(|(i, bit)| {
+                        let c = ctx.narrow(&BitOpStep::from(i));
+                        let carry = &carry;
+                        async move {
+                            Ok::<_, Error>(bit.multiply(carry, c, record_id).await? + bit + carry)
+                        }
+                    }),
+                )
+                .await?;
+            Ok(BitDecomposed::new(sum))
+        }
+    }
+}
+
+/// Computes `x + c` for a public constant `c`. The sum has one more bit than `x`, for the carry
+/// out of its most significant bit. Bits of `c` that `x` does not have are ignored.
+///
+/// Knowing `c` saves nothing: there is still a multiplication for every carry.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+pub async fn add_constant<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    c: u128,
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let one = S::share_known_value(&ctx, V::ALL_ONES);
+    let c = (0..x.len())
+        .map(|i| {
+            if i < u128::BITS as usize && (c >> i) & 1 == 1 {
+                one.clone()
+            } else {
+                S::ZERO
+            }
+        })
+        .collect::<Vec<_>>();
+    let (sum, carry) = add_with_carry(ctx, record_id, adder, x, &c).await?;
+    Ok(BitDecomposed::new(sum.into_iter().chain([carry])))
+}
+
+/// Computes `x - y` and the borrow out of the most significant bit, which is set iff `x < y`.
+///
+/// The difference is computed as `x + ¬y + 1`, so the cost is the same as for [`add_with_carry`].
+///
+/// # Errors
+/// Propagates errors from multiplications.
+///
+/// # Panics
+/// If `y` has more bits than `x`.
+pub async fn subtract_with_borrow<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    y: &[S],
+) -> Result<(BitDecomposed<S>, S), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert!(y.len() <= x.len(), "y must not be wider than x");
+    let one = S::share_known_value(&ctx, V::ALL_ONES);
+    let not_y = negate(&pad(y, x.len(), &S::ZERO), &one);
+    let (difference, carry) = add_bits(ctx, record_id, adder, x, &not_y, true, true).await?;
+    Ok((difference, one + &carry.unwrap()))
+}
+
+/// Computes `x - y`, with the same number of bits as `x`.
+///
+/// When saturating, the result is `0` whenever `x < y`.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+///
+/// # Panics
+/// If `y` has more bits than `x`.
+pub async fn subtract<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    overflow: Overflow,
+    x: &[S],
+    y: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert!(y.len() <= x.len(), "y must not be wider than x");
+    let one = S::share_known_value(&ctx, V::ALL_ONES);
+    let not_y = negate(&pad(y, x.len(), &S::ZERO), &one);
+    match overflow {
+        Overflow::Wrap => Ok(add_bits(ctx, record_id, adder, x, &not_y, true, false)
+            .await?
+            .0),
+        #[cfg(feature = "descriptive-gate")]
+        Overflow::Saturate => {
+            let (difference, carry) =
+                add_bits(ctx.clone(), record_id, adder, x, &not_y, true, true).await?;
+            // The carry out is the negated borrow, so it is zero exactly when `x < y`.
+            let carry = carry.unwrap();
+            let ctx = ctx.narrow(&Step::Saturate);
+            let difference = ctx
+                .parallel_join(difference.iter().enumerate().map(|(i, bit)| {
+                    bit.multiply(&carry, ctx.narrow(&BitOpStep::from(i)), record_id)
+                }))
+                .await?;
+            Ok(BitDecomposed::new(difference))
+        }
+    }
+}
+
+#[cfg(feature = "descriptive-gate")]
+/// Computes `x * c` for a public constant `c`.
+///
+/// The product is exact: it has as many bits as `x` and `c` together. It is the sum of copies of
+/// `x` shifted by the positions of the set bits in `c`. Shifts are free, and the sums are added
+/// in a binary tree to minimize the number of rounds.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+pub async fn multiply_by_constant<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    c: u128,
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let c_bits = (u128::BITS - c.leading_zeros()) as usize;
+    let terms = (0..c_bits)
+        .filter(|j| (c >> j) & 1 == 1)
+        .map(|j| (j, x.to_vec()))
+        .collect::<Vec<_>>();
+    sum_shifted(ctx, record_id, adder, terms, x.len() + c_bits).await
+}
+
+#[cfg(feature = "descriptive-gate")]
+/// Computes `x * y`.
+///
+/// The product is exact: it has as many bits as `x` and `y` together. All the partial products
+/// `x ∧ y[j]` are computed in a single round, and then added in a binary tree.
+///
+/// # Errors
+/// Propagates errors from multiplications.
+pub async fn multiply<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    y: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let partial_ctx = ctx.narrow(&Step::PartialProducts);
+    let partial_products = partial_ctx
+        .parallel_join(y.iter().enumerate().flat_map(|(j, y_bit)| {
+            let c = partial_ctx.narrow(&BitOpStep::from(j));
+            x.iter().enumerate().map(move |(i, x_bit)| {
+                x_bit.multiply(y_bit, c.narrow(&BitOpStep::from(i)), record_id)
+            })
+        }))
+        .await?;
+    let terms = if x.is_empty() {
+        Vec::new()
+    } else {
+        partial_products
+            .chunks(x.len())
+            .map(<[S]>::to_vec)
+            .enumerate()
+            .collect()
+    };
+    sum_shifted(ctx, record_id, adder, terms, x.len() + y.len()).await
+}
+
+/// Shifts `x` towards the most significant bit by `k` positions, keeping the number of bits.
+#[must_use]
+pub fn shift_left<V, S>(x: &[S], k: usize) -> BitDecomposed<S>
+where
+    V: Gf2Vector,
+    S: LinearSecretSharing<V>,
+{
+    BitDecomposed::new(
+        repeat(S::ZERO)
+            .take(k)
+            .chain(x.iter().cloned())
+            .take(x.len()),
+    )
+}
+
+/// Shifts `x` towards the least significant bit by `k` positions, keeping the number of bits.
+#[must_use]
+pub fn shift_right<V, S>(x: &[S], k: usize) -> BitDecomposed<S>
+where
+    V: Gf2Vector,
+    S: LinearSecretSharing<V>,
+{
+    BitDecomposed::new(
+        x.iter()
+            .skip(k)
+            .cloned()
+            .chain(repeat(S::ZERO))
+            .take(x.len()),
+    )
+}
+
+fn pad<S: Clone>(x: &[S], len: usize, zero: &S) -> Vec<S> {
+    x.iter().chain(repeat(zero)).take(len).cloned().collect()
+}
+
+fn negate<V, S>(x: &[S], one: &S) -> Vec<S>
+where
+    V: Gf2Vector,
+    S: LinearSecretSharing<V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    x.iter().map(|bit| one + bit).collect()
+}
+
+/// Adds `x` and `y`, which must have the same number of bits, plus a public carry in.
+///
+/// The carry out of the most significant bit is only computed if `carry_out` is set, which saves
+/// one multiplication for ripple carry adders.
+async fn add_bits<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    x: &[S],
+    y: &[S],
+    carry_in: bool,
+    carry_out: bool,
+) -> Result<(BitDecomposed<S>, Option<S>), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    debug_assert_eq!(x.len(), y.len());
+    match adder {
+        Adder::RippleCarry => ripple_carry(ctx, record_id, x, y, carry_in, carry_out).await,
+        #[cfg(feature = "descriptive-gate")]
+        Adder::ParallelPrefix => parallel_prefix(ctx, record_id, x, y, carry_in, carry_out).await,
+    }
+}
+
+async fn ripple_carry<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    x: &[S],
+    y: &[S],
+    carry_in: bool,
+    carry_out: bool,
+) -> Result<(BitDecomposed<S>, Option<S>), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let mut carry = if carry_in {
+        S::share_known_value(&ctx, V::ALL_ONES)
+    } else {
+        S::ZERO
+    };
+    let n = x.len();
+    let mut sum = Vec::with_capacity(n);
+    for (i, (x, y)) in zip(x, y).enumerate() {
+        let compute_carry_out = carry_out || i + 1 < n;
+        sum.push(
+            one_bit_adder(
+                ctx.narrow(&BitOpStep::from(i)),
+                record_id,
+                x,
+                y,
+                &mut carry,
+                compute_carry_out,
+            )
+            .await?,
+        );
+    }
+    Ok((BitDecomposed::new(sum), carry_out.then_some(carry)))
+}
+
+/// Adds one bit of `x` and `y` to `carry` and returns the sum bit, `x ⊕ y ⊕ c`, which is free.
+///
+/// If `compute_carry_out` is set, `carry` is replaced with the carry out of this bit,
+/// `c ⊕ ((x ⊕ c) ∧ (y ⊕ c))`, which costs a single multiplication. This is the adder from section
+/// 3.1 of "Improved Garbled Circuit Building Blocks and Applications to Auctions and Computing
+/// Minima", `https://encrypto.de/papers/KSS09.pdf`. Otherwise `carry` is left as it is.
+async fn one_bit_adder<C, V, SB>(
+    ctx: C,
+    record_id: RecordId,
+    x: &SB,
+    y: &SB,
+    carry: &mut SB,
+    compute_carry_out: bool,
+) -> Result<SB, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    SB: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a SB: LinearRefOps<'a, SB, V>,
+{
+    let sum_bit = x + y + &*carry;
+
+    if compute_carry_out {
+        let x_xor_carry = x + &*carry;
+        let y_xor_carry = y + &*carry;
+
+        // There are two cases when the carry out is different from the carry in
+        // (1) When the carry in is 0 and both `x` and `y` are 1
+        // (2) When the carry in is 1 and both `x` and `y` are 0
+        // So by computing `(x ⊕ c) ∧ (y ⊕ c)` we isolate those cases with a single multiplication
+        *carry = x_xor_carry.multiply(&y_xor_carry, ctx, record_id).await? + &*carry;
+    }
+
+    Ok(sum_bit)
+}
+
+#[cfg(feature = "descriptive-gate")]
+/// Kogge-Stone adder.
+///
+/// Every bit either generates a carry, `g_i = x_i ∧ y_i`, or propagates the carry from the bit
+/// below it, `p_i = x_i ⊕ y_i`. Both properties combine over adjacent spans of bits as
+/// `(g, p) ∘ (g', p') = (g ⊕ (p ∧ g'), p ∧ p')`, where `(g', p')` is the lower span. Because this
+/// is associative, the carries out of every prefix `0..=i` can be computed in `⌈log2(n)⌉` rounds,
+/// doubling the length of the spans in every round.
+async fn parallel_prefix<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    x: &[S],
+    y: &[S],
+    carry_in: bool,
+    carry_out: bool,
+) -> Result<(BitDecomposed<S>, Option<S>), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let one = S::share_known_value(&ctx, V::ALL_ONES);
+    let n = x.len();
+    // The carry out of bit `i` is needed for all but the most significant bit.
+    let carries = if carry_out { n } else { n.saturating_sub(1) };
+
+    let mut propagate = zip(x, y).map(|(x, y)| x + y).collect::<Vec<_>>();
+    let generate_ctx = ctx.narrow(&Step::Generate);
+    let mut generate =
+        generate_ctx
+            .parallel_join(zip(x, y).take(carries).enumerate().map(|(i, (x, y))| {
+                x.multiply(y, generate_ctx.narrow(&BitOpStep::from(i)), record_id)
+            }))
+            .await?;
+    if carry_in && carries > 0 {
+        // A public carry into bit 0 is generated unless both inputs are zero. Generating and
+        // propagating are mutually exclusive, so their OR is just their sum.
+        generate[0] = &generate[0] + &propagate[0];
+    }
+    let sum_propagate = propagate.clone();
+
+    let mut span = 1;
+    let mut round = 0_usize;
+    while span < carries {
+        let round_ctx = ctx.narrow(&Step::Propagate).narrow(&BitOpStep::from(round));
+        let generate_ctx = round_ctx.narrow(&Step::Generate);
+        let propagate_ctx = round_ctx.narrow(&Step::Propagate);
+        // Spans that reach bit 0 are final and do not need their propagate bit anymore, so it is
+        // only computed for spans that will be combined again in the next round.
+        let (new_generate, new_propagate) = try_join(
+            generate_ctx.parallel_join((span..carries).map(|i| {
+                propagate[i].multiply(
+                    &generate[i - span],
+                    generate_ctx.narrow(&BitOpStep::from(i)),
+                    record_id,
+                )
+            })),
+            propagate_ctx.parallel_join((2 * span..carries).map(|i| {
+                propagate[i].multiply(
+                    &propagate[i - span],
+                    propagate_ctx.narrow(&BitOpStep::from(i)),
+                    record_id,
+                )
+            })),
+        )
+        .await?;
+        for (i, g) in (span..carries).zip(new_generate) {
+            generate[i] = g + &generate[i];
+        }
+        for (i, p) in (2 * span..carries).zip(new_propagate) {
+            propagate[i] = p;
+        }
+        span *= 2;
+        round += 1;
+    }
+
+    let sum = sum_propagate.into_iter().enumerate().map(|(i, p)| match i {
+        0 if carry_in => p + &one,
+        0 => p,
+        _ => p + &generate[i - 1],
+    });
+    let sum = BitDecomposed::new(sum);
+    let carry = carry_out.then(|| {
+        if n == 0 {
+            if carry_in {
+                one
+            } else {
+                S::ZERO
+            }
+        } else {
+            generate[n - 1].clone()
+        }
+    });
+    Ok((sum, carry))
+}
+
+#[cfg(feature = "descriptive-gate")]
+/// Adds integers that are shifted by public offsets, truncating the sum to `width` bits.
+///
+/// Each term is a pair of its offset and its bits. Terms are added pairwise in a binary tree, and
+/// the bits of one term that are below the offset of the other are copied for free.
+async fn sum_shifted<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    mut terms: Vec<(usize, Vec<S>)>,
+    width: usize,
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    terms.sort_by_key(|(offset, _)| *offset);
+    let mut depth = 0_usize;
+    while terms.len() > 1 {
+        let depth_ctx = ctx.narrow(&Step::Reduce).narrow(&BitOpStep::from(depth));
+        let odd = if terms.len() % 2 == 1 {
+            terms.pop()
+        } else {
+            None
+        };
+        let mut pairs = terms.into_iter();
+        let mut additions = Vec::new();
+        while let (Some(lo), Some(hi)) = (pairs.next(), pairs.next()) {
+            let c = depth_ctx.narrow(&BitOpStep::from(additions.len()));
+            additions.push(add_shifted(c, record_id, adder, lo, hi, width));
+        }
+        terms = depth_ctx.parallel_join(additions).await?;
+        terms.extend(odd);
+        depth += 1;
+    }
+
+    let (offset, bits) = terms.pop().unwrap_or((0, Vec::new()));
+    Ok(BitDecomposed::new(
+        repeat(S::ZERO)
+            .take(offset)
+            .chain(bits)
+            .chain(repeat(S::ZERO))
+            .take(width),
+    ))
+}
+
+#[cfg(feature = "descriptive-gate")]
+async fn add_shifted<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    adder: Adder,
+    (lo_offset, lo): (usize, Vec<S>),
+    (hi_offset, hi): (usize, Vec<S>),
+    width: usize,
+) -> Result<(usize, Vec<S>), Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    debug_assert!(lo_offset <= hi_offset);
+    let shift = hi_offset - lo_offset;
+    let mut output = pad(&lo, shift.min(lo.len()), &S::ZERO);
+    if lo.len() <= shift {
+        // The terms do not overlap.
+        output.extend(repeat(S::ZERO).take(shift - lo.len()));
+        output.extend(hi);
+    } else {
+        let overlap = &lo[shift..];
+        let len = overlap.len().max(hi.len());
+        // Bits at or above `width` are discarded, so neither is the carry out of them needed.
+        let available = width.saturating_sub(hi_offset);
+        let (len, carry_out) = if len < available {
+            (len, true)
+        } else {
+            (available, false)
+        };
+        let (sum, carry) = add_bits(
+            ctx,
+            record_id,
+            adder,
+            &pad(overlap, len, &S::ZERO),
+            &pad(&hi, len, &S::ZERO),
+            false,
+            carry_out,
+        )
+        .await?;
+        output.extend(sum);
+        output.extend(carry);
+    }
+    Ok((lo_offset, output))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{
+        add, add_constant, add_with_carry, multiply, multiply_by_constant, shift_left, shift_right,
+        subtract, subtract_with_borrow, Adder, Overflow,
+    };
+    use crate::{
+        ff::{Field, Gf2},
+        protocol::{context::Context, RecordId},
+        secret_sharing::{
+            replicated::semi_honest::AdditiveShare as Replicated,
+            vector::{Gf2Vector, Gf2x64},
+            BitDecomposed,
+        },
+        test_fixture::{get_bits, Reconstruct, Runner, TestWorld},
+    };
+
+    const BITS: u32 = 6;
+    const ADDERS: [Adder; 2] = [Adder::RippleCarry, Adder::ParallelPrefix];
+
+    fn to_u128<V: Gf2Vector>(bits: &BitDecomposed<V>, lane: usize) -> u128 {
+        bits.iter()
+            .enumerate()
+            .map(|(i, bit)| bit.lane(lane).as_u128() << i)
+            .sum()
+    }
+
+    fn reconstruct(result: &[BitDecomposed<Replicated<Gf2>>; 3]) -> u128 {
+        let [s0, s1, s2] = result;
+        to_u128(&[s0, s1, s2].reconstruct(), 0)
+    }
+
+    fn inputs() -> Vec<(u32, u32)> {
+        let max = (1 << BITS) - 1;
+        let mut rng = thread_rng();
+        [(0, 0), (0, max), (max, 0), (max, max), (1, max), (max, 1)]
+            .into_iter()
+            .chain((0..10).map(|_| (rng.gen_range(0..=max), rng.gen_range(0..=max))))
+            .collect()
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum Operation {
+        Add(Overflow),
+        Subtract(Overflow),
+        AddWithCarry,
+        SubtractWithBorrow,
+        Multiply,
+    }
+
+    impl Operation {
+        fn plaintext(self, a: u32, b: u32) -> u128 {
+            let (a, b, max) = (u128::from(a), u128::from(b), (1 << BITS) - 1);
+            match self {
+                Self::Add(Overflow::Wrap) => (a + b) & max,
+                Self::Add(Overflow::Saturate) => (a + b).min(max),
+                Self::Subtract(Overflow::Wrap) => a.wrapping_sub(b) & max,
+                Self::Subtract(Overflow::Saturate) => a.saturating_sub(b),
+                // The carry or borrow bit is reported above the result.
+                Self::AddWithCarry => a + b,
+                Self::SubtractWithBorrow => a.wrapping_sub(b) & ((max << 1) | 1),
+                Self::Multiply => a * b,
+            }
+        }
+
+        async fn evaluate(self, adder: Adder, a: u32, b: u32) -> u128 {
+            let world = TestWorld::default();
+            let result = world
+                .semi_honest(
+                    (get_bits::<Gf2>(a, BITS), get_bits::<Gf2>(b, BITS)),
+                    |ctx, (a, b): (BitDecomposed<_>, BitDecomposed<_>)| async move {
+                        let ctx = ctx.set_total_records(1);
+                        let record_id = RecordId(0);
+                        match self {
+                            Self::Add(overflow) => {
+                                add(ctx, record_id, adder, overflow, &a, &b).await
+                            }
+                            Self::Subtract(overflow) => {
+                                subtract(ctx, record_id, adder, overflow, &a, &b).await
+                            }
+                            Self::AddWithCarry => add_with_carry(ctx, record_id, adder, &a, &b)
+                                .await
+                                .map(|(sum, carry)| {
+                                    BitDecomposed::new(sum.into_iter().chain([carry]))
+                                }),
+                            Self::SubtractWithBorrow => {
+                                subtract_with_borrow(ctx, record_id, adder, &a, &b)
+                                    .await
+                                    .map(|(difference, borrow)| {
+                                        BitDecomposed::new(difference.into_iter().chain([borrow]))
+                                    })
+                            }
+                            Self::Multiply => multiply(ctx, record_id, adder, &a, &b).await,
+                        }
+                        .unwrap()
+                    },
+                )
+                .await;
+            reconstruct(&result)
+        }
+    }
+
+    #[tokio::test]
+    pub async fn operations() {
+        let operations = [
+            Operation::Add(Overflow::Wrap),
+            Operation::Add(Overflow::Saturate),
+            Operation::Subtract(Overflow::Wrap),
+            Operation::Subtract(Overflow::Saturate),
+            Operation::AddWithCarry,
+            Operation::SubtractWithBorrow,
+            Operation::Multiply,
+        ];
+        for (a, b) in inputs() {
+            for operation in operations {
+                for adder in ADDERS {
+                    assert_eq!(
+                        operation.plaintext(a, b),
+                        operation.evaluate(adder, a, b).await,
+                        "{operation:?} with {adder:?} on {a} and {b}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    pub async fn narrow_operand() {
+        let world = TestWorld::default();
+        for adder in ADDERS {
+            let result = world
+                .semi_honest(
+                    (get_bits::<Gf2>(60, BITS), get_bits::<Gf2>(5, 3)),
+                    |ctx, (a, b): (BitDecomposed<_>, BitDecomposed<_>)| async move {
+                        add(
+                            ctx.set_total_records(1),
+                            RecordId(0),
+                            adder,
+                            Overflow::Wrap,
+                            &a,
+                            &b,
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+            assert_eq!(1, reconstruct(&result));
+        }
+    }
+
+    #[tokio::test]
+    pub async fn constant_addition() {
+        let max = (1 << BITS) - 1;
+        for (a, c) in [
+            (0, 0),
+            (0, 1),
+            (1, 0),
+            (1, 1),
+            (3, 7),
+            (21, 20),
+            (max, max),
+            (max, 1),
+        ] {
+            for adder in ADDERS {
+                let result = TestWorld::default()
+                    .semi_honest(
+                        get_bits::<Gf2>(a, BITS),
+                        |ctx, a: BitDecomposed<_>| async move {
+                            add_constant(ctx.set_total_records(1), RecordId(0), adder, &a, c.into())
+                                .await
+                                .unwrap()
+                        },
+                    )
+                    .await;
+                assert_eq!(u128::from(a + c), reconstruct(&result), "{a} + {c}");
+            }
+        }
+
+        // bits of the constant above the width of the input are dropped
+        let result = TestWorld::default()
+            .semi_honest(
+                get_bits::<Gf2>(1, BITS),
+                |ctx, a: BitDecomposed<_>| async move {
+                    add_constant(
+                        ctx.set_total_records(1),
+                        RecordId(0),
+                        Adder::RippleCarry,
+                        &a,
+                        1 << BITS | 2,
+                    )
+                    .await
+                    .unwrap()
+                },
+            )
+            .await;
+        assert_eq!(3, reconstruct(&result));
+    }
+
+    #[tokio::test]
+    pub async fn constant_multiplication() {
+        let mut rng = thread_rng();
+        for c in [0, 1, 2, 5, 64, 255] {
+            let a = rng.gen_range(0..1 << BITS);
+            for adder in ADDERS {
+                let result = TestWorld::default()
+                    .semi_honest(
+                        get_bits::<Gf2>(a, BITS),
+                        |ctx, a: BitDecomposed<_>| async move {
+                            multiply_by_constant(
+                                ctx.set_total_records(1),
+                                RecordId(0),
+                                adder,
+                                &a,
+                                c,
+                            )
+                            .await
+                            .unwrap()
+                        },
+                    )
+                    .await;
+                assert_eq!(u128::from(a) * c, reconstruct(&result), "{a} * {c}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    pub async fn shifts() {
+        let result = TestWorld::default()
+            .semi_honest(
+                get_bits::<Gf2>(0b10_1101, BITS),
+                |_ctx, a: BitDecomposed<_>| async move { (shift_left(&a, 2), shift_right(&a, 2)) },
+            )
+            .await;
+        let left = result.clone().map(|(left, _)| left);
+        let right = result.map(|(_, right)| right);
+        assert_eq!(0b11_0100, reconstruct(&left));
+        assert_eq!(0b1011, reconstruct(&right));
+    }
+
+    /// Packs bit `i` of every value into the vector `i`.
+    fn transpose(values: &[u32]) -> BitDecomposed<Gf2x64> {
+        BitDecomposed::decompose(BITS, |i| {
+            values
+                .iter()
+                .map(|v| Gf2::truncate_from((v >> i) & 1))
+                .collect::<Gf2x64>()
+        })
+    }
+
+    #[tokio::test]
+    pub async fn vectorized() {
+        let mut rng = thread_rng();
+        let a = (0..Gf2x64::LANES)
+            .map(|_| rng.gen_range(0..1 << BITS))
+            .collect::<Vec<u32>>();
+        let b = (0..Gf2x64::LANES)
+            .map(|_| rng.gen_range(0..1 << BITS))
+            .collect::<Vec<u32>>();
+
+        for adder in ADDERS {
+            let [s0, s1, s2] = TestWorld::default()
+                .semi_honest(
+                    (transpose(&a), transpose(&b)),
+                    |ctx, (a, b): (BitDecomposed<_>, BitDecomposed<_>)| async move {
+                        let ctx = ctx.set_total_records(1);
+                        let (sum, product) = futures::future::try_join(
+                            add(
+                                ctx.narrow("add"),
+                                RecordId(0),
+                                adder,
+                                Overflow::Saturate,
+                                &a,
+                                &b,
+                            ),
+                            multiply(ctx.narrow("multiply"), RecordId(0), adder, &a, &b),
+                        )
+                        .await
+                        .unwrap();
+                        (sum, product)
+                    },
+                )
+                .await;
+            let sum = [&s0.0, &s1.0, &s2.0].reconstruct();
+            let product = [&s0.1, &s1.1, &s2.1].reconstruct();
+            for lane in 0..Gf2x64::LANES {
+                let (a, b) = (u128::from(a[lane]), u128::from(b[lane]));
+                assert_eq!((a + b).min((1 << BITS) - 1), to_u128(&sum, lane));
+                assert_eq!(a * b, to_u128(&product, lane));
+            }
+        }
+    }
+}
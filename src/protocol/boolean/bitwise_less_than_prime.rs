@@ -193,11 +193,17 @@ impl BitwiseLessThanPrime {
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = Step)]
     CheckTrimmed,
+    #[step(child = crate::protocol::step::BitOpStep)]
     CheckIfAnyOnes,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     LeadingOnesOrRest,
+    #[step(child = crate::protocol::step::BitOpStep)]
     CheckIfAllOnes,
+    #[step(child = crate::protocol::step::BitOpStep)]
     CheckLeastSignificantBits,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     AllOnesAndFinalBits,
 }
 
@@ -147,8 +147,11 @@ fn compute_r_bounds(b: u128, c: u128, p: u128) -> RBounds {
 #[derive(Step)]
 pub(crate) enum GreaterThanConstantStep {
     Reveal,
+    #[step(child = Step)]
     CompareLo,
+    #[step(child = Step)]
     CompareHi,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     And,
 }
 
@@ -294,7 +297,9 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::step::BitOpStep)]
     PrefixOr,
+    #[step(child = crate::protocol::basics::sum_of_product::malicious::Step)]
     DotProduct,
 }
 
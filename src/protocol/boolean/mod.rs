@@ -12,7 +12,7 @@ use crate::{
     secret_sharing::{Linear as LinearSecretSharing, SecretSharing},
 };
 
-pub mod add_constant;
+pub mod arithmetic;
 pub mod bitwise_equal;
 pub mod bitwise_less_than_prime;
 pub mod comparison;
@@ -20,6 +20,9 @@ pub mod generate_random_bits;
 pub mod or;
 pub mod random_bits_generator;
 pub mod saturating_sum;
+// No protocol compares shared values yet, so the compact gate has no steps for this.
+#[cfg(feature = "descriptive-gate")]
+pub mod shared_comparison;
 pub mod solved_bits;
 mod xor;
 
@@ -1,16 +1,17 @@
 use crate::{
     error::Error,
-    ff::Field,
     protocol::{basics::SecureMul, context::Context, RecordId},
-    secret_sharing::Linear as LinearSecretSharing,
+    secret_sharing::{Linear as LinearSecretSharing, SharedValue},
 };
 
 /// Secure OR protocol with two inputs, `a, b ∈ {0,1} ⊆ F_p`.
 /// It computes `[a] + [b] - [ab]`
 ///
+/// Vectors of Gf2 values compute OR of every lane with a single multiplication.
+///
 /// ## Errors
 /// Fails if the multiplication protocol fails.
-pub async fn or<F: Field, C: Context, S: LinearSecretSharing<F> + SecureMul<C>>(
+pub async fn or<V: SharedValue, C: Context, S: LinearSecretSharing<V> + SecureMul<C>>(
     ctx: C,
     record_id: RecordId,
     a: &S,
@@ -22,13 +23,20 @@ pub async fn or<F: Field, C: Context, S: LinearSecretSharing<F> + SecureMul<C>>(
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use rand::distributions::{Distribution, Standard};
+    use rand::{
+        distributions::{Distribution, Standard},
+        thread_rng, Rng,
+    };
 
     use super::or;
     use crate::{
-        ff::{Field, Fp31},
+        ff::{Field, Fp31, Gf2},
         protocol::{context::Context, RecordId},
-        secret_sharing::{replicated::malicious::ExtendableField, SharedValue},
+        secret_sharing::{
+            replicated::malicious::ExtendableField,
+            vector::{Gf2Vector, Gf2x64},
+            SharedValue,
+        },
         test_fixture::{Reconstruct, Runner, TestWorld},
     };
 
@@ -68,6 +76,36 @@ mod tests {
         result
     }
 
+    #[tokio::test]
+    pub async fn vectorized() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let (a, b) = (rng.gen::<Gf2x64>(), rng.gen::<Gf2x64>());
+
+        let result = world
+            .semi_honest((a, b), |ctx, (a_share, b_share)| async move {
+                or(
+                    ctx.set_total_records(1),
+                    RecordId::from(0_u32),
+                    &a_share,
+                    &b_share,
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        for i in 0..Gf2x64::LANES {
+            let expected = if a.lane(i) == Gf2::ONE || b.lane(i) == Gf2::ONE {
+                Gf2::ONE
+            } else {
+                Gf2::ZERO
+            };
+            assert_eq!(expected, result.lane(i));
+        }
+    }
+
     #[tokio::test]
     pub async fn all() {
         type F = Fp31;
@@ -7,14 +7,15 @@ use ipa_macros::Step;
 
 use crate::{
     error::Error,
-    ff::PrimeField,
+    ff::{Field, PrimeField},
     helpers::TotalRecords,
     protocol::{
         boolean::solved_bits::{solved_bits, RandomBitsShare},
         context::UpgradedContext,
         BasicProtocols, RecordId,
     },
-    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
+    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps, SecretSharing},
+    sync::Mutex,
 };
 
 /// A struct that generates random sharings of bits from the
@@ -23,19 +24,98 @@ use crate::{
 ///
 /// This object is safe to share with multiple threads.  It uses an atomic counter
 /// to manage concurrent accesses.
+///
+/// A generator that is created [`with_pool`] takes the bits for every record from the pool,
+/// when they are available, so they don't need to be generated during the online phase.
+///
+/// [`with_pool`]: Self::with_pool
 #[derive(Debug)]
-pub struct RandomBitsGenerator<F, C, S> {
+pub struct RandomBitsGenerator<F: Field, C, S: SecretSharing<F>> {
     ctx: C,
     fallback_ctx: C,
     fallback_count: AtomicU32,
+    pool: Option<RandomBitsPool<F, S>>,
     _marker: PhantomData<(F, S)>,
 }
 
+/// Random bits generated ahead of time, before the protocol that needs them can run.
+///
+/// Generating random bits does not depend on the inputs of the query, so it can be done as
+/// soon as the number of records is known, for example while the inputs are still being
+/// uploaded. Bits are generated with the same context and record ids that the generator would
+/// use to generate them in the online phase, so the helpers consume the pool in the same order
+/// regardless of the order in which records are processed. In malicious contexts, the bits carry
+/// MACs under the validator that owns the context, and the multiplications that produced them
+/// are checked when that validator validates the output of the protocol.
+#[derive(Debug)]
+pub struct RandomBitsPool<F: Field, S: SecretSharing<F>> {
+    /// `None` for the records where `solved_bits` aborted, or whose bits were already taken.
+    bits: Mutex<Vec<Option<RandomBitsShare<F, S>>>>,
+}
+
+impl<F, S> RandomBitsPool<F, S>
+where
+    F: PrimeField,
+    S: LinearSecretSharing<F>,
+{
+    /// Generates random bits for records `0..count`. `ctx` must be the same context that is given
+    /// to [`RandomBitsGenerator::with_pool`] later.
+    ///
+    /// # Errors
+    /// If generating any of the bits fails.
+    pub async fn generate<C>(ctx: C, count: usize) -> Result<Self, Error>
+    where
+        C: UpgradedContext<F, Share = S>,
+        S: BasicProtocols<C, F>,
+        for<'a> &'a S: LinearRefOps<'a, S, F>,
+    {
+        let ctx = ctx.set_total_records(count);
+        let bits = ctx
+            .try_join((0..count).map(|i| solved_bits(ctx.clone(), RecordId::from(i))))
+            .await?;
+        Ok(Self {
+            bits: Mutex::new(bits),
+        })
+    }
+
+    /// Number of records that the pool was generated for.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bits.lock().unwrap().len()
+    }
+
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, record_id: RecordId) -> Pooled<F, S> {
+        match self.bits.lock().unwrap().get_mut(usize::from(record_id)) {
+            None => Pooled::NotCovered,
+            Some(slot) => slot.take().map_or(Pooled::Used, Pooled::Available),
+        }
+    }
+}
+
+enum Pooled<F: Field, S: SecretSharing<F>> {
+    /// The pool was not generated for this record.
+    NotCovered,
+    /// The pool used this record id, but has no bits for it.
+    Used,
+    Available(RandomBitsShare<F, S>),
+}
+
 /// Special context that is used when values generated using the standard method are larger
 /// than the prime for the field. It is grossly inefficient to use, because communications
 /// are unbuffered, but a prime that is close to a power of 2 helps reduce how often we need it.
 #[derive(Step)]
 pub(crate) enum FallbackStep {
+    #[step(child = crate::protocol::boolean::solved_bits::Step)]
     Fallback,
 }
 
@@ -55,10 +135,21 @@ where
             ctx,
             fallback_ctx,
             fallback_count: AtomicU32::new(0),
+            pool: None,
             _marker: PhantomData,
         }
     }
 
+    /// Creates a generator that takes the bits from `pool`, which must have been generated with
+    /// the same `ctx`.
+    #[must_use]
+    pub fn with_pool(ctx: C, pool: RandomBitsPool<F, S>) -> Self {
+        Self {
+            pool: Some(pool),
+            ..Self::new(ctx)
+        }
+    }
+
     /// Takes the next `RandomBitsShare` that is available.  As the underlying
     /// generator can fail, this will draw from that repeatedly until a value is produced.
     ///
@@ -67,7 +158,17 @@ where
     /// inner members multiple times, I/O errors while executing MPC protocols,
     /// read from an empty buffer, etc.
     pub async fn generate(&self, record_id: RecordId) -> Result<RandomBitsShare<F, S>, Error> {
-        let share = if let Some(v) = solved_bits(self.ctx.clone(), record_id).await? {
+        let pooled = self
+            .pool
+            .as_ref()
+            .map_or(Pooled::NotCovered, |pool| pool.take(record_id));
+        let share = match pooled {
+            Pooled::Available(v) => Some(v),
+            // The next attempt must use the fallback context.
+            Pooled::Used => None,
+            Pooled::NotCovered => solved_bits(self.ctx.clone(), record_id).await?,
+        };
+        let share = if let Some(v) = share {
             v
         } else {
             loop {
@@ -98,8 +199,9 @@ mod tests {
 
     use futures::future::try_join_all;
 
-    use super::RandomBitsGenerator;
+    use super::{RandomBitsGenerator, RandomBitsPool};
     use crate::{
+        error::Error,
         ff::{Field, Fp31},
         protocol::{
             context::{Context, UpgradableContext, Validator},
@@ -177,6 +279,35 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    pub async fn pool() {
+        /// Enough records for some of them to abort on a field of size 31.
+        const COUNT: usize = 100;
+
+        let world = TestWorld::default();
+        let contexts = world.malicious_contexts();
+        let validators = contexts.map(UpgradableContext::validator::<Fp31>);
+
+        let m_result = join3v(validators.iter().map(|v| async move {
+            let pool = RandomBitsPool::generate(v.context(), COUNT).await?;
+            assert_eq!(COUNT, pool.len());
+            let rbg = RandomBitsGenerator::with_pool(v.context().set_total_records(COUNT), pool);
+            let bits = try_join_all((0..COUNT).map(|i| rbg.generate(RecordId::from(i)))).await?;
+            Ok::<_, Error>((
+                bits.into_iter().map(|share| share.b_p).collect::<Vec<_>>(),
+                rbg.fallbacks(),
+            ))
+        }))
+        .await;
+        let [(m0, f0), (m1, f1), (m2, f2)] = m_result;
+        assert_eq!(f0, f1);
+        assert_eq!(f0, f2);
+
+        let result = join3v(zip(validators, [m0, m1, m2]).map(|(v, m)| v.validate(m))).await;
+        let bits: Vec<Fp31> = result.reconstruct(); // reconstruct() will validate the values.
+        assert_eq!(COUNT, bits.len());
+    }
+
     #[tokio::test]
     pub async fn malicious() {
         let world = TestWorld::default();
@@ -1,17 +1,32 @@
 use crate::{
     error::Error,
-    ff::{Field, Gf2},
-    protocol::{boolean::or::or, context::Context, step::BitOpStep, BasicProtocols, RecordId},
-    secret_sharing::{BitDecomposed, Linear as LinearSecretSharing, LinearRefOps},
+    protocol::{
+        boolean::{
+            arithmetic::{add_with_carry, Adder},
+            or::or,
+        },
+        context::Context,
+        step::BitOpStep,
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::{
+        vector::Gf2Vector, BitDecomposed, Linear as LinearSecretSharing, LinearRefOps,
+    },
 };
 
+/// A sum that saturates at `2^n`, where `n` is the number of bits in `sum`.
+///
+/// Shares may be over [`Gf2`] or over any [`Gf2Vector`], in which case each lane holds an
+/// independent sum and every step of the computation processes all lanes at once.
+///
+/// [`Gf2`]: crate::ff::Gf2
 #[derive(Debug)]
-pub struct SaturatingSum<S: LinearSecretSharing<Gf2>> {
+pub struct SaturatingSum<S> {
     pub sum: BitDecomposed<S>,
     pub is_saturated: S,
 }
 
-impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
+impl<S> SaturatingSum<S> {
     pub fn new(value: BitDecomposed<S>, is_saturated: S) -> SaturatingSum<S> {
         SaturatingSum {
             sum: value,
@@ -25,7 +40,7 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
     ///
     /// # Panics
     /// If something try to add a bit decomposed value larger than this `SaturatingSum` can accomodate
-    pub async fn add<C>(
+    pub async fn add<C, V>(
         &self,
         ctx: C,
         record_id: RecordId,
@@ -33,184 +48,36 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
     ) -> Result<SaturatingSum<S>, Error>
     where
         C: Context,
-        S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
-        for<'a> &'a S: LinearRefOps<'a, S, Gf2>,
+        V: Gf2Vector,
+        S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+        for<'a> &'a S: LinearRefOps<'a, S, V>,
     {
-        assert!(self.sum.len() >= value.len());
-
-        let mut output_sum = Vec::with_capacity(self.sum.len());
-        let mut carry_in = S::ZERO;
-        let zero = S::ZERO;
-        for i in 0..self.sum.len() {
-            let c = ctx.narrow(&BitOpStep::from(i));
-            // When adding a value with fewer bits than the saturating sum can express
-            // we still must compute the carries, which still requires a single multiplication
-            // so there is no savings
-            let x = value.get(i).unwrap_or(&zero);
-            let sum_bit = one_bit_adder(c, record_id, x, &self.sum[i], &mut carry_in).await?;
-
-            output_sum.push(sum_bit);
-        }
+        let (sum, carry_out) =
+            add_with_carry(ctx.clone(), record_id, Adder::RippleCarry, &self.sum, value).await?;
         let is_saturated = or(
             ctx.narrow(&BitOpStep::from(self.sum.len())),
             record_id,
-            &carry_in,
+            &carry_out,
             &self.is_saturated,
         )
         .await?;
 
-        Ok(SaturatingSum::new(
-            BitDecomposed::new(output_sum),
-            is_saturated,
-        ))
-    }
-
-    ///
-    /// NOTE: ignores the `is_saturated` flag. The return value is non-sensical if `is_saturated` is true
-    ///
-    /// Only returns the least significant `num_bits` of the delta.
-    ///
-    /// # Errors
-    /// If one of the multiplications errors
-    ///
-    /// # Panics
-    /// If you ask for more bits than the `SaturatingSum` is using
-    ///
-    pub async fn truncated_delta_to_saturation_point<C>(
-        &self,
-        ctx: C,
-        record_id: RecordId,
-        num_bits: u32,
-    ) -> Result<BitDecomposed<S>, Error>
-    where
-        C: Context,
-        S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
-        for<'a> &'a S: LinearRefOps<'a, S, Gf2>,
-    {
-        assert!(num_bits as usize <= self.sum.len());
-
-        let mut carry_in = S::share_known_value(&ctx, Gf2::ONE);
-        let mut output = vec![];
-        for (i, bit) in self.sum.iter().enumerate().take(num_bits as usize) {
-            let c = ctx.narrow(&BitOpStep::from(i));
-
-            let compute_carry_out = i < (num_bits as usize) - 1;
-            let difference_bit = one_bit_subtractor(
-                c,
-                record_id,
-                &S::ZERO,
-                bit,
-                &mut carry_in,
-                compute_carry_out,
-            )
-            .await?;
-
-            output.push(difference_bit);
-        }
-        Ok(BitDecomposed::new(output))
-    }
-}
-
-///
-/// This improved one-bit adder that only requires a single multiplication was taken from:
-/// "Improved Garbled Circuit Building Blocks and Applications to Auctions and Computing Minima"
-/// `https://encrypto.de/papers/KSS09.pdf`
-///
-/// Section 3.1 Integer Addition, Subtraction and Multiplication
-///
-/// For each bit, the `sum_bit` can be efficiently computed as just `s_i = x_i ⊕ y_i ⊕ c_i`
-/// This can be computed "for free" in Gf2
-///
-/// The `carry_out` bit can be efficiently computed with just a single multiplication as:
-/// `c_(i+1) = c_i ⊕ ((x_i ⊕ c_i) & (y_i ⊕ c_i))`
-///
-/// Returns `sum_bit`
-///
-/// The mutable refernce to `carry_in` is mutated to take on the value of the `carry_out` bit
-///
-async fn one_bit_adder<C, SB>(
-    ctx: C,
-    record_id: RecordId,
-    x: &SB,
-    y: &SB,
-    carry_in: &mut SB,
-) -> Result<SB, Error>
-where
-    C: Context,
-    SB: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
-    for<'a> &'a SB: LinearRefOps<'a, SB, Gf2>,
-{
-    // compute sum bit as x XOR y XOR carry_in
-    let sum_bit = x + y + &*carry_in;
-
-    let x_xor_carry_in = x + &*carry_in;
-    let y_xor_carry_in = y + &*carry_in;
-
-    // There are two cases when the `carry_out` bit is different from the `carry_in` bit
-    // (1) When the `carry_in` bit is 0 and both `x` and `y` are 1
-    // (2) When the `carry_in` bit is 1 and both `x` and `y` are 0
-    // So by computing `(x ⊕ c) ∧ (y ⊕ c)` we isolate those cases with a single multiplication
-    *carry_in = x_xor_carry_in
-        .multiply(&y_xor_carry_in, ctx, record_id)
-        .await?
-        + &*carry_in;
-
-    Ok(sum_bit)
-}
-
-///
-/// This improved one-bit subtractor that only requires a single multiplication was taken from:
-/// "Improved Garbled Circuit Building Blocks and Applications to Auctions and Computing Minima"
-/// `https://encrypto.de/papers/KSS09.pdf`
-///
-/// Section 3.1 Integer Addition, Subtraction and Multiplication
-///
-/// For each bit, the `difference_bit` can be efficiently computed as just `d_i = x_i ⊕ !y_i ⊕ c_i`
-/// This can be computed "for free" in Gf2
-///
-/// The `carry_out` bit can be efficiently computed with just a single multiplication as:
-/// `c_(i+1) = c_i ⊕ ((x_i ⊕ c_i) ∧ !(y_i ⊕ c_i))`
-///
-/// Returns `difference_bit`
-///
-/// If `compute_carry_out` is set to `true`, then the mutable refernce to `carry_in` is mutated to take on the value of the `carry_out` bit
-///
-async fn one_bit_subtractor<C, SB>(
-    ctx: C,
-    record_id: RecordId,
-    x: &SB,
-    y: &SB,
-    carry_in: &mut SB,
-    compute_carry_out: bool,
-) -> Result<SB, Error>
-where
-    C: Context,
-    SB: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
-    for<'a> &'a SB: LinearRefOps<'a, SB, Gf2>,
-{
-    // compute difference bit as not_y XOR x XOR carry_in
-    let difference_bit = SB::share_known_value(&ctx, Gf2::ONE) + y + x + &*carry_in;
-    if compute_carry_out {
-        let x_xor_carry_in = x + &*carry_in;
-        let y_xor_carry_in = y + &*carry_in;
-        let not_y_xor_carry_in = SB::share_known_value(&ctx, Gf2::ONE) + &y_xor_carry_in;
-
-        *carry_in = x_xor_carry_in
-            .multiply(&not_y_xor_carry_in, ctx, record_id)
-            .await?
-            + &*carry_in;
+        Ok(SaturatingSum::new(sum, is_saturated))
     }
-    Ok(difference_bit)
 }
 
 #[cfg(all(test, unit_test))]
 mod tests {
+    use rand::{thread_rng, Rng};
+
     use super::SaturatingSum;
     use crate::{
         ff::{Field, Gf2},
         protocol::{context::Context, RecordId},
         secret_sharing::{
-            replicated::semi_honest::AdditiveShare as Replicated, BitDecomposed, SharedValue,
+            replicated::semi_honest::AdditiveShare as Replicated,
+            vector::{Gf2Vector, Gf2x64},
+            BitDecomposed, SharedValue,
         },
         test_fixture::{get_bits, Reconstruct, Runner, TestWorld},
     };
@@ -279,30 +146,32 @@ mod tests {
         assert_eq!(64, saturating_add(60, 6, 5, 3).await);
     }
 
-    #[tokio::test]
-    pub async fn truncated_delta() {
-        assert_eq!(2, truncated_delta_to_saturation_point(30, 5, 3).await);
-        assert_eq!(2, truncated_delta_to_saturation_point(30, 5, 2).await);
-        assert_eq!(0, truncated_delta_to_saturation_point(30, 5, 1).await);
-        assert_eq!(1, truncated_delta_to_saturation_point(31, 5, 1).await);
-        assert_eq!(0, truncated_delta_to_saturation_point(32, 5, 1).await);
-        assert_eq!(7, truncated_delta_to_saturation_point(25, 5, 3).await);
-        assert_eq!(3, truncated_delta_to_saturation_point(61, 6, 3).await);
-        assert_eq!(1, truncated_delta_to_saturation_point(15, 4, 1).await);
-        assert_eq!(1, truncated_delta_to_saturation_point(15, 4, 2).await);
-        assert_eq!(1, truncated_delta_to_saturation_point(15, 4, 2).await);
-        assert_eq!(1, truncated_delta_to_saturation_point(15, 4, 4).await);
+    /// Packs bit `i` of every value into the vector `i`.
+    fn transpose(values: &[u32], num_bits: u32) -> BitDecomposed<Gf2x64> {
+        BitDecomposed::decompose(num_bits, |i| {
+            values
+                .iter()
+                .map(|v| Gf2::truncate_from((v >> i) & 1))
+                .collect::<Gf2x64>()
+        })
     }
 
-    async fn saturating_add(a: u32, num_a_bits: u32, b: u32, num_b_bits: u32) -> u128 {
+    #[tokio::test]
+    pub async fn vectorized_addition() {
+        const A_BITS: u32 = 5;
+        const B_BITS: u32 = 3;
         let world = TestWorld::default();
-
-        let a_bits = get_bits::<Gf2>(a, num_a_bits);
-        let b_bits = get_bits::<Gf2>(b, num_b_bits);
-
-        let foo = world
+        let mut rng = thread_rng();
+        let a = (0..Gf2x64::LANES)
+            .map(|_| rng.gen_range(0..1 << A_BITS))
+            .collect::<Vec<u32>>();
+        let b = (0..Gf2x64::LANES)
+            .map(|_| rng.gen_range(0..1 << B_BITS))
+            .collect::<Vec<u32>>();
+
+        let result = world
             .semi_honest(
-                (a_bits, b_bits),
+                (transpose(&a, A_BITS), transpose(&b, B_BITS)),
                 |ctx, (a_bits, b_bits): (BitDecomposed<_>, BitDecomposed<_>)| async move {
                     let a = SaturatingSum::new(a_bits, Replicated::ZERO);
                     a.add(ctx.set_total_records(1), RecordId(0), &b_bits)
@@ -312,25 +181,38 @@ mod tests {
             )
             .await;
 
-        foo.reconstruct()
+        let [s0, s1, s2] = &result;
+        let sum: BitDecomposed<Gf2x64> = [&s0.sum, &s1.sum, &s2.sum].reconstruct();
+        let is_saturated = [&s0.is_saturated, &s1.is_saturated, &s2.is_saturated].reconstruct();
+        for lane in 0..Gf2x64::LANES {
+            let actual = if is_saturated.lane(lane) == Gf2::ONE {
+                1 << A_BITS
+            } else {
+                sum.iter()
+                    .enumerate()
+                    .map(|(i, bits)| u32::try_from(bits.lane(lane).as_u128()).unwrap() << i)
+                    .sum()
+            };
+            assert_eq!(std::cmp::min(a[lane] + b[lane], 1 << A_BITS), actual);
+        }
     }
 
-    async fn truncated_delta_to_saturation_point(a: u32, num_a_bits: u32, num_b_bits: u32) -> u128 {
+    async fn saturating_add(a: u32, num_a_bits: u32, b: u32, num_b_bits: u32) -> u128 {
         let world = TestWorld::default();
 
         let a_bits = get_bits::<Gf2>(a, num_a_bits);
+        let b_bits = get_bits::<Gf2>(b, num_b_bits);
 
         let foo = world
-            .semi_honest(a_bits, |ctx, a_bits: BitDecomposed<_>| async move {
-                let a = SaturatingSum::new(a_bits, Replicated::ZERO);
-                a.truncated_delta_to_saturation_point(
-                    ctx.set_total_records(1),
-                    RecordId(0),
-                    num_b_bits,
-                )
-                .await
-                .unwrap()
-            })
+            .semi_honest(
+                (a_bits, b_bits),
+                |ctx, (a_bits, b_bits): (BitDecomposed<_>, BitDecomposed<_>)| async move {
+                    let a = SaturatingSum::new(a_bits, Replicated::ZERO);
+                    a.add(ctx.set_total_records(1), RecordId(0), &b_bits)
+                        .await
+                        .unwrap()
+                },
+            )
             .await;
 
         foo.reconstruct()
@@ -0,0 +1,604 @@
+use std::iter::zip;
+
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{
+        basics::SecureMul,
+        boolean::{
+            arithmetic::{subtract_with_borrow, Adder},
+            or::or,
+            xor,
+        },
+        context::Context,
+        step::BitOpStep,
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::{
+        vector::Gf2Vector, BitDecomposed, Linear as LinearSecretSharing, LinearRefOps, SharedValue,
+    },
+};
+
+// Comparisons between two secret-shared integers.
+//
+// Unlike the functions in `comparison`, which compare a shared value against a public constant,
+// both operands here are bitwise shared: `a[i]` and `b[i]` are shares of the `i`-th bit (least
+// significant first) of two unsigned integers with the same number of bits.
+//
+// Ordering comparisons are the borrow out of `a - b`, computed by the adders in `arithmetic`, so
+// they need bits over `Gf2`, or vectors of them. Equality only needs `xor` and `or`, which work for
+// bits in a prime field as well.
+
+#[derive(Step)]
+pub(crate) enum Step {
+    #[step(child = BitOpStep)]
+    Subtract,
+    #[step(child = BitOpStep)]
+    Xor,
+    #[step(child = BitOpStep)]
+    Any,
+    #[step(child = Step)]
+    Condition,
+    #[step(child = BitOpStep)]
+    Select,
+}
+
+/// Compares `[a]` and `[b]`, and returns `1` iff `a < b`
+///
+/// This is the borrow out of the most significant bit of `a - b`, see [`subtract_with_borrow`].
+/// It costs one multiplication per bit.
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_less_than<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[S],
+    b: &[S],
+) -> Result<S, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "operands must have the same number of bits"
+    );
+    let (_, borrow) = subtract_with_borrow(
+        ctx.narrow(&Step::Subtract),
+        record_id,
+        Adder::RippleCarry,
+        a,
+        b,
+    )
+    .await?;
+    Ok(borrow)
+}
+
+/// Compares `[a]` and `[b]`, and returns `1` iff `a > b`
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_greater_than<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[S],
+    b: &[S],
+) -> Result<S, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    bitwise_less_than(ctx, record_id, b, a).await
+}
+
+/// Compares `[a]` and `[b]`, and returns `1` iff `a == b`
+///
+/// The values are equal unless any bit of `a ⊕ b` is set. This costs one multiplication per bit
+/// for the XORs, and one less for the ORs, which are combined in a binary tree.
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_equal<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[S],
+    b: &[S],
+) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "operands must have the same number of bits"
+    );
+    let xor_ctx = ctx.narrow(&Step::Xor);
+    let differences = xor_ctx
+        .parallel_join(zip(a, b).enumerate().map(|(i, (a_bit, b_bit))| {
+            let c = xor_ctx.narrow(&BitOpStep::from(i));
+            async move { xor(c, record_id, a_bit, b_bit).await }
+        }))
+        .await?;
+    let any_different = any(ctx.narrow(&Step::Any), record_id, differences).await?;
+
+    Ok(S::share_known_value(&ctx, F::ONE) - &any_different)
+}
+
+/// Computes the OR of all `bits`, in `⌈log2(n)⌉` rounds of [`or`].
+async fn any<V, C, S>(ctx: C, record_id: RecordId, mut bits: Vec<S>) -> Result<S, Error>
+where
+    V: SharedValue,
+    C: Context,
+    S: LinearSecretSharing<V> + SecureMul<C>,
+{
+    let mut ors = 0;
+    while bits.len() > 1 {
+        let odd = if bits.len() % 2 == 1 {
+            bits.pop()
+        } else {
+            None
+        };
+        let pairs = bits.chunks_exact(2).enumerate().map(|(i, pair)| {
+            let c = ctx.narrow(&BitOpStep::from(ors + i));
+            async move { or(c, record_id, &pair[0], &pair[1]).await }
+        });
+        let round = ctx.parallel_join(pairs).await?;
+        ors += round.len();
+        bits = round.into_iter().chain(odd).collect();
+    }
+
+    Ok(bits.pop().unwrap_or(S::ZERO))
+}
+
+/// Returns `[a]` if `condition` is `1` and `[b]` if it is `0`, computed as `b + condition * (a - b)`.
+/// The values may be of any size, this is not limited to bits.
+///
+/// # Errors
+/// Propagates errors from multiplications
+pub async fn select<V, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    condition: &S,
+    a: &S,
+    b: &S,
+) -> Result<S, Error>
+where
+    V: SharedValue,
+    C: Context,
+    S: LinearSecretSharing<V> + SecureMul<C>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    Ok(condition.multiply(&(a - b), ctx, record_id).await? + b)
+}
+
+/// Bitwise [`select`]: every bit of `[a]` or `[b]` is selected with the same `condition`.
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_select<V, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    condition: &S,
+    a: &[S],
+    b: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    V: SharedValue,
+    C: Context,
+    S: LinearSecretSharing<V> + SecureMul<C>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "operands must have the same number of bits"
+    );
+    BitDecomposed::try_from(
+        ctx.parallel_join(zip(a, b).enumerate().map(|(i, (a_bit, b_bit))| {
+            let c = ctx.narrow(&BitOpStep::from(i));
+            async move { select(c, record_id, condition, a_bit, b_bit).await }
+        }))
+        .await?,
+    )
+}
+
+/// Returns the smaller of `[a]` and `[b]`.
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_min<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[S],
+    b: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let a_lt_b = bitwise_less_than(ctx.narrow(&Step::Condition), record_id, a, b).await?;
+    bitwise_select(ctx.narrow(&Step::Select), record_id, &a_lt_b, a, b).await
+}
+
+/// Returns the larger of `[a]` and `[b]`.
+///
+/// # Errors
+/// Propagates errors from multiplications
+///
+/// # Panics
+/// If `a` and `b` have different number of bits.
+pub async fn bitwise_max<C, V, S>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[S],
+    b: &[S],
+) -> Result<BitDecomposed<S>, Error>
+where
+    C: Context,
+    V: Gf2Vector,
+    S: LinearSecretSharing<V> + BasicProtocols<C, V>,
+    for<'a> &'a S: LinearRefOps<'a, S, V>,
+{
+    let a_gt_b = bitwise_greater_than(ctx.narrow(&Step::Condition), record_id, a, b).await?;
+    bitwise_select(ctx.narrow(&Step::Select), record_id, &a_gt_b, a, b).await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use proptest::{
+        collection::vec,
+        prelude::{prop_oneof, ProptestConfig, Strategy},
+        proptest,
+    };
+    use rand::distributions::{Distribution, Standard};
+
+    use super::{
+        bitwise_equal, bitwise_greater_than, bitwise_less_than, bitwise_max, bitwise_min, select,
+    };
+    use crate::{
+        error::Error,
+        ff::{Field, Fp31, Fp32BitPrime, Gf2, PrimeField},
+        protocol::{context::Context, BasicProtocols, RecordId},
+        secret_sharing::{
+            replicated::malicious::ExtendableField, vector::Gf2Vector, BitDecomposed,
+            Linear as LinearSecretSharing, LinearRefOps,
+        },
+        seq_join::SeqJoin,
+        test_executor::run,
+        test_fixture::{bits_to_value, get_bits, Reconstruct, Runner, TestWorld},
+    };
+
+    #[derive(Clone, Copy, Debug)]
+    enum Predicate {
+        Lt,
+        Gt,
+        Eq,
+    }
+
+    impl Predicate {
+        fn plaintext(self, a: u32, b: u32) -> bool {
+            match self {
+                Self::Lt => a < b,
+                Self::Gt => a > b,
+                Self::Eq => a == b,
+            }
+        }
+
+        async fn evaluate<F, C, S>(
+            self,
+            ctx: C,
+            record_id: RecordId,
+            a: &[S],
+            b: &[S],
+        ) -> Result<S, Error>
+        where
+            F: Field + Gf2Vector,
+            C: Context,
+            S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+            for<'a> &'a S: LinearRefOps<'a, S, F>,
+        {
+            match self {
+                Self::Lt => bitwise_less_than(ctx, record_id, a, b).await,
+                Self::Gt => bitwise_greater_than(ctx, record_id, a, b).await,
+                Self::Eq => bitwise_equal(ctx, record_id, a, b).await,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum Extremum {
+        Min,
+        Max,
+    }
+
+    impl Extremum {
+        fn plaintext(self, a: u32, b: u32) -> u32 {
+            match self {
+                Self::Min => a.min(b),
+                Self::Max => a.max(b),
+            }
+        }
+
+        async fn evaluate<F, C, S>(
+            self,
+            ctx: C,
+            record_id: RecordId,
+            a: &[S],
+            b: &[S],
+        ) -> Result<BitDecomposed<S>, Error>
+        where
+            C: Context,
+            F: Gf2Vector,
+            S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+            for<'a> &'a S: LinearRefOps<'a, S, F>,
+        {
+            match self {
+                Self::Min => bitwise_min(ctx, record_id, a, b).await,
+                Self::Max => bitwise_max(ctx, record_id, a, b).await,
+            }
+        }
+    }
+
+    const BITS: u32 = 6;
+    const MAX: u32 = (1 << BITS) - 1;
+
+    /// Pairs of values, with equal pairs as likely as unequal ones.
+    fn pairs() -> impl Strategy<Value = Vec<(u32, u32)>> {
+        let pair = prop_oneof![(0..=MAX, 0..=MAX), (0..=MAX).prop_map(|v| (v, v)),];
+        vec(pair, 1..20)
+    }
+
+    type Bits<F> = std::vec::IntoIter<BitDecomposed<F>>;
+
+    fn split<F: Field>(pairs: &[(u32, u32)]) -> (Bits<F>, Bits<F>) {
+        let (a, b): (Vec<_>, Vec<_>) = pairs
+            .iter()
+            .map(|&(a, b)| (get_bits::<F>(a, BITS), get_bits::<F>(b, BITS)))
+            .unzip();
+        (a.into_iter(), b.into_iter())
+    }
+
+    async fn check_predicate<F>(world: &TestWorld, pairs: &[(u32, u32)], predicate: Predicate)
+    where
+        F: ExtendableField + Gf2Vector,
+        Standard: Distribution<F>,
+    {
+        let expected = pairs
+            .iter()
+            .map(|&(a, b)| F::truncate_from(predicate.plaintext(a, b)))
+            .collect::<Vec<_>>();
+
+        let result =
+            world
+                .semi_honest(split::<F>(pairs), |ctx, (a, b)| async move {
+                    let ctx = ctx.set_total_records(a.len());
+                    ctx.try_join(zip(a.iter(), b.iter()).enumerate().map(|(i, (a, b))| {
+                        predicate.evaluate(ctx.clone(), RecordId::from(i), a, b)
+                    }))
+                    .await
+                    .unwrap()
+                })
+                .await;
+        assert_eq!(expected, result.reconstruct(), "{predicate:?}");
+
+        let m_result =
+            world
+                .upgraded_malicious(split::<F>(pairs), |ctx, (a, b)| async move {
+                    let ctx = ctx.set_total_records(a.len());
+                    ctx.try_join(zip(a.iter(), b.iter()).enumerate().map(|(i, (a, b))| {
+                        predicate.evaluate(ctx.clone(), RecordId::from(i), a, b)
+                    }))
+                    .await
+                    .unwrap()
+                })
+                .await;
+        assert_eq!(expected, m_result.reconstruct(), "{predicate:?}");
+    }
+
+    async fn check_extremum<F>(world: &TestWorld, pairs: &[(u32, u32)], extremum: Extremum)
+    where
+        F: ExtendableField + Gf2Vector,
+        Standard: Distribution<F>,
+    {
+        let expected = pairs
+            .iter()
+            .map(|&(a, b)| u128::from(extremum.plaintext(a, b)))
+            .collect::<Vec<_>>();
+
+        let result: Vec<BitDecomposed<F>> = world
+            .semi_honest(split::<F>(pairs), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(a.len());
+                ctx.try_join(
+                    zip(a.iter(), b.iter())
+                        .enumerate()
+                        .map(|(i, (a, b))| extremum.evaluate(ctx.clone(), RecordId::from(i), a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let result = result.iter().map(|v| bits_to_value(v)).collect::<Vec<_>>();
+        assert_eq!(expected, result, "{extremum:?}");
+
+        let m_result: Vec<BitDecomposed<F>> = world
+            .upgraded_malicious(split::<F>(pairs), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(a.len());
+                ctx.try_join(
+                    zip(a.iter(), b.iter())
+                        .enumerate()
+                        .map(|(i, (a, b))| extremum.evaluate(ctx.clone(), RecordId::from(i), a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = m_result
+            .iter()
+            .map(|v| bits_to_value(v))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, m_result, "{extremum:?}");
+    }
+
+    async fn check_all<F>(pairs: Vec<(u32, u32)>)
+    where
+        F: ExtendableField + Gf2Vector,
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        for predicate in [Predicate::Lt, Predicate::Gt, Predicate::Eq] {
+            check_predicate::<F>(&world, &pairs, predicate).await;
+        }
+        for extremum in [Extremum::Min, Extremum::Max] {
+            check_extremum::<F>(&world, &pairs, extremum).await;
+        }
+    }
+
+    /// Only equality is defined for bits in a prime field.
+    async fn check_equal<F>(pairs: Vec<(u32, u32)>)
+    where
+        F: ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let expected = pairs
+            .iter()
+            .map(|&(a, b)| F::truncate_from(a == b))
+            .collect::<Vec<_>>();
+
+        let result = world
+            .semi_honest(split::<F>(&pairs), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(a.len());
+                ctx.try_join(
+                    zip(a.iter(), b.iter())
+                        .enumerate()
+                        .map(|(i, (a, b))| bitwise_equal(ctx.clone(), RecordId::from(i), a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+        assert_eq!(expected, result.reconstruct());
+
+        let m_result = world
+            .upgraded_malicious(split::<F>(&pairs), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(a.len());
+                ctx.try_join(
+                    zip(a.iter(), b.iter())
+                        .enumerate()
+                        .map(|(i, (a, b))| bitwise_equal(ctx.clone(), RecordId::from(i), a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+        assert_eq!(expected, m_result.reconstruct());
+    }
+
+    #[tokio::test]
+    async fn extremes() {
+        let pairs = vec![(0, 0), (0, MAX), (MAX, 0), (MAX, MAX), (MAX - 1, MAX)];
+        check_all::<Gf2>(pairs.clone()).await;
+        check_equal::<Fp31>(pairs.clone()).await;
+        check_equal::<Fp32BitPrime>(pairs).await;
+    }
+
+    async fn check_select(condition: bool, a: Fp32BitPrime, b: Fp32BitPrime) {
+        let world = TestWorld::default();
+        let expected = if condition { a } else { b };
+        let condition = Fp32BitPrime::truncate_from(condition);
+
+        let result = world
+            .semi_honest((condition, (a, b)), |ctx, (condition, (a, b))| async move {
+                select(
+                    ctx.set_total_records(1),
+                    RecordId::FIRST,
+                    &condition,
+                    &a,
+                    &b,
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+        assert_eq!(expected, result.reconstruct());
+
+        let m_result = world
+            .upgraded_malicious((condition, (a, b)), |ctx, (condition, (a, b))| async move {
+                select(
+                    ctx.set_total_records(1),
+                    RecordId::FIRST,
+                    &condition,
+                    &a,
+                    &b,
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+        assert_eq!(expected, m_result.reconstruct());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(10))]
+        #[test]
+        #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
+        fn gf2(pairs in pairs()) {
+            run(move || check_all::<Gf2>(pairs.clone()));
+        }
+
+        #[test]
+        #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
+        fn fp31(pairs in pairs()) {
+            run(move || check_equal::<Fp31>(pairs.clone()));
+        }
+
+        #[test]
+        #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
+        fn fp32_bit_prime(pairs in pairs()) {
+            run(move || check_equal::<Fp32BitPrime>(pairs.clone()));
+        }
+
+        #[test]
+        #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
+        fn select_arithmetic(
+            condition: bool,
+            a in 0..Fp32BitPrime::PRIME,
+            b in 0..Fp32BitPrime::PRIME,
+        ) {
+            let (a, b) = (Fp32BitPrime::truncate_from(a), Fp32BitPrime::truncate_from(b));
+            run(move || check_select(condition, a, b));
+        }
+    }
+}
@@ -141,7 +141,9 @@ where
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     RandomBits,
+    #[step(child = crate::protocol::boolean::bitwise_less_than_prime::Step)]
     IsPLessThanB,
     RevealC,
 }
@@ -18,9 +18,9 @@ use crate::{
         },
         context::{
             prss::InstrumentedIndexedSharedRandomness,
-            validator::{Malicious as Validator, MaliciousAccumulator},
+            validator::{Malicious as Validator, MaliciousAccumulator, ScopedCheck},
             Base, Context as ContextTrait, InstrumentedSequentialSharedRandomness,
-            SpecialAccessToUpgradedContext, UpgradableContext, UpgradedContext,
+            ScopedValidation, SpecialAccessToUpgradedContext, UpgradableContext, UpgradedContext,
         },
         prss::Endpoint as PrssEndpoint,
         step::{Gate, Step, StepNarrow},
@@ -189,6 +189,10 @@ impl<'a, F: ExtendableField> Upgraded<'a, F> {
 /// Upgrades all use this step to distinguish protocol steps from the step that is used to upgrade inputs.
 #[derive(Step)]
 pub(crate) enum UpgradeStep {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
+    #[step(child = crate::protocol::context::upgrade::Upgrade2DVectors)]
+    #[step(child = crate::protocol::context::upgrade::UpgradeModConvStep)]
+    #[step(child = crate::protocol::context::upgrade::UpgradeTripleStep)]
     Upgrade,
 }
 
@@ -343,6 +347,27 @@ impl<'a, F: ExtendableField> SeqJoin for Upgraded<'a, F> {
     }
 }
 
+/// The scoped context shares `r` with this one, but accumulates MACs separately, so that they can
+/// be checked without waiting for the validator.
+impl<'a, F: ExtendableField> ScopedValidation for Upgraded<'a, F> {
+    type Check = ScopedCheck<'a, F>;
+
+    fn scoped_validation(&self) -> (Self, Self::Check) {
+        let (check, accumulator) = ScopedCheck::new(&self.as_base(), self.inner.r_share.clone());
+        let ctx = Self {
+            inner: Arc::new(UpgradedInner {
+                prss: self.inner.prss,
+                gateway: self.inner.gateway,
+                accumulator,
+                r_share: self.inner.r_share.clone(),
+            }),
+            gate: self.gate.clone(),
+            total_records: self.total_records,
+        };
+        (ctx, check)
+    }
+}
+
 /// Sometimes it is required to reinterpret malicious context as semi-honest. Ideally
 /// protocols should be generic over `SecretShare` trait and not requiring this cast and taking
 /// `ProtocolContext<'a, S: SecretShare<F>, F: Field>` as the context. If that is not possible,
@@ -90,7 +90,7 @@ pub trait UpgradableContext: Context {
 }
 
 #[async_trait]
-pub trait UpgradedContext<F: ExtendableField>: Context {
+pub trait UpgradedContext<F: ExtendableField>: ScopedValidation {
     // TODO: can we add BasicProtocols to this so that we don't need it as a constraint everywhere.
     type Share: SecretSharing<F> + 'static;
 
@@ -133,6 +133,35 @@ pub trait UpgradedContext<F: ExtendableField>: Context {
     ) -> Result<Self::Share, Error>;
 }
 
+/// Contexts that can validate a part of the protocol as soon as that part completes, instead of
+/// leaving it to the validator that runs at the end of the protocol.
+pub trait ScopedValidation: Context {
+    type Check: ScopeCheck;
+
+    /// Returns a copy of this context, together with the check for everything that is computed
+    /// using that copy. The check must be validated before any value computed in the scope is
+    /// revealed or used to make decisions.
+    #[must_use]
+    fn scoped_validation(&self) -> (Self, Self::Check);
+}
+
+/// Validation of a scope created by [`ScopedValidation::scoped_validation`].
+#[async_trait]
+pub trait ScopeCheck: Send {
+    /// ## Errors
+    /// If one of the helpers deviated from the protocol inside the scope, or if communication
+    /// with other helpers fails.
+    async fn validate(self) -> Result<(), Error>;
+}
+
+/// Semi-honest contexts have nothing to check.
+#[async_trait]
+impl ScopeCheck for () {
+    async fn validate(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 pub trait SpecialAccessToUpgradedContext<F: ExtendableField>: UpgradedContext<F> {
     /// This is the base context type.  This will always be `Base`, but use
     /// an associated type to avoid having to bind this trait to the lifetime
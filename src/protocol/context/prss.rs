@@ -1,6 +1,6 @@
 //! Metric-aware PRSS decorators
 
-use rand_core::{Error, RngCore};
+use rand_core::{CryptoRng, Error, RngCore};
 
 use crate::{
     helpers::Role,
@@ -42,6 +42,12 @@ impl SharedRandomness for InstrumentedIndexedSharedRandomness<'_> {
         metrics::increment_counter!(INDEXED_PRSS_GENERATED, STEP => step, ROLE => self.role.as_static_str());
         self.inner.generate_values(index)
     }
+
+    fn generate_arrays<const N: usize, I: Into<u128>>(&self, index: I) -> ([u128; N], [u128; N]) {
+        let step = self.step.as_ref().to_string();
+        metrics::increment_counter!(INDEXED_PRSS_GENERATED, STEP => step, ROLE => self.role.as_static_str());
+        self.inner.generate_arrays(index)
+    }
 }
 
 /// Wrapper for `SequentialSharedRandomness` that instrument calls to generate random values.
@@ -82,3 +88,5 @@ impl RngCore for InstrumentedSequentialSharedRandomness<'_> {
         self.inner.try_fill_bytes(dest)
     }
 }
+
+impl CryptoRng for InstrumentedSequentialSharedRandomness<'_> {}
@@ -16,8 +16,8 @@ use crate::{
         basics::{ShareKnownValue, ZeroPositions},
         context::{
             validator::SemiHonest as Validator, Base, InstrumentedIndexedSharedRandomness,
-            InstrumentedSequentialSharedRandomness, SpecialAccessToUpgradedContext,
-            UpgradableContext, UpgradedContext,
+            InstrumentedSequentialSharedRandomness, ScopedValidation,
+            SpecialAccessToUpgradedContext, UpgradableContext, UpgradedContext,
         },
         prss::Endpoint as PrssEndpoint,
         step::{Gate, Step, StepNarrow},
@@ -107,6 +107,14 @@ impl<'a> UpgradableContext for Context<'a> {
     }
 }
 
+impl ScopedValidation for Context<'_> {
+    type Check = ();
+
+    fn scoped_validation(&self) -> (Self, Self::Check) {
+        (self.clone(), ())
+    }
+}
+
 impl<'a> SeqJoin for Context<'a> {
     fn active_work(&self) -> NonZeroUsize {
         self.inner.active_work()
@@ -193,6 +201,10 @@ impl<'a, F: ExtendableField> SeqJoin for Upgraded<'a, F> {
 // return Ok() and never trigger communications.
 #[derive(Step)]
 pub(crate) enum UpgradeStep {
+    #[step(child = crate::protocol::context::upgrade::Upgrade2DVectors)]
+    #[step(child = crate::protocol::context::upgrade::UpgradeModConvStep)]
+    #[step(child = crate::protocol::context::upgrade::UpgradeTripleStep)]
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     UpgradeSemiHonest,
 }
 
@@ -248,6 +260,14 @@ impl<'a, F: ExtendableField> UpgradedContext<F> for Upgraded<'a, F> {
     }
 }
 
+impl<F: ExtendableField> ScopedValidation for Upgraded<'_, F> {
+    type Check = ();
+
+    fn scoped_validation(&self) -> (Self, Self::Check) {
+        (self.clone(), ())
+    }
+}
+
 impl<'a, F: ExtendableField> SpecialAccessToUpgradedContext<F> for Upgraded<'a, F> {
     type Base = Base<'a>;
 
@@ -95,6 +95,7 @@ where
 }
 
 #[derive(Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
 pub(crate) enum UpgradeTripleStep {
     UpgradeBitTriple0,
     UpgradeBitTriple1,
@@ -164,8 +165,11 @@ where
 }
 
 #[derive(Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
 pub(crate) enum Upgrade2DVectors {
     #[dynamic]
+    #[step(child = UpgradeModConvStep)]
+    #[step(child = UpgradeTripleStep)]
     Upgrade2d(usize),
 }
 
@@ -251,6 +255,7 @@ impl<F: Field, T: LinearSecretSharing<F>> IPAModulusConvertedInputRowWrapper<F,
 }
 
 #[derive(Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
 pub(crate) enum UpgradeModConvStep {
     UpgradeModConv1,
     UpgradeModConv2,
@@ -13,9 +13,9 @@ use crate::{
     ff::Field,
     helpers::Direction,
     protocol::{
-        basics::{check_zero, Reveal},
+        basics::{check_zero, Reveal, SecureMul},
         context::{
-            Base, Context, MaliciousContext, SemiHonestContext, UpgradableContext,
+            Base, Context, MaliciousContext, ScopeCheck, SemiHonestContext, UpgradableContext,
             UpgradedMaliciousContext, UpgradedSemiHonestContext,
         },
         prss::SharedRandomness,
@@ -72,8 +72,10 @@ impl<F: ExtendableField> Debug for SemiHonest<'_, F> {
 #[derive(Step)]
 pub(crate) enum Step {
     /// For the execution of the malicious protocol.
+    #[step(transparent)]
     MaliciousProtocol,
     /// The final validation steps.
+    #[step(child = ValidateStep)]
     Validate,
 }
 
@@ -83,7 +85,11 @@ pub(crate) enum ValidateStep {
     PropagateUAndW,
     /// Reveal the value of `r`, necessary for validation.
     RevealR,
+    /// Multiply `w` by `r` without revealing `r`, used by scoped checks.
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
+    MultiplyByR,
     /// Check that there is no disagreement between accumulated values.
+    #[step(child = crate::protocol::basics::check_zero::Step)]
     CheckZero,
 }
 
@@ -219,7 +225,7 @@ impl<'a, F: ExtendableField> Validator<MaliciousContext<'a>, F> for Malicious<'a
     #[tracing::instrument(name = "validate", skip_all, fields(gate = %self.validate_ctx.gate().as_ref()))]
     async fn validate<D: DowngradeMalicious>(self, values: D) -> Result<D::Target, Error> {
         // send our `u_i+1` value to the helper on the right
-        let (u_share, w_share) = self.propagate_u_and_w().await?;
+        let (u_share, w_share) = propagate_u_and_w(&self.validate_ctx, &self.u_and_w).await?;
 
         // This should probably be done in parallel with the futures above
         let narrow_ctx = self
@@ -273,34 +279,109 @@ impl<'a, F: ExtendableField> Malicious<'a, F> {
     pub fn r_share(&self) -> &Replicated<F::ExtendedField> {
         &self.r_share
     }
+}
 
-    /// Turns out local values for `u` and `w` into proper replicated shares.
-    async fn propagate_u_and_w(
-        &self,
-    ) -> Result<(Replicated<F::ExtendedField>, Replicated<F::ExtendedField>), Error> {
-        let propagate_ctx = self
-            .validate_ctx
-            .narrow(&ValidateStep::PropagateUAndW)
-            .set_total_records(2);
-        let helper_right = propagate_ctx.send_channel(propagate_ctx.role().peer(Direction::Right));
-        let helper_left = propagate_ctx.recv_channel(propagate_ctx.role().peer(Direction::Left));
-        let (u_local, w_local) = {
-            let state = self.u_and_w.lock().unwrap();
-            (state.u, state.w)
+/// Turns out local values for `u` and `w` into proper replicated shares.
+async fn propagate_u_and_w<F: Field>(
+    validate_ctx: &Base<'_>,
+    u_and_w: &Mutex<AccumulatorState<F>>,
+) -> Result<(Replicated<F>, Replicated<F>), Error> {
+    let propagate_ctx = validate_ctx
+        .narrow(&ValidateStep::PropagateUAndW)
+        .set_total_records(2);
+    let helper_right = propagate_ctx.send_channel(propagate_ctx.role().peer(Direction::Right));
+    let helper_left = propagate_ctx.recv_channel(propagate_ctx.role().peer(Direction::Left));
+    let (u_local, w_local) = {
+        let state = u_and_w.lock().unwrap();
+        (state.u, state.w)
+    };
+    try_join(
+        helper_right.send(RecordId::FIRST, u_local),
+        helper_right.send(RecordId::FIRST + 1, w_local),
+    )
+    .await?;
+    let (u_left, w_left): (F, F) = try_join(
+        helper_left.receive(RecordId::FIRST),
+        helper_left.receive(RecordId::FIRST + 1),
+    )
+    .await?;
+    let u_share = Replicated::new(u_left, u_local);
+    let w_share = Replicated::new(w_left, w_local);
+    Ok((u_share, w_share))
+}
+
+/// Checks the MACs accumulated by one part of a malicious protocol as soon as that part completes.
+///
+/// [`Malicious`] validator reveals `r`, so it can only check its MACs once, at the very end of the
+/// protocol. A scoped check shares `r` with the context it was created from, but keeps its own `u`
+/// and `w`. Instead of revealing `r`, it computes `[T] = [u] - [r]*[w]` with one multiplication
+/// and runs `CheckZero` on it. `r` stays secret and the enclosing validator is not affected.
+pub struct ScopedCheck<'a, F: ExtendableField> {
+    r_share: Replicated<F::ExtendedField>,
+    u_and_w: Arc<Mutex<AccumulatorState<F::ExtendedField>>>,
+    validate_ctx: Base<'a>,
+}
+
+impl<'a, F: ExtendableField> ScopedCheck<'a, F> {
+    /// Creates a new check and the accumulator that feeds it. `ctx` must be narrowed to a step
+    /// that is unique to this check.
+    pub(super) fn new(
+        ctx: &Base<'a>,
+        r_share: Replicated<F::ExtendedField>,
+    ) -> (Self, MaliciousAccumulator<F>) {
+        let validate_ctx = ctx.narrow(&Step::Validate);
+        let state = {
+            let prss = validate_ctx.prss();
+            AccumulatorState::new(prss.zero(RecordId::FIRST), prss.zero(RecordId::FIRST + 1))
         };
-        try_join(
-            helper_right.send(RecordId::FIRST, u_local),
-            helper_right.send(RecordId::FIRST + 1, w_local),
-        )
-        .await?;
-        let (u_left, w_left): (F::ExtendedField, F::ExtendedField) = try_join(
-            helper_left.receive(RecordId::FIRST),
-            helper_left.receive(RecordId::FIRST + 1),
+        let u_and_w = Arc::new(Mutex::new(state));
+        let accumulator = MaliciousAccumulator::<F> {
+            inner: Arc::downgrade(&u_and_w),
+        };
+        (
+            Self {
+                r_share,
+                u_and_w,
+                validate_ctx,
+            },
+            accumulator,
         )
-        .await?;
-        let u_share = Replicated::new(u_left, u_local);
-        let w_share = Replicated::new(w_left, w_local);
-        Ok((u_share, w_share))
+    }
+}
+
+#[async_trait]
+impl<F: ExtendableField> ScopeCheck for ScopedCheck<'_, F> {
+    /// ## Errors
+    /// If the two information theoretic MACs are not equal (after multiplying by `r`), which
+    /// means that one of the helpers launched an additive attack inside the scope.
+    ///
+    /// ## Panics
+    /// Will panic if the mutex is poisoned
+    #[tracing::instrument(name = "scoped_validate", skip_all, fields(gate = %self.validate_ctx.gate().as_ref()))]
+    async fn validate(self) -> Result<(), Error> {
+        let (u_share, w_share) = propagate_u_and_w(&self.validate_ctx, &self.u_and_w).await?;
+
+        let rw = self
+            .r_share
+            .multiply(
+                &w_share,
+                self.validate_ctx
+                    .narrow(&ValidateStep::MultiplyByR)
+                    .set_total_records(1),
+                RecordId::FIRST,
+            )
+            .await?;
+        let t = u_share - &rw;
+
+        let check_zero_ctx = self
+            .validate_ctx
+            .narrow(&ValidateStep::CheckZero)
+            .set_total_records(1);
+        if check_zero(check_zero_ctx, RecordId::FIRST, &t).await? {
+            Ok(())
+        } else {
+            Err(Error::MaliciousSecurityCheckFailed)
+        }
     }
 }
 
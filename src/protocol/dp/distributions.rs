@@ -5,6 +5,8 @@ use rand::{
     Rng,
 };
 
+use super::Error;
+
 /// Returns `true` iff `a` and `b` are close to each other. `a` and `b` are considered close if
 /// |a-b| < 10^(-precision).
 #[cfg(all(test, unit_test))]
@@ -70,12 +72,106 @@ impl From<BoxMuller> for RoundedBoxMuller {
     }
 }
 
+/// Discrete Laplace distribution, which samples `x` with probability proportional to
+/// `exp(-epsilon * |x|)`.
+///
+/// Rounding a continuous sample leaks the value the noise is added to through floating-point
+/// artifacts. This uses the sampler of [`CKS20`] instead, which only needs uniform integers and
+/// integer arithmetic, so its output follows the distribution exactly. `epsilon` is rounded down
+/// to a multiple of `2^-20`, which only adds noise.
+///
+/// [`CKS20`]: https://arxiv.org/abs/2004.00010
+#[derive(Debug)]
+pub struct DiscreteLaplace {
+    /// `epsilon` is `numerator / DiscreteLaplace::DENOMINATOR`.
+    numerator: u64,
+}
+
+impl DiscreteLaplace {
+    const DENOMINATOR: u64 = 1 << 20;
+
+    /// ## Errors
+    /// If epsilon is smaller than `2^-20`, or too large to be represented.
+    pub fn new(epsilon: f64) -> Result<Self, Error> {
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let numerator = (epsilon * Self::DENOMINATOR as f64).floor() as u64;
+        if !(1..u64::from(u32::MAX)).contains(&numerator) {
+            return Err(Error::BadEpsilon(epsilon));
+        }
+
+        Ok(Self { numerator })
+    }
+
+    /// The epsilon that samples are drawn with, after rounding.
+    #[must_use]
+    pub fn epsilon(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let epsilon = self.numerator as f64 / Self::DENOMINATOR as f64;
+        epsilon
+    }
+}
+
+impl Distribution<i64> for DiscreteLaplace {
+    fn sample<R>(&self, rng: &mut R) -> i64
+    where
+        R: ?Sized + Rng,
+    {
+        // Algorithm 2 of CKS20, with scale `DENOMINATOR / numerator`.
+        loop {
+            let u = rng.gen_range(0..Self::DENOMINATOR);
+            if !bernoulli_exp(rng, u, Self::DENOMINATOR) {
+                continue;
+            }
+            let mut v = 0;
+            while bernoulli_exp(rng, 1, 1) {
+                v += 1;
+            }
+            let y = i64::try_from((u + Self::DENOMINATOR * v) / self.numerator).unwrap();
+            let negative = rng.gen::<bool>();
+            if negative && y == 0 {
+                continue;
+            }
+
+            return if negative { -y } else { y };
+        }
+    }
+}
+
+/// Returns `true` with probability `exp(-numerator / denominator)`, see Algorithm 1 of
+/// [`CKS20`](DiscreteLaplace).
+fn bernoulli_exp<R: ?Sized + Rng>(rng: &mut R, numerator: u64, denominator: u64) -> bool {
+    // exp(-x) = exp(-1)^floor(x) * exp(-(x - floor(x)))
+    for _ in 0..numerator / denominator {
+        if !bernoulli_exp_at_most_one(rng, 1, 1) {
+            return false;
+        }
+    }
+    bernoulli_exp_at_most_one(rng, numerator % denominator, denominator)
+}
+
+/// Returns `true` with probability `exp(-numerator / denominator)`, if the fraction is at most 1.
+fn bernoulli_exp_at_most_one<R: ?Sized + Rng>(
+    rng: &mut R,
+    numerator: u64,
+    denominator: u64,
+) -> bool {
+    let mut k = 1_u128;
+    while rng.gen_range(0..u128::from(denominator) * k) < u128::from(numerator) {
+        k += 1;
+    }
+    k % 2 == 1
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
     use std::iter::repeat_with;
 
-    use rand::{distributions::Distribution, thread_rng};
-    use rand_core::RngCore;
+    use rand::{distributions::Distribution, rngs::StdRng, thread_rng};
+    use rand_core::{RngCore, SeedableRng};
 
     use super::*;
 
@@ -125,4 +221,54 @@ mod tests {
         };
         check(&nd, &mut rng, 1_u8);
     }
+
+    #[test]
+    fn bernoulli_exp_probability() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let n = 100_000;
+        for (numerator, denominator) in [(0, 1), (1, 2), (1, 1), (5, 2)] {
+            let hits = repeat_with(|| bernoulli_exp(&mut rng, numerator, denominator))
+                .take(n)
+                .filter(|&b| b)
+                .count();
+            #[allow(clippy::cast_precision_loss)]
+            let expected = f64::exp(-(numerator as f64) / denominator as f64);
+            #[allow(clippy::cast_precision_loss)]
+            let actual = hits as f64 / n as f64;
+            assert!(is_close(actual, expected, 2), "{actual} != {expected}");
+        }
+    }
+
+    #[test]
+    fn discrete_laplace_sample() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let dl = DiscreteLaplace::new(f64::ln(2.0)).unwrap();
+        let n = 100_000;
+        let samples = repeat_with(|| dl.sample(&mut rng))
+            .take(n)
+            .collect::<Vec<_>>();
+
+        // With epsilon = ln(2), P(0) = 1/3 and P(x) = 1/3 * 2^-|x|, so the variance is 4.
+        #[allow(clippy::cast_precision_loss)]
+        let frequency = |x: i64| samples.iter().filter(|&&s| s == x).count() as f64 / n as f64;
+        assert!(is_close(frequency(0), 1.0 / 3.0, 2));
+        assert!(is_close(frequency(-2), 1.0 / 12.0, 2));
+        assert!(is_close(frequency(2), 1.0 / 12.0, 2));
+        #[allow(clippy::cast_precision_loss)]
+        let variance = samples.iter().map(|&s| (s * s) as f64).sum::<f64>() / n as f64;
+        assert!(is_close(variance, 4.0, 1));
+    }
+
+    #[test]
+    fn discrete_laplace_bad_epsilon() {
+        assert!(DiscreteLaplace::new(0.0).is_err());
+        assert!(DiscreteLaplace::new(1e-9).is_err());
+        assert!(DiscreteLaplace::new(-1.0).is_err());
+        assert!(DiscreteLaplace::new(f64::NAN).is_err());
+        assert!(is_close(
+            DiscreteLaplace::new(0.5).unwrap().epsilon(),
+            0.5,
+            6
+        ));
+    }
 }
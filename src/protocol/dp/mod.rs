@@ -1,5 +1,7 @@
 mod distributions;
 mod insecure;
 
+pub(crate) use distributions::DiscreteLaplace;
 #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
 pub use insecure::DiscreteDp as InsecureDiscreteDp;
+pub use insecure::Error;
@@ -14,7 +14,7 @@ use crate::{
     ff::{Field, GaloisField, Gf2, PrimeField, Serializable},
     helpers::{query::IpaQueryConfig, Role},
     protocol::{
-        attribution::secure_attribution,
+        attribution::{secure_attribution, AttributionRandomBits},
         basics::Reshare,
         context::{
             Context, UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext,
@@ -26,6 +26,7 @@ use crate::{
             generate_permutation::{
                 generate_permutation_and_reveal_shuffled, ShuffledPermutationWrapper,
             },
+            quicksort::quicksort_and_reveal_shuffled,
         },
         BasicProtocols, RecordId,
     },
@@ -35,26 +36,49 @@ use crate::{
             semi_honest::AdditiveShare as Replicated,
             ReplicatedSecretSharing,
         },
-        BitDecomposed, Linear as LinearSecretSharing, LinearRefOps,
+        BitDecomposed, Linear as LinearSecretSharing, LinearRefOps, SecretSharing,
     },
 };
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::sort::SortStep)]
+    #[step(child = crate::protocol::sort::quicksort::Step)]
     GenSortPermutationFromMatchKeys,
+    #[step(child = crate::protocol::sort::ApplyInvStep)]
     ApplySortPermutation,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::context::malicious::UpgradeStep)]
+    #[step(child = crate::protocol::context::semi_honest::UpgradeStep)]
+    #[step(child = Step)]
+    #[step(child = crate::protocol::attribution::AttributionStep)]
+    #[step(child = crate::protocol::attribution::Step)]
+    #[step(child = crate::protocol::attribution::aggregate_credit::Step)]
     AfterConvertAllBits,
+    #[step(child = crate::protocol::context::malicious::UpgradeStep)]
+    #[step(child = crate::protocol::context::semi_honest::UpgradeStep)]
     UpgradeMatchKeyBits,
+    #[step(child = crate::protocol::context::malicious::UpgradeStep)]
+    #[step(child = crate::protocol::context::semi_honest::UpgradeStep)]
     UpgradeBreakdownKeyBits,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = Step)]
+    #[step(child = crate::protocol::attribution::Step)]
     BinaryValidator,
 }
 
 #[derive(Step)]
 pub(crate) enum IPAInputRowResharableStep {
+    #[step(child = crate::protocol::sort::ReshareStep)]
     Timestamp,
+    #[step(child = crate::protocol::basics::reshare::InnerVectorElementStep)]
     MatchKeyShares,
+    #[step(child = crate::protocol::sort::ReshareStep)]
     TriggerBit,
+    #[step(child = crate::protocol::basics::reshare::InnerVectorElementStep)]
     BreakdownKey,
+    #[step(child = crate::protocol::sort::ReshareStep)]
     TriggerValue,
 }
 
@@ -293,6 +317,41 @@ where
     }
 }
 
+/// Work that IPA can do before its inputs are known: creating the validator for the arithmetic
+/// part of the protocol, and generating the random bits that attribution needs.
+pub struct IpaPreprocessing<V, F: Field, S: SecretSharing<F>> {
+    validator: V,
+    random_bits: AttributionRandomBits<F, S>,
+}
+
+/// Runs [`IpaPreprocessing`] for up to `query_size` input rows.
+///
+/// This only needs the size of the query, so helpers can run it while the inputs are still being
+/// uploaded. If there are fewer rows than `query_size`, the extra random bits are not used.
+///
+/// # Errors
+/// If generating the random bits fails.
+pub async fn ipa_preprocessing<C, S, F>(
+    sh_ctx: C,
+    query_size: usize,
+    config: &IpaQueryConfig,
+) -> Result<IpaPreprocessing<C::Validator<F>, F, S>, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C::UpgradedContext<F>, F>,
+    for<'r> &'r S: LinearRefOps<'r, S, F>,
+    F: PrimeField + ExtendableField,
+{
+    let validator = sh_ctx.narrow(&Step::AfterConvertAllBits).validator();
+    let random_bits =
+        AttributionRandomBits::generate(validator.context(), query_size, config).await?;
+    Ok(IpaPreprocessing {
+        validator,
+        random_bits,
+    })
+}
+
 /// IPA Protocol
 ///
 /// We return `Replicated<F>` as output since there is compute after this and in `aggregate_credit`, last communication operation was sort.
@@ -300,12 +359,58 @@ where
 /// Propagates errors from multiplications
 /// # Panics
 /// Propagates errors from multiplications
-#[allow(clippy::too_many_lines)]
 pub async fn ipa<'a, C, S, SB, F, MK, BK>(
     sh_ctx: C,
     input_rows: &[IPAInputRow<F, MK, BK>],
     config: IpaQueryConfig,
 ) -> Result<Vec<Replicated<F>>, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F>
+        + BasicProtocols<C::UpgradedContext<F>, F>
+        + Reshare<C::UpgradedContext<F>, RecordId>
+        + Serializable
+        + DowngradeMalicious<Target = Replicated<F>>
+        + 'static,
+    for<'r> &'r S: LinearRefOps<'r, S, F>,
+    C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = SB>,
+    SB: LinearSecretSharing<Gf2>
+        + BasicProtocols<C::UpgradedContext<Gf2>, Gf2>
+        + DowngradeMalicious<Target = Replicated<Gf2>>
+        + 'static,
+    for<'r> &'r SB: LinearRefOps<'r, SB, Gf2>,
+    F: PrimeField + ExtendableField,
+    MK: GaloisField,
+    BK: GaloisField,
+    ShuffledPermutationWrapper<S, C::UpgradedContext<F>>: DowngradeMalicious<Target = Vec<u32>>,
+    for<'u> UpgradeContext<'u, C::UpgradedContext<F>, F, RecordId>: UpgradeToMalicious<'u, BitConversionTriple<Replicated<F>>, BitConversionTriple<S>>
+        + UpgradeToMalicious<
+            'u,
+            ArithmeticallySharedIPAInputs<F, Replicated<F>>,
+            ArithmeticallySharedIPAInputs<F, S>,
+        >,
+{
+    let preprocessing = IpaPreprocessing {
+        validator: sh_ctx.narrow(&Step::AfterConvertAllBits).validator(),
+        random_bits: AttributionRandomBits::default(),
+    };
+    ipa_with_preprocessing(sh_ctx, preprocessing, input_rows, config).await
+}
+
+/// IPA Protocol, using the results of [`ipa_preprocessing`] with the same `sh_ctx`.
+///
+/// # Errors
+/// Propagates errors from multiplications
+/// # Panics
+/// Propagates errors from multiplications
+#[allow(clippy::too_many_lines)]
+pub async fn ipa_with_preprocessing<'a, C, S, SB, F, MK, BK>(
+    sh_ctx: C,
+    preprocessing: IpaPreprocessing<C::Validator<F>, F, S>,
+    input_rows: &[IPAInputRow<F, MK, BK>],
+    config: IpaQueryConfig,
+) -> Result<Vec<Replicated<F>>, Error>
 where
     C: UpgradableContext,
     C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
@@ -337,21 +442,28 @@ where
     // However, we immediately copy the complete input into separate vectors for different pieces
     // (MK, BK, credit), so streaming could still be beneficial.
 
-    let mk_shares: Vec<_> = input_rows.iter().map(|x| x.mk_shares.clone()).collect();
+    let gf2_match_key_bits = get_gf2_match_key_bits(input_rows);
 
-    let sort_permutation = generate_permutation_and_reveal_shuffled(
-        sh_ctx.narrow(&Step::GenSortPermutationFromMatchKeys),
-        stream_iter(mk_shares),
-        config.num_multi_bits,
-        MK::BITS,
-    )
-    .await
-    .unwrap();
+    let sort_ctx = sh_ctx.narrow(&Step::GenSortPermutationFromMatchKeys);
+    let sort_permutation = if config.gf2_sort {
+        quicksort_and_reveal_shuffled(sort_ctx, gf2_match_key_bits.clone()).await?
+    } else {
+        let mk_shares: Vec<_> = input_rows.iter().map(|x| x.mk_shares.clone()).collect();
+        generate_permutation_and_reveal_shuffled(
+            sort_ctx,
+            stream_iter(mk_shares),
+            config.num_multi_bits,
+            MK::BITS,
+        )
+        .await?
+    };
 
-    let validator = sh_ctx.narrow(&Step::AfterConvertAllBits).validator();
+    let IpaPreprocessing {
+        validator,
+        random_bits,
+    } = preprocessing;
     let m_ctx = validator.context();
 
-    let gf2_match_key_bits = get_gf2_match_key_bits(input_rows);
     let gf2_breakdown_key_bits = get_gf2_breakdown_key_bits(input_rows);
 
     let binary_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<Gf2>();
@@ -401,6 +513,7 @@ where
     secure_attribution(
         validator,
         binary_validator,
+        random_bits,
         arithmetically_shared_values,
         binary_shared_values,
         config,
@@ -465,8 +578,8 @@ pub mod tests {
         test_fixture::{
             input::GenericReportTestInput,
             ipa::{ipa_in_the_clear, test_ipa, IpaSecurityModel},
-            logging, EventGenerator, EventGeneratorConfig, Reconstruct, Runner, TestWorld,
-            TestWorldConfig,
+            logging, Reconstruct, Runner, TestWorld, TestWorldConfig, WorkloadConfig,
+            WorkloadGenerator,
         },
     };
 
@@ -544,6 +657,43 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn malicious_gf2_sort() {
+        const PER_USER_CAP: u32 = 3;
+        const EXPECTED: &[u128] = &[0, 2, 3];
+        const MAX_BREAKDOWN_KEY: u32 = 3;
+        const NUM_MULTI_BITS: u32 = 3;
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+                [
+                    { timestamp: 1, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                    { timestamp: 2, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                    { timestamp: 3, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                    { timestamp: 4, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                    { timestamp: 5, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+                ];
+                (Fp31, MatchKey, BreakdownKey)
+            );
+
+            let config = IpaQueryConfig {
+                gf2_sort: true,
+                ..IpaQueryConfig::no_window(PER_USER_CAP, MAX_BREAKDOWN_KEY, NUM_MULTI_BITS)
+            };
+            let result: Vec<_> = world
+                .malicious(records.into_iter(), |ctx, input_rows| async move {
+                    ipa::<_, _, _, _, MatchKey, BreakdownKey>(ctx, &input_rows, config)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+            assert_eq!(result, EXPECTED);
+        });
+    }
+
     #[test]
     fn semi_honest_with_attribution_window() {
         const PER_USER_CAP: u32 = 3;
@@ -797,17 +947,12 @@ pub mod tests {
         } else {
             NUM_USERS * MAX_RECORDS_PER_USER
         };
-        let raw_data = EventGenerator::with_config(
+        let raw_data = WorkloadGenerator::new(
             rand::thread_rng(),
-            EventGeneratorConfig::new(
-                u64::from(NUM_USERS),
-                MAX_TRIGGER_VALUE,
-                MAX_BREAKDOWN_KEY,
-                MAX_RECORDS_PER_USER,
-            ),
+            WorkloadConfig::uniform(MAX_TRIGGER_VALUE, MAX_BREAKDOWN_KEY, MAX_RECORDS_PER_USER)
+                .with_max_match_key(u64::from(NUM_USERS)),
         )
-        .take(usize::try_from(max_events).unwrap())
-        .collect::<Vec<_>>();
+        .generate(usize::try_from(max_events).unwrap());
 
         for per_user_cap in [1, 3] {
             let expected_results = ipa_in_the_clear(
@@ -832,6 +977,8 @@ pub mod tests {
                     attribution_window_seconds: ATTRIBUTION_WINDOW_SECONDS,
                     num_multi_bits: NUM_MULTI_BITS,
                     plaintext_match_keys: true,
+                    offline_preprocessing: false,
+                    gf2_sort: false,
                 },
                 security,
             )
@@ -1132,9 +1279,14 @@ pub mod tests {
                 cap_one(),
                 Malicious,
                 PerfMetrics {
-                    records_sent: 35_163,
-                    bytes_sent: 130_068,
-                    indexed_prss: 72_447,
+                    records_sent: 35_598,
+                    // MACs are twice as wide with the quadratic extension of Fp32BitPrime
+                    bytes_sent: if cfg!(feature = "extended-mac-field") {
+                        195_372
+                    } else {
+                        131_808
+                    },
+                    indexed_prss: 72_882,
                     seq_prss: 1132,
                 },
             )
@@ -1147,13 +1299,108 @@ pub mod tests {
                 cap_three(),
                 Malicious,
                 PerfMetrics {
-                    records_sent: 53_865,
-                    bytes_sent: 204_876,
-                    indexed_prss: 109_734,
+                    records_sent: 54_300,
+                    // MACs are twice as wide with the quadratic extension of Fp32BitPrime
+                    bytes_sent: if cfg!(feature = "extended-mac-field") {
+                        315_216
+                    } else {
+                        206_616
+                    },
+                    indexed_prss: 110_169,
                     seq_prss: 1132,
                 },
             )
             .await;
         }
     }
+
+    /// Checks that [`cost::ipa`] predicts what IPA sends, and how many rounds it takes.
+    ///
+    /// [`cost::ipa`]: crate::test_fixture::cost::ipa
+    #[cfg(all(test, unit_test))]
+    mod cost_model {
+        use super::*;
+        use crate::test_fixture::{cost, cost::Cost, simulate::Simulator};
+
+        const QUERY_SIZE: usize = 24;
+        const MAX_BREAKDOWN_KEY: u32 = 8;
+
+        async fn measure_and_compare(config: IpaQueryConfig, security: IpaSecurityModel) {
+            let records = WorkloadGenerator::new(
+                thread_rng(),
+                WorkloadConfig::uniform(5, MAX_BREAKDOWN_KEY, 10).with_max_match_key(10_000),
+            )
+            .generate(QUERY_SIZE);
+            let expected = ipa_in_the_clear(
+                &records,
+                config.per_user_credit_cap,
+                config.attribution_window_seconds,
+                config.max_breakdown_key,
+            );
+
+            let simulator = Simulator::new(TestWorldConfig {
+                gateway_config: GatewayConfig::new(QUERY_SIZE),
+                ..Default::default()
+            });
+            test_ipa::<Fp32BitPrime>(simulator.world(), &records, &expected, config, security)
+                .await;
+
+            let model =
+                cost::ipa::<Fp32BitPrime, MatchKey, BreakdownKey>(QUERY_SIZE, &config, security);
+            let measured = Cost::measured(&simulator);
+            assert!(
+                model.is_close_to(&measured, 0.1),
+                "{security:?} IPA with {config:?}: expected {model:?}, measured {measured:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn semi_honest() {
+            let config = IpaQueryConfig {
+                max_breakdown_key: MAX_BREAKDOWN_KEY,
+                ..Default::default()
+            };
+            measure_and_compare(config, IpaSecurityModel::SemiHonest).await;
+        }
+
+        #[tokio::test]
+        async fn semi_honest_cap_one_with_attribution_window() {
+            let config = IpaQueryConfig {
+                per_user_credit_cap: 1,
+                max_breakdown_key: MAX_BREAKDOWN_KEY,
+                attribution_window_seconds: NonZeroU32::new(600),
+                ..Default::default()
+            };
+            measure_and_compare(config, IpaSecurityModel::SemiHonest).await;
+        }
+
+        #[tokio::test]
+        async fn malicious_with_attribution_window() {
+            let config = IpaQueryConfig {
+                max_breakdown_key: MAX_BREAKDOWN_KEY,
+                attribution_window_seconds: NonZeroU32::new(600),
+                ..Default::default()
+            };
+            measure_and_compare(config, IpaSecurityModel::Malicious).await;
+        }
+
+        /// Capacity planning needs costs for inputs far too large to run.
+        #[test]
+        fn billion_rows() {
+            let config = IpaQueryConfig::default();
+            let small = cost::ipa::<Fp32BitPrime, MatchKey, BreakdownKey>(
+                1_000,
+                &config,
+                IpaSecurityModel::Malicious,
+            );
+            let large = cost::ipa::<Fp32BitPrime, MatchKey, BreakdownKey>(
+                1_000_000_000,
+                &config,
+                IpaSecurityModel::Malicious,
+            );
+            // communication grows linearly, but rounds only with the depth of the prefix trees
+            assert!(large.records_sent > small.records_sent * 999_000);
+            assert!(large.rounds < small.rounds * 2);
+        }
+    }
 }
@@ -52,9 +52,14 @@ use crate::{
 #[derive(Step)]
 pub(crate) enum ConvertSharesStep {
     #[dynamic]
+    #[step(child = ConvertSharesStep)]
     ConvertBit(u32),
+    #[step(child = crate::protocol::context::malicious::UpgradeStep)]
+    #[step(child = crate::protocol::context::semi_honest::UpgradeStep)]
     Upgrade,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     Xor1,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     Xor2,
 }
 
@@ -7,6 +7,7 @@ use ipa_macros::Step;
 use crate::{
     error::Error,
     ff::{Field, GaloisField, Gf2, PrimeField, Serializable},
+    helpers::TotalRecords,
     protocol::{
         basics::{SecureMul, ShareKnownValue},
         boolean::or::or,
@@ -24,6 +25,8 @@ use crate::{
     },
     seq_join::seq_join,
 };
+#[cfg(any(test, feature = "test-fixture"))]
+use crate::{rand::Rng, secret_sharing::IntoShares};
 
 pub struct PrfShardedIpaInputRow<FV: GaloisField> {
     prf_of_match_key: u64,
@@ -68,14 +71,14 @@ impl InputsRequiredFromPrevRow {
 
         let (ever_encountered_a_trigger_event, did_source_get_attributed) = try_join(
             or(
-                ctx.narrow(&Step::EverEncounteredTriggerEvent),
+                ctx.narrow(&AttributionStep::EverEncounteredTriggerEvent),
                 record_id,
                 &input_row.is_trigger_bit,
                 &self.ever_encountered_a_trigger_event,
             ),
             is_source_event.multiply(
                 &self.ever_encountered_a_trigger_event,
-                ctx.narrow(&Step::DidSourceReceiveAttribution),
+                ctx.narrow(&AttributionStep::DidSourceReceiveAttribution),
                 record_id,
             ),
         )
@@ -83,14 +86,14 @@ impl InputsRequiredFromPrevRow {
 
         let (updated_is_saturated, capped_label) = try_join(
             or(
-                ctx.narrow(&Step::ComputeSaturatingSum),
+                ctx.narrow(&AttributionStep::ComputeSaturatingSum),
                 record_id,
                 &self.is_saturated,
                 &did_source_get_attributed,
             ),
             did_source_get_attributed.multiply(
                 &(share_of_one - &self.is_saturated),
-                ctx.narrow(&Step::IsAttributedSourceAndPrevRowNotSaturated),
+                ctx.narrow(&AttributionStep::IsAttributedSourceAndPrevRowNotSaturated),
                 record_id,
             ),
         )
@@ -101,7 +104,7 @@ impl InputsRequiredFromPrevRow {
         });
 
         let capped_attributed_feature_vector = compute_capped_feature_vector(
-            ctx.narrow(&Step::ComputedCappedFeatureVector),
+            ctx.narrow(&AttributionStep::ComputedCappedFeatureVector),
             record_id,
             &capped_label,
             &unbitpacked_feature_vector,
@@ -116,12 +119,13 @@ impl InputsRequiredFromPrevRow {
 }
 
 #[derive(Step)]
-pub enum UserNthRowStep {
+pub enum UserRowStep {
     #[dynamic]
+    #[step(child = AttributionStep)]
     Row(usize),
 }
 
-impl From<usize> for UserNthRowStep {
+impl From<usize> for UserRowStep {
     fn from(v: usize) -> Self {
         Self::Row(v)
     }
@@ -129,13 +133,32 @@ impl From<usize> for UserNthRowStep {
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = UserRowStep)]
     BinaryValidator,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = ConversionStep)]
     PrimeFieldValidator,
+}
+
+/// Steps of the attribution circuit, for each row of a user after the first one.
+#[derive(Step)]
+pub(crate) enum AttributionStep {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     EverEncounteredTriggerEvent,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     DidSourceReceiveAttribution,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     ComputeSaturatingSum,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IsAttributedSourceAndPrevRowNotSaturated,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputedCappedFeatureVector,
+}
+
+#[derive(Step)]
+pub(crate) enum ConversionStep {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ModulusConvertFeatureVectorBits,
 }
 
@@ -144,13 +167,15 @@ where
     C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
 {
     let mut context_per_row_depth = Vec::with_capacity(histogram.len());
-    for (row_number, num_users_having_that_row_number) in histogram.iter().enumerate() {
+    for row_number in 0..histogram.len() {
         if row_number == 0 {
             // no multiplications needed for each user's row 0. No context needed
         } else {
+            // Only the users in flight that have this many rows send records at this depth. That
+            // may never be enough to fill a send buffer, so send every record right away.
             let ctx_for_row_number = root_ctx
-                .narrow(&UserNthRowStep::from(row_number))
-                .set_total_records(*num_users_having_that_row_number);
+                .narrow(&UserRowStep::from(row_number))
+                .set_total_records(TotalRecords::Indeterminate);
             context_per_row_depth.push(ctx_for_row_number);
         }
     }
@@ -201,9 +226,9 @@ where
 ///
 /// This is useful for performing logistic regression: `https://github.com/patcg-individual-drafts/ipa/blob/main/logistic_regression.md`
 ///
-/// Due to limitation in our infra, it's necessary to set the total number of records each channel will ever need to process.
-/// The number of records each channel processes is a function of the distribution of number of records per user.
-/// Rather than calculate this histogram within this function (challenging to do while streaming), at present the caller must pass this in.
+/// The number of row depths, and the number of records that are converted to `Z_p`, are a function of the distribution
+/// of number of records per user. Rather than calculate this histogram within this function (challenging to do while
+/// streaming), at present the caller must pass this in.
 ///
 /// The count at a given index indicates the number of users having at least that many rows of data.
 ///
@@ -279,7 +304,7 @@ where
     // modulus convert feature vector bits from shares in `Z_2` to shares in `Z_p`
     let converted_feature_vector_bits = convert_bits(
         prime_field_ctx
-            .narrow(&Step::ModulusConvertFeatureVectorBits)
+            .narrow(&ConversionStep::ModulusConvertFeatureVectorBits)
             .set_total_records(num_outputs),
         flattenned_stream,
         0..FV::BITS,
@@ -367,27 +392,62 @@ where
     ))
 }
 
+/// The cleartext of a [`PrfShardedIpaInputRow`], for tests and benchmarks to secret-share.
+#[cfg(any(test, feature = "test-fixture"))]
+#[derive(Debug, Clone)]
+pub struct PreShardedAndSortedOPRFTestInput<FV: GaloisField> {
+    pub prf_of_match_key: u64,
+    pub is_trigger_bit: Gf2,
+    pub feature_vector: FV,
+}
+
+#[cfg(any(test, feature = "test-fixture"))]
+impl<FV> IntoShares<PrfShardedIpaInputRow<FV>> for PreShardedAndSortedOPRFTestInput<FV>
+where
+    FV: GaloisField + IntoShares<Replicated<FV>>,
+{
+    fn share_with<R: Rng>(self, rng: &mut R) -> [PrfShardedIpaInputRow<FV>; 3] {
+        let PreShardedAndSortedOPRFTestInput {
+            prf_of_match_key,
+            is_trigger_bit,
+            feature_vector,
+        } = self;
+
+        let [is_trigger_bit0, is_trigger_bit1, is_trigger_bit2] = is_trigger_bit.share_with(rng);
+        let [feature_vector0, feature_vector1, feature_vector2] = feature_vector.share_with(rng);
+
+        [
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit0,
+                feature_vector: feature_vector0,
+            },
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit1,
+                feature_vector: feature_vector1,
+            },
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit2,
+                feature_vector: feature_vector2,
+            },
+        ]
+    }
+}
+
 #[cfg(all(test, unit_test))]
 pub mod tests {
     use crate::{
-        ff::{Field, Fp32BitPrime, GaloisField, Gf2, Gf32Bit},
+        ff::{Field, Fp32BitPrime, Gf2, Gf32Bit},
         protocol::prf_sharding::feature_label_dot_product::{
-            compute_feature_label_dot_product, PrfShardedIpaInputRow,
-        },
-        rand::Rng,
-        secret_sharing::{
-            replicated::semi_honest::AdditiveShare as Replicated, IntoShares, SharedValue,
+            compute_feature_label_dot_product, PreShardedAndSortedOPRFTestInput,
         },
+        secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, SharedValue},
         test_executor::run,
         test_fixture::{Reconstruct, Runner, TestWorld},
     };
 
-    struct PreShardedAndSortedOPRFTestInput<FV: GaloisField> {
-        prf_of_match_key: u64,
-        is_trigger_bit: Gf2,
-        feature_vector: FV,
-    }
-
     fn test_input(
         prf_of_match_key: u64,
         is_trigger: bool,
@@ -402,42 +462,6 @@ pub mod tests {
         }
     }
 
-    impl<FV> IntoShares<PrfShardedIpaInputRow<FV>> for PreShardedAndSortedOPRFTestInput<FV>
-    where
-        FV: GaloisField + IntoShares<Replicated<FV>>,
-    {
-        fn share_with<R: Rng>(self, rng: &mut R) -> [PrfShardedIpaInputRow<FV>; 3] {
-            let PreShardedAndSortedOPRFTestInput {
-                prf_of_match_key,
-                is_trigger_bit,
-                feature_vector,
-            } = self;
-
-            let [is_trigger_bit0, is_trigger_bit1, is_trigger_bit2] =
-                is_trigger_bit.share_with(rng);
-            let [feature_vector0, feature_vector1, feature_vector2] =
-                feature_vector.share_with(rng);
-
-            [
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit0,
-                    feature_vector: feature_vector0,
-                },
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit1,
-                    feature_vector: feature_vector1,
-                },
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit2,
-                    feature_vector: feature_vector2,
-                },
-            ]
-        }
-    }
-
     #[test]
     fn semi_honest() {
         run(|| async move {
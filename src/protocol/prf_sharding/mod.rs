@@ -3,8 +3,9 @@ use std::iter::{repeat, zip};
 use embed_doc_image::embed_doc_image;
 use futures::{stream::iter as stream_iter, TryStreamExt};
 use futures_util::{future::try_join, StreamExt};
-use ipa_macros::Step;
+use ipa_macros::{Reshare, Step};
 
+use self::padding::{pad_and_reveal_prf, PaddingParameters, PrfShardedIpaUnrevealedInputRow};
 use super::{
     basics::if_else, boolean::saturating_sum::SaturatingSum, modulus_conversion::convert_bits,
     step::BitOpStep,
@@ -12,9 +13,13 @@ use super::{
 use crate::{
     error::Error,
     ff::{Field, GaloisField, Gf2, PrimeField, Serializable},
+    helpers::{query::PrfShardedIpaQueryConfig, TotalRecords},
     protocol::{
-        basics::{SecureMul, ShareKnownValue},
-        boolean::or::or,
+        basics::{BasicProtocols, SecureMul, ShareKnownValue},
+        boolean::{
+            arithmetic::{subtract, Adder, Overflow},
+            or::or,
+        },
         context::{UpgradableContext, UpgradedContext, Validator},
         RecordId,
     },
@@ -27,10 +32,13 @@ use crate::{
     },
     seq_join::{seq_join, seq_try_join_all},
 };
+#[cfg(any(test, feature = "test-fixture"))]
+use crate::{rand::Rng, secret_sharing::IntoShares};
 
-#[cfg(feature = "descriptive-gate")]
 pub mod feature_label_dot_product;
+pub mod padding;
 
+#[derive(Debug)]
 pub struct PrfShardedIpaInputRow<BK: GaloisField, TV: GaloisField> {
     prf_of_match_key: u64,
     is_trigger_bit: Replicated<Gf2>,
@@ -95,13 +103,13 @@ impl InputsRequiredFromPrevRow {
 
         let (ever_encountered_a_source_event, attributed_breakdown_key_bits) = try_join(
             or(
-                ctx.narrow(&Step::EverEncounteredSourceEvent),
+                ctx.narrow(&AttributionStep::EverEncounteredSourceEvent),
                 record_id,
                 &is_source_event,
                 &self.ever_encountered_a_source_event,
             ),
             breakdown_key_of_most_recent_source_event(
-                ctx.narrow(&Step::AttributedBreakdownKey),
+                ctx.narrow(&AttributionStep::AttributedBreakdownKey),
                 record_id,
                 &input_row.is_trigger_bit,
                 &self.attributed_breakdown_key_bits,
@@ -114,13 +122,13 @@ impl InputsRequiredFromPrevRow {
             .is_trigger_bit
             .multiply(
                 &ever_encountered_a_source_event,
-                ctx.narrow(&Step::DidTriggerGetAttributed),
+                ctx.narrow(&AttributionStep::DidTriggerGetAttributed),
                 record_id,
             )
             .await?;
 
         let attributed_trigger_value = zero_out_trigger_value_unless_attributed(
-            ctx.narrow(&Step::AttributedTriggerValue),
+            ctx.narrow(&AttributionStep::AttributedTriggerValue),
             record_id,
             &did_trigger_get_attributed,
             &tv,
@@ -130,7 +138,7 @@ impl InputsRequiredFromPrevRow {
         let updated_sum = self
             .saturating_sum
             .add(
-                ctx.narrow(&Step::ComputeSaturatingSum),
+                ctx.narrow(&AttributionStep::ComputeSaturatingSum),
                 record_id,
                 &attributed_trigger_value,
             )
@@ -139,12 +147,13 @@ impl InputsRequiredFromPrevRow {
         let (is_saturated_and_prev_row_not_saturated, difference_to_cap) = try_join(
             updated_sum.is_saturated.multiply(
                 &(share_of_one - &self.saturating_sum.is_saturated),
-                ctx.narrow(&Step::IsSaturatedAndPrevRowNotSaturated),
+                ctx.narrow(&AttributionStep::IsSaturatedAndPrevRowNotSaturated),
                 record_id,
             ),
-            updated_sum.truncated_delta_to_saturation_point(
-                ctx.narrow(&Step::ComputeDifferenceToCap),
+            compute_truncated_difference_to_cap(
+                ctx.narrow(&AttributionStep::ComputeDifferenceToCap),
                 record_id,
+                &updated_sum.sum,
                 TV::BITS,
             ),
         )
@@ -174,7 +183,7 @@ impl InputsRequiredFromPrevRow {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Reshare)]
 pub struct CappedAttributionOutputs {
     pub did_trigger_get_attributed: Replicated<Gf2>,
     pub attributed_breakdown_key_bits: BitDecomposed<Replicated<Gf2>>,
@@ -184,6 +193,7 @@ pub struct CappedAttributionOutputs {
 #[derive(Step)]
 pub enum UserNthRowStep {
     #[dynamic]
+    #[step(child = AttributionStep)]
     Row(usize),
 }
 
@@ -193,9 +203,12 @@ impl From<usize> for UserNthRowStep {
     }
 }
 
+/// Steps of the binary tree that moves values to their breakdown, for breakdown keys of up to 8
+/// bits.
 #[derive(Step)]
 pub enum BinaryTreeDepthStep {
-    #[dynamic]
+    #[dynamic(8)]
+    #[step(child = crate::protocol::step::BitOpStep)]
     Depth(usize),
 }
 
@@ -207,18 +220,44 @@ impl From<usize> for BinaryTreeDepthStep {
 
 #[derive(Step)]
 pub(crate) enum Step {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = UserNthRowStep)]
+    #[step(child = AggregationStep)]
     BinaryValidator,
+    #[step(child = padding::Step)]
+    PadAndRevealPrf,
+}
+
+/// Steps of the attribution circuit, for each row of a user after the first one.
+#[derive(Step)]
+pub(crate) enum AttributionStep {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     EverEncounteredSourceEvent,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     DidTriggerGetAttributed,
+    #[step(child = crate::protocol::step::BitOpStep)]
     AttributedBreakdownKey,
+    #[step(child = crate::protocol::step::BitOpStep)]
     AttributedTriggerValue,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputeSaturatingSum,
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
     IsSaturatedAndPrevRowNotSaturated,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputeDifferenceToCap,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputedCappedAttributedTriggerValueNotSaturatedCase,
+    #[step(child = crate::protocol::step::BitOpStep)]
     ComputedCappedAttributedTriggerValueJustSaturatedCase,
+}
+
+#[derive(Step)]
+pub(crate) enum AggregationStep {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ModulusConvertBreakdownKeyBits,
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ModulusConvertConversionValueBits,
+    #[step(child = BinaryTreeDepthStep)]
     MoveValueToCorrectBreakdown,
 }
 
@@ -240,13 +279,15 @@ where
     C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
 {
     let mut context_per_row_depth = Vec::with_capacity(histogram.len());
-    for (row_number, num_users_having_that_row_number) in histogram.iter().enumerate() {
+    for row_number in 0..histogram.len() {
         if row_number == 0 {
             // no multiplications needed for each user's row 0. No context needed
         } else {
+            // Only the users in flight that have this many rows send records at this depth. That
+            // may never be enough to fill a send buffer, so send every record right away.
             let ctx_for_row_number = root_ctx
                 .narrow(&UserNthRowStep::from(row_number))
-                .set_total_records(*num_users_having_that_row_number);
+                .set_total_records(TotalRecords::Indeterminate);
             context_per_row_depth.push(ctx_for_row_number);
         }
     }
@@ -278,6 +319,32 @@ where
     rows_chunked_by_user
 }
 
+/// Rows that were dropped because their user had more than the maximum number of rows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedRows {
+    /// The number of users that had rows dropped.
+    pub users: usize,
+    /// The total number of rows dropped.
+    pub rows: usize,
+}
+
+/// Drops the oldest rows of every user with more than `max_rows_per_user` rows.
+fn truncate_rows_per_user<S>(
+    rows_chunked_by_user: &mut [Vec<S>],
+    max_rows_per_user: usize,
+) -> TruncatedRows {
+    let mut truncated = TruncatedRows::default();
+    for user_rows in rows_chunked_by_user {
+        if user_rows.len() > max_rows_per_user {
+            let excess = user_rows.len() - max_rows_per_user;
+            user_rows.drain(..excess);
+            truncated.users += 1;
+            truncated.rows += excess;
+        }
+    }
+    truncated
+}
+
 /// Sub-protocol of the PRF-sharded IPA Protocol
 ///
 /// After the computation of the per-user PRF, addition of dummy records and shuffling,
@@ -289,6 +356,10 @@ where
 ///
 /// This circuit will compute attribution, and per-user capping.
 ///
+/// Every row of a user is processed under a step of its own, so only the most recent
+/// `max_rows_per_user` rows of each user are processed. The older rows are dropped, and
+/// reported in the output.
+///
 /// The output of this circuit is the input to the next stage: Aggregation.
 ///
 /// # Errors
@@ -299,7 +370,8 @@ pub async fn attribution_and_capping<C, BK, TV>(
     sh_ctx: C,
     input_rows: Vec<PrfShardedIpaInputRow<BK, TV>>,
     num_saturating_sum_bits: usize,
-) -> Result<Vec<CappedAttributionOutputs>, Error>
+    max_rows_per_user: usize,
+) -> Result<(Vec<CappedAttributionOutputs>, TruncatedRows), Error>
 where
     C: UpgradableContext,
     C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
@@ -309,8 +381,10 @@ where
     assert!(num_saturating_sum_bits > TV::BITS as usize);
     assert!(TV::BITS > 0);
     assert!(BK::BITS > 0);
+    assert!(max_rows_per_user > 0);
 
-    let rows_chunked_by_user = chunk_rows_by_user(input_rows);
+    let mut rows_chunked_by_user = chunk_rows_by_user(input_rows);
+    let truncated = truncate_rows_per_user(&mut rows_chunked_by_user, max_rows_per_user);
     let histogram = compute_histogram_of_users_with_row_count(&rows_chunked_by_user);
     let binary_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<Gf2>();
     let binary_m_ctx = binary_validator.context();
@@ -337,10 +411,13 @@ where
         ));
     }
     let outputs_chunked_by_user = seq_try_join_all(sh_ctx.active_work(), futures).await?;
-    Ok(outputs_chunked_by_user
-        .into_iter()
-        .flatten()
-        .collect::<Vec<CappedAttributionOutputs>>())
+    Ok((
+        outputs_chunked_by_user
+            .into_iter()
+            .flatten()
+            .collect::<Vec<CappedAttributionOutputs>>(),
+        truncated,
+    ))
 }
 
 async fn evaluate_per_user_attribution_circuit<C, BK, TV>(
@@ -482,6 +559,37 @@ where
     ))
 }
 
+///
+/// Computes the difference between the cap and the cumulative sum, where the cap is `2^n` for an
+/// `n`-bit `sum`. Only the least significant `num_bits` of the difference are returned.
+///
+/// The result is meaningless once the sum is saturated.
+///
+/// # Panics
+/// If `num_bits` is larger than the number of bits in `sum`.
+///
+async fn compute_truncated_difference_to_cap<C>(
+    ctx: C,
+    record_id: RecordId,
+    sum: &BitDecomposed<Replicated<Gf2>>,
+    num_bits: u32,
+) -> Result<BitDecomposed<Replicated<Gf2>>, Error>
+where
+    C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+{
+    // `2^n - sum` is the same as `0 - sum` modulo `2^num_bits`.
+    let sum = &sum[..num_bits as usize];
+    subtract(
+        ctx,
+        record_id,
+        Adder::RippleCarry,
+        Overflow::Wrap,
+        &vec![Replicated::ZERO; sum.len()],
+        sum,
+    )
+    .await
+}
+
 ///
 /// To provide a differential privacy guarantee, we need to bound the maximum contribution from any given user to some cap.
 ///
@@ -512,8 +620,10 @@ async fn compute_capped_trigger_value<C>(
 where
     C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
 {
-    let narrowed_ctx1 = ctx.narrow(&Step::ComputedCappedAttributedTriggerValueNotSaturatedCase);
-    let narrowed_ctx2 = ctx.narrow(&Step::ComputedCappedAttributedTriggerValueJustSaturatedCase);
+    let narrowed_ctx1 =
+        ctx.narrow(&AttributionStep::ComputedCappedAttributedTriggerValueNotSaturatedCase);
+    let narrowed_ctx2 =
+        ctx.narrow(&AttributionStep::ComputedCappedAttributedTriggerValueJustSaturatedCase);
 
     let zero = &Replicated::share_known_value(&narrowed_ctx1, Gf2::ZERO);
 
@@ -555,7 +665,8 @@ pub async fn attribution_and_capping_and_aggregation<C, BK, TV, F, S, SB>(
     sh_ctx: C,
     input_rows: Vec<PrfShardedIpaInputRow<BK, TV>>,
     num_saturating_sum_bits: usize,
-) -> Result<Vec<S>, Error>
+    max_rows_per_user: usize,
+) -> Result<PrfShardedIpaOutput<S>, Error>
 where
     C: UpgradableContext,
     C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
@@ -568,10 +679,79 @@ where
     let prime_field_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<F>();
     let prime_field_m_ctx = prime_field_validator.context();
 
-    let user_level_attributions: Vec<CappedAttributionOutputs> =
-        attribution_and_capping(sh_ctx, input_rows, num_saturating_sum_bits).await?;
+    let (user_level_attributions, truncated) = attribution_and_capping(
+        sh_ctx,
+        input_rows,
+        num_saturating_sum_bits,
+        max_rows_per_user,
+    )
+    .await?;
+
+    Ok(PrfShardedIpaOutput {
+        breakdowns: do_aggregation::<_, BK, TV, F, S>(prime_field_m_ctx, user_level_attributions)
+            .await?,
+        truncated,
+    })
+}
 
-    do_aggregation::<_, BK, TV, F, S>(prime_field_m_ctx, user_level_attributions).await
+/// The output of the PRF-sharded IPA Protocol.
+#[derive(Debug)]
+pub struct PrfShardedIpaOutput<S> {
+    /// The sum of the capped trigger values attributed to each breakdown key.
+    pub breakdowns: Vec<S>,
+    /// The rows that were not processed because their user had too many rows.
+    pub truncated: TruncatedRows,
+}
+
+/// The PRF-sharded IPA Protocol
+///
+/// Takes rows with the PRF of their match key still secret-shared, adds dummy users if the query
+/// is configured to, reveals the PRF values and then computes attribution, per-user capping and
+/// aggregation per breakdown key.
+///
+/// # Errors
+/// If the padding parameters or the maximum number of rows per user of the query are invalid, or
+/// if any of the sub-protocols fails.
+/// # Panics
+/// If there are more than `u32::MAX` rows after padding.
+pub async fn prf_sharded_ipa<C, BK, TV, F, S>(
+    sh_ctx: C,
+    input_rows: Vec<PrfShardedIpaUnrevealedInputRow<BK, TV>>,
+    config: &PrfShardedIpaQueryConfig,
+) -> Result<PrfShardedIpaOutput<S>, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + Serializable + SecureMul<C::UpgradedContext<F>>,
+    C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+    Replicated<Gf2>: BasicProtocols<C::UpgradedContext<Gf2>, Gf2>,
+    F: PrimeField + ExtendableField,
+    TV: GaloisField,
+    BK: GaloisField,
+{
+    if !(1..=PrfShardedIpaQueryConfig::MAX_ROWS_PER_USER).contains(&config.max_rows_per_user) {
+        return Err(Error::InvalidQueryParameter(format!(
+            "max_rows_per_user must be within [1, {}], got: {}",
+            PrfShardedIpaQueryConfig::MAX_ROWS_PER_USER,
+            config.max_rows_per_user
+        )));
+    }
+    let padding = PaddingParameters::from_config(config)
+        .map_err(|e| Error::InvalidQueryParameter(e.to_string()))?;
+    let input_rows = pad_and_reveal_prf(
+        sh_ctx.narrow(&Step::PadAndRevealPrf),
+        input_rows,
+        padding.as_ref(),
+    )
+    .await?;
+
+    attribution_and_capping_and_aggregation::<_, BK, TV, F, S, Replicated<Gf2>>(
+        sh_ctx,
+        input_rows,
+        usize::try_from(config.num_saturating_sum_bits).unwrap(),
+        usize::try_from(config.max_rows_per_user).unwrap(),
+    )
+    .await
 }
 
 /// Sub-protocol of the PRF-sharded IPA Protocol
@@ -606,14 +786,14 @@ where
 
     // modulus convert breakdown keys
     let converted_bks = convert_bits(
-        ctx.narrow(&Step::ModulusConvertBreakdownKeyBits)
+        ctx.narrow(&AggregationStep::ModulusConvertBreakdownKeyBits)
             .set_total_records(num_records),
         stream_iter(bk_vec),
         0..BK::BITS,
     );
     // modulus convert attributed value
     let converted_values = convert_bits(
-        ctx.narrow(&Step::ModulusConvertConversionValueBits)
+        ctx.narrow(&AggregationStep::ModulusConvertConversionValueBits)
             .set_total_records(num_records),
         stream_iter(tv_vec),
         0..TV::BITS,
@@ -627,7 +807,7 @@ where
     let row_contributions_stream = converted_bks
         .zip(large_field_values)
         .zip(futures::stream::repeat(
-            ctx.narrow(&Step::MoveValueToCorrectBreakdown)
+            ctx.narrow(&AggregationStep::MoveValueToCorrectBreakdown)
                 .set_total_records(num_records),
         ))
         .enumerate()
@@ -728,11 +908,65 @@ where
     Ok(row_contribution)
 }
 
+/// The cleartext of a [`PrfShardedIpaInputRow`], for tests and benchmarks to secret-share.
+#[cfg(any(test, feature = "test-fixture"))]
+#[derive(Debug, Clone)]
+pub struct PreShardedAndSortedOPRFTestInput<BK: GaloisField, TV: GaloisField> {
+    pub prf_of_match_key: u64,
+    pub is_trigger_bit: Gf2,
+    pub breakdown_key: BK,
+    pub trigger_value: TV,
+}
+
+#[cfg(any(test, feature = "test-fixture"))]
+impl<BK, TV> IntoShares<PrfShardedIpaInputRow<BK, TV>> for PreShardedAndSortedOPRFTestInput<BK, TV>
+where
+    BK: GaloisField + IntoShares<Replicated<BK>>,
+    TV: GaloisField + IntoShares<Replicated<TV>>,
+{
+    fn share_with<R: Rng>(self, rng: &mut R) -> [PrfShardedIpaInputRow<BK, TV>; 3] {
+        let PreShardedAndSortedOPRFTestInput {
+            prf_of_match_key,
+            is_trigger_bit,
+            breakdown_key,
+            trigger_value,
+        } = self;
+
+        let [is_trigger_bit0, is_trigger_bit1, is_trigger_bit2] = is_trigger_bit.share_with(rng);
+        let [breakdown_key0, breakdown_key1, breakdown_key2] = breakdown_key.share_with(rng);
+        let [trigger_value0, trigger_value1, trigger_value2] = trigger_value.share_with(rng);
+
+        [
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit0,
+                breakdown_key: breakdown_key0,
+                trigger_value: trigger_value0,
+            },
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit1,
+                breakdown_key: breakdown_key1,
+                trigger_value: trigger_value1,
+            },
+            PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: is_trigger_bit2,
+                breakdown_key: breakdown_key2,
+                trigger_value: trigger_value2,
+            },
+        ]
+    }
+}
+
 #[cfg(all(test, unit_test))]
 pub mod tests {
     use rand::thread_rng;
 
-    use super::{attribution_and_capping, CappedAttributionOutputs, PrfShardedIpaInputRow};
+    use super::{
+        attribution_and_capping, compute_truncated_difference_to_cap, CappedAttributionOutputs,
+        PreShardedAndSortedOPRFTestInput, TruncatedRows,
+    };
     use crate::{
         ff::{Field, Fp32BitPrime, GaloisField, Gf2, Gf3Bit, Gf5Bit, Gf8Bit},
         protocol::{
@@ -749,15 +983,15 @@ pub mod tests {
             SharedValue,
         },
         test_executor::run,
-        test_fixture::{get_bits, Reconstruct, Runner, TestWorld},
+        test_fixture::{
+            cost::{self, Cost},
+            get_bits,
+            simulate::Simulator,
+            Reconstruct, Runner, TestWorld, TestWorldConfig,
+        },
     };
 
-    struct PreShardedAndSortedOPRFTestInput<BK: GaloisField, TV: GaloisField> {
-        prf_of_match_key: u64,
-        is_trigger_bit: Gf2,
-        breakdown_key: BK,
-        trigger_value: TV,
-    }
+    const MAX_ROWS_PER_USER: usize = 8;
 
     fn oprf_test_input(
         prf_of_match_key: u64,
@@ -817,47 +1051,6 @@ pub mod tests {
         capped_attributed_trigger_value: BitDecomposed<Gf2>,
     }
 
-    impl<BK, TV> IntoShares<PrfShardedIpaInputRow<BK, TV>> for PreShardedAndSortedOPRFTestInput<BK, TV>
-    where
-        BK: GaloisField + IntoShares<Replicated<BK>>,
-        TV: GaloisField + IntoShares<Replicated<TV>>,
-    {
-        fn share_with<R: Rng>(self, rng: &mut R) -> [PrfShardedIpaInputRow<BK, TV>; 3] {
-            let PreShardedAndSortedOPRFTestInput {
-                prf_of_match_key,
-                is_trigger_bit,
-                breakdown_key,
-                trigger_value,
-            } = self;
-
-            let [is_trigger_bit0, is_trigger_bit1, is_trigger_bit2] =
-                is_trigger_bit.share_with(rng);
-            let [breakdown_key0, breakdown_key1, breakdown_key2] = breakdown_key.share_with(rng);
-            let [trigger_value0, trigger_value1, trigger_value2] = trigger_value.share_with(rng);
-
-            [
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit0,
-                    breakdown_key: breakdown_key0,
-                    trigger_value: trigger_value0,
-                },
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit1,
-                    breakdown_key: breakdown_key1,
-                    trigger_value: trigger_value1,
-                },
-                PrfShardedIpaInputRow {
-                    prf_of_match_key,
-                    is_trigger_bit: is_trigger_bit2,
-                    breakdown_key: breakdown_key2,
-                    trigger_value: trigger_value2,
-                },
-            ]
-        }
-    }
-
     impl IntoShares<CappedAttributionOutputs> for PreAggregationTestInputInBits {
         fn share_with<R: Rng>(self, rng: &mut R) -> [CappedAttributionOutputs; 3] {
             let PreAggregationTestInputInBits {
@@ -972,9 +1165,11 @@ pub mod tests {
                         ctx,
                         input_rows,
                         num_saturating_bits,
+                        MAX_ROWS_PER_USER,
                     )
                     .await
                     .unwrap()
+                    .0
                 })
                 .await
                 .reconstruct();
@@ -982,6 +1177,110 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn semi_honest_attribution_and_capping_drops_oldest_rows() {
+        run(|| async move {
+            let world = TestWorld::default();
+
+            let records: Vec<PreShardedAndSortedOPRFTestInput<Gf5Bit, Gf3Bit>> = vec![
+                /* First User */
+                oprf_test_input(123, false, 17, 0),
+                oprf_test_input(123, true, 0, 7),
+                oprf_test_input(123, false, 20, 0),
+                oprf_test_input(123, true, 0, 3),
+                /* Second User */
+                oprf_test_input(234, false, 12, 0),
+                oprf_test_input(234, true, 0, 5),
+                /* Third User, whose first three rows are dropped */
+                oprf_test_input(345, false, 20, 0),
+                oprf_test_input(345, true, 0, 7),
+                oprf_test_input(345, false, 18, 0),
+                oprf_test_input(345, false, 12, 0),
+                oprf_test_input(345, true, 0, 7),
+                oprf_test_input(345, true, 0, 7),
+                oprf_test_input(345, true, 0, 7),
+                oprf_test_input(345, true, 0, 7),
+            ];
+
+            let expected: [PreAggregationTestOutputInDecimal; 8] = [
+                decimal_bd_key_and_value(17, 7),
+                decimal_bd_key_and_value(20, 0),
+                decimal_bd_key_and_value(20, 3),
+                decimal_bd_key_and_value(12, 5),
+                decimal_bd_key_and_value(12, 7),
+                decimal_bd_key_and_value(12, 7),
+                decimal_bd_key_and_value(12, 7),
+                decimal_bd_key_and_value(12, 7),
+            ];
+            let num_saturating_bits: usize = 5;
+
+            let [r0, r1, r2] = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| async move {
+                    attribution_and_capping::<_, Gf5Bit, Gf3Bit>(
+                        ctx,
+                        input_rows,
+                        num_saturating_bits,
+                        5,
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await;
+            for (_, truncated) in [&r0, &r1, &r2] {
+                assert_eq!(truncated, &TruncatedRows { users: 1, rows: 3 });
+            }
+            let result: Vec<_> = [r0.0, r1.0, r2.0].reconstruct();
+            assert_eq!(result, &expected);
+        });
+    }
+
+    #[tokio::test]
+    async fn attribution_and_capping_cost_model() {
+        const ROWS_PER_USER: [usize; 4] = [4, 2, 9, 1];
+        const TRUNCATE_TO: usize = 6;
+        let num_saturating_bits: usize = 5;
+
+        let mut rng = thread_rng();
+        let records = (0_u64..)
+            .zip(ROWS_PER_USER)
+            .flat_map(|(user, rows)| (0..rows).map(move |_| user))
+            .map(|user| {
+                let is_trigger = rng.gen::<bool>();
+                if is_trigger {
+                    oprf_test_input(user, true, 0, rng.gen_range(1..8))
+                } else {
+                    oprf_test_input(user, false, rng.gen_range(0..32), 0)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let simulator = Simulator::new(TestWorldConfig::default());
+        simulator
+            .world()
+            .semi_honest(records.into_iter(), |ctx, input_rows| async move {
+                attribution_and_capping::<_, Gf5Bit, Gf3Bit>(
+                    ctx,
+                    input_rows,
+                    num_saturating_bits,
+                    TRUNCATE_TO,
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+
+        let model = cost::attribution_and_capping::<Gf5Bit, Gf3Bit>(
+            ROWS_PER_USER,
+            num_saturating_bits,
+            TRUNCATE_TO,
+        );
+        let measured = Cost::measured(&simulator);
+        assert!(
+            model.is_close_to(&measured, 0.1),
+            "expected {model:?}, measured {measured:?}"
+        );
+    }
+
     #[test]
     fn semi_honest_aggregation_capping_attribution() {
         run(|| async move {
@@ -1023,9 +1322,10 @@ pub mod tests {
                         Fp32BitPrime,
                         _,
                         Replicated<Gf2>,
-                    >(ctx, input_rows, num_saturating_bits)
+                    >(ctx, input_rows, num_saturating_bits, MAX_ROWS_PER_USER)
                     .await
                     .unwrap()
+                    .breakdowns
                 })
                 .await
                 .reconstruct();
@@ -1159,4 +1459,40 @@ pub mod tests {
             }
         }
     }
+
+    #[test]
+    fn semi_honest_truncated_difference_to_cap() {
+        run(|| async move {
+            assert_eq!(2, truncated_difference_to_cap(30, 5, 3).await);
+            assert_eq!(2, truncated_difference_to_cap(30, 5, 2).await);
+            assert_eq!(0, truncated_difference_to_cap(30, 5, 1).await);
+            assert_eq!(1, truncated_difference_to_cap(31, 5, 1).await);
+            assert_eq!(0, truncated_difference_to_cap(32, 5, 1).await);
+            assert_eq!(7, truncated_difference_to_cap(25, 5, 3).await);
+            assert_eq!(3, truncated_difference_to_cap(61, 6, 3).await);
+            assert_eq!(1, truncated_difference_to_cap(15, 4, 1).await);
+            assert_eq!(1, truncated_difference_to_cap(15, 4, 2).await);
+            assert_eq!(1, truncated_difference_to_cap(15, 4, 4).await);
+        });
+    }
+
+    async fn truncated_difference_to_cap(sum: u32, sum_bits: u32, num_bits: u32) -> u128 {
+        let world = TestWorld::default();
+
+        world
+            .semi_honest(get_bits::<Gf2>(sum, sum_bits), |ctx, sum| async move {
+                let validator = ctx.validator::<Gf2>();
+                let ctx = validator.context();
+                compute_truncated_difference_to_cap(
+                    ctx.set_total_records(1),
+                    RecordId(0),
+                    &sum,
+                    num_bits,
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct()
+    }
 }
@@ -0,0 +1,618 @@
+use std::iter::repeat_with;
+
+use ipa_macros::{Reshare, Step};
+use rand::{distributions::Distribution, Rng};
+use rand_core::{CryptoRng, RngCore};
+
+use super::PrfShardedIpaInputRow;
+use crate::{
+    error::Error,
+    ff::{Field, GaloisField, Gf2, Gf32Bit, Gf40Bit},
+    helpers::{query::PrfShardedIpaQueryConfig, Direction, Role},
+    protocol::{
+        basics::{BasicProtocols, Reveal, ShareKnownValue},
+        context::{Context, UpgradableContext, UpgradedContext, Validator},
+        dp::{self, DiscreteLaplace},
+        sort::{apply_sort::apply_sort_permutation, quicksort::quicksort_and_reveal_shuffled},
+        RecordId,
+    },
+    secret_sharing::{
+        replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+        BitDecomposed, Linear as LinearSecretSharing, LinearRefOps, SharedValue,
+    },
+    seq_join::SeqJoin,
+};
+
+#[derive(Step)]
+pub(crate) enum Step {
+    #[step(child = DummyRowsStep)]
+    DummiesHiddenFromH1,
+    #[step(child = DummyRowsStep)]
+    DummiesHiddenFromH2,
+    #[step(child = DummyRowsStep)]
+    DummiesHiddenFromH3,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::sort::quicksort::Step)]
+    SortByPrf,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = RevealStep)]
+    BinaryValidator,
+}
+
+#[derive(Step)]
+pub(crate) enum DummyRowsStep {
+    DummyRowCount,
+}
+
+#[derive(Step)]
+pub(crate) enum RevealStep {
+    #[step(child = crate::protocol::sort::ApplyInvStep)]
+    ApplySortPermutation,
+    RevealPrf,
+}
+
+impl Step {
+    fn dummies_hidden_from(role: Role) -> Self {
+        match role {
+            Role::H1 => Self::DummiesHiddenFromH1,
+            Role::H2 => Self::DummiesHiddenFromH2,
+            Role::H3 => Self::DummiesHiddenFromH3,
+        }
+    }
+}
+
+/// An input row of the PRF-sharded IPA protocol, before the PRF of its match key is revealed.
+///
+/// The rows of each user must be in time order, but they do not need to be adjacent.
+#[derive(Debug, Reshare)]
+pub struct PrfShardedIpaUnrevealedInputRow<BK: GaloisField, TV: GaloisField> {
+    prf_of_match_key: Replicated<Gf40Bit>,
+    is_trigger_bit: Replicated<Gf2>,
+    breakdown_key: Replicated<BK>,
+    trigger_value: Replicated<TV>,
+}
+
+impl<BK: GaloisField, TV: GaloisField> PrfShardedIpaUnrevealedInputRow<BK, TV> {
+    /// A trigger event with a zero trigger value. It does not change the attribution of the other
+    /// events of a user, so it contributes nothing to the output, whichever user it ends up with.
+    fn dummy<C: Context>(ctx: &C, prf_of_match_key: Replicated<Gf40Bit>) -> Self {
+        Self {
+            prf_of_match_key,
+            is_trigger_bit: Replicated::share_known_value(ctx, Gf2::ONE),
+            breakdown_key: Replicated::ZERO,
+            trigger_value: Replicated::ZERO,
+        }
+    }
+}
+
+/// Parameters of the dummy users that helpers add before the PRF of the match keys is revealed.
+///
+/// For every number of rows from 1 to `max_user_rows`, each pair of helpers adds dummy users with
+/// that many rows. Adding or removing a user changes the number of users with a given number of
+/// rows by one, so the number of dummy users is drawn from a discrete Laplace distribution with
+/// sensitivity 1. The distribution is shifted such that it is negative, and clamped to zero, with
+/// probability at most `delta`. It is clamped to twice the shift on the other side, which happens
+/// with the same probability, so that the helper the dummy users are hidden from can bound the
+/// number of dummy rows it receives.
+///
+/// Queries set `max_user_rows` to the maximum number of rows per user, because attribution drops
+/// the rows beyond it anyway.
+///
+/// The helper that a pair hides its dummy users from still learns how many dummy rows the pair
+/// added, which is the sum of `k` times the number of dummy users with `k` rows. Once the PRF
+/// values are revealed, this only tells it the sum of `k` times the number of real users with `k`
+/// rows, for `k` up to `max_user_rows`. Every helper can compute that sum from the number of
+/// input rows and the users with more rows than `max_user_rows`, which are not padded. In
+/// particular, with `max_user_rows` set to 1 the dummy row count reveals the number of real
+/// users with one row, which is already public.
+#[derive(Debug)]
+pub struct PaddingParameters {
+    noise: DiscreteLaplace,
+    shift: i64,
+    max_user_rows: u32,
+}
+
+impl PaddingParameters {
+    /// ## Errors
+    /// If epsilon or delta is out of range.
+    pub fn new(epsilon: f64, delta: f64, max_user_rows: u32) -> Result<Self, dp::Error> {
+        let noise = DiscreteLaplace::new(epsilon)?;
+        if !(f64::MIN_POSITIVE..1.0).contains(&delta) {
+            return Err(dp::Error::BadDelta(delta));
+        }
+        // A discrete Laplace sample is below `-shift` with probability at most
+        // `exp(-epsilon * (shift + 1))`.
+        #[allow(clippy::cast_possible_truncation)]
+        let shift = (f64::ln(1.0 / delta) / noise.epsilon()).ceil() as i64;
+
+        Ok(Self {
+            noise,
+            shift,
+            max_user_rows,
+        })
+    }
+
+    /// Returns the padding of the query, or `None` if the query does not add dummy users.
+    ///
+    /// ## Errors
+    /// If the padding epsilon or delta of the query is out of range.
+    pub fn from_config(config: &PrfShardedIpaQueryConfig) -> Result<Option<Self>, dp::Error> {
+        config
+            .padding_epsilon
+            .map(|epsilon| Self::new(epsilon, config.padding_delta, config.max_rows_per_user))
+            .transpose()
+    }
+
+    /// Maximum number of dummy users with a given number of rows.
+    fn max_dummy_users(&self) -> usize {
+        usize::try_from(self.shift.saturating_mul(2)).unwrap_or(usize::MAX)
+    }
+
+    /// Maximum number of dummy rows that a pair of helpers adds, if every number of rows gets
+    /// [`max_dummy_users`] users.
+    ///
+    /// [`max_dummy_users`]: Self::max_dummy_users
+    fn max_dummy_rows(&self) -> usize {
+        let max_user_rows = usize::try_from(self.max_user_rows).unwrap();
+        (1..=max_user_rows).fold(0, |total: usize, rows_per_user| {
+            total.saturating_add(rows_per_user.saturating_mul(self.max_dummy_users()))
+        })
+    }
+
+    /// Returns the number of dummy users to add with 1, 2, ..., `max_user_rows` rows.
+    fn sample_dummy_users<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Vec<usize> {
+        (0..self.max_user_rows)
+            .map(|_| {
+                let count = self.shift.saturating_add(self.noise.sample(rng));
+                usize::try_from(count)
+                    .unwrap_or(0)
+                    .min(self.max_dummy_users())
+            })
+            .collect()
+    }
+}
+
+/// Generates the dummy rows of the pair of helpers other than `hidden_from`.
+///
+/// The pair samples the dummy users from the randomness they share, and sets the shares of
+/// `hidden_from` to zero. `hidden_from` only learns how many dummy rows the pair added.
+///
+/// ## Errors
+/// If `hidden_from` receives more dummy rows than the pair can add with `padding`, which means
+/// that the sender deviated from the protocol.
+async fn dummy_rows<C, BK, TV>(
+    ctx: C,
+    hidden_from: Role,
+    padding: &PaddingParameters,
+) -> Result<Vec<PrfShardedIpaUnrevealedInputRow<BK, TV>>, Error>
+where
+    C: Context,
+    BK: GaloisField,
+    TV: GaloisField,
+{
+    let count_ctx = ctx
+        .narrow(&DummyRowsStep::DummyRowCount)
+        .set_total_records(1);
+    let sender = hidden_from.peer(Direction::Right);
+
+    if ctx.role() == hidden_from {
+        let count = count_ctx
+            .recv_channel::<Gf32Bit>(sender)
+            .receive(RecordId::FIRST)
+            .await?;
+        let count = usize::try_from(count.as_u128())
+            .ok()
+            .filter(|&count| count <= padding.max_dummy_rows())
+            .ok_or(Error::MaliciousSecurityCheckFailed)?;
+        return Ok(
+            repeat_with(|| PrfShardedIpaUnrevealedInputRow::dummy(&ctx, Replicated::ZERO))
+                .take(count)
+                .collect(),
+        );
+    }
+
+    // `sender` shares its right share with the other helper of the pair, which shares its left
+    // share with `sender`.
+    let is_sender = ctx.role() == sender;
+    let mut rng = if is_sender {
+        ctx.prss_rng().1
+    } else {
+        ctx.prss_rng().0
+    };
+
+    let mut rows = Vec::new();
+    for (rows_per_user, users) in (1..).zip(padding.sample_dummy_users(&mut rng)) {
+        for _ in 0..users {
+            let prf = Gf40Bit::truncate_from(rng.gen::<u64>());
+            let prf_share = if is_sender {
+                Replicated::new(Gf40Bit::ZERO, prf)
+            } else {
+                Replicated::new(prf, Gf40Bit::ZERO)
+            };
+            rows.extend(
+                repeat_with(|| PrfShardedIpaUnrevealedInputRow::dummy(&ctx, prf_share.clone()))
+                    .take(rows_per_user),
+            );
+        }
+    }
+
+    if is_sender {
+        let count = u32::try_from(rows.len()).expect("dummy row count fits in u32");
+        count_ctx
+            .send_channel(hidden_from)
+            .send(RecordId::FIRST, Gf32Bit::truncate_from(count))
+            .await?;
+    }
+
+    Ok(rows)
+}
+
+/// Sub-protocol of the PRF-sharded IPA Protocol
+///
+/// Adds dummy users to the input if `padding` is given, then sorts the rows by the PRF of their
+/// match key and reveals it. The output is the input of [`attribution_and_capping`].
+///
+/// Every pair of helpers adds dummy users that the third helper does not know about, so that no
+/// helper learns exactly how many users have a given number of rows. Dummy users get fresh random
+/// PRF values, and all their rows are trigger events with a zero trigger value. These never
+/// contribute to the output, even if a dummy PRF value collides with the PRF of a real user.
+///
+/// The sort only reveals comparisons after shuffling the rows, and it is stable, so the rows of
+/// each user end up adjacent to one another and in the same order as in the input.
+///
+/// [`attribution_and_capping`]: super::attribution_and_capping
+///
+/// # Errors
+/// Propagates errors from communication, the sort and the reveal.
+/// # Panics
+/// If there are more than `u32::MAX` rows.
+pub async fn pad_and_reveal_prf<C, S, BK, TV>(
+    sh_ctx: C,
+    input_rows: Vec<PrfShardedIpaUnrevealedInputRow<BK, TV>>,
+    padding: Option<&PaddingParameters>,
+) -> Result<Vec<PrfShardedIpaInputRow<BK, TV>>, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = S>,
+    S: LinearSecretSharing<Gf2> + BasicProtocols<C::UpgradedContext<Gf2>, Gf2> + 'static,
+    for<'r> &'r S: LinearRefOps<'r, S, Gf2>,
+    BK: GaloisField,
+    TV: GaloisField,
+{
+    let mut rows = input_rows;
+    if let Some(padding) = padding {
+        let dummies = sh_ctx
+            .try_join(Role::all().iter().map(|&hidden_from| {
+                dummy_rows(
+                    sh_ctx.narrow(&Step::dummies_hidden_from(hidden_from)),
+                    hidden_from,
+                    padding,
+                )
+            }))
+            .await?;
+        rows.extend(dummies.into_iter().flatten());
+    }
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sort_keys = rows
+        .iter()
+        .map(|row| {
+            BitDecomposed::decompose(Gf40Bit::BITS, |i| {
+                row.prf_of_match_key.map(|v| Gf2::truncate_from(v[i]))
+            })
+        })
+        .collect();
+    let sort_permutation =
+        quicksort_and_reveal_shuffled(sh_ctx.narrow(&Step::SortByPrf), sort_keys).await?;
+
+    let binary_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<Gf2>();
+    let binary_m_ctx = binary_validator.context();
+    let sorted_rows = apply_sort_permutation(
+        binary_m_ctx.narrow(&RevealStep::ApplySortPermutation),
+        rows,
+        &sort_permutation,
+    )
+    .await?;
+
+    let reveal_ctx = binary_m_ctx
+        .narrow(&RevealStep::RevealPrf)
+        .set_total_records(sorted_rows.len());
+    let prfs = reveal_ctx
+        .try_join(sorted_rows.iter().enumerate().map(|(i, row)| {
+            row.prf_of_match_key
+                .reveal(reveal_ctx.clone(), RecordId::from(i))
+        }))
+        .await?;
+
+    Ok(sorted_rows
+        .into_iter()
+        .zip(prfs)
+        .map(|(row, prf)| PrfShardedIpaInputRow {
+            prf_of_match_key: u64::try_from(prf.as_u128()).unwrap(),
+            is_trigger_bit: row.is_trigger_bit,
+            breakdown_key: row.breakdown_key,
+            trigger_value: row.trigger_value,
+        })
+        .collect())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{thread_rng, Rng};
+
+    use super::{pad_and_reveal_prf, PaddingParameters, PrfShardedIpaUnrevealedInputRow};
+    use crate::{
+        ff::{Field, Fp32BitPrime, GaloisField, Gf2, Gf3Bit, Gf40Bit, Gf5Bit},
+        helpers::query::PrfShardedIpaQueryConfig,
+        protocol::prf_sharding::{prf_sharded_ipa, PrfShardedIpaInputRow, TruncatedRows},
+        secret_sharing::{
+            replicated::semi_honest::AdditiveShare as Replicated, IntoShares, SharedValue,
+        },
+        test_executor::run,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    const EPSILON: f64 = 20.0;
+    const DELTA: f64 = 1e-6;
+    const MAX_USER_ROWS: u32 = 2;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestRow {
+        prf_of_match_key: u64,
+        is_trigger: bool,
+        breakdown_key: u8,
+        trigger_value: u8,
+    }
+
+    fn test_row(
+        prf_of_match_key: u64,
+        is_trigger: bool,
+        breakdown_key: u8,
+        trigger_value: u8,
+    ) -> TestRow {
+        TestRow {
+            prf_of_match_key,
+            is_trigger,
+            breakdown_key,
+            trigger_value,
+        }
+    }
+
+    impl IntoShares<PrfShardedIpaUnrevealedInputRow<Gf5Bit, Gf3Bit>> for TestRow {
+        fn share_with<R: Rng>(
+            self,
+            rng: &mut R,
+        ) -> [PrfShardedIpaUnrevealedInputRow<Gf5Bit, Gf3Bit>; 3] {
+            let is_trigger_bit = if self.is_trigger { Gf2::ONE } else { Gf2::ZERO };
+            let [prf0, prf1, prf2] = Gf40Bit::truncate_from(self.prf_of_match_key).share_with(rng);
+            let [is_trigger0, is_trigger1, is_trigger2] = is_trigger_bit.share_with(rng);
+            let [bk0, bk1, bk2] = Gf5Bit::truncate_from(self.breakdown_key).share_with(rng);
+            let [tv0, tv1, tv2] = Gf3Bit::truncate_from(self.trigger_value).share_with(rng);
+
+            [
+                (prf0, is_trigger0, bk0, tv0),
+                (prf1, is_trigger1, bk1, tv1),
+                (prf2, is_trigger2, bk2, tv2),
+            ]
+            .map(
+                |(prf_of_match_key, is_trigger_bit, breakdown_key, trigger_value)| {
+                    PrfShardedIpaUnrevealedInputRow {
+                        prf_of_match_key,
+                        is_trigger_bit,
+                        breakdown_key,
+                        trigger_value,
+                    }
+                },
+            )
+        }
+    }
+
+    impl Reconstruct<TestRow> for [&PrfShardedIpaInputRow<Gf5Bit, Gf3Bit>; 3] {
+        fn reconstruct(&self) -> TestRow {
+            let [s0, s1, s2] = self;
+            assert_eq!(s0.prf_of_match_key, s1.prf_of_match_key);
+            assert_eq!(s0.prf_of_match_key, s2.prf_of_match_key);
+
+            TestRow {
+                prf_of_match_key: s0.prf_of_match_key,
+                is_trigger: [&s0.is_trigger_bit, &s1.is_trigger_bit, &s2.is_trigger_bit]
+                    .reconstruct()
+                    == Gf2::ONE,
+                breakdown_key: u8::try_from(
+                    [&s0.breakdown_key, &s1.breakdown_key, &s2.breakdown_key]
+                        .reconstruct()
+                        .as_u128(),
+                )
+                .unwrap(),
+                trigger_value: u8::try_from(
+                    [&s0.trigger_value, &s1.trigger_value, &s2.trigger_value]
+                        .reconstruct()
+                        .as_u128(),
+                )
+                .unwrap(),
+            }
+        }
+    }
+
+    /// Rows of two users, interleaved.
+    fn inputs() -> Vec<TestRow> {
+        vec![
+            test_row(123, false, 17, 0),
+            test_row(234, false, 12, 0),
+            test_row(123, true, 0, 7),
+            test_row(234, true, 0, 5),
+            test_row(123, false, 20, 0),
+            test_row(123, true, 0, 3),
+        ]
+    }
+
+    /// Checks that the rows are grouped by user, that the rows of the real users are in input
+    /// order, and that the dummy users only have zero-value trigger events.
+    fn check_padded(inputs: &[TestRow], output: &[TestRow]) {
+        assert!(output
+            .windows(2)
+            .all(|w| w[0].prf_of_match_key <= w[1].prf_of_match_key));
+
+        let mut users = HashMap::<u64, Vec<TestRow>>::new();
+        for row in output {
+            users.entry(row.prf_of_match_key).or_default().push(*row);
+        }
+        for prf in [123, 234] {
+            let expected = inputs
+                .iter()
+                .filter(|row| row.prf_of_match_key == prf)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(users.remove(&prf).unwrap(), expected);
+        }
+
+        assert!(!users.is_empty());
+        for (prf, rows) in users {
+            assert!(rows.len() <= usize::try_from(MAX_USER_ROWS).unwrap());
+            assert!(rows.iter().all(|row| *row == test_row(prf, true, 0, 0)));
+        }
+    }
+
+    #[test]
+    fn semi_honest() {
+        run(|| async move {
+            let world = TestWorld::default();
+            let padding = PaddingParameters::new(EPSILON, DELTA, MAX_USER_ROWS).unwrap();
+
+            let result: Vec<TestRow> = world
+                .semi_honest(inputs().into_iter(), |ctx, input_rows| {
+                    let padding = &padding;
+                    async move {
+                        pad_and_reveal_prf::<_, _, Gf5Bit, Gf3Bit>(ctx, input_rows, Some(padding))
+                            .await
+                            .unwrap()
+                    }
+                })
+                .await
+                .reconstruct();
+            check_padded(&inputs(), &result);
+        });
+    }
+
+    #[test]
+    fn malicious() {
+        run(|| async move {
+            let world = TestWorld::default();
+            let padding = PaddingParameters::new(EPSILON, DELTA, MAX_USER_ROWS).unwrap();
+
+            let result: Vec<TestRow> = world
+                .malicious(inputs().into_iter(), |ctx, input_rows| {
+                    let padding = &padding;
+                    async move {
+                        pad_and_reveal_prf::<_, _, Gf5Bit, Gf3Bit>(ctx, input_rows, Some(padding))
+                            .await
+                            .unwrap()
+                    }
+                })
+                .await
+                .reconstruct();
+            check_padded(&inputs(), &result);
+        });
+    }
+
+    #[test]
+    fn one_row_per_user() {
+        run(|| async move {
+            let world = TestWorld::default();
+            let padding = PaddingParameters::new(EPSILON, DELTA, 1).unwrap();
+            let inputs = vec![
+                test_row(123, true, 0, 5),
+                test_row(234, false, 3, 0),
+                test_row(345, true, 0, 1),
+            ];
+
+            let result: Vec<TestRow> = world
+                .semi_honest(inputs.clone().into_iter(), |ctx, input_rows| {
+                    let padding = &padding;
+                    async move {
+                        pad_and_reveal_prf::<_, _, Gf5Bit, Gf3Bit>(ctx, input_rows, Some(padding))
+                            .await
+                            .unwrap()
+                    }
+                })
+                .await
+                .reconstruct();
+
+            // Every user, real or dummy, has a single row. The number of users after padding is
+            // the number of rows, which all helpers know, so the number of dummy rows that each
+            // helper receives does not tell it anything about the real users.
+            assert!(result.len() > inputs.len());
+            assert!(result
+                .windows(2)
+                .all(|w| w[0].prf_of_match_key < w[1].prf_of_match_key));
+            for row in inputs {
+                assert!(result.contains(&row));
+            }
+        });
+    }
+
+    #[test]
+    fn dummies_do_not_contribute() {
+        run(|| async move {
+            let world = TestWorld::default();
+            let config = PrfShardedIpaQueryConfig {
+                num_saturating_sum_bits: 5,
+                padding_epsilon: Some(EPSILON),
+                padding_delta: DELTA,
+                max_rows_per_user: MAX_USER_ROWS,
+            };
+
+            // The first user has 4 rows, so its oldest source and trigger events are dropped.
+            // Dummy users never have more rows than the maximum.
+            let mut expected = [0_u128; 32];
+            expected[12] = 5;
+            expected[20] = 3;
+
+            let [r0, r1, r2] = world
+                .semi_honest(inputs().into_iter(), |ctx, input_rows| async move {
+                    prf_sharded_ipa::<_, Gf5Bit, Gf3Bit, Fp32BitPrime, Replicated<Fp32BitPrime>>(
+                        ctx, input_rows, &config,
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await;
+            for r in [&r0, &r1, &r2] {
+                assert_eq!(r.truncated, TruncatedRows { users: 1, rows: 2 });
+            }
+            let result: Vec<_> = [r0.breakdowns, r1.breakdowns, r2.breakdowns].reconstruct();
+            assert_eq!(result, &expected);
+        });
+    }
+
+    #[test]
+    fn dummy_rows_are_bounded() {
+        let padding = PaddingParameters::new(1.0, 0.1, 3).unwrap();
+        assert_eq!(padding.max_dummy_users(), 6);
+        assert_eq!(padding.max_dummy_rows(), 36);
+
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let users = padding.sample_dummy_users(&mut rng);
+            assert!(users.iter().all(|&n| n <= padding.max_dummy_users()));
+            let rows: usize = (1..).zip(users).map(|(k, n)| k * n).sum();
+            assert!(rows <= padding.max_dummy_rows());
+        }
+    }
+
+    #[test]
+    fn padding_from_config() {
+        let config = PrfShardedIpaQueryConfig::default();
+        assert!(PaddingParameters::from_config(&config).unwrap().is_none());
+
+        let config = PrfShardedIpaQueryConfig {
+            padding_epsilon: Some(0.0),
+            ..config
+        };
+        assert!(PaddingParameters::from_config(&config).is_err());
+    }
+}
@@ -20,6 +20,19 @@ pub trait SharedRandomness {
     #[must_use]
     fn generate_values<I: Into<u128>>(&self, index: I) -> (u128, u128);
 
+    /// Generate `N` random values that are known to the left helper and `N` random values
+    /// that are known to the right helper, all for the same index. The first value of each
+    /// array is the value that `generate_values` returns for that index.
+    #[must_use]
+    fn generate_arrays<const N: usize, I: Into<u128>>(&self, index: I) -> ([u128; N], [u128; N]);
+
+    /// Generate two random values of any type that can be drawn from PRSS, one that is known
+    /// to the left helper and one that is known to the right helper.
+    #[must_use]
+    fn generate<T: FromPrss, I: Into<u128>>(&self, index: I) -> (T, T) {
+        T::from_prss(self, index)
+    }
+
     /// Generate two random field values, one that is known to the left helper
     /// and one that is known to the right helper.
     #[must_use]
@@ -93,6 +106,19 @@ pub trait SharedRandomness {
     }
 }
 
+/// Values that can be generated from PRSS.
+pub trait FromPrss: Sized {
+    /// Generate two random values, one that is known to the left helper and one that is known
+    /// to the right helper.
+    fn from_prss<R: SharedRandomness + ?Sized, I: Into<u128>>(prss: &R, index: I) -> (Self, Self);
+}
+
+impl<F: Field> FromPrss for F {
+    fn from_prss<R: SharedRandomness + ?Sized, I: Into<u128>>(prss: &R, index: I) -> (F, F) {
+        prss.generate_fields(index)
+    }
+}
+
 // The key exchange component of a participant.
 pub struct KeyExchange {
     sk: EphemeralSecret,
@@ -3,7 +3,7 @@ use std::{collections::HashMap, fmt::Debug};
 #[cfg(debug_assertions)]
 use std::{collections::HashSet, fmt::Formatter};
 
-pub use crypto::{Generator, GeneratorFactory, KeyExchange, SharedRandomness};
+pub use crypto::{FromPrss, Generator, GeneratorFactory, KeyExchange, SharedRandomness};
 use x25519_dalek::PublicKey;
 
 use super::step::Gate;
@@ -82,6 +82,26 @@ impl SharedRandomness for IndexedSharedRandomness {
 
         (self.left.generate(index), self.right.generate(index))
     }
+
+    /// Values beyond the first one are generated at `index + k * 2^64`, which record indices
+    /// never reach.
+    fn generate_arrays<const N: usize, I: Into<u128>>(&self, index: I) -> ([u128; N], [u128; N]) {
+        let index = index.into();
+        debug_assert!(
+            index < 1 << 64,
+            "index {index} is too large to generate arrays"
+        );
+        #[cfg(debug_assertions)]
+        {
+            self.used.insert(index);
+        }
+
+        let sub_index = |k: usize| index + ((k as u128) << 64);
+        (
+            std::array::from_fn(|k| self.left.generate(sub_index(k))),
+            std::array::from_fn(|k| self.right.generate(sub_index(k))),
+        )
+    }
 }
 
 /// An implementation of `RngCore` that uses the same underlying `Generator`.
@@ -260,7 +280,9 @@ pub mod test {
 
     use rand::prelude::SliceRandom;
 
-    use super::{Generator, KeyExchange, SequentialSharedRandomness};
+    #[cfg(debug_assertions)]
+    use super::UsedSet;
+    use super::{Generator, IndexedSharedRandomness, KeyExchange, SequentialSharedRandomness};
     use crate::{
         ff::{Field, Fp31},
         protocol::{
@@ -343,6 +365,35 @@ pub mod test {
         assert_eq!(r3_l, r2_r);
     }
 
+    #[test]
+    fn three_party_arrays() {
+        const IDX: u128 = 7;
+        let [p1, p2, p3] = participants();
+
+        let step = Gate::default();
+        let (r1_l, r1_r) = p1.indexed(&step).generate_arrays::<3, _>(IDX);
+        let (r2_l, r2_r) = p2.indexed(&step).generate_arrays::<3, _>(IDX);
+        let (r3_l, r3_r) = p3.indexed(&step).generate_arrays::<3, _>(IDX);
+
+        assert_eq!(r1_l, r3_r);
+        assert_eq!(r2_l, r1_r);
+        assert_eq!(r3_l, r2_r);
+        assert_ne!(r1_l[0], r1_l[1]);
+        assert_ne!(r1_l[1], r1_l[2]);
+
+        // the first value of an array is the value generated for the same index
+        let (left, right) = make();
+        let prss = || IndexedSharedRandomness {
+            left: left.clone(),
+            right: right.clone(),
+            #[cfg(debug_assertions)]
+            used: UsedSet::new(Gate::default()),
+        };
+        let (l, r) = prss().generate_values(IDX);
+        let (l_arr, r_arr) = prss().generate_arrays::<2, _>(IDX);
+        assert_eq!([l, r], [l_arr[0], r_arr[0]]);
+    }
+
     #[test]
     fn three_party_zero_u128() {
         const IDX: u128 = 7;
@@ -0,0 +1,880 @@
+use embed_doc_image::embed_doc_image;
+use ipa_macros::Step;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    error::Error,
+    helpers::{Direction, Role},
+    protocol::{
+        basics::Reshare,
+        context::{Context, ScopeCheck, ScopedValidation},
+        sort::apply::{apply, apply_inv},
+        step, NoRecord, RecordId,
+    },
+};
+
+#[derive(Step, Clone, Copy)]
+#[step(child = crate::protocol::sort::ReshareStep)]
+#[step(child = crate::protocol::basics::reshare::InnerVectorElementStep)]
+#[step(child = crate::protocol::basics::reshare::ReshareFieldStep)]
+#[step(child = crate::protocol::ipa::IPAInputRowResharableStep)]
+pub(crate) enum ShuffleStep {
+    Shuffle1,
+    Shuffle2,
+    Shuffle3,
+}
+
+/// Rows that can be shuffled.
+///
+/// Shuffling reshares every row, so this is implemented for everything that implements
+/// [`Reshare`]: semi-honest and malicious shares, [`BitDecomposed`] values of them, and structs of
+/// shares that derive [`Reshare`](ipa_macros::Reshare).
+///
+/// [`BitDecomposed`]: crate::secret_sharing::BitDecomposed
+pub trait Shuffleable<C: Context>: Reshare<C, RecordId> + Send + Sync {}
+
+impl<C: Context, T: Reshare<C, RecordId> + Send + Sync> Shuffleable<C> for T {}
+
+#[derive(Debug)]
+/// This is SHUFFLE(Algorithm 1) described in <https://eprint.iacr.org/2019/695.pdf>.
+/// This protocol shuffles the given inputs across 3 helpers making them indistinguishable to the helpers
+/// We call shuffle with helpers involved as (H2, H3), (H3, H1) and (H1, H2). In other words, the shuffle is being called for
+/// H1, H2 and H3 respectively (since they do not participate in the step) and hence are the recipients of the shuffle.
+enum ShuffleOrUnshuffle {
+    Shuffle,
+    Unshuffle,
+}
+
+impl step::Step for ShuffleOrUnshuffle {}
+impl AsRef<str> for ShuffleOrUnshuffle {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Shuffle => "shuffle",
+            Self::Unshuffle => "unshuffle",
+        }
+    }
+}
+
+/// The two of the three random permutations of a shuffle that are known to this helper.
+///
+/// A shuffle applies three permutations one after another, each of them known to two helpers
+/// only, so no helper learns how the rows were shuffled. The same permutations can be used to
+/// shuffle several vectors of the same length in the same way, and to [`unshuffle`] them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShufflePermutations {
+    left: Vec<u32>,
+    right: Vec<u32>,
+}
+
+impl ShufflePermutations {
+    /// Generates the permutations for shuffling `count` rows from the randomness of `ctx`, which
+    /// must be narrowed to a step that is not used for anything else. All helpers must call this
+    /// with the same `ctx` and `count`.
+    ///
+    /// ## Panics
+    /// If `count` does not fit in `u32`.
+    #[must_use]
+    pub fn generate<C: Context>(ctx: &C, count: usize) -> Self {
+        get_two_of_three_random_permutations(count.try_into().unwrap(), ctx.prss_rng()).into()
+    }
+
+    /// Returns the number of rows that these permutations shuffle.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    fn as_slices(&self) -> (&[u32], &[u32]) {
+        (&self.left, &self.right)
+    }
+}
+
+impl From<(Vec<u32>, Vec<u32>)> for ShufflePermutations {
+    fn from((left, right): (Vec<u32>, Vec<u32>)) -> Self {
+        assert_eq!(left.len(), right.len());
+        Self { left, right }
+    }
+}
+
+/// Shuffles `rows` with the given permutations.
+///
+/// In the malicious setting, the rows are checked before they are returned. See
+/// [`shuffle_shares`] for details.
+///
+/// ## Errors
+/// If communication fails or if one of the helpers deviated from the protocol.
+///
+/// ## Panics
+/// If the number of rows does not match the permutations.
+pub async fn shuffle<C, R>(
+    ctx: C,
+    rows: Vec<R>,
+    permutations: &ShufflePermutations,
+) -> Result<Vec<R>, Error>
+where
+    C: ScopedValidation,
+    R: Shuffleable<C>,
+{
+    assert_eq!(rows.len(), permutations.len());
+    shuffle_shares(rows, permutations.as_slices(), ctx).await
+}
+
+/// Undoes [`shuffle`] with the same permutations, putting the rows back in their original order.
+///
+/// In the malicious setting, the rows are checked before they are returned, in the same way as
+/// for [`shuffle`].
+///
+/// ## Errors
+/// If communication fails or if one of the helpers deviated from the protocol.
+///
+/// ## Panics
+/// If the number of rows does not match the permutations.
+pub async fn unshuffle<C, R>(
+    ctx: C,
+    rows: Vec<R>,
+    permutations: &ShufflePermutations,
+) -> Result<Vec<R>, Error>
+where
+    C: ScopedValidation,
+    R: Shuffleable<C>,
+{
+    assert_eq!(rows.len(), permutations.len());
+    let (ctx, check) = ctx.scoped_validation();
+    let output = unshuffle_shares(rows, permutations.as_slices(), ctx).await?;
+    check.validate().await?;
+    Ok(output)
+}
+
+/// This implements Fisher Yates shuffle described here <https://en.wikipedia.org/wiki/Fisher%E2%80%93Yates_shuffle>
+pub fn get_two_of_three_random_permutations<R: Rng>(
+    batch_size: u32,
+    mut rng: (R, R),
+) -> (Vec<u32>, Vec<u32>) {
+    let mut left_permutation = (0..batch_size).collect::<Vec<_>>();
+    let mut right_permutation = left_permutation.clone();
+
+    left_permutation.shuffle(&mut rng.0);
+    right_permutation.shuffle(&mut rng.1);
+
+    (left_permutation, right_permutation)
+}
+
+fn shuffle_for_helper(which_step: ShuffleStep) -> Role {
+    match which_step {
+        ShuffleStep::Shuffle1 => Role::H1,
+        ShuffleStep::Shuffle2 => Role::H2,
+        ShuffleStep::Shuffle3 => Role::H3,
+    }
+}
+
+/// `shuffle_or_unshuffle_once` is called for the helpers
+/// i)   2 helpers receive permutation pair and choose the permutation to be applied
+/// ii)  2 helpers apply the permutation to their shares
+/// iii) reshare to `to_helper`
+#[tracing::instrument(name = "shuffle_once", skip_all, fields(to = ?shuffle_for_helper(which_step)))]
+async fn shuffle_or_unshuffle_once<C, I>(
+    mut input: Vec<I>,
+    random_permutations: (&[u32], &[u32]),
+    shuffle_or_unshuffle: ShuffleOrUnshuffle,
+    ctx: &C,
+    which_step: ShuffleStep,
+) -> Result<Vec<I>, Error>
+where
+    C: Context,
+    I: Reshare<C, RecordId> + Send + Sync,
+{
+    let to_helper = shuffle_for_helper(which_step);
+    let ctx = ctx.narrow(&which_step);
+
+    if to_helper != ctx.role() {
+        let permutation_to_apply = if to_helper.peer(Direction::Left) == ctx.role() {
+            random_permutations.0
+        } else {
+            random_permutations.1
+        };
+
+        match shuffle_or_unshuffle {
+            ShuffleOrUnshuffle::Shuffle => apply_inv(permutation_to_apply, &mut input),
+            ShuffleOrUnshuffle::Unshuffle => apply(permutation_to_apply, &mut input),
+        }
+    }
+    input.reshare(ctx, NoRecord, to_helper).await
+}
+
+#[embed_doc_image("shuffle", "images/sort/shuffle.png")]
+/// Shuffle calls `shuffle_or_unshuffle_once` three times with 2 helpers shuffling the shares each time.
+/// Order of calling `shuffle_or_unshuffle_once` is shuffle with (H2, H3), (H3, H1) and (H1, H2).
+/// Each shuffle requires communication between helpers to perform reshare.
+/// Infrastructure has a pre-requisite to distinguish each communication step uniquely.
+/// For this, we have three shuffle steps one per `shuffle_or_unshuffle_once` i.e. Step1, Step2 and Step3.
+/// The Shuffle object receives a step function and appends a `ShuffleStep` to form a concrete step
+///
+/// In the malicious setting, two helpers that apply a permutation can tamper with the rows they
+/// shuffle. The shuffle runs in a scope of its own, and the MACs of all reshared rows are checked
+/// before the output is returned, so tampering is detected before anyone relies on the order.
+///
+/// ![Shuffle steps][shuffle]
+///
+/// ## Errors
+/// If communication fails or if one of the helpers deviated from the protocol.
+pub async fn shuffle_shares<C, I>(
+    input: Vec<I>,
+    random_permutations: (&[u32], &[u32]),
+    ctx: C,
+) -> Result<Vec<I>, Error>
+where
+    C: ScopedValidation,
+    I: Reshare<C, RecordId> + Send + Sync,
+{
+    let (ctx, check) = ctx.scoped_validation();
+    let input = shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Shuffle,
+        &ctx,
+        ShuffleStep::Shuffle1,
+    )
+    .await?;
+    let input = shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Shuffle,
+        &ctx,
+        ShuffleStep::Shuffle2,
+    )
+    .await?;
+    let output = shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Shuffle,
+        &ctx,
+        ShuffleStep::Shuffle3,
+    )
+    .await?;
+
+    check.validate().await?;
+    Ok(output)
+}
+
+#[embed_doc_image("unshuffle", "images/sort/unshuffle.png")]
+/// Unshuffle calls `shuffle_or_unshuffle_once` three times with 2 helpers shuffling the shares each time in the opposite order to shuffle.
+/// Order of calling `shuffle_or_unshuffle_once` is shuffle with (H1, H2), (H3, H1) and (H2, H3)
+///
+/// Unlike [`shuffle_shares`], this does not check the output in the malicious setting. Use
+/// [`unshuffle`] for that.
+/// ![Unshuffle steps][unshuffle]
+///
+/// ## Errors
+/// If communication fails.
+pub async fn unshuffle_shares<C, I>(
+    input: Vec<I>,
+    random_permutations: (&[u32], &[u32]),
+    ctx: C,
+) -> Result<Vec<I>, Error>
+where
+    C: Context,
+    I: Reshare<C, RecordId> + Send + Sync,
+{
+    let input = shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Unshuffle,
+        &ctx,
+        ShuffleStep::Shuffle3,
+    )
+    .await?;
+    let input = shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Unshuffle,
+        &ctx,
+        ShuffleStep::Shuffle2,
+    )
+    .await?;
+    shuffle_or_unshuffle_once(
+        input,
+        random_permutations,
+        ShuffleOrUnshuffle::Unshuffle,
+        &ctx,
+        ShuffleStep::Shuffle1,
+    )
+    .await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use crate::{
+        protocol::{shuffle::get_two_of_three_random_permutations, step::Gate},
+        rand::thread_rng,
+        test_fixture::{make_participants, permutation_valid},
+    };
+
+    #[test]
+    fn random_sequence_generated() {
+        const BATCH_SIZE: u32 = 10000;
+
+        let [p1, p2, p3] = make_participants(&mut thread_rng());
+        let step = Gate::default();
+        let perm1 = get_two_of_three_random_permutations(BATCH_SIZE, p1.sequential(&step));
+        let perm2 = get_two_of_three_random_permutations(BATCH_SIZE, p2.sequential(&step));
+        let perm3 = get_two_of_three_random_permutations(BATCH_SIZE, p3.sequential(&step));
+
+        assert_eq!(perm1.1, perm2.0);
+        assert_eq!(perm2.1, perm3.0);
+        assert_eq!(perm3.1, perm1.0);
+
+        // Due to less randomness, the below three asserts can fail. However, the chance of failure is
+        // 1/18Quintillian (a billion billion since u64 is used to generate randomness)! Hopefully we should not hit that
+        assert_ne!(perm1.0, perm1.1);
+        assert_ne!(perm2.0, perm2.1);
+        assert_ne!(perm3.0, perm3.1);
+
+        assert!(permutation_valid(&perm1.0));
+        assert!(permutation_valid(&perm2.0));
+        assert!(permutation_valid(&perm3.0));
+    }
+
+    mod semi_honest {
+        use std::collections::HashSet;
+
+        use crate::{
+            ff::{Field, Fp31},
+            protocol::{
+                context::Context,
+                shuffle::{get_two_of_three_random_permutations, shuffle_shares, unshuffle_shares},
+            },
+            test_fixture::{Reconstruct, Runner, TestWorld},
+        };
+
+        #[tokio::test]
+        async fn semi_honest() {
+            const BATCHSIZE: u8 = 25;
+            let world = TestWorld::default();
+
+            let input: Vec<u8> = (0..BATCHSIZE).collect();
+            let hashed_input: HashSet<u8> = input.clone().into_iter().collect();
+
+            let result = world
+                .semi_honest(
+                    input
+                        .clone()
+                        .into_iter()
+                        .map(u128::from)
+                        .map(Fp31::truncate_from),
+                    |ctx, m_shares| async move {
+                        let perms =
+                            get_two_of_three_random_permutations(BATCHSIZE.into(), ctx.prss_rng());
+                        shuffle_shares(
+                            m_shares,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.clone(),
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+
+            let mut hashed_output_secret = HashSet::new();
+            let mut output_secret = Vec::new();
+            for val in result.reconstruct() {
+                output_secret.push(u8::from(val));
+                hashed_output_secret.insert(u8::from(val));
+            }
+
+            // Secrets should be shuffled
+            assert_ne!(output_secret, input);
+
+            // Shuffled output should have same inputs
+            assert_eq!(hashed_output_secret, hashed_input);
+        }
+
+        #[tokio::test]
+        async fn shuffle_unshuffle() {
+            const BATCHSIZE: usize = 5;
+
+            let world = TestWorld::default();
+            let input: Vec<u128> = (0..u128::try_from(BATCHSIZE).unwrap()).collect();
+
+            let result = world
+                .semi_honest(
+                    input.clone().into_iter().map(Fp31::truncate_from),
+                    |ctx, m_shares| async move {
+                        let perms = get_two_of_three_random_permutations(
+                            BATCHSIZE.try_into().unwrap(),
+                            ctx.prss_rng(),
+                        );
+                        let shuffled = shuffle_shares(
+                            m_shares,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.clone(),
+                        )
+                        .await
+                        .unwrap();
+
+                        unshuffle_shares(
+                            shuffled,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.narrow("unshuffle"),
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+
+            assert_eq!(&input[..], &result.reconstruct());
+        }
+    }
+
+    mod malicious {
+        use std::collections::HashSet;
+
+        use crate::{
+            error::Error,
+            ff::{Field, Fp31, Fp32BitPrime},
+            helpers::Role,
+            protocol::{
+                context::{Context, UpgradableContext, UpgradedContext, Validator},
+                shuffle::{get_two_of_three_random_permutations, shuffle_shares, unshuffle_shares},
+            },
+            secret_sharing::replicated::{
+                malicious::{
+                    AdditiveShare as MaliciousReplicated,
+                    ThisCodeIsAuthorizedToDowngradeFromMalicious,
+                },
+                semi_honest::AdditiveShare as Replicated,
+                ReplicatedSecretSharing,
+            },
+            test_fixture::{Reconstruct, Runner, TestWorld},
+        };
+
+        #[tokio::test]
+        async fn malicious() {
+            const BATCHSIZE: u8 = 25;
+            let world = TestWorld::default();
+
+            let input: Vec<u8> = (0..BATCHSIZE).collect();
+            let hashed_input: HashSet<u8> = input.clone().into_iter().collect();
+
+            let input_u128: Vec<u128> = input.iter().map(|x| u128::from(*x)).collect();
+
+            let result = world
+                .upgraded_malicious(
+                    input_u128.clone().into_iter().map(Fp31::truncate_from),
+                    |ctx, m_shares| async move {
+                        let perms =
+                            get_two_of_three_random_permutations(BATCHSIZE.into(), ctx.prss_rng());
+                        shuffle_shares(
+                            m_shares,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.clone(),
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+
+            let mut hashed_output_secret = HashSet::new();
+            let mut output_secret = Vec::new();
+            for val in result.reconstruct() {
+                output_secret.push(u8::try_from(val.as_u128()).unwrap());
+                hashed_output_secret.insert(u8::try_from(val.as_u128()).unwrap());
+            }
+
+            // Secrets should be shuffled
+            assert_ne!(output_secret, input);
+
+            // Shuffled output should have same inputs
+            assert_eq!(hashed_output_secret, hashed_input);
+        }
+
+        #[tokio::test]
+        async fn shuffle_unshuffle() {
+            const BATCHSIZE: usize = 5;
+
+            let world = TestWorld::default();
+            let input: Vec<u128> = (0..u128::try_from(BATCHSIZE).unwrap()).collect();
+
+            let result = world
+                .upgraded_malicious(
+                    input.clone().into_iter().map(Fp31::truncate_from),
+                    |ctx, m_shares| async move {
+                        let perms = get_two_of_three_random_permutations(
+                            BATCHSIZE.try_into().unwrap(),
+                            ctx.prss_rng(),
+                        );
+                        let shuffled = shuffle_shares(
+                            m_shares,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.clone(),
+                        )
+                        .await
+                        .unwrap();
+
+                        unshuffle_shares(
+                            shuffled,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            ctx.narrow("unshuffle"),
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+
+            assert_eq!(&input[..], &result.reconstruct());
+        }
+
+        /// One of the helpers applies a permutation that differs from the one its peer applies.
+        #[tokio::test]
+        async fn tampered_permutation() {
+            const BATCHSIZE: u32 = 10;
+            let world = TestWorld::default();
+            let input = (0..u128::from(BATCHSIZE)).map(Fp32BitPrime::truncate_from);
+
+            for malicious_actor in [Role::H1, Role::H2, Role::H3] {
+                world
+                    .malicious(input.clone(), |ctx, shares| async move {
+                        let v = ctx.validator::<Fp32BitPrime>();
+                        let m_ctx = v.context();
+                        let m_shares = m_ctx.upgrade(shares).await.unwrap();
+                        let (mut left, mut right) =
+                            get_two_of_three_random_permutations(BATCHSIZE, m_ctx.prss_rng());
+                        if m_ctx.role() == malicious_actor {
+                            left.swap(0, 1);
+                            right.swap(0, 1);
+                        }
+
+                        let result =
+                            shuffle_shares(m_shares, (&left, &right), m_ctx.narrow("shuffle"))
+                                .await;
+                        assert!(matches!(result, Err(Error::MaliciousSecurityCheckFailed)));
+                    })
+                    .await;
+            }
+        }
+
+        /// One of the helpers changes its share of a row, but cannot change the MAC accordingly.
+        /// H1 only receives the output of the first shuffle, so its input shares are not used.
+        #[tokio::test]
+        async fn additive_attack() {
+            const BATCHSIZE: u32 = 10;
+            let world = TestWorld::default();
+            let input = (0..u128::from(BATCHSIZE)).map(Fp32BitPrime::truncate_from);
+
+            for malicious_actor in [Role::H2, Role::H3] {
+                world
+                    .malicious(input.clone(), |ctx, shares| async move {
+                        let v = ctx.validator::<Fp32BitPrime>();
+                        let m_ctx = v.context();
+                        let mut m_shares: Vec<MaliciousReplicated<Fp32BitPrime>> =
+                            m_ctx.upgrade(shares).await.unwrap();
+                        if m_ctx.role() == malicious_actor {
+                            let x = m_shares[0].x().access_without_downgrade();
+                            let x = Replicated::new(x.left() + Fp32BitPrime::ONE, x.right());
+                            m_shares[0] = MaliciousReplicated::new(x, m_shares[0].rx().clone());
+                        }
+                        let perms =
+                            get_two_of_three_random_permutations(BATCHSIZE, m_ctx.prss_rng());
+
+                        let result = shuffle_shares(
+                            m_shares,
+                            (perms.0.as_slice(), perms.1.as_slice()),
+                            m_ctx.narrow("shuffle"),
+                        )
+                        .await;
+                        assert!(matches!(result, Err(Error::MaliciousSecurityCheckFailed)));
+                    })
+                    .await;
+            }
+        }
+    }
+
+    mod rows {
+        use std::collections::HashSet;
+
+        use crate::{
+            accumulation_test_input,
+            ff::{Fp31, Fp32BitPrime},
+            protocol::{
+                attribution::input::AccumulateCreditInputRow,
+                context::{Context, UpgradableContext, Validator},
+                shuffle::{get_two_of_three_random_permutations, shuffle_shares},
+                BreakdownKey, MatchKey,
+            },
+            rand::{thread_rng, Rng},
+            secret_sharing::{
+                replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+                BitDecomposed,
+            },
+            test_fixture::{
+                bits_to_value, get_bits, input::GenericReportTestInput, Reconstruct, Runner,
+                TestWorld,
+            },
+        };
+
+        #[tokio::test]
+        async fn shuffle_attribution_input_row() {
+            const BATCHSIZE: u8 = 25;
+            let world = TestWorld::default();
+            let mut rng = thread_rng();
+
+            let mut input: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> =
+                Vec::with_capacity(BATCHSIZE.into());
+            input.resize_with(BATCHSIZE.into(), || {
+                accumulation_test_input!(
+                    {
+                        is_trigger_report: rng.gen::<u8>(),
+                        helper_bit: rng.gen::<u8>(),
+                        active_bit: rng.gen::<u8>(),
+                        credit: rng.gen::<u8>(),
+                    };
+                    (Fp31, MatchKey, BreakdownKey)
+                )
+            });
+            let hashed_input: HashSet<[u8; 3]> = input
+                .iter()
+                .map(|x| {
+                    [
+                        u8::from(x.is_trigger_report.unwrap()),
+                        u8::from(x.helper_bit.unwrap()),
+                        u8::from(x.trigger_value),
+                    ]
+                })
+                .collect();
+
+            let result: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = world
+                .semi_honest(
+                    input.clone().into_iter(),
+                    |ctx, shares: Vec<AccumulateCreditInputRow<Fp31, Replicated<_>>>| async move {
+                        let validator = ctx.validator::<Fp31>(); // Just ignore this here.
+                        let ctx = validator.context();
+
+                        let perms =
+                            get_two_of_three_random_permutations(BATCHSIZE.into(), ctx.prss_rng());
+
+                        shuffle_shares(shares, (perms.0.as_slice(), perms.1.as_slice()), ctx)
+                            .await
+                            .unwrap()
+                    },
+                )
+                .await
+                .reconstruct();
+
+            let mut hashed_output_secret = HashSet::new();
+            let mut output_secret = Vec::new();
+            for val in result {
+                output_secret.push(val);
+                hashed_output_secret.insert([
+                    u8::from(val.is_trigger_report.unwrap()),
+                    u8::from(val.helper_bit.unwrap()),
+                    u8::from(val.trigger_value),
+                ]);
+            }
+
+            // Secrets should be shuffled
+            assert_ne!(output_secret, input);
+
+            // Shuffled output should have same inputs
+            assert_eq!(hashed_output_secret, hashed_input);
+        }
+
+        fn share_appears_anywhere(
+            x: &Replicated<Fp32BitPrime>,
+            inputs: &[BitDecomposed<Replicated<Fp32BitPrime>>],
+        ) -> bool {
+            inputs.iter().any(|row| {
+                row.iter()
+                    .any(|share| share.left() == x.left() && share.right() == x.right())
+            })
+        }
+
+        #[tokio::test]
+        async fn shuffle_vec_of_replicated() {
+            const BIT_LENGTH: u32 = 32;
+            let some_numbers = vec![
+                123_456_789,
+                234_567_890,
+                345_678_901,
+                456_789_012,
+                567_890_123,
+            ];
+            let some_numbers_as_bits = some_numbers
+                .iter()
+                .map(|&x| get_bits::<Fp32BitPrime>(x, BIT_LENGTH))
+                .collect::<Vec<_>>();
+            let world = TestWorld::default();
+
+            let result = world
+                .semi_honest(some_numbers_as_bits.into_iter(), |ctx, shares| async move {
+                    let copy_of_input = shares.clone();
+                    let perms = get_two_of_three_random_permutations(5, ctx.prss_rng());
+                    let shuffled_shares =
+                        shuffle_shares(shares, (perms.0.as_slice(), perms.1.as_slice()), ctx)
+                            .await
+                            .unwrap();
+
+                    assert!(!shuffled_shares.iter().any(|row| row
+                        .iter()
+                        .any(|x| share_appears_anywhere(x, &copy_of_input))));
+
+                    shuffled_shares
+                })
+                .await
+                .reconstruct();
+
+            let mut reconstructed_inputs = result
+                .iter()
+                .map(|vec| u32::try_from(bits_to_value(vec)).unwrap())
+                .collect::<Vec<_>>();
+            reconstructed_inputs.sort_unstable();
+            assert_eq!(reconstructed_inputs, some_numbers);
+        }
+    }
+
+    mod api {
+        use std::collections::HashMap;
+
+        use ipa_macros::Reshare;
+
+        use crate::{
+            ff::{Field, Fp31},
+            protocol::{
+                basics::Reshare,
+                context::{Context, ScopedValidation},
+                shuffle::{shuffle, unshuffle, ShufflePermutations},
+                RecordId,
+            },
+            secret_sharing::Linear as LinearSecretSharing,
+            seq_join::SeqJoin,
+            test_fixture::{Reconstruct, Runner, TestWorld},
+        };
+
+        #[derive(Reshare)]
+        struct Row<S> {
+            value: S,
+            double: S,
+        }
+
+        /// Shuffles rows of `(x, 2x)` and unshuffles them again. Returns the shuffled values, the
+        /// shuffled doubles and the unshuffled values, one after another.
+        async fn shuffle_and_unshuffle<C, S>(ctx: C, values: Vec<S>) -> Vec<S>
+        where
+            C: ScopedValidation,
+            S: LinearSecretSharing<Fp31> + Reshare<C, RecordId> + Send + Sync,
+        {
+            let permutations = ShufflePermutations::generate(&ctx.narrow("generate"), values.len());
+            let rows = values
+                .into_iter()
+                .map(|value| Row {
+                    double: value.clone() + &value,
+                    value,
+                })
+                .collect::<Vec<_>>();
+
+            let shuffled = shuffle(ctx.narrow("shuffle"), rows, &permutations)
+                .await
+                .unwrap();
+            let mut output = shuffled
+                .iter()
+                .map(|row| row.value.clone())
+                .chain(shuffled.iter().map(|row| row.double.clone()))
+                .collect::<Vec<_>>();
+
+            let unshuffled = unshuffle(ctx.narrow("unshuffle"), shuffled, &permutations)
+                .await
+                .unwrap();
+            output.extend(unshuffled.into_iter().map(|row| row.value));
+            output
+        }
+
+        fn check_shuffle_and_unshuffle(input: &[Fp31], output: &[Fp31]) {
+            let (shuffled, rest) = output.split_at(input.len());
+            let (doubles, unshuffled) = rest.split_at(input.len());
+
+            // Every field of a row moves with the row.
+            for (value, double) in shuffled.iter().zip(doubles) {
+                assert_eq!(*value + *value, *double);
+            }
+            let mut sorted = shuffled.to_vec();
+            sorted.sort_by_key(Field::as_u128);
+            assert_eq!(sorted, input);
+
+            assert_eq!(unshuffled, input);
+        }
+
+        #[tokio::test]
+        async fn semi_honest_inverse() {
+            const COUNT: u128 = 20;
+            let world = TestWorld::default();
+            let input = (0..COUNT).map(Fp31::truncate_from).collect::<Vec<_>>();
+
+            let output = world
+                .semi_honest(input.clone().into_iter(), |ctx, values| async move {
+                    shuffle_and_unshuffle(ctx, values).await
+                })
+                .await
+                .reconstruct();
+            check_shuffle_and_unshuffle(&input, &output);
+        }
+
+        #[tokio::test]
+        async fn malicious_inverse() {
+            const COUNT: u128 = 20;
+            let world = TestWorld::default();
+            let input = (0..COUNT).map(Fp31::truncate_from).collect::<Vec<_>>();
+
+            let output = world
+                .upgraded_malicious(input.clone().into_iter(), |ctx, values| async move {
+                    shuffle_and_unshuffle(ctx, values).await
+                })
+                .await
+                .reconstruct();
+            check_shuffle_and_unshuffle(&input, &output);
+        }
+
+        /// Shuffles three rows many times, and checks that all six orders are about as likely.
+        #[tokio::test]
+        async fn uniform() {
+            const TRIALS: usize = 600;
+            const ROWS: usize = 3;
+            let world = TestWorld::default();
+            let input = (0..TRIALS * ROWS)
+                .map(|i| Fp31::truncate_from(u128::try_from(i % ROWS).unwrap()))
+                .collect::<Vec<_>>();
+
+            let output = world
+                .semi_honest(input.into_iter(), |ctx, values| async move {
+                    let trials = values.chunks(ROWS).enumerate().map(|(i, rows)| {
+                        let ctx = ctx.narrow(&format!("trial{i}"));
+                        let rows = rows.to_vec();
+                        async move {
+                            let permutations = ShufflePermutations::generate(&ctx, ROWS);
+                            shuffle(ctx, rows, &permutations).await
+                        }
+                    });
+                    ctx.parallel_join(trials).await.unwrap().concat()
+                })
+                .await
+                .reconstruct();
+
+            let mut counts = HashMap::new();
+            for order in output.chunks(ROWS) {
+                let order = order.iter().map(Field::as_u128).collect::<Vec<_>>();
+                *counts.entry(order).or_insert(0) += 1;
+            }
+            // 100 of each order are expected, with a standard deviation of about 9.
+            assert_eq!(counts.len(), 6);
+            for count in counts.values() {
+                assert!((55..=145).contains(count), "{counts:?}");
+            }
+        }
+    }
+}
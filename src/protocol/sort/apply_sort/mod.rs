@@ -1,12 +1,9 @@
-pub mod shuffle;
-
-pub use shuffle::shuffle_shares;
-
 use crate::{
     error::Error,
     protocol::{
         basics::Reshare,
-        context::Context,
+        context::ScopedValidation,
+        shuffle::shuffle_shares,
         sort::{
             apply::apply_inv, generate_permutation::RevealedAndRandomPermutations,
             ApplyInvStep::ShuffleInputs,
@@ -16,7 +13,8 @@ use crate::{
 };
 
 /// # Errors
-/// Propagates errors from shuffle/reshare
+/// Propagates errors from shuffle/reshare, including a failed check of the shuffle in the
+/// malicious setting.
 #[tracing::instrument(name = "apply_sort", skip_all, fields(gate = %ctx.gate().as_ref()))]
 pub async fn apply_sort_permutation<C, I>(
     ctx: C,
@@ -24,7 +22,7 @@ pub async fn apply_sort_permutation<C, I>(
     sort_permutation: &RevealedAndRandomPermutations,
 ) -> Result<Vec<I>, Error>
 where
-    C: Context,
+    C: ScopedValidation,
     I: Reshare<C, RecordId> + Send + Sync,
 {
     let mut shuffled_objects = shuffle_shares(
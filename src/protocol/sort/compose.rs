@@ -6,7 +6,8 @@ use crate::{
     protocol::{
         basics::Reshare,
         context::Context,
-        sort::{apply::apply, shuffle::unshuffle_shares, ComposeStep::UnshuffleRho},
+        shuffle::unshuffle_shares,
+        sort::{apply::apply, ComposeStep::UnshuffleRho},
         RecordId,
     },
     secret_sharing::SecretSharing,
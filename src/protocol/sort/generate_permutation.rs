@@ -11,10 +11,10 @@ use crate::{
             UpgradedMaliciousContext, UpgradedSemiHonestContext, Validator,
         },
         modulus_conversion::{BitConversionTriple, ToBitConversionTriples},
+        shuffle::{get_two_of_three_random_permutations, shuffle_shares},
         sort::{
-            generate_permutation_opt::generate_permutation_opt,
-            shuffle::{get_two_of_three_random_permutations, shuffle_shares},
-            ShuffleRevealPermutationStep, SortStep,
+            generate_permutation_opt::generate_permutation_opt, ShuffleRevealPermutationStep,
+            SortStep,
         },
         BasicProtocols, NoRecord, RecordId,
     },
@@ -2,12 +2,12 @@ pub mod apply_sort;
 pub mod bit_permutation;
 pub mod generate_permutation;
 pub mod generate_permutation_opt;
+pub mod quicksort;
 
-mod apply;
+pub(crate) mod apply;
 mod compose;
 mod multi_bit_permutation;
 mod secureapplyinv;
-mod shuffle;
 
 use ipa_macros::Step;
 
@@ -20,29 +20,34 @@ use crate::{
 
 #[derive(Step)]
 pub(crate) enum SortStep {
+    #[step(child = crate::protocol::modulus_conversion::convert_shares::ConvertSharesStep)]
     ModulusConversion,
+    #[step(child = crate::protocol::basics::sum_of_product::malicious::Step)]
+    #[step(child = crate::protocol::step::BitOpStep)]
     BitPermutation,
+    #[step(child = ComposeStep)]
     Compose,
+    #[step(child = ShuffleRevealPermutationStep)]
     ShuffleRevealPermutation,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = SortStep)]
+    #[step(child = crate::protocol::step::IpaProtocolStep)]
     SortKeys,
     #[dynamic]
+    #[step(child = ApplyInvStep)]
     MultiApplyInv(u32),
 }
 
-#[derive(Step, Clone, Copy)]
-pub(crate) enum ShuffleStep {
-    Shuffle1,
-    Shuffle2,
-    Shuffle3,
-}
-
 #[derive(Step)]
 pub(crate) enum ApplyInvStep {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::shuffle::ShuffleStep)]
     ShuffleInputs,
 }
 
 #[derive(Step)]
 pub(crate) enum ComposeStep {
+    #[step(child = crate::protocol::shuffle::ShuffleStep)]
     UnshuffleRho,
 }
 
@@ -50,6 +55,8 @@ pub(crate) enum ComposeStep {
 pub(crate) enum ShuffleRevealPermutationStep {
     Generate,
     Reveal,
+    #[step(child = crate::protocol::shuffle::ShuffleStep)]
+    #[step(child = crate::protocol::context::validator::Step)]
     Shuffle,
 }
 
@@ -130,7 +137,7 @@ where
     // 5: . | . | . | . | . | + | . | -
     // 6: . | . | . | . | . | . | + | -
     // 7: . | . | . | . | . | . | . | +
-    Ok(BitDecomposed::decompose(1 << num_bits, |i| {
+    Ok(BitDecomposed::new((0..1 << num_bits).map(|i| {
         // Small optimization: skip the blank area and start with the first "+".
         let mut check = precomputed_combinations[i].clone();
         for (j, combination) in precomputed_combinations.iter().enumerate().skip(i + 1) {
@@ -143,7 +150,7 @@ where
             }
         }
         check
-    }))
+    })))
 }
 
 //
@@ -260,4 +267,9 @@ mod test {
         )
         .await;
     }
+
+    #[tokio::test]
+    async fn eight_bit_onehot() {
+        check_onehot(8).await;
+    }
 }
@@ -0,0 +1,474 @@
+use std::iter::{repeat, zip};
+
+use futures::future::try_join;
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::{Field, Gf2},
+    protocol::{
+        context::{
+            Context, ScopeCheck, ScopedValidation, UpgradableContext, UpgradedContext, Validator,
+        },
+        shuffle::{get_two_of_three_random_permutations, shuffle_shares},
+        sort::generate_permutation::RevealedAndRandomPermutations,
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::{
+        replicated::semi_honest::AdditiveShare as Replicated, BitDecomposed,
+        Linear as LinearSecretSharing, LinearRefOps, SharedValue,
+    },
+};
+
+#[derive(Step)]
+pub(crate) enum Step {
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::context::malicious::UpgradeStep)]
+    #[step(child = crate::protocol::context::semi_honest::UpgradeStep)]
+    UpgradeSortKeys,
+    GenerateShuffle,
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::shuffle::ShuffleStep)]
+    ShuffleSortKeys,
+    #[dynamic(4)]
+    #[step(child = RoundStep)]
+    Rounds(usize),
+}
+
+#[derive(Step)]
+pub(crate) enum RoundStep {
+    #[dynamic]
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = CompareStep)]
+    Round(usize),
+}
+
+#[derive(Step)]
+pub(crate) enum CompareStep {
+    #[step(child = LessThanStep)]
+    Compare,
+    RevealComparison,
+}
+
+#[derive(Step)]
+pub(crate) enum LessThanStep {
+    #[step(child = crate::protocol::basics::mul::malicious::Step)]
+    CompareBits,
+    #[dynamic(6)]
+    #[step(child = MergeStep)]
+    MergeDepth(usize),
+}
+
+#[derive(Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
+pub(crate) enum MergeStep {
+    MergeLessThan,
+    MergeEqual,
+}
+
+/// The maximum number of rounds of the quicksort, 64 for each of the `Rounds` steps.
+const MAX_ROUNDS: usize = 256;
+
+/// Generates a sort permutation from boolean shares of the sort keys, shuffles and reveals it.
+/// The output has the same form as [`generate_permutation_and_reveal_shuffled`] and can be
+/// applied with [`apply_sort_permutation`].
+///
+/// Unlike the radix sort, this never converts the keys to a prime field. It follows the
+/// shuffle-then-sort approach from "Practically Efficient Multi-party Sorting Protocols from
+/// Comparison Sort Algorithms" by K. Hamada, R. Kikuchi, D. Ikarashi, K. Chida and K. Takahashi:
+/// 1. The index of every key is appended to it as its least significant bits, so that all keys
+///    are distinct and ties are broken by the original order, which keeps the sort stable.
+/// 2. The keys are shuffled with the same random permutations that are returned for applying the
+///    sort later.
+/// 3. The shuffled keys are sorted by a quicksort that compares them in MPC and reveals the result
+///    of each comparison. Since the keys are distinct and their order is random, the comparisons
+///    only reveal a uniformly random permutation.
+///
+/// Each round of the quicksort compares every unsorted element to the pivot of its segment, so the
+/// sort needs `O(log n)` rounds on average. Each comparison costs `O(k)` multiplications in
+/// `O(log k)` rounds for `k`-bit keys. In the malicious setting, every round is validated before
+/// its comparisons are revealed. The sort gives up after 256 rounds, which is far more than random
+/// keys need.
+///
+/// [`generate_permutation_and_reveal_shuffled`]: crate::protocol::sort::generate_permutation::generate_permutation_and_reveal_shuffled
+/// [`apply_sort_permutation`]: crate::protocol::sort::apply_sort::apply_sort_permutation
+///
+/// # Errors
+/// Propagates errors from multiplications and reveals, including failed malicious checks.
+/// Returns [`Error::Unsupported`] if the sort does not finish within 256 rounds.
+/// # Panics
+/// If the keys together with the index of the last record do not fit in
+/// [`BitDecomposed`], or if there are more than `u32::MAX` keys.
+#[tracing::instrument(name = "quicksort", skip_all)]
+pub async fn quicksort_and_reveal_shuffled<C, S>(
+    sh_ctx: C,
+    sort_keys: Vec<BitDecomposed<Replicated<Gf2>>>,
+) -> Result<RevealedAndRandomPermutations, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = S>,
+    S: LinearSecretSharing<Gf2> + BasicProtocols<C::UpgradedContext<Gf2>, Gf2> + 'static,
+    for<'r> &'r S: LinearRefOps<'r, S, Gf2>,
+{
+    let count = sort_keys.len();
+    let index_bits = usize::BITS - count.saturating_sub(1).leading_zeros();
+
+    // The quicksort checks each round on its own, so the validator only provides `r`.
+    let validator = sh_ctx.validator::<Gf2>();
+    let m_ctx = validator.context();
+
+    let (upgrade_ctx, check) = m_ctx.narrow(&Step::UpgradeSortKeys).scoped_validation();
+    let sort_keys: Vec<BitDecomposed<S>> = upgrade_ctx.upgrade(sort_keys).await?;
+    check.validate().await?;
+
+    let sort_keys = sort_keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| {
+            BitDecomposed::new(
+                (0..index_bits)
+                    .map(|b| {
+                        m_ctx.share_known_value(if (i >> b) & 1 == 1 {
+                            Gf2::ONE
+                        } else {
+                            Gf2::ZERO
+                        })
+                    })
+                    .chain(key),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let randoms_for_shuffle = get_two_of_three_random_permutations(
+        count.try_into().unwrap(),
+        m_ctx.narrow(&Step::GenerateShuffle).prss_rng(),
+    );
+    let shuffled_keys = shuffle_shares(
+        sort_keys,
+        (&randoms_for_shuffle.0, &randoms_for_shuffle.1),
+        m_ctx.narrow(&Step::ShuffleSortKeys),
+    )
+    .await?;
+
+    let mut order = (0..count).collect::<Vec<_>>();
+    let mut segments = if count > 1 { vec![(0, count)] } else { vec![] };
+    let mut round = 0;
+    while !segments.is_empty() {
+        let pairs = segments
+            .iter()
+            .flat_map(|&(start, end)| {
+                let pivot = order[start];
+                order[start + 1..end].iter().map(move |&i| (i, pivot))
+            })
+            .collect::<Vec<_>>();
+        if round == MAX_ROUNDS {
+            return Err(Error::Unsupported(format!(
+                "quicksort did not finish in {MAX_ROUNDS} rounds"
+            )));
+        }
+        // Dynamic steps only go up to 64, which the quicksort can exceed on large inputs.
+        let round_ctx = m_ctx
+            .narrow(&Step::Rounds(round / 64))
+            .narrow(&RoundStep::Round(round % 64));
+        let less_than_pivot = compare_to_pivots(round_ctx, &shuffled_keys, &pairs).await?;
+
+        let mut next_segments = Vec::new();
+        let mut comparisons = less_than_pivot.into_iter();
+        for (start, end) in segments {
+            let pivot = order[start];
+            let (lower, upper): (Vec<_>, Vec<_>) = order[start + 1..end]
+                .iter()
+                .partition(|_| comparisons.next().unwrap());
+            let split = start + lower.len();
+            order[start..split].copy_from_slice(&lower);
+            order[split] = pivot;
+            order[split + 1..end].copy_from_slice(&upper);
+
+            if lower.len() > 1 {
+                next_segments.push((start, split));
+            }
+            if upper.len() > 1 {
+                next_segments.push((split + 1, end));
+            }
+        }
+        segments = next_segments;
+        round += 1;
+    }
+
+    let mut revealed = vec![0; count];
+    for (rank, i) in order.into_iter().enumerate() {
+        revealed[i] = rank.try_into().unwrap();
+    }
+
+    Ok(RevealedAndRandomPermutations {
+        revealed,
+        randoms_for_shuffle,
+    })
+}
+
+/// Returns, for every pair `(i, pivot)`, whether `keys[i] < keys[pivot]`.
+async fn compare_to_pivots<C, S>(
+    ctx: C,
+    keys: &[BitDecomposed<S>],
+    pairs: &[(usize, usize)],
+) -> Result<Vec<bool>, Error>
+where
+    C: ScopedValidation,
+    S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
+    for<'r> &'r S: LinearRefOps<'r, S, Gf2>,
+{
+    let (ctx, check) = ctx.scoped_validation();
+    let pairs = pairs
+        .iter()
+        .map(|&(x, pivot)| (&keys[x][..], &keys[pivot][..]))
+        .collect::<Vec<_>>();
+    let less_than = less_than(ctx.narrow(&CompareStep::Compare), &pairs).await?;
+    check.validate().await?;
+
+    let reveal_ctx = ctx
+        .narrow(&CompareStep::RevealComparison)
+        .set_total_records(pairs.len());
+    let revealed = ctx
+        .parallel_join(
+            zip(repeat(reveal_ctx), less_than.iter())
+                .enumerate()
+                .map(|(i, (ctx, bit))| async move { bit.reveal(ctx, RecordId::from(i)).await }),
+        )
+        .await?;
+    Ok(revealed.into_iter().map(|bit| bit == Gf2::ONE).collect())
+}
+
+/// Compares every pair `([x], [y])` of equally long keys, and returns `1` iff `x < y`.
+///
+/// Every bit gives a pair of `x[i] < y[i]` and `x[i] == y[i]`. The pairs are merged in a tree,
+/// where the higher half decides the result unless it is equal:
+/// `lt = lt_high + eq_high * lt_low` and `eq = eq_high * eq_low`.
+/// For `k`-bit inputs, this costs fewer than `3k` multiplications in `1 + ⌈log2(k)⌉` rounds.
+/// The bits of all comparisons at the same depth are multiplied under one step, with record
+/// `i * width + j` for bit (or merge) `j` of comparison `i`.
+async fn less_than<C, S>(ctx: C, pairs: &[(&[S], &[S])]) -> Result<Vec<S>, Error>
+where
+    C: Context,
+    S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
+    for<'r> &'r S: LinearRefOps<'r, S, Gf2>,
+{
+    let width = pairs.first().map_or(0, |(x, _)| x.len());
+    debug_assert!(pairs
+        .iter()
+        .all(|(x, y)| x.len() == width && y.len() == width));
+    if width == 0 {
+        return Ok(vec![S::ZERO; pairs.len()]);
+    }
+    let one = S::share_known_value(&ctx, Gf2::ONE);
+
+    let bits_ctx = ctx
+        .narrow(&LessThanStep::CompareBits)
+        .set_total_records(pairs.len() * width);
+    let bits = ctx
+        .parallel_join(pairs.iter().enumerate().flat_map(|(i, (x, y))| {
+            let (bits_ctx, one) = (&bits_ctx, &one);
+            zip(x.iter(), y.iter()).enumerate().map(move |(j, (x, y))| {
+                let eq = one.clone() + x + y;
+                async move {
+                    let xy = x
+                        .multiply(y, bits_ctx.clone(), RecordId::from(i * width + j))
+                        .await?;
+                    Ok::<_, Error>((y - &xy, eq))
+                }
+            })
+        }))
+        .await?;
+    let mut pairs = bits.chunks(width).map(<[_]>::to_vec).collect::<Vec<_>>();
+
+    let mut width = width;
+    let mut depth = 0;
+    while width > 1 {
+        let merges = width / 2;
+        let depth_ctx = ctx.narrow(&LessThanStep::MergeDepth(depth));
+        let (lt_ctx, eq_ctx) = (
+            depth_ctx
+                .narrow(&MergeStep::MergeLessThan)
+                .set_total_records(pairs.len() * merges),
+            depth_ctx
+                .narrow(&MergeStep::MergeEqual)
+                .set_total_records(pairs.len() * merges),
+        );
+        let merged = ctx
+            .parallel_join(pairs.iter().enumerate().flat_map(|(i, bits)| {
+                let (lt_ctx, eq_ctx) = (&lt_ctx, &eq_ctx);
+                bits.chunks_exact(2).enumerate().map(move |(j, pair)| {
+                    let [(lt_low, eq_low), (lt_high, eq_high)] = pair else {
+                        unreachable!()
+                    };
+                    let record_id = RecordId::from(i * merges + j);
+                    async move {
+                        let (lt, eq) = try_join(
+                            eq_high.multiply(lt_low, lt_ctx.clone(), record_id),
+                            eq_high.multiply(eq_low, eq_ctx.clone(), record_id),
+                        )
+                        .await?;
+                        Ok::<_, Error>((lt + lt_high, eq))
+                    }
+                })
+            }))
+            .await?;
+        pairs = zip(pairs, merged.chunks(merges))
+            .map(|(bits, merged)| {
+                let mut merged = merged.to_vec();
+                if width % 2 == 1 {
+                    merged.push(bits[width - 1].clone());
+                }
+                merged
+            })
+            .collect();
+        width = (width + 1) / 2;
+        depth += 1;
+    }
+
+    Ok(pairs
+        .into_iter()
+        .map(|mut bits| bits.pop().unwrap().0)
+        .collect())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::quicksort_and_reveal_shuffled;
+    use crate::{
+        ff::{Field, Fp31, GaloisField, Gf2, Gf3Bit},
+        protocol::{
+            basics::{BasicProtocols, Reshare},
+            context::{
+                UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext, Validator,
+            },
+            sort::apply_sort::apply_sort_permutation,
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::{
+            replicated::{malicious::DowngradeMalicious, semi_honest::AdditiveShare as Replicated},
+            BitDecomposed, Linear as LinearSecretSharing, LinearRefOps, SharedValue,
+        },
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    /// Sorts the keys and applies the permutation to the index of each record.
+    async fn sort_indices<C, S, SB>(
+        ctx: C,
+        records: Vec<(BitDecomposed<Replicated<Gf2>>, Replicated<Fp31>)>,
+    ) -> Vec<Replicated<Fp31>>
+    where
+        C: UpgradableContext,
+        C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = SB>,
+        SB: LinearSecretSharing<Gf2> + BasicProtocols<C::UpgradedContext<Gf2>, Gf2> + 'static,
+        for<'r> &'r SB: LinearRefOps<'r, SB, Gf2>,
+        C::UpgradedContext<Fp31>: UpgradedContext<Fp31, Share = S>,
+        S: LinearSecretSharing<Fp31> + Reshare<C::UpgradedContext<Fp31>, RecordId>,
+        Vec<S>: DowngradeMalicious<Target = Vec<Replicated<Fp31>>>,
+        for<'u> UpgradeContext<'u, C::UpgradedContext<Fp31>, Fp31>:
+            UpgradeToMalicious<'u, Vec<Replicated<Fp31>>, Vec<S>>,
+    {
+        let (keys, indices): (Vec<_>, Vec<_>) = records.into_iter().unzip();
+        let permutation = quicksort_and_reveal_shuffled(ctx.narrow("sort"), keys)
+            .await
+            .unwrap();
+
+        let validator = ctx.narrow("apply").validator::<Fp31>();
+        let m_ctx = validator.context();
+        let indices = m_ctx.upgrade(indices).await.unwrap();
+        let sorted = apply_sort_permutation(m_ctx, indices, &permutation)
+            .await
+            .unwrap();
+        validator.validate(sorted).await.unwrap()
+    }
+
+    /// Generates 3-bit keys, which have many duplicates, and the order in which a stable sort
+    /// puts them.
+    fn inputs(count: usize) -> (Vec<(BitDecomposed<Gf2>, Fp31)>, Vec<u128>) {
+        let mut rng = thread_rng();
+        let keys = (0..count)
+            .map(|_| Gf3Bit::truncate_from(rng.gen_range(0..8_u128)))
+            .collect::<Vec<_>>();
+        let records = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                (
+                    BitDecomposed::decompose(Gf3Bit::BITS, |b| Gf2::truncate_from(key[b])),
+                    Fp31::truncate_from(u128::try_from(i).unwrap()),
+                )
+            })
+            .collect();
+        let mut expected = (0..count).collect::<Vec<_>>();
+        expected.sort_by_key(|&i| keys[i].as_u128());
+        (
+            records,
+            expected
+                .into_iter()
+                .map(|i| i.try_into().unwrap())
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        const COUNT: usize = 25;
+        let world = TestWorld::default();
+        let (records, expected) = inputs(COUNT);
+
+        let result = world
+            .semi_honest(records.into_iter(), |ctx, records| async move {
+                sort_indices(ctx, records).await
+            })
+            .await;
+        assert_eq!(
+            result
+                .reconstruct()
+                .iter()
+                .map(Field::as_u128)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        const COUNT: usize = 25;
+        let world = TestWorld::default();
+        let (records, expected) = inputs(COUNT);
+
+        let result = world
+            .malicious(records.into_iter(), |ctx, records| async move {
+                sort_indices(ctx, records).await
+            })
+            .await;
+        assert_eq!(
+            result
+                .reconstruct()
+                .iter()
+                .map(Field::as_u128)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn single_record() {
+        let world = TestWorld::default();
+        let (records, expected) = inputs(1);
+
+        let result = world
+            .semi_honest(records.into_iter(), |ctx, records| async move {
+                sort_indices(ctx, records).await
+            })
+            .await;
+        assert_eq!(
+            result
+                .reconstruct()
+                .iter()
+                .map(Field::as_u128)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
@@ -2,16 +2,14 @@ use crate::{
     error::Error,
     protocol::{
         basics::Reshare,
-        context::Context,
-        sort::{
-            apply::apply_inv, apply_sort::shuffle_shares as shuffle_vectors,
-            ApplyInvStep::ShuffleInputs,
-        },
+        context::ScopedValidation,
+        shuffle::shuffle_shares as shuffle_vectors,
+        sort::{apply::apply_inv, ApplyInvStep::ShuffleInputs},
         RecordId,
     },
 };
 
-pub async fn secureapplyinv_multi<C: Context, I: Reshare<C, RecordId> + Send + Sync>(
+pub async fn secureapplyinv_multi<C: ScopedValidation, I: Reshare<C, RecordId> + Send + Sync>(
     ctx: C,
     input: Vec<I>,
     random_permutations_for_shuffle: (&[u32], &[u32]),
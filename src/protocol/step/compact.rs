@@ -1,17 +1,33 @@
-use std::fmt::{Debug, Display, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    sync::RwLock,
+};
 
 use ipa_macros::Gate;
+use once_cell::sync::Lazy;
 
-use super::StepNarrow;
+use super::{CompactSteps, StepNarrow};
 use crate::helpers::{prss_protocol::PrssExchangeStep, query::QueryType};
 
+/// A gate that is represented by a number rather than by its path of steps.
+///
+/// States are numbered when they are first narrowed to, after checking that the step is declared
+/// as a child of the current one with `#[step(child = ...)]`. The steps narrowed from the root are
+/// declared on this struct. The numbers are only meaningful within a process; helpers exchange
+/// gates by their string representation.
 #[derive(Gate, Clone, Hash, PartialEq, Eq, Default)]
+#[step(child = crate::protocol::ipa::Step)]
+#[step(child = crate::protocol::aggregation::Step)]
+#[step(child = crate::protocol::prf_sharding::Step)]
+#[step(child = crate::protocol::prf_sharding::feature_label_dot_product::Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
 #[cfg_attr(
     feature = "enable-serde",
     derive(serde::Deserialize),
     serde(from = "&str")
 )]
-pub struct Compact(pub u16);
+pub struct Compact(pub u32);
 
 // serde::Deserialize requires From<&str> implementation
 impl From<&str> for Compact {
@@ -33,10 +49,177 @@ impl Debug for Compact {
     }
 }
 
-const ROOT_STATE: u16 = 0;
-const PRSS_EXCHANGE_STATE: u16 = 65531;
-const QUERY_TYPE_SEMIHONEST_STATE: u16 = 65533;
-const QUERY_TYPE_MALICIOUS_STATE: u16 = 65532;
+/// A state of `Compact` gate that has been narrowed to.
+struct State {
+    parent: u32,
+    /// Declarations of the step enum and index of the step that was narrowed to, `None` for the
+    /// root.
+    step: Option<(&'static CompactSteps, u16)>,
+    /// String representation of the state, e.g. "a/b/c".
+    path: &'static str,
+    children: HashMap<&'static str, u32>,
+}
+
+/// All states that have been narrowed to, indexed by their number. Paths are leaked, so that
+/// `as_ref` can return them; there is one per gate the process has used.
+static STATES: Lazy<RwLock<Vec<State>>> = Lazy::new(|| {
+    RwLock::new(vec![State {
+        parent: ROOT_STATE,
+        step: None,
+        path: "",
+        children: HashMap::new(),
+    }])
+});
+
+impl Compact {
+    /// Narrows to the child state that is reached by the step with index `step` of the step enum
+    /// declared by `steps`. This is called by the `StepNarrow` implementations generated by
+    /// `derive(Step)`.
+    ///
+    /// ## Panics
+    /// If the step enum is not declared as a child of the current step.
+    #[must_use]
+    pub(crate) fn narrow_to(&self, steps: &'static CompactSteps, step: u16) -> Self {
+        let name = steps.names[usize::from(step)];
+        let parent = usize::try_from(self.0).unwrap();
+        if let Some(&child) = STATES
+            .read()
+            .unwrap()
+            .get(parent)
+            .and_then(|state| state.children.get(name))
+        {
+            return Self(child);
+        }
+
+        let mut states = STATES.write().unwrap();
+        if parent < states.len() {
+            if let Some(&child) = states[parent].children.get(name) {
+                return Self(child);
+            }
+            if Self::is_declared(&states, self.0, steps) {
+                return Self(Self::insert(&mut states, self.0, (steps, step)));
+            }
+        }
+        drop(states);
+        panic!("Invalid state transition: {} -> {name}", self.as_ref());
+    }
+
+    #[must_use]
+    pub fn deserialize(s: &str) -> Compact {
+        let mut state = ROOT_STATE;
+        for name in s.split('/') {
+            match Self::child(state, name) {
+                Some(child) => state = child,
+                None => return Self(static_deserialize_state_map(s)),
+            }
+        }
+        Self(state)
+    }
+
+    /// Returns the child of `parent` that is reached by narrowing the step named `name`, if the
+    /// step graph declares it.
+    fn child(parent: u32, name: &str) -> Option<u32> {
+        let index = usize::try_from(parent).unwrap();
+        if let Some(&child) = STATES.read().unwrap()[index].children.get(name) {
+            return Some(child);
+        }
+
+        let mut states = STATES.write().unwrap();
+        if let Some(&child) = states[index].children.get(name) {
+            return Some(child);
+        }
+        let step = Self::declared_children(&states, parent)
+            .into_iter()
+            .find_map(|steps| {
+                steps
+                    .names
+                    .iter()
+                    .position(|&step| step == name)
+                    .map(|step| (steps, u16::try_from(step).unwrap()))
+            })?;
+        Some(Self::insert(&mut states, parent, step))
+    }
+
+    /// Returns whether the declarations allow narrowing `state` with a step of `steps`.
+    fn is_declared(states: &[State], state: u32, steps: &'static CompactSteps) -> bool {
+        Self::declared_children(states, state)
+            .into_iter()
+            .any(|child| std::ptr::eq(child, steps))
+    }
+
+    /// Returns the step enums that `state` is declared to narrow to. A transparent step has the
+    /// children of its parent, except for its own enum.
+    fn declared_children(states: &[State], state: u32) -> Vec<&'static CompactSteps> {
+        let state = &states[usize::try_from(state).unwrap()];
+        match state.step {
+            None => Self::ROOTS.iter().map(|steps| steps()).collect(),
+            Some((steps, step)) => {
+                let step = usize::from(step);
+                let mut children = steps.children[step]
+                    .iter()
+                    .map(|child| child())
+                    .collect::<Vec<_>>();
+                if steps.transparent[step] {
+                    children.extend(
+                        Self::declared_children(states, state.parent)
+                            .into_iter()
+                            .filter(|&child| !std::ptr::eq(child, steps)),
+                    );
+                }
+                children
+            }
+        }
+    }
+
+    /// Numbers a new child of `parent`.
+    ///
+    /// ## Panics
+    /// If the numbers run into the static states.
+    fn insert(
+        states: &mut Vec<State>,
+        parent: u32,
+        (steps, step): (&'static CompactSteps, u16),
+    ) -> u32 {
+        let id = u32::try_from(states.len()).unwrap();
+        assert!(
+            id < FIRST_STATIC_STATE,
+            "Compact gate ran out of states to number"
+        );
+        let name = steps.names[usize::from(step)];
+        let index = usize::try_from(parent).unwrap();
+        let path = if parent == ROOT_STATE {
+            name
+        } else {
+            Box::leak(format!("{}/{name}", states[index].path).into_boxed_str())
+        };
+        states[index].children.insert(name, id);
+        states.push(State {
+            parent,
+            step: Some((steps, step)),
+            path,
+            children: HashMap::new(),
+        });
+        id
+    }
+}
+
+impl AsRef<str> for Compact {
+    fn as_ref(&self) -> &str {
+        if self.0 != ROOT_STATE {
+            if let Some(state) = STATES.read().unwrap().get(usize::try_from(self.0).unwrap()) {
+                return state.path;
+            }
+        }
+        static_reverse_state_map(self.0)
+    }
+}
+
+/// The lowest state reserved for the static states below.
+const FIRST_STATIC_STATE: u32 = u32::MAX - 7;
+const ROOT_STATE: u32 = 0;
+const PRSS_EXCHANGE_STATE: u32 = u32::MAX - 4;
+const QUERY_TYPE_SEMIHONEST_STATE: u32 = u32::MAX - 2;
+const QUERY_TYPE_MALICIOUS_STATE: u32 = u32::MAX - 3;
 
 impl StepNarrow<QueryType> for Compact {
     fn narrow(&self, step: &QueryType) -> Self {
@@ -55,7 +238,7 @@ impl StepNarrow<PrssExchangeStep> for Compact {
 }
 
 // Reverse of `static_state_map` for `Compact::as_ref()`
-fn static_reverse_state_map(state: u16) -> &'static str {
+fn static_reverse_state_map(state: u32) -> &'static str {
     match state {
         ROOT_STATE => "run-0",
         QUERY_TYPE_SEMIHONEST_STATE => QueryType::SEMIHONEST_IPA_STR,
@@ -65,7 +248,7 @@ fn static_reverse_state_map(state: u16) -> &'static str {
     }
 }
 
-fn static_deserialize_state_map(s: &str) -> u16 {
+fn static_deserialize_state_map(s: &str) -> u32 {
     if s == "run-0" {
         return ROOT_STATE;
     } else if s == QueryType::SEMIHONEST_IPA_STR {
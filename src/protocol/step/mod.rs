@@ -35,6 +35,28 @@ pub trait StepNarrow<S: Step + ?Sized> {
 /// hierarchy of steps at each layer into a unique identifier.
 pub trait Step: AsRef<str> {}
 
+/// The steps of a step enum and the step enums each of them is declared to narrow to, as generated
+/// by `derive(Step)`. `Compact` gate checks every narrow against these declarations.
+#[cfg(feature = "compact-gate")]
+pub trait CompactStep: Step {
+    fn steps() -> &'static CompactSteps;
+}
+
+#[cfg(feature = "compact-gate")]
+pub type CompactStepsFn = fn() -> &'static CompactSteps;
+
+/// Declarations of a step enum, indexed by the number of the step in the enum. Each enum has a
+/// single instance, so enums are identified by its address.
+#[cfg(feature = "compact-gate")]
+pub struct CompactSteps {
+    /// Names of the steps, e.g. `bit0..bit63` for a `#[dynamic] Bit` variant.
+    pub names: &'static [&'static str],
+    /// The step enums declared as children of each step.
+    pub children: &'static [&'static [CompactStepsFn]],
+    /// Whether each step is `#[step(transparent)]`.
+    pub transparent: &'static [bool],
+}
+
 // In test code, allow a string (or string reference) to be used as a `Step`.
 #[cfg(any(feature = "test-fixture", debug_assertions))]
 impl Step for String {}
@@ -51,9 +73,13 @@ impl Step for str {}
 ///
 /// This is a temporary solution for narrowing contexts until the infra is
 /// updated with a new step scheme.
+///
+/// One-hot encoding an 8-bit breakdown key narrows to a step for every one of its
+/// 256 values, which is what bounds the number of steps.
 #[derive(Step)]
+#[step(child = crate::protocol::basics::mul::malicious::Step)]
 pub enum BitOpStep {
-    #[dynamic]
+    #[dynamic(256)]
     Bit(usize),
 }
 
@@ -80,5 +106,7 @@ impl From<usize> for BitOpStep {
 pub(crate) enum IpaProtocolStep {
     /// Sort shares by the match key
     #[dynamic]
+    #[step(child = crate::protocol::context::validator::Step)]
+    #[step(child = crate::protocol::sort::SortStep)]
     Sort(usize),
 }
@@ -0,0 +1,213 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    ff::FieldType,
+    helpers::query::{QueryConfig, QueryType},
+    protocol::{BreakdownKey, MatchKey, QueryId},
+    secret_sharing::SharedValue,
+    sync::Mutex,
+};
+
+/// Number of fields other than match key and breakdown key that IPA keeps per input row
+/// (trigger bit, trigger value, timestamp and the helper bits computed during attribution).
+const IPA_EXTRA_VALUES_PER_ROW: u64 = 4;
+
+/// Protocols keep several copies of the input around (sort permutations, reshared rows,
+/// intermediate attribution results), so the raw input footprint is scaled by this factor.
+const WORKING_SET_FACTOR: u64 = 4;
+
+/// How long a report collector is asked to wait before retrying a query that was rejected
+/// because this helper is short on memory. Queries take minutes to hours to complete, so there is
+/// no point in retrying sooner.
+const BUSY_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Limits that [`Processor`] applies to new queries before accepting them. The number of queries
+/// each report collector may run is limited by the query router, see [`QueryRateLimitConfig`].
+///
+/// [`Processor`]: crate::query::QueryProcessor
+/// [`QueryRateLimitConfig`]: crate::config::QueryRateLimitConfig
+#[derive(Clone, Debug, Default)]
+pub struct AdmissionConfig {
+    /// Memory budget for running queries. Queries whose estimated footprint does not fit into
+    /// the remaining budget are rejected. `None` disables the memory check. Completed queries
+    /// count towards it until their results are collected.
+    pub max_memory_bytes: Option<u64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AdmissionError {
+    #[error(
+        "query requires an estimated {required} bytes of memory, but only {available} bytes are available"
+    )]
+    InsufficientMemory { required: u64, available: u64 },
+    #[error("query requires an estimated {required} bytes of memory, which exceeds the helper limit of {limit} bytes")]
+    QueryTooLarge { required: u64, limit: u64 },
+}
+
+impl AdmissionError {
+    /// Returns how long the caller should wait before submitting the same query again. `None`
+    /// means that retrying will not help.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::InsufficientMemory { .. } => Some(BUSY_RETRY_AFTER),
+            Self::QueryTooLarge { .. } => None,
+        }
+    }
+}
+
+/// Decides whether a new query can be accepted given the queries that are already running.
+pub struct Admission {
+    config: AdmissionConfig,
+    reserved: Mutex<HashMap<QueryId, u64>>,
+}
+
+impl Admission {
+    #[must_use]
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            reserved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admits query `query_id` and reserves memory for it. `is_active` reports whether a query
+    /// previously admitted is still known to the processor; reservations for queries that are
+    /// gone are released before the check.
+    ///
+    /// ## Errors
+    /// If accepting this query would exceed the memory budget.
+    ///
+    /// ## Panics
+    /// If the reservation mutex is poisoned.
+    pub fn admit<F: Fn(&QueryId) -> bool>(
+        &self,
+        query_id: QueryId,
+        config: &QueryConfig,
+        is_active: F,
+    ) -> Result<(), AdmissionError> {
+        let mut reserved = self.reserved.lock().unwrap();
+        reserved.retain(|id, _| *id != query_id && is_active(id));
+
+        let required = estimate_memory(config);
+        if let Some(limit) = self.config.max_memory_bytes {
+            if required > limit {
+                return Err(AdmissionError::QueryTooLarge { required, limit });
+            }
+            let available = limit.saturating_sub(reserved.values().sum());
+            if required > available {
+                return Err(AdmissionError::InsufficientMemory {
+                    required,
+                    available,
+                });
+            }
+        }
+
+        reserved.insert(query_id, required);
+        Ok(())
+    }
+}
+
+/// Rough estimate of the peak memory a helper needs to execute the query described by `config`.
+/// It is intentionally conservative: the goal is to keep a helper from accepting work it cannot
+/// finish, not to predict the footprint precisely.
+#[must_use]
+pub fn estimate_memory(config: &QueryConfig) -> u64 {
+    let field_bytes: u64 = match config.field_type {
+        #[cfg(any(test, feature = "weak-field"))]
+        FieldType::Fp31 => 1,
+        FieldType::Fp32BitPrime => 4,
+        FieldType::Fp61BitPrime => 8,
+    };
+    let (values_per_row, malicious) = match config.query_type {
+        #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+        QueryType::TestMultiply => (2, false),
+        QueryType::SemiHonestIpa(_) => (ipa_values_per_row(), false),
+        QueryType::MaliciousIpa(_) => (ipa_values_per_row(), true),
+        QueryType::SemiHonestSparseAggregate(c) => {
+            (aggregate_values_per_row(c.contribution_bits.into()), false)
+        }
+        QueryType::MaliciousSparseAggregate(c) => {
+            (aggregate_values_per_row(c.contribution_bits.into()), true)
+        }
+    };
+
+    // replicated sharing keeps two values per share, malicious sharing doubles that again
+    // to store `r * x`.
+    let share_bytes = 2 * field_bytes * if malicious { 2 } else { 1 };
+
+    u64::from(u32::from(config.size))
+        .saturating_mul(values_per_row)
+        .saturating_mul(share_bytes)
+        .saturating_mul(WORKING_SET_FACTOR)
+}
+
+fn ipa_values_per_row() -> u64 {
+    u64::from(MatchKey::BITS + BreakdownKey::BITS) + IPA_EXTRA_VALUES_PER_ROW
+}
+
+fn aggregate_values_per_row(contribution_bits: u32) -> u64 {
+    u64::from(contribution_bits + BreakdownKey::BITS)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+    use crate::helpers::query::{IpaQueryConfig, SparseAggregateQueryConfig};
+
+    fn ipa_config(size: u32) -> QueryConfig {
+        QueryConfig::new(
+            QueryType::MaliciousIpa(IpaQueryConfig::default()),
+            FieldType::Fp32BitPrime,
+            size,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn malicious_costs_more() {
+        let semi_honest = QueryConfig::new(
+            QueryType::SemiHonestIpa(IpaQueryConfig::default()),
+            FieldType::Fp32BitPrime,
+            1000,
+        )
+        .unwrap();
+        assert_eq!(
+            2 * estimate_memory(&semi_honest),
+            estimate_memory(&ipa_config(1000))
+        );
+    }
+
+    #[test]
+    fn estimate_grows_with_contribution_bits() {
+        let aggregate = |contribution_bits: u32| {
+            QueryConfig::new(
+                QueryType::SemiHonestSparseAggregate(SparseAggregateQueryConfig {
+                    contribution_bits: contribution_bits.try_into().unwrap(),
+                    num_contributions: 8,
+                }),
+                FieldType::Fp32BitPrime,
+                1000,
+            )
+            .unwrap()
+        };
+        assert!(estimate_memory(&aggregate(8)) < estimate_memory(&aggregate(32)));
+    }
+
+    #[test]
+    fn rejects_large_queries() {
+        let limit = estimate_memory(&ipa_config(1000));
+        let admission = Admission::new(AdmissionConfig {
+            max_memory_bytes: Some(limit),
+        });
+
+        admission
+            .admit(QueryId, &ipa_config(1000), |_| true)
+            .unwrap();
+        let err = admission
+            .admit(QueryId, &ipa_config(1001), |_| true)
+            .unwrap_err();
+        assert!(matches!(err, AdmissionError::QueryTooLarge { .. }));
+        assert!(err.retry_after().is_none());
+    }
+}
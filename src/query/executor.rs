@@ -1,15 +1,18 @@
 use std::{
     fmt::Debug,
     future::{ready, Future},
+    iter,
+    num::NonZeroUsize,
     pin::Pin,
     sync::Arc,
+    time::Instant,
 };
 
 use ::tokio::sync::oneshot;
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt};
 use generic_array::GenericArray;
-use rand::rngs::StdRng;
-use rand_core::SeedableRng;
+use rand::rngs::{OsRng, StdRng};
+use rand_core::{RngCore, SeedableRng};
 #[cfg(all(feature = "shuttle", test))]
 use shuttle::future as tokio;
 use typenum::Unsigned;
@@ -17,8 +20,9 @@ use typenum::Unsigned;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
 use crate::query::runner::execute_test_multiply;
 use crate::{
-    ff::{FieldType, Fp32BitPrime, Serializable},
+    ff::{FieldType, Fp32BitPrime, Fp61BitPrime, Serializable},
     helpers::{
+        capture::{Capture, PrssSeed},
         negotiate_prss,
         query::{QueryConfig, QueryType},
         BodyStream, Gateway,
@@ -33,56 +37,122 @@ use crate::{
         runner::{IpaQuery, QueryResult, SparseAggregateQuery},
         state::RunningQuery,
     },
+    telemetry::{
+        labels::QUERY_TYPE,
+        metrics::{QUERIES_STARTED, QUERY_LATENCY},
+    },
 };
 
 pub trait Result: Send + Debug {
     fn into_bytes(self: Box<Self>) -> Vec<u8>;
+
+    /// Serializes the result lazily, `rows_per_chunk` rows at a time, so it can be streamed to
+    /// the report collector without holding the whole serialized result in memory.
+    ///
+    /// The default implementation serializes the entire result into a single chunk.
+    #[must_use]
+    fn into_chunks(
+        self: Box<Self>,
+        rows_per_chunk: NonZeroUsize,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        let _ = rows_per_chunk;
+        Box::new(iter::once(self.into_bytes()))
+    }
+}
+
+/// Serializes `rows` back to back.
+fn serialize_rows<T: Serializable>(rows: impl ExactSizeIterator<Item = T>) -> Vec<u8> {
+    let mut r = vec![0u8; rows.len() * T::Size::USIZE];
+    for (i, row) in rows.enumerate() {
+        row.serialize(GenericArray::from_mut_slice(
+            &mut r[(i * T::Size::USIZE)..((i + 1) * T::Size::USIZE)],
+        ));
+    }
+
+    r
 }
 
 impl<T> Result for Vec<T>
 where
-    T: Serializable,
+    T: Serializable + Send + 'static,
     Vec<T>: Debug + Send,
 {
     fn into_bytes(self: Box<Self>) -> Vec<u8> {
-        let mut r = vec![0u8; self.len() * T::Size::USIZE];
-        for (i, row) in self.into_iter().enumerate() {
-            row.serialize(GenericArray::from_mut_slice(
-                &mut r[(i * T::Size::USIZE)..((i + 1) * T::Size::USIZE)],
-            ));
-        }
+        serialize_rows(self.into_iter())
+    }
 
-        r
+    fn into_chunks(
+        self: Box<Self>,
+        rows_per_chunk: NonZeroUsize,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        let mut rows = self.into_iter();
+        Box::new(iter::from_fn(move || {
+            let chunk = serialize_rows(rows.by_ref().take(rows_per_chunk.get()));
+            (!chunk.is_empty()).then_some(chunk)
+        }))
     }
 }
 
-#[allow(clippy::too_many_lines)]
 pub fn execute(
     config: QueryConfig,
     key_registry: Arc<KeyRegistry<KeyPair>>,
     gateway: Gateway,
     input: BodyStream,
+) -> RunningQuery {
+    let mut prss_seed = PrssSeed::default();
+    OsRng.fill_bytes(&mut prss_seed);
+    execute_with_prss_seed(config, key_registry, gateway, input, prss_seed)
+}
+
+/// Same as [`execute`], but negotiates PRSS with randomness generated from `prss_seed`. Replaying
+/// a query needs that to make the helper send the same messages again.
+#[allow(clippy::too_many_lines)]
+pub fn execute_with_prss_seed(
+    config: QueryConfig,
+    key_registry: Arc<KeyRegistry<KeyPair>>,
+    gateway: Gateway,
+    input: BodyStream,
+    prss_seed: PrssSeed,
 ) -> RunningQuery {
     match (config.query_type, config.field_type) {
         #[cfg(any(test, feature = "weak-field"))]
-        (QueryType::TestMultiply, FieldType::Fp31) => {
-            do_query(config, gateway, input, |prss, gateway, _config, input| {
+        (QueryType::TestMultiply, FieldType::Fp31) => do_query(
+            config,
+            gateway,
+            input,
+            prss_seed,
+            |prss, gateway, _config, input| {
                 Box::pin(execute_test_multiply::<crate::ff::Fp31>(
                     prss, gateway, input,
                 ))
-            })
-        }
+            },
+        ),
         #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
-        (QueryType::TestMultiply, FieldType::Fp32BitPrime) => {
-            do_query(config, gateway, input, |prss, gateway, _config, input| {
+        (QueryType::TestMultiply, FieldType::Fp32BitPrime) => do_query(
+            config,
+            gateway,
+            input,
+            prss_seed,
+            |prss, gateway, _config, input| {
                 Box::pin(execute_test_multiply::<Fp32BitPrime>(prss, gateway, input))
-            })
-        }
+            },
+        ),
+        #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+        (QueryType::TestMultiply, FieldType::Fp61BitPrime) => do_query(
+            config,
+            gateway,
+            input,
+            prss_seed,
+            |prss, gateway, _config, input| {
+                Box::pin(execute_test_multiply::<Fp61BitPrime>(prss, gateway, input))
+            },
+        ),
         #[cfg(any(test, feature = "weak-field"))]
         (QueryType::SemiHonestIpa(ipa_config), FieldType::Fp31) => do_query(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
@@ -96,6 +166,7 @@ pub fn execute(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
@@ -105,11 +176,26 @@ pub fn execute(
                 )
             },
         ),
+        (QueryType::SemiHonestIpa(ipa_config), FieldType::Fp61BitPrime) => do_query(
+            config,
+            gateway,
+            input,
+            prss_seed,
+            move |prss, gateway, config, input| {
+                let ctx = SemiHonestContext::new(prss, gateway);
+                Box::pin(
+                    IpaQuery::<Fp61BitPrime, _, _>::new(ipa_config, key_registry)
+                        .execute(ctx, config.size, input)
+                        .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                )
+            },
+        ),
         #[cfg(any(test, feature = "weak-field"))]
         (QueryType::MaliciousIpa(ipa_config), FieldType::Fp31) => do_query(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = MaliciousContext::new(prss, gateway);
                 Box::pin(
@@ -123,6 +209,7 @@ pub fn execute(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = MaliciousContext::new(prss, gateway);
                 Box::pin(
@@ -132,11 +219,26 @@ pub fn execute(
                 )
             },
         ),
+        (QueryType::MaliciousIpa(ipa_config), FieldType::Fp61BitPrime) => do_query(
+            config,
+            gateway,
+            input,
+            prss_seed,
+            move |prss, gateway, config, input| {
+                let ctx = MaliciousContext::new(prss, gateway);
+                Box::pin(
+                    IpaQuery::<Fp61BitPrime, _, _>::new(ipa_config, key_registry)
+                        .execute(ctx, config.size, input)
+                        .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                )
+            },
+        ),
         #[cfg(any(test, feature = "weak-field"))]
         (QueryType::SemiHonestSparseAggregate(aggregate_config), FieldType::Fp31) => do_query(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
@@ -154,6 +256,7 @@ pub fn execute(
                 config,
                 gateway,
                 input,
+                prss_seed,
                 move |prss, gateway, config, input| {
                     let ctx = SemiHonestContext::new(prss, gateway);
                     Box::pin(
@@ -167,11 +270,31 @@ pub fn execute(
                 },
             )
         }
+        (QueryType::SemiHonestSparseAggregate(aggregate_config), FieldType::Fp61BitPrime) => {
+            do_query(
+                config,
+                gateway,
+                input,
+                prss_seed,
+                move |prss, gateway, config, input| {
+                    let ctx = SemiHonestContext::new(prss, gateway);
+                    Box::pin(
+                        SparseAggregateQuery::<Fp61BitPrime, _, _>::new(
+                            aggregate_config,
+                            key_registry,
+                        )
+                        .execute(ctx, config.size, input)
+                        .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                    )
+                },
+            )
+        }
         #[cfg(any(test, feature = "weak-field"))]
         (QueryType::MaliciousSparseAggregate(aggregate_config), FieldType::Fp31) => do_query(
             config,
             gateway,
             input,
+            prss_seed,
             move |prss, gateway, config, input| {
                 let ctx = MaliciousContext::new(prss, gateway);
                 Box::pin(
@@ -189,6 +312,7 @@ pub fn execute(
                 config,
                 gateway,
                 input,
+                prss_seed,
                 move |prss, gateway, config, input| {
                     let ctx = MaliciousContext::new(prss, gateway);
                     Box::pin(
@@ -202,6 +326,25 @@ pub fn execute(
                 },
             )
         }
+        (QueryType::MaliciousSparseAggregate(aggregate_config), FieldType::Fp61BitPrime) => {
+            do_query(
+                config,
+                gateway,
+                input,
+                prss_seed,
+                move |prss, gateway, config, input| {
+                    let ctx = MaliciousContext::new(prss, gateway);
+                    Box::pin(
+                        SparseAggregateQuery::<Fp61BitPrime, _, _>::new(
+                            aggregate_config,
+                            key_registry,
+                        )
+                        .execute(ctx, config.size, input)
+                        .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                    )
+                },
+            )
+        }
     }
 }
 
@@ -209,6 +352,7 @@ pub fn do_query<F>(
     config: QueryConfig,
     gateway: Gateway,
     input_stream: BodyStream,
+    prss_seed: PrssSeed,
     query_impl: F,
 ) -> RunningQuery
 where
@@ -222,26 +366,51 @@ where
         + 'static,
 {
     let (tx, rx) = oneshot::channel();
+    let progress = crate::sync::Arc::clone(gateway.progress());
+    progress.expect_steps_for(&config.query_type);
 
     let join_handle = tokio::spawn(async move {
-        // TODO: make it a generic argument for this function
-        let mut rng = StdRng::from_entropy();
+        let query_type = config.query_type.as_ref().to_string();
+        let start = Instant::now();
+        metrics::increment_counter!(QUERIES_STARTED, QUERY_TYPE => query_type.clone());
+
+        let mut input_stream = input_stream;
+        if let Some(capture) = gateway.capture() {
+            capture.record_prss_seed(&prss_seed);
+            input_stream = capture_input(crate::sync::Arc::clone(capture), input_stream);
+        }
+
+        let mut rng = StdRng::from_seed(prss_seed);
         // Negotiate PRSS first
         let step = Gate::default().narrow(&config.query_type);
         let prss = negotiate_prss(&gateway, &step, &mut rng).await.unwrap();
 
-        tx.send(query_impl(&prss, &gateway, &config, input_stream).await)
-            .unwrap();
+        let result = query_impl(&prss, &gateway, &config, input_stream).await;
+        metrics::histogram!(QUERY_LATENCY, start.elapsed(), QUERY_TYPE => query_type);
+
+        tx.send(result).unwrap();
     });
 
     RunningQuery {
         result: rx,
         join_handle,
+        progress,
     }
 }
 
+/// Records every chunk of `input` to `capture` as the query reads it.
+fn capture_input(capture: crate::sync::Arc<Capture>, input: BodyStream) -> BodyStream {
+    BodyStream::from_stream(input.inspect(move |chunk| {
+        if let Ok(chunk) = chunk {
+            capture.record_input(chunk);
+        }
+    }))
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
+    use std::num::NonZeroUsize;
+
     use crate::{
         ff::{Field, Fp31},
         query::ProtocolResult,
@@ -258,4 +427,18 @@ mod tests {
             AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn serialize_result_in_chunks() {
+        let [input, ..] = (0u128..=4).map(Fp31::truncate_from).share();
+        let expected = Box::new(input.clone()).into_bytes();
+        let chunks = Box::new(input)
+            .into_chunks(NonZeroUsize::new(2).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![4, 4, 2],
+            chunks.iter().map(Vec::len).collect::<Vec<_>>()
+        );
+        assert_eq!(expected, chunks.concat());
+    }
 }
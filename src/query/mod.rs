@@ -1,13 +1,19 @@
+mod admission;
 mod completion;
 mod executor;
 mod processor;
+mod progress;
+#[cfg(feature = "in-memory-infra")]
+pub mod replay;
 mod runner;
 mod state;
 
+pub use admission::{estimate_memory, AdmissionConfig, AdmissionError};
 use completion::Handle as CompletionHandle;
 pub use executor::Result as ProtocolResult;
 pub use processor::{
     NewQueryError, PrepareQueryError, Processor as QueryProcessor, QueryCompletionError,
     QueryInputError, QueryStatusError,
 };
+pub use progress::{ProgressTracker, QueryProgress};
 pub use state::QueryStatus;
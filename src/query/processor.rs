@@ -1,7 +1,9 @@
 use std::{
     collections::hash_map::Entry,
     fmt::{Debug, Formatter},
+    path::PathBuf,
     sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use futures::{future::try_join, stream};
@@ -9,15 +11,17 @@ use futures::{future::try_join, stream};
 use crate::{
     error::Error as ProtocolError,
     helpers::{
+        capture::Capture,
         query::{PrepareQuery, QueryConfig, QueryInput},
         Gateway, GatewayConfig, Role, RoleAssignment, Transport, TransportError, TransportImpl,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::QueryId,
     query::{
+        admission::{Admission, AdmissionConfig, AdmissionError},
         executor,
         state::{QueryState, QueryStatus, RemoveQuery, RunningQueries, StateError},
-        CompletionHandle, ProtocolResult,
+        CompletionHandle, ProtocolResult, QueryProgress,
     },
 };
 
@@ -41,6 +45,8 @@ use crate::{
 pub struct Processor {
     queries: RunningQueries,
     key_registry: Arc<KeyRegistry<KeyPair>>,
+    admission: Admission,
+    capture_dir: Option<PathBuf>,
 }
 
 impl Default for Processor {
@@ -48,6 +54,8 @@ impl Default for Processor {
         Self {
             queries: RunningQueries::default(),
             key_registry: Arc::new(KeyRegistry::<KeyPair>::empty()),
+            admission: Admission::new(AdmissionConfig::default()),
+            capture_dir: None,
         }
     }
 }
@@ -58,6 +66,8 @@ pub enum NewQueryError {
     State(#[from] StateError),
     #[error(transparent)]
     Transport(#[from] TransportError),
+    #[error("query rejected: {0}")]
+    Rejected(#[from] AdmissionError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -111,15 +121,56 @@ impl Debug for Processor {
 
 impl Processor {
     #[must_use]
-    pub fn new(key_registry: KeyRegistry<KeyPair>) -> Self {
+    pub fn new(key_registry: KeyRegistry<KeyPair>, admission_config: AdmissionConfig) -> Self {
         Self {
             queries: RunningQueries::default(),
             key_registry: Arc::new(key_registry),
+            admission: Admission::new(admission_config),
+            capture_dir: None,
+        }
+    }
+
+    /// Makes this helper capture the traffic of every query it runs to a file in `dir`, so that
+    /// its side of the query can be replayed later. See [`crate::helpers::capture`].
+    #[must_use]
+    pub fn with_capture_dir(mut self, dir: PathBuf) -> Self {
+        self.capture_dir = Some(dir);
+        self
+    }
+
+    /// Opens the capture for a query this helper is about to run, if captures are enabled.
+    /// A capture that can't be created is logged and skipped, the query runs anyway.
+    fn capture(&self, role: Role, prepare: &PrepareQuery) -> Option<Capture> {
+        let dir = self.capture_dir.as_ref()?;
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = dir.join(format!(
+            "{}-{started}-{}.capture",
+            prepare.query_id,
+            role.as_static_str()
+        ));
+        match Capture::create(&path) {
+            Ok(capture) => {
+                capture.record_query(role, prepare);
+                tracing::info!("capturing {:?} to {}", prepare.query_id, path.display());
+                Some(capture)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "can't capture {:?} to {}: {e}",
+                    prepare.query_id,
+                    path.display()
+                );
+                None
+            }
         }
     }
 
     /// Upon receiving a new query request:
     /// * processor generates new query id
+    /// * checks that this helper has capacity to run it (see [`AdmissionConfig`])
     /// * assigns roles to helpers in the ring. Helper that received new query request becomes `Role::H1` (aka coordinator).
     /// The coordinator is in theory free to choose helpers for `Role::H2` and `Role::H3` arbitrarily (aka followers), however, this is not currently exercised.
     /// * Requests Infra and Network layer to create resources for this query
@@ -128,7 +179,8 @@ impl Processor {
     /// * returns query configuration
     ///
     /// ## Errors
-    /// When other peers failed to acknowledge this query
+    /// When other peers failed to acknowledge this query or this helper does not have enough
+    /// capacity to run it.
     #[allow(clippy::missing_panics_doc)]
     pub async fn new_query(
         &self,
@@ -140,6 +192,10 @@ impl Processor {
         handle.set_state(QueryState::Preparing(req))?;
         let guard = handle.remove_query_on_drop();
 
+        self.admission.admit(query_id, &req, |id| {
+            self.queries.inner.lock().unwrap().contains_key(id)
+        })?;
+
         let id = transport.identity();
         let [right, left] = id.others();
 
@@ -219,12 +275,21 @@ impl Processor {
                         input.query_id, query_id,
                         "received inputs for a different query"
                     );
-                    let gateway = Gateway::new(
+                    let prepare = PrepareQuery {
+                        query_id,
+                        config,
+                        roles: role_assignment,
+                    };
+                    let capture = self.capture(prepare.roles.role(transport.identity()), &prepare);
+                    let mut gateway = Gateway::new(
                         query_id,
                         GatewayConfig::from(&config),
-                        role_assignment,
+                        prepare.roles,
                         transport,
                     );
+                    if let Some(capture) = capture {
+                        gateway = gateway.with_capture(crate::sync::Arc::new(capture));
+                    }
                     queries.insert(
                         input.query_id,
                         QueryState::Running(executor::execute(
@@ -256,6 +321,22 @@ impl Processor {
     /// ## Panics
     /// If the query collection mutex is poisoned.
     pub fn query_status(&self, query_id: QueryId) -> Result<QueryStatus, QueryStatusError> {
+        self.query_status_with_progress(query_id)
+            .map(|(status, _)| status)
+    }
+
+    /// Returns the query status along with its progress, if it is running. Both are read under
+    /// the same lock, so the progress is never reported for a query that has already completed.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn query_status_with_progress(
+        &self,
+        query_id: QueryId,
+    ) -> Result<(QueryStatus, Option<QueryProgress>), QueryStatusError> {
         let mut queries = self.queries.inner.lock().unwrap();
         let Some(mut state) = queries.remove(&query_id) else {
             return Err(QueryStatusError::NoSuchQuery(query_id));
@@ -268,8 +349,12 @@ impl Processor {
         }
 
         let status = QueryStatus::from(&state);
+        let progress = match &state {
+            QueryState::Running(running) => Some(running.progress.snapshot()),
+            _ => None,
+        };
         queries.insert(query_id, state);
-        Ok(status)
+        Ok((status, progress))
     }
 
     /// Awaits the query completion
@@ -623,6 +708,8 @@ mod tests {
                             attribution_window_seconds: None,
                             num_multi_bits: 3,
                             plaintext_match_keys: true,
+                            offline_preprocessing: false,
+                            gf2_sort: false,
                         }),
                     },
                 )
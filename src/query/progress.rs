@@ -0,0 +1,321 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::query::{IpaQueryConfig, QueryType},
+    protocol::step::Gate,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Steps that wrap the actual protocol step without contributing to the progress of the query.
+/// Malicious contexts narrow into these before running the protocol under validation.
+const WRAPPER_STEPS: &[&str] = &["malicious_protocol", "validate", "upgrade_semi_honest"];
+
+/// Number of gate components, after the root and wrapper steps are stripped, that identify a
+/// top-level step. `after_convert_all_bits/perform_user_capping` is a top-level step, the
+/// individual bits it processes are not.
+const TOP_LEVEL_DEPTH: usize = 2;
+
+/// Records are reported to the tracker in batches of this size, so that senders don't contend on
+/// the shared counter for every record.
+const RECORDS_BATCH: usize = 256;
+
+/// Progress of a running query, as reported by the query status API.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryProgress {
+    /// Number of top-level steps this helper has moved past.
+    pub completed_steps: usize,
+    /// Estimated number of top-level steps the query will go through, if known for the query type.
+    pub estimated_total_steps: Option<usize>,
+    /// Top-level step that the query entered last.
+    pub current_step: Option<String>,
+    /// Highest number of records sent to a peer on any channel since the current step started.
+    pub records_processed: usize,
+}
+
+impl QueryProgress {
+    /// Fraction of the query that is complete, between `0.0` and `1.0`. `None` if the total
+    /// number of steps is unknown.
+    #[must_use]
+    pub fn fraction(&self) -> Option<f64> {
+        self.estimated_total_steps.filter(|&n| n > 0).map(|total| {
+            // step counts are small, the conversion is lossless
+            #[allow(clippy::cast_precision_loss)]
+            let fraction = self.completed_steps as f64 / total as f64;
+            fraction.min(1.0)
+        })
+    }
+}
+
+/// Tracks the top-level steps a query goes through. Fed by the gateway as channels are opened
+/// and records are sent.
+#[derive(Default)]
+pub struct ProgressTracker {
+    steps: Mutex<Steps>,
+    records: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Steps {
+    seen: Vec<String>,
+    estimated_total: Option<usize>,
+}
+
+impl Debug for ProgressTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ProgressTracker({:?})", self.snapshot())
+    }
+}
+
+impl ProgressTracker {
+    /// Sets the number of top-level steps expected for `query_type`.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn expect_steps_for(&self, query_type: &QueryType) {
+        let estimated_total = match query_type {
+            // PRSS negotiation precedes every query and is not an IPA step
+            QueryType::SemiHonestIpa(config) => Some(ipa_top_level_steps(config, false) + 1),
+            QueryType::MaliciousIpa(config) => Some(ipa_top_level_steps(config, true) + 1),
+            _ => None,
+        };
+        self.steps.lock().unwrap().estimated_total = estimated_total;
+    }
+
+    /// Records that a channel has been opened for `gate`. If it belongs to a top-level step that
+    /// hasn't been seen before, the query is considered to have moved to that step.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn enter(&self, gate: &Gate) {
+        let Some(step) = top_level_step(gate.as_ref()) else {
+            return;
+        };
+        let mut steps = self.steps.lock().unwrap();
+        if !steps.seen.iter().any(|s| s == &step) {
+            steps.seen.push(step);
+            self.records.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that the record with index `record` was sent on one of the channels. Only every
+    /// [`RECORDS_BATCH`]th record and the `last` record on a channel are reported.
+    pub fn record_sent(&self, record: usize, last: bool) {
+        if !last && (record + 1) % RECORDS_BATCH != 0 {
+            return;
+        }
+        // shuttle atomics don't support `fetch_max`
+        let mut cur = self.records.load(Ordering::Relaxed);
+        while cur <= record {
+            match self.records.compare_exchange_weak(
+                cur,
+                record + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => cur = actual,
+            }
+        }
+    }
+
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn snapshot(&self) -> QueryProgress {
+        let steps = self.steps.lock().unwrap();
+        QueryProgress {
+            // the last step seen is still in progress
+            completed_steps: steps.seen.len().saturating_sub(1),
+            estimated_total_steps: steps.estimated_total,
+            current_step: steps.seen.last().cloned(),
+            records_processed: self.records.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Number of distinct top-level steps that an IPA run with `config` sends messages in.
+fn ipa_top_level_steps(config: &IpaQueryConfig, malicious: bool) -> usize {
+    // match key sort, two sort permutation applications, breakdown key modulus conversion,
+    // helper bits (computed and converted), equality checks, stop bits, credit checks and capping
+    let mut steps = 10;
+    if config.per_user_credit_cap > 1 || config.attribution_window_seconds.is_some() {
+        steps += 1; // accumulate_credit
+    }
+    if config.attribution_window_seconds.is_some() {
+        steps += 1; // apply_attribution_window
+    }
+    if config.gf2_sort {
+        // shuffle_sort_keys and rounds0 replace sort_keys. Quicksort groups its rounds by 64, so
+        // any practical input stays within the first group.
+        steps += 1;
+        if malicious {
+            steps += 1; // upgrade_sort_keys
+        }
+    }
+    if malicious {
+        // upgrades, `r` reveal, `u` and `w` propagation and zero checks for both validators
+        steps += 9;
+    }
+    steps
+}
+
+/// Extracts the top-level step from a gate path. Both descriptive gates (`protocol/a/b/c`) and
+/// paths with qualified steps (`mod::Step::a/mod::Step::b/mod::Step::c`) are accepted.
+fn top_level_step(gate: &str) -> Option<String> {
+    let components = gate
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .map(|c| c.rsplit("::").next().unwrap())
+        .skip_while(|&c| c == "protocol")
+        .filter(|c| !WRAPPER_STEPS.contains(c))
+        .take(TOP_LEVEL_DEPTH)
+        .collect::<Vec<_>>();
+
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{collections::HashSet, num::NonZeroU32};
+
+    use super::*;
+    use crate::{
+        ff::Fp32BitPrime,
+        ipa_test_input,
+        protocol::{ipa::ipa, step::StepNarrow, BreakdownKey, MatchKey},
+        telemetry::{metrics::RECORDS_SENT, StepsStats},
+        test_fixture::{input::GenericReportTestInput, Runner, TestWorld, TestWorldConfig},
+    };
+
+    fn gate(path: &[&str]) -> Gate {
+        path.iter()
+            .fold(Gate::default(), |gate, &step| gate.narrow(step))
+    }
+
+    #[test]
+    fn top_level() {
+        assert_eq!(None, top_level_step("protocol"));
+        assert_eq!(
+            Some("after_convert_all_bits/perform_user_capping".to_owned()),
+            top_level_step(
+                "ipa::protocol::ipa::Step::after_convert_all_bits/\
+                ipa::protocol::context::validator::Step::malicious_protocol/\
+                ipa::protocol::attribution::AttributionStep::perform_user_capping/\
+                ipa::protocol::attribution::credit_capping::Step::report_level_capping"
+            )
+        );
+        assert_eq!(
+            Some("gen_sort_permutation_from_match_keys/sort_keys".to_owned()),
+            top_level_step("protocol/gen_sort_permutation_from_match_keys/sort_keys/sort0/bit0")
+        );
+    }
+
+    #[test]
+    fn tracks_steps() {
+        let tracker = ProgressTracker::default();
+        assert_eq!(QueryProgress::default(), tracker.snapshot());
+
+        tracker.enter(&gate(&["sort", "keys", "bit0"]));
+        tracker.record_sent(4, true);
+        tracker.enter(&gate(&["sort", "keys", "bit1"]));
+        tracker.record_sent(2, true);
+        let progress = tracker.snapshot();
+        assert_eq!(0, progress.completed_steps);
+        assert_eq!(Some("sort/keys".to_owned()), progress.current_step);
+        assert_eq!(5, progress.records_processed);
+
+        tracker.enter(&gate(&["attribution", "malicious_protocol", "capping"]));
+        let progress = tracker.snapshot();
+        assert_eq!(1, progress.completed_steps);
+        assert_eq!(
+            Some("attribution/capping".to_owned()),
+            progress.current_step
+        );
+        assert_eq!(0, progress.records_processed);
+        assert_eq!(None, progress.fraction());
+
+        tracker.record_sent(8, false);
+        assert_eq!(0, tracker.snapshot().records_processed);
+        tracker.record_sent(9, true);
+        assert_eq!(10, tracker.snapshot().records_processed);
+
+        // going back to a step seen before does not move progress backwards
+        tracker.enter(&gate(&["sort", "keys", "bit0"]));
+        assert_eq!(1, tracker.snapshot().completed_steps);
+
+        tracker.expect_steps_for(&QueryType::SemiHonestIpa(IpaQueryConfig::default()));
+        assert!(tracker.snapshot().fraction().unwrap() > 0.0);
+    }
+
+    /// Runs IPA and collects the top-level steps that sent at least one record.
+    async fn ipa_steps(config: IpaQueryConfig, malicious: bool) -> HashSet<String> {
+        let world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+        let records: Vec<GenericReportTestInput<Fp32BitPrime, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 1, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 2, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                { timestamp: 3, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 4, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                { timestamp: 5, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+            ];
+            (Fp32BitPrime, MatchKey, BreakdownKey)
+        );
+        if malicious {
+            world
+                .malicious(records.into_iter(), |ctx, rows| async move {
+                    ipa::<_, _, _, Fp32BitPrime, MatchKey, BreakdownKey>(ctx, &rows, config)
+                        .await
+                        .unwrap()
+                })
+                .await;
+        } else {
+            world
+                .semi_honest(records.into_iter(), |ctx, rows| async move {
+                    ipa::<_, _, _, Fp32BitPrime, MatchKey, BreakdownKey>(ctx, &rows, config)
+                        .await
+                        .unwrap()
+                })
+                .await;
+        }
+
+        let metrics = world.metrics_snapshot();
+        StepsStats::from(&metrics)
+            .all_steps()
+            .filter(|(_, stats)| stats.get(RECORDS_SENT) > 0)
+            .filter_map(|(step, _)| top_level_step(&step.replace("run-0/", "")))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ipa_steps_match_config() {
+        for (malicious, attribution_window_seconds, per_user_credit_cap, gf2_sort) in [
+            (false, None, 1, false),
+            (false, NonZeroU32::new(10), 3, true),
+            (true, None, 3, false),
+            (true, NonZeroU32::new(10), 1, true),
+        ] {
+            let config = IpaQueryConfig {
+                per_user_credit_cap,
+                attribution_window_seconds,
+                gf2_sort,
+                max_breakdown_key: 3,
+                num_multi_bits: 3,
+                ..IpaQueryConfig::default()
+            };
+            assert_eq!(
+                ipa_top_level_steps(&config, malicious),
+                ipa_steps(config, malicious).await.len(),
+                "malicious: {malicious}, {config:?}"
+            );
+        }
+    }
+}
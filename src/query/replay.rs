@@ -0,0 +1,281 @@
+//! Runs one helper's side of a captured query again, without its peers.
+//!
+//! [`replay`] takes a [`Recording`] of a query, written by a helper that ran it with captures
+//! enabled, and runs the same query on an in-memory network. The replayed helper gets the recorded
+//! input and PRSS seed, and its peers are stand-ins that send it exactly the messages it received
+//! the first time. Everything it sends is compared with what it sent the first time, so a
+//! helper-local failure can be reproduced and debugged offline, with a debugger or extra logging.
+//!
+//! Peers only send what the recording has, up to the first record missing on each channel. If the
+//! replayed helper waits for anything else, the replay does not finish, so callers should apply a
+//! timeout.
+
+use std::{collections::HashMap, iter};
+
+use bytes::Bytes;
+use futures::{future::ready, stream, StreamExt};
+#[cfg(all(feature = "shuttle", test))]
+use shuttle::future as tokio;
+
+use crate::{
+    error::Error,
+    helpers::{
+        capture::{Capture, ChannelRecords, Recording},
+        BodyStream, ChannelId, Gateway, GatewayConfig, InMemoryNetwork, RouteId, Transport,
+    },
+    hpke::{KeyPair, KeyRegistry},
+    protocol::RecordId,
+    query::{executor, ProtocolResult},
+    sync::{Arc, Mutex},
+};
+
+/// A message the replayed helper sent differently than the first time.
+#[derive(Debug)]
+pub struct Divergence {
+    pub channel_id: ChannelId,
+    pub record_id: RecordId,
+    /// What was sent the first time, if anything.
+    pub recorded: Option<Vec<u8>>,
+    /// What was sent by the replay, if anything.
+    pub replayed: Option<Vec<u8>>,
+}
+
+pub struct ReplayReport {
+    pub result: Result<Box<dyn ProtocolResult>, Error>,
+    /// Messages sent by the replay that differ from the recording, ordered by step, peer and
+    /// record.
+    pub divergences: Vec<Divergence>,
+}
+
+/// Capture sink that the replay reads back once the query is done.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Replays the helper that captured `recording`. `key_registry` must have the keys this helper
+/// used to decrypt its input, if the query needs them.
+///
+/// ## Panics
+/// If the replayed helper panics, or if the in-memory network fails.
+pub async fn replay(recording: &Recording, key_registry: KeyRegistry<KeyPair>) -> ReplayReport {
+    let network = InMemoryNetwork::default();
+    let prepare = &recording.prepare;
+    let identity = prepare.roles.identity(recording.role);
+
+    for (channel_id, records) in &recording.received {
+        let peer = network.transport(prepare.roles.identity(channel_id.role));
+        let data = stream::iter(iter::once(received_stream(records)));
+        peer.send(
+            identity,
+            (RouteId::Records, prepare.query_id, channel_id.gate.clone()),
+            data,
+        )
+        .await
+        .unwrap();
+    }
+    // Peers must read what the replayed helper sends, or it stops sending once its buffers are
+    // full. The data itself is taken from the capture.
+    let drains = recording
+        .sent
+        .keys()
+        .map(|channel_id| {
+            let peer = network.transport(prepare.roles.identity(channel_id.role));
+            let stream = peer.receive(identity, (prepare.query_id, channel_id.gate.clone()));
+            tokio::spawn(stream.for_each(|_| ready(())))
+        })
+        .collect::<Vec<_>>();
+
+    let buffer = Buffer::default();
+    let capture = Arc::new(Capture::new(buffer.clone()));
+    capture.record_query(recording.role, prepare);
+    let gateway = Gateway::new(
+        prepare.query_id,
+        GatewayConfig::from(&prepare.config),
+        prepare.roles.clone(),
+        network.transport(identity),
+    )
+    .with_capture(Arc::clone(&capture));
+    let input = stream::once(ready(Ok(Bytes::from(recording.input.clone()))));
+
+    let result = executor::execute_with_prss_seed(
+        prepare.config,
+        std::sync::Arc::new(key_registry),
+        gateway,
+        BodyStream::from_stream(input),
+        recording.prss_seed,
+    )
+    .await;
+    for drain in drains {
+        drain.abort();
+    }
+    // The gateway may still hold on to the capture, so it is not written completely until it is
+    // flushed.
+    capture.flush().await;
+
+    let replayed = Recording::from_reader(buffer.0.lock().unwrap().as_slice())
+        .expect("the replay captures everything a recording needs");
+    ReplayReport {
+        result,
+        divergences: divergences(&recording.sent, &replayed.sent),
+    }
+}
+
+/// Data a peer sent on a channel, as far as the recording has it.
+fn received_stream(records: &ChannelRecords) -> Vec<u8> {
+    records
+        .iter()
+        .enumerate()
+        .take_while(|(i, (&record_id, _))| usize::from(record_id) == *i)
+        .flat_map(|(_, (_, data))| data.iter().copied())
+        .collect()
+}
+
+fn divergences(
+    recorded: &HashMap<ChannelId, ChannelRecords>,
+    replayed: &HashMap<ChannelId, ChannelRecords>,
+) -> Vec<Divergence> {
+    let empty = ChannelRecords::new();
+    let mut divergences = Vec::new();
+    for channel_id in recorded
+        .keys()
+        .chain(replayed.keys().filter(|c| !recorded.contains_key(c)))
+    {
+        let recorded = recorded.get(channel_id).unwrap_or(&empty);
+        let replayed = replayed.get(channel_id).unwrap_or(&empty);
+        let mut record_ids = recorded.keys().chain(replayed.keys()).collect::<Vec<_>>();
+        record_ids.sort();
+        record_ids.dedup();
+        for &record_id in record_ids {
+            let (recorded, replayed) = (recorded.get(&record_id), replayed.get(&record_id));
+            if recorded != replayed {
+                divergences.push(Divergence {
+                    channel_id: channel_id.clone(),
+                    record_id,
+                    recorded: recorded.cloned(),
+                    replayed: replayed.cloned(),
+                });
+            }
+        }
+    }
+    divergences.sort_by(|a, b| {
+        let key = |d: &Divergence| {
+            (
+                d.channel_id.gate.as_ref().to_owned(),
+                d.channel_id.role.as_static_str(),
+                d.record_id,
+            )
+        };
+        key(a).cmp(&key(b))
+    });
+
+    divergences
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::replay;
+    use crate::{
+        ff::{Field, FieldType, Fp31},
+        helpers::{
+            capture::Recording,
+            query::{IpaQueryConfig, QueryConfig, QueryType},
+            Role,
+        },
+        hpke::KeyRegistry,
+        ipa_test_input,
+        protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey},
+        test_fixture::{input::GenericReportTestInput, TestApp},
+    };
+
+    /// Reads the captures in `dir`, in the order of helper roles.
+    fn recordings(dir: &Path) -> [Recording; 3] {
+        let mut recordings = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| Recording::read(entry.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        recordings.sort_by_key(|r| r.role as usize);
+        recordings.try_into().unwrap()
+    }
+
+    async fn test_multiply(dir: &Path) -> [Vec<u8>; 3] {
+        let app = TestApp::with_capture_dir(dir);
+        let input = [4_u128, 5, 3, 7].map(Fp31::truncate_from);
+        app.execute_query(
+            input.into_iter(),
+            QueryConfig::new(QueryType::TestMultiply, FieldType::Fp31, 4).unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_test_multiply() {
+        let dir = tempfile::tempdir().unwrap();
+        let outputs = test_multiply(dir.path()).await;
+
+        for (recording, output) in recordings(dir.path()).iter().zip(outputs) {
+            let report = replay(recording, KeyRegistry::empty()).await;
+            assert!(report.divergences.is_empty(), "{:?}", report.divergences);
+            assert_eq!(output, report.result.unwrap().into_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_ipa() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = TestApp::with_capture_dir(dir.path());
+        let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 0, match_key: 68362, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+            ];
+            (Fp31, MatchKey, BreakdownKey)
+        );
+        let config = IpaQueryConfig {
+            plaintext_match_keys: true,
+            ..IpaQueryConfig::default()
+        };
+        let outputs = app
+            .execute_query::<_, Vec<IPAInputRow<_, _, _>>>(
+                records.into_iter(),
+                QueryConfig::new(QueryType::SemiHonestIpa(config), FieldType::Fp31, 3).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let recordings = recordings(dir.path());
+        let report = replay(&recordings[1], KeyRegistry::empty()).await;
+        assert!(report.divergences.is_empty(), "{:?}", report.divergences);
+        assert_eq!(outputs[1], report.result.unwrap().into_bytes());
+    }
+
+    #[tokio::test]
+    async fn detects_divergence() {
+        let dir = tempfile::tempdir().unwrap();
+        let outputs = test_multiply(dir.path()).await;
+        let [mut recording, ..] = recordings(dir.path());
+        assert_eq!(Role::H1, recording.role);
+
+        // a different seed makes PRSS, and every multiplication share sent, different
+        recording.prss_seed[0] ^= 1;
+        let report = replay(&recording, KeyRegistry::empty()).await;
+        assert!(!report.divergences.is_empty());
+        assert!(report
+            .divergences
+            .iter()
+            .all(|d| d.recorded.is_some() && d.replayed.is_some()));
+        assert_ne!(outputs[0], report.result.unwrap().into_bytes());
+    }
+}
@@ -0,0 +1,231 @@
+//! Golden tests for every query type, see [`crate::test_fixture::golden`]. Each query runs on a
+//! seeded test world with fixed input shares, and its outputs and communication are compared with
+//! the snapshot in `tests/golden/<query type>.json`.
+
+use std::{iter::zip, sync::Arc};
+
+use generic_array::GenericArray;
+use rand::rngs::StdRng;
+use rand_core::SeedableRng;
+use typenum::Unsigned;
+
+use super::{test_multiply::execute_test_multiply_internal, IpaQuery, SparseAggregateQuery};
+use crate::{
+    error::Error,
+    ff::{Field, Fp32BitPrime, Gf8Bit, Serializable},
+    helpers::query::{IpaQueryConfig, QuerySize, QueryType, SparseAggregateQueryConfig},
+    hpke::KeyRegistry,
+    ipa_test_input,
+    protocol::{
+        aggregation::SparseAggregateInputRow, context::Context, ipa::IPAInputRow, BreakdownKey,
+        MatchKey,
+    },
+    query::ProtocolResult,
+    secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, IntoShares},
+    test_fixture::{
+        golden::{assert_snapshot, Snapshot, GOLDEN_SEED},
+        input::GenericReportTestInput,
+        Reconstruct, Runner, TestWorld, TestWorldConfig,
+    },
+};
+
+fn serialize<T: Serializable>(rows: Vec<T>) -> Vec<u8> {
+    let mut buf = vec![0u8; rows.len() * T::Size::USIZE];
+    for (row, chunk) in zip(rows, buf.chunks_mut(T::Size::USIZE)) {
+        row.serialize(GenericArray::from_mut_slice(chunk));
+    }
+    buf
+}
+
+fn into_bytes<T: ProtocolResult + 'static>(outputs: [T; 3]) -> [Vec<u8>; 3] {
+    outputs.map(|output| Box::new(output).into_bytes())
+}
+
+fn ipa_config() -> IpaQueryConfig {
+    IpaQueryConfig {
+        num_multi_bits: 3,
+        per_user_credit_cap: 3,
+        attribution_window_seconds: None,
+        max_breakdown_key: 3,
+        plaintext_match_keys: true,
+        offline_preprocessing: false,
+        gf2_sort: false,
+    }
+}
+
+fn ipa_input() -> [Vec<u8>; 3] {
+    let records: Vec<GenericReportTestInput<Fp32BitPrime, MatchKey, BreakdownKey>> = ipa_test_input!(
+        [
+            { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+            { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+            { timestamp: 0, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+            { timestamp: 0, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+            { timestamp: 0, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+        ];
+        (Fp32BitPrime, MatchKey, BreakdownKey)
+    );
+    let shares: [Vec<IPAInputRow<Fp32BitPrime, MatchKey, BreakdownKey>>; 3] = records
+        .into_iter()
+        .share_with(&mut StdRng::seed_from_u64(GOLDEN_SEED));
+    shares.map(serialize)
+}
+
+/// `(breakdown key, contribution value)` pairs.
+const AGGREGATE_INPUT: &[(u128, u128)] = &[(0, 3), (2, 1), (7, 5), (2, 4), (0, 0), (5, 9)];
+const AGGREGATE_EXPECTED: &[u128] = &[3, 0, 5, 0, 0, 9, 0, 5];
+
+fn aggregate_input() -> [Vec<u8>; 3] {
+    let mut rng = StdRng::seed_from_u64(GOLDEN_SEED);
+    let mut rows: [Vec<SparseAggregateInputRow<Gf8Bit, BreakdownKey>>; 3] = Default::default();
+    for &(breakdown_key, value) in AGGREGATE_INPUT {
+        let breakdown_key = BreakdownKey::truncate_from(breakdown_key).share_with(&mut rng);
+        let value = Gf8Bit::truncate_from(value).share_with(&mut rng);
+        for (rows, (breakdown_key, contribution_value)) in zip(&mut rows, zip(breakdown_key, value))
+        {
+            rows.push(SparseAggregateInputRow {
+                contribution_value,
+                breakdown_key,
+            });
+        }
+    }
+    rows.map(serialize)
+}
+
+/// Runs `query_type` on `input` and checks the reconstructed result against `expected` and the
+/// snapshot of the run against the one checked in.
+async fn run(query_type: QueryType, query_size: usize, input: [Vec<u8>; 3], expected: &[u128]) {
+    let world = TestWorld::new_with(
+        TestWorldConfig::default()
+            .with_seed(GOLDEN_SEED)
+            .enable_metrics(),
+    );
+    let query_size = QuerySize::try_from(query_size).unwrap();
+    let key_registry = Arc::new(KeyRegistry::empty());
+
+    let input = &input;
+    let key_registry = &key_registry;
+    // Helpers get their own shares of the input, which are already in `input`.
+    #[allow(clippy::large_futures)]
+    let outputs: [Result<Vec<Replicated<Fp32BitPrime>>, Error>; 3] =
+        match &query_type {
+            QueryType::TestMultiply => {
+                world
+                    .semi_honest((), |ctx, ()| {
+                        let input = input[ctx.role()].clone();
+                        execute_test_multiply_internal::<Fp32BitPrime>(ctx, input.into())
+                    })
+                    .await
+            }
+            QueryType::SemiHonestIpa(config) => {
+                world
+                    .semi_honest((), |ctx, ()| {
+                        let input = input[ctx.role()].clone();
+                        IpaQuery::<Fp32BitPrime, _, _>::new(*config, Arc::clone(key_registry))
+                            .execute(ctx, query_size, input.into())
+                    })
+                    .await
+            }
+            QueryType::MaliciousIpa(config) => {
+                world
+                    .malicious((), |ctx, ()| {
+                        let input = input[ctx.role()].clone();
+                        IpaQuery::<Fp32BitPrime, _, _>::new(*config, Arc::clone(key_registry))
+                            .execute(ctx, query_size, input.into())
+                    })
+                    .await
+            }
+            QueryType::SemiHonestSparseAggregate(config) => {
+                world
+                    .semi_honest((), |ctx, ()| {
+                        let input = input[ctx.role()].clone();
+                        SparseAggregateQuery::<Fp32BitPrime, _, _>::new(
+                            *config,
+                            Arc::clone(key_registry),
+                        )
+                        .execute(ctx, query_size, input.into())
+                    })
+                    .await
+            }
+            QueryType::MaliciousSparseAggregate(config) => {
+                world
+                    .malicious((), |ctx, ()| {
+                        let input = input[ctx.role()].clone();
+                        SparseAggregateQuery::<Fp32BitPrime, _, _>::new(
+                            *config,
+                            Arc::clone(key_registry),
+                        )
+                        .execute(ctx, query_size, input.into())
+                    })
+                    .await
+            }
+        };
+    let outputs = outputs.map(Result::unwrap);
+
+    let result = outputs
+        .clone()
+        .reconstruct()
+        .into_iter()
+        .map(|v| v.as_u128())
+        .collect::<Vec<_>>();
+    assert_eq!(expected, result);
+    assert_snapshot(
+        query_type.as_ref(),
+        &Snapshot::new(&world, into_bytes(outputs)),
+    );
+}
+
+#[tokio::test]
+async fn test_multiply() {
+    let mut rng = StdRng::seed_from_u64(GOLDEN_SEED);
+    let input = [1_u128, 2, 3, 4, 5, 6, 7, 8]
+        .map(Fp32BitPrime::truncate_from)
+        .into_iter()
+        .share_with(&mut rng)
+        .map(serialize::<Replicated<Fp32BitPrime>>);
+
+    run(QueryType::TestMultiply, 8, input, &[2, 12, 30, 56]).await;
+}
+
+#[tokio::test]
+async fn semi_honest_ipa() {
+    run(
+        QueryType::SemiHonestIpa(ipa_config()),
+        5,
+        ipa_input(),
+        &[0, 2, 3],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn malicious_ipa() {
+    run(
+        QueryType::MaliciousIpa(ipa_config()),
+        5,
+        ipa_input(),
+        &[0, 2, 3],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn semi_honest_sparse_aggregate() {
+    run(
+        QueryType::SemiHonestSparseAggregate(SparseAggregateQueryConfig::default()),
+        AGGREGATE_INPUT.len(),
+        aggregate_input(),
+        AGGREGATE_EXPECTED,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn malicious_sparse_aggregate() {
+    run(
+        QueryType::MaliciousSparseAggregate(SparseAggregateQueryConfig::default()),
+        AGGREGATE_INPUT.len(),
+        aggregate_input(),
+        AGGREGATE_EXPECTED,
+    )
+    .await;
+}
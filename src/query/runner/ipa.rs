@@ -1,6 +1,7 @@
 use std::marker::PhantomData;
 
 use futures::{
+    future::try_join,
     stream::{iter, repeat},
     Stream, StreamExt, TryStreamExt,
 };
@@ -16,7 +17,10 @@ use crate::{
     protocol::{
         basics::{Reshare, ShareKnownValue},
         context::{UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext},
-        ipa::{ipa, ArithmeticallySharedIPAInputs, IPAInputRow},
+        ipa::{
+            ipa, ipa_preprocessing, ipa_with_preprocessing, ArithmeticallySharedIPAInputs,
+            IPAInputRow,
+        },
         modulus_conversion::BitConversionTriple,
         sort::generate_permutation::ShuffledPermutationWrapper,
         BasicProtocols, BreakdownKey, MatchKey, RecordId,
@@ -88,62 +92,75 @@ where
         tracing::info!("New query: {config:?}");
         let sz = usize::from(query_size);
 
-        let input = if config.plaintext_match_keys {
-            let mut v = assert_stream_send(RecordsStream::<
-                IPAInputRow<F, MatchKey, BreakdownKey>,
-                _,
-            >::new(input_stream))
-            .try_concat()
-            .await?;
-            v.truncate(sz);
-            v
-        } else {
-            assert_stream_send(LengthDelimitedStream::<
-                EncryptedReport<F, MatchKey, BreakdownKey, _>,
-                _,
-            >::new(input_stream))
-            .map_err(Into::<Error>::into)
-            .map_ok(|enc_reports| {
-                iter(enc_reports.into_iter().map(|enc_report| {
-                    enc_report
-                        .decrypt(key_registry.as_ref())
-                        .map_err(Into::<Error>::into)
-                }))
-            })
-            .try_flatten()
-            .take(sz)
-            .zip(repeat(ctx.clone()))
-            .map(|(res, ctx)| {
-                res.and_then(|report| {
-                    let timestamp = Replicated::<F>::share_known_value(
-                        &ctx,
-                        F::try_from(report.timestamp.into())
-                            .map_err(|_| InvalidReportError::Timestamp(report.timestamp))?,
-                    );
-                    let breakdown_key =
-                        Replicated::<BreakdownKey>::share_known_value(&ctx, report.breakdown_key);
-                    let is_trigger_bit = Replicated::<F>::share_known_value(
-                        &ctx,
-                        match report.event_type {
-                            EventType::Source => F::ZERO,
-                            EventType::Trigger => F::ONE,
-                        },
-                    );
-
-                    Ok(IPAInputRow {
-                        timestamp,
-                        mk_shares: report.mk_shares,
-                        is_trigger_bit,
-                        breakdown_key,
-                        trigger_value: report.trigger_value,
+        let input = async {
+            let input = if config.plaintext_match_keys {
+                let mut v = assert_stream_send(RecordsStream::<
+                    IPAInputRow<F, MatchKey, BreakdownKey>,
+                    _,
+                >::new(input_stream))
+                .try_concat()
+                .await?;
+                v.truncate(sz);
+                v
+            } else {
+                assert_stream_send(LengthDelimitedStream::<
+                    EncryptedReport<F, MatchKey, BreakdownKey, _>,
+                    _,
+                >::new(input_stream))
+                .map_err(Into::<Error>::into)
+                .map_ok(|enc_reports| {
+                    iter(enc_reports.into_iter().map(|enc_report| {
+                        enc_report
+                            .decrypt(key_registry.as_ref())
+                            .map_err(Into::<Error>::into)
+                    }))
+                })
+                .try_flatten()
+                .take(sz)
+                .zip(repeat(ctx.clone()))
+                .map(|(res, ctx)| {
+                    res.and_then(|report| {
+                        let timestamp = Replicated::<F>::share_known_value(
+                            &ctx,
+                            F::try_from(report.timestamp.into())
+                                .map_err(|_| InvalidReportError::Timestamp(report.timestamp))?,
+                        );
+                        let breakdown_key = Replicated::<BreakdownKey>::share_known_value(
+                            &ctx,
+                            report.breakdown_key,
+                        );
+                        let is_trigger_bit = Replicated::<F>::share_known_value(
+                            &ctx,
+                            match report.event_type {
+                                EventType::Source => F::ZERO,
+                                EventType::Trigger => F::ONE,
+                            },
+                        );
+
+                        Ok(IPAInputRow {
+                            timestamp,
+                            mk_shares: report.mk_shares,
+                            is_trigger_bit,
+                            breakdown_key,
+                            trigger_value: report.trigger_value,
+                        })
                     })
                 })
-            })
-            .try_collect::<Vec<_>>()
-            .await?
+                .try_collect::<Vec<_>>()
+                .await?
+            };
+            Ok::<_, Error>(input)
         };
 
-        ipa(ctx, input.as_slice(), config).await
+        if config.offline_preprocessing {
+            // Preprocessing does not depend on the inputs, so it runs while they are uploaded.
+            let (preprocessing, input) =
+                try_join(ipa_preprocessing(ctx.clone(), sz, &config), input).await?;
+            ipa_with_preprocessing(ctx, preprocessing, input.as_slice(), config).await
+        } else {
+            let input = input.await?;
+            ipa(ctx, input.as_slice(), config).await
+        }
     }
 }
 
@@ -160,7 +177,7 @@ pub fn assert_stream_send<'a, T>(
 /// no dependency on `weak-field` feature because it is enabled in tests by default
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::iter::zip;
+    use std::{iter::zip, num::NonZeroU32};
 
     use generic_array::GenericArray;
     use rand::rngs::StdRng;
@@ -225,6 +242,8 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: true,
+                offline_preprocessing: false,
+                gf2_sort: false,
             };
             let input = BodyStream::from(shares);
             // Note that we ignore the last 2 records to test that runner follows the rule
@@ -284,6 +303,63 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: true,
+                offline_preprocessing: false,
+                gf2_sort: false,
+            };
+            IpaQuery::<Fp31, _, _>::new(query_config, Arc::new(KeyRegistry::empty())).execute(
+                ctx,
+                query_size,
+                shares.into(),
+            )
+        }))
+        .await;
+
+        assert_eq!(results.reconstruct(), EXPECTED);
+    }
+
+    #[tokio::test]
+    async fn malicious_ipa_offline_preprocessing() {
+        const EXPECTED: &[u128] = &[0, 2, 3];
+
+        let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                { timestamp: 0, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 5, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                { timestamp: 5, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+            ];
+            (Fp31, MatchKey, BreakdownKey)
+        );
+        // Preprocessing generates random bits for more rows than the query ends up having.
+        let query_size = QuerySize::try_from(records.len() + 3).unwrap();
+
+        let records = records.into_iter().share().map(|shares| {
+            shares
+                .into_iter()
+                .flat_map(|share: IPAInputRow<Fp31, MatchKey, BreakdownKey>| {
+                    let mut buf = [0u8;
+                        <IPAInputRow<Fp31, MatchKey, BreakdownKey> as Serializable>::Size::USIZE];
+                    share.serialize(GenericArray::from_mut_slice(&mut buf));
+
+                    buf
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let world = TestWorld::default();
+        let contexts = world.malicious_contexts();
+
+        #[allow(clippy::large_futures)]
+        let results = join3v(records.into_iter().zip(contexts).map(|(shares, ctx)| {
+            let query_config = IpaQueryConfig {
+                num_multi_bits: 3,
+                per_user_credit_cap: 3,
+                attribution_window_seconds: NonZeroU32::new(10),
+                max_breakdown_key: 3,
+                plaintext_match_keys: true,
+                offline_preprocessing: true,
+                gf2_sort: false,
             };
             IpaQuery::<Fp31, _, _>::new(query_config, Arc::new(KeyRegistry::empty())).execute(
                 ctx,
@@ -340,6 +416,8 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: false,
+                offline_preprocessing: false,
+                gf2_sort: false,
             };
             let input = BodyStream::from(buffer);
             IpaQuery::<Fp31, _, _>::new(query_config, Arc::clone(&key_registry))
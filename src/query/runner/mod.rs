@@ -1,4 +1,6 @@
 mod aggregate;
+#[cfg(all(test, unit_test, feature = "descriptive-gate"))]
+mod golden;
 mod ipa;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
 mod test_multiply;
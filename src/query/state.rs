@@ -12,8 +12,8 @@ use serde::{Deserialize, Serialize};
 use crate::{
     helpers::{query::QueryConfig, RoleAssignment},
     protocol::QueryId,
-    query::runner::QueryResult,
-    sync::Mutex,
+    query::{runner::QueryResult, ProgressTracker},
+    sync::{Arc, Mutex},
     task::JoinHandle,
 };
 
@@ -88,6 +88,9 @@ pub struct RunningQuery {
     /// We could return the result via the JoinHandle, except that we want to check the status
     /// of the task, and shuttle doesn't implement `JoinHandle::is_finished`.
     pub join_handle: JoinHandle<()>,
+
+    /// Steps the query went through so far, fed by the gateway.
+    pub progress: Arc<ProgressTracker>,
 }
 
 impl RunningQuery {
@@ -12,7 +12,8 @@ pub struct BitDecomposed<S> {
 }
 
 impl<S> BitDecomposed<S> {
-    const MAX: usize = 64;
+    /// Large enough to hold the one-hot encoding of an 8-bit value.
+    const MAX: usize = 256;
 
     /// Create a new value from an iterator.
     /// # Panics
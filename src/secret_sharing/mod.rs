@@ -3,6 +3,7 @@ pub mod replicated;
 mod decomposed;
 mod into_shares;
 mod scheme;
+pub mod vector;
 
 use std::{
     fmt::Debug,
@@ -12,8 +12,12 @@ use futures::{
 use generic_array::{ArrayLength, GenericArray};
 use typenum::Unsigned;
 
+#[cfg(any(test, feature = "weak-field"))]
+use crate::ff::Fp31;
+#[cfg(feature = "extended-mac-field")]
+use crate::ff::Fp32BitPrimeExt;
 use crate::{
-    ff::{Field, Gf2, Gf32Bit, PrimeField, Serializable},
+    ff::{Field, Fp32BitPrime, Fp61BitPrime, Gf2, Gf32Bit, Serializable},
     secret_sharing::{
         replicated::semi_honest::AdditiveShare as SemiHonestAdditiveShare, BitDecomposed,
         Linear as LinearSecretSharing, SecretSharing, SharedValue,
@@ -44,11 +48,34 @@ pub trait ExtendableField: Field {
     fn to_extended(&self) -> Self::ExtendedField;
 }
 
-impl<F: PrimeField> ExtendableField for F {
-    type ExtendedField = F;
+/// Prime fields that are large enough to provide an acceptable level of statistical security
+/// use themselves as the extended field.
+macro_rules! self_extendable_field {
+    ($field:ty) => {
+        impl ExtendableField for $field {
+            type ExtendedField = Self;
+
+            fn to_extended(&self) -> Self::ExtendedField {
+                *self
+            }
+        }
+    };
+}
+
+#[cfg(any(test, feature = "weak-field"))]
+self_extendable_field!(Fp31);
+self_extendable_field!(Fp61BitPrime);
+#[cfg(not(feature = "extended-mac-field"))]
+self_extendable_field!(Fp32BitPrime);
+
+// With a 32-bit prime field, the chance of an additive attack to go undetected is `2^-32`. The
+// quadratic extension of it squares that. `f(x) = x + 0i`
+#[cfg(feature = "extended-mac-field")]
+impl ExtendableField for Fp32BitPrime {
+    type ExtendedField = Fp32BitPrimeExt;
 
     fn to_extended(&self) -> Self::ExtendedField {
-        *self
+        Fp32BitPrimeExt::from(*self)
     }
 }
 
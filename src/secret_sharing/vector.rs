@@ -0,0 +1,336 @@
+//! Vectors of Gf2 values that are shared and computed on as a single value.
+//!
+//! Boolean circuits over [`Gf2`] send one bit per record through the infrastructure, and the
+//! overhead of a message dwarfs its payload. A vector packs many independent Gf2 lanes into one
+//! [`SharedValue`]: addition is XOR and multiplication is AND, lane by lane. A replicated share of
+//! a vector, i.e. `AdditiveShare<Gf2x256>`, uses the same multiplication, reshare and reveal
+//! protocols as any other shared value, but each of them processes all lanes with a single
+//! message.
+
+use std::fmt::{Debug, Formatter};
+
+use generic_array::GenericArray;
+use typenum::{U32, U8};
+
+use crate::{
+    ff::{Field, Gf2, Serializable},
+    protocol::prss::{FromPrss, SharedRandomness},
+    secret_sharing::{Block, SharedValue},
+};
+
+/// Shared values made of independent Gf2 lanes.
+pub trait Gf2Vector: SharedValue + FromPrss {
+    /// Number of lanes in this vector.
+    const LANES: usize;
+
+    /// Value with every lane set to one.
+    const ALL_ONES: Self;
+
+    /// Returns the value of the lane `i`.
+    fn lane(&self, i: usize) -> Gf2;
+
+    /// Sets the value of the lane `i`.
+    fn set_lane(&mut self, i: usize, v: Gf2);
+}
+
+/// A single Gf2 value is a vector of one lane. This allows protocols written over vectors to run
+/// on scalar shares as well.
+impl Gf2Vector for Gf2 {
+    const LANES: usize = 1;
+    const ALL_ONES: Self = Gf2::ONE;
+
+    fn lane(&self, i: usize) -> Gf2 {
+        debug_assert_eq!(0, i);
+        *self
+    }
+
+    fn set_lane(&mut self, i: usize, v: Gf2) {
+        debug_assert_eq!(0, i);
+        *self = v;
+    }
+}
+
+impl Block for [u64; 1] {
+    type Size = U8;
+}
+
+impl Block for [u64; 4] {
+    type Size = U32;
+}
+
+macro_rules! gf2_vector_impl {
+    ( $modname:ident, $name:ident, $words:expr, $size:ty ) => {
+        mod $modname {
+            use super::*;
+
+            #[doc = concat!("Vector of ", stringify!($words), " * 64 Gf2 lanes.")]
+            #[derive(Clone, Copy, PartialEq, Eq)]
+            pub struct $name([u64; $words]);
+
+            impl $name {
+                /// Number of `u128` values drawn from PRSS to generate a random vector.
+                const PRSS_CHUNKS: usize = ($words + 1) / 2;
+
+                #[must_use]
+                pub fn from_words(words: [u64; $words]) -> Self {
+                    Self(words)
+                }
+
+                #[must_use]
+                pub fn words(&self) -> &[u64; $words] {
+                    &self.0
+                }
+
+                fn from_random(chunks: &[u128; $name::PRSS_CHUNKS]) -> Self {
+                    let mut words = [0_u64; $words];
+                    for (i, word) in words.iter_mut().enumerate() {
+                        #[allow(clippy::cast_possible_truncation)]
+                        {
+                            *word = (chunks[i / 2] >> (64 * (i % 2))) as u64;
+                        }
+                    }
+                    Self(words)
+                }
+            }
+
+            impl SharedValue for $name {
+                type Storage = [u64; $words];
+                const BITS: u32 = $words * 64;
+                const ZERO: Self = Self([0; $words]);
+            }
+
+            impl Gf2Vector for $name {
+                const LANES: usize = $words * 64;
+                const ALL_ONES: Self = Self([u64::MAX; $words]);
+
+                fn lane(&self, i: usize) -> Gf2 {
+                    debug_assert!(i < Self::LANES);
+                    Gf2::truncate_from((self.0[i / 64] >> (i % 64)) & 1)
+                }
+
+                fn set_lane(&mut self, i: usize, v: Gf2) {
+                    debug_assert!(i < Self::LANES);
+                    let mask = 1 << (i % 64);
+                    if v == Gf2::ONE {
+                        self.0[i / 64] |= mask;
+                    } else {
+                        self.0[i / 64] &= !mask;
+                    }
+                }
+            }
+
+            impl FromPrss for $name {
+                fn from_prss<R: SharedRandomness + ?Sized, I: Into<u128>>(
+                    prss: &R,
+                    index: I,
+                ) -> (Self, Self) {
+                    let (l, r) = prss.generate_arrays::<{ $name::PRSS_CHUNKS }, _>(index);
+                    (Self::from_random(&l), Self::from_random(&r))
+                }
+            }
+
+            /// Builds a vector from lane values. Missing lanes are set to zero.
+            ///
+            /// ## Panics
+            /// If the iterator yields more values than there are lanes.
+            impl FromIterator<Gf2> for $name {
+                fn from_iter<T: IntoIterator<Item = Gf2>>(iter: T) -> Self {
+                    let mut v = Self::ZERO;
+                    for (i, lane) in iter.into_iter().enumerate() {
+                        assert!(i < Self::LANES, "too many lanes for {}", stringify!($name));
+                        v.set_lane(i, lane);
+                    }
+                    v
+                }
+            }
+
+            impl std::ops::Add for $name {
+                type Output = Self;
+
+                fn add(mut self, rhs: Self) -> Self::Output {
+                    self += rhs;
+                    self
+                }
+            }
+
+            impl std::ops::AddAssign for $name {
+                #[allow(clippy::suspicious_op_assign_impl)]
+                fn add_assign(&mut self, rhs: Self) {
+                    for (a, b) in self.0.iter_mut().zip(rhs.0) {
+                        *a ^= b;
+                    }
+                }
+            }
+
+            impl std::ops::Sub for $name {
+                type Output = Self;
+
+                #[allow(clippy::suspicious_arithmetic_impl)]
+                fn sub(self, rhs: Self) -> Self::Output {
+                    self + rhs
+                }
+            }
+
+            impl std::ops::SubAssign for $name {
+                #[allow(clippy::suspicious_op_assign_impl)]
+                fn sub_assign(&mut self, rhs: Self) {
+                    *self += rhs;
+                }
+            }
+
+            impl std::ops::Mul for $name {
+                type Output = Self;
+
+                fn mul(mut self, rhs: Self) -> Self::Output {
+                    self *= rhs;
+                    self
+                }
+            }
+
+            impl std::ops::MulAssign for $name {
+                #[allow(clippy::suspicious_op_assign_impl)]
+                fn mul_assign(&mut self, rhs: Self) {
+                    for (a, b) in self.0.iter_mut().zip(rhs.0) {
+                        *a &= b;
+                    }
+                }
+            }
+
+            impl std::ops::Neg for $name {
+                type Output = Self;
+
+                fn neg(self) -> Self::Output {
+                    self
+                }
+            }
+
+            impl Serializable for $name {
+                type Size = $size;
+
+                fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+                    for (chunk, word) in buf.chunks_mut(8).zip(self.0) {
+                        chunk.copy_from_slice(&word.to_le_bytes());
+                    }
+                }
+
+                fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
+                    let mut words = [0_u64; $words];
+                    for (word, chunk) in words.iter_mut().zip(buf.chunks(8)) {
+                        *word = u64::from_le_bytes(chunk.try_into().unwrap());
+                    }
+                    Self(words)
+                }
+            }
+
+            impl rand::distributions::Distribution<$name> for rand::distributions::Standard {
+                fn sample<R: crate::rand::Rng + ?Sized>(&self, rng: &mut R) -> $name {
+                    $name(rng.gen())
+                }
+            }
+
+            impl Debug for $name {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    write!(f, concat!(stringify!($name), "_"))?;
+                    for word in self.0.iter().rev() {
+                        write!(f, "{word:016x}")?;
+                    }
+                    Ok(())
+                }
+            }
+
+            #[cfg(all(test, unit_test))]
+            mod tests {
+                use generic_array::GenericArray;
+                use rand::{thread_rng, Rng};
+
+                use super::*;
+
+                #[test]
+                fn lanes() {
+                    let mut rng = thread_rng();
+                    let a = (0..$name::LANES)
+                        .map(|_| rng.gen::<Gf2>())
+                        .collect::<Vec<_>>();
+                    let b = (0..$name::LANES)
+                        .map(|_| rng.gen::<Gf2>())
+                        .collect::<Vec<_>>();
+                    let va = a.iter().copied().collect::<$name>();
+                    let vb = b.iter().copied().collect::<$name>();
+
+                    let (sum, product) = (va + vb, va * vb);
+                    for i in 0..$name::LANES {
+                        assert_eq!(a[i], va.lane(i));
+                        assert_eq!(a[i] + b[i], sum.lane(i));
+                        assert_eq!(a[i] * b[i], product.lane(i));
+                    }
+                    assert_eq!($name::ZERO, va - va);
+                    assert_eq!(va, va * $name::ALL_ONES);
+                }
+
+                #[test]
+                fn serde() {
+                    let v = thread_rng().gen::<$name>();
+                    let mut buf = GenericArray::default();
+                    v.serialize(&mut buf);
+
+                    assert_eq!(v, $name::deserialize(&buf));
+                }
+            }
+        }
+
+        pub use $modname::$name;
+    };
+}
+
+gf2_vector_impl!(gf2x64, Gf2x64, 1, U8);
+gf2_vector_impl!(gf2x256, Gf2x256, 4, U32);
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{thread_rng, Rng};
+
+    use super::{Gf2Vector, Gf2x256};
+    use crate::{
+        helpers::Role,
+        protocol::{
+            basics::{Reshare, Reveal, SecureMul},
+            context::Context,
+            RecordId,
+        },
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    #[tokio::test]
+    async fn multiply_reshare_reveal() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let (a, b) = (rng.gen::<Gf2x256>(), rng.gen::<Gf2x256>());
+
+        let result = world
+            .semi_honest((a, b), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(1);
+                let ab = a
+                    .multiply(&b, ctx.narrow("mul"), RecordId::FIRST)
+                    .await
+                    .unwrap();
+                let ab = ab
+                    .reshare(ctx.narrow("reshare"), RecordId::FIRST, Role::H2)
+                    .await
+                    .unwrap();
+                let revealed = ab
+                    .reveal(ctx.narrow("reveal"), RecordId::FIRST)
+                    .await
+                    .unwrap();
+                (ab, revealed)
+            })
+            .await;
+
+        let [(s0, r0), (s1, r1), (s2, r2)] = result;
+        assert_eq!(a * b, [s0, s1, s2].reconstruct());
+        for r in [r0, r1, r2] {
+            for i in 0..Gf2x256::LANES {
+                assert_eq!(a.lane(i) * b.lane(i), r.lane(i));
+            }
+        }
+    }
+}
@@ -1,15 +1,18 @@
+#[cfg(feature = "web-app")]
+pub mod prometheus;
 pub mod stats;
 mod step_stats;
 
-pub use step_stats::CsvExporter as StepStatsCsvExporter;
+pub use step_stats::{CsvExporter as StepStatsCsvExporter, StepStats, StepsStats};
 
 pub mod labels {
     pub const STEP: &str = "step";
     pub const ROLE: &str = "role";
+    pub const QUERY_TYPE: &str = "query_type";
 }
 
 pub mod metrics {
-    use metrics::{describe_counter, Unit};
+    use metrics::{describe_counter, describe_histogram, Unit};
 
     pub const REQUESTS_RECEIVED: &str = "requests.received";
     pub const RECORDS_SENT: &str = "records.sent";
@@ -17,6 +20,9 @@ pub mod metrics {
     pub const INDEXED_PRSS_GENERATED: &str = "i.prss.gen";
     pub const SEQUENTIAL_PRSS_GENERATED: &str = "s.prss.gen";
     pub const STEP_NARROWED: &str = "step.narrowed";
+    pub const QUERIES_STARTED: &str = "queries.started";
+    pub const QUERY_LATENCY: &str = "query.latency";
+    pub const STEP_BYTES_SENT: &str = "step.bytes.sent";
 
     #[cfg(feature = "web-app")]
     pub mod web {
@@ -105,5 +111,23 @@ pub mod metrics {
             Unit::Count,
             "Number of times the step is narrowed"
         );
+
+        describe_counter!(
+            QUERIES_STARTED,
+            Unit::Count,
+            "Number of queries this helper started executing"
+        );
+
+        describe_histogram!(
+            QUERY_LATENCY,
+            Unit::Seconds,
+            "Time it took to execute a query, from PRSS negotiation to the result"
+        );
+
+        describe_histogram!(
+            STEP_BYTES_SENT,
+            Unit::Bytes,
+            "Size of the batches of messages flushed from the infrastructure layer to the network, per step"
+        );
     }
 }
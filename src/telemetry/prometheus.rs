@@ -0,0 +1,143 @@
+//! Exports metrics emitted by the helper in Prometheus text format.
+//!
+//! The exporter is served on a separate admin port, so it can be scraped without a helper
+//! certificate and without exposing it to the other helpers.
+
+use std::net::SocketAddr;
+
+use axum::{routing::get, Extension, Router};
+use axum_server::Handle;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use metrics_tracing_context::TracingContextLayer;
+use metrics_util::layers::Layer;
+use tokio::task::JoinHandle;
+
+use crate::telemetry::metrics::{register, QUERY_LATENCY, STEP_BYTES_SENT};
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Buckets for [`QUERY_LATENCY`], in seconds. Queries range from sub-second test queries to
+/// multi-hour IPA runs.
+const QUERY_LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0,
+];
+
+/// Buckets for [`STEP_BYTES_SENT`], in bytes. Batches range from a single message on
+/// indeterminate channels to a full active work window of shares.
+const STEP_BYTES_SENT_BUCKETS: &[f64] = &[
+    8.0,
+    64.0,
+    512.0,
+    4096.0,
+    32768.0,
+    262_144.0,
+    1_048_576.0,
+    8_388_608.0,
+];
+
+fn builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(QUERY_LATENCY.into()), QUERY_LATENCY_BUCKETS)?
+        .set_buckets_for_metric(
+            Matcher::Full(STEP_BYTES_SENT.into()),
+            STEP_BYTES_SENT_BUCKETS,
+        )
+}
+
+/// Installs the Prometheus recorder as the global metrics recorder and registers all metrics
+/// used by IPA with it.
+///
+/// ## Errors
+/// If a metrics recorder has already been installed.
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    let recorder = builder()?.build_recorder();
+    let handle = recorder.handle();
+
+    // use span fields as dimensions for metric, same as the CLI collector
+    let recorder = TracingContextLayer::all().layer(recorder);
+    metrics::set_boxed_recorder(Box::new(recorder))?;
+
+    register();
+
+    Ok(handle)
+}
+
+#[allow(clippy::unused_async)] // needs to be async for axum handler
+async fn render(handle: Extension<PrometheusHandle>) -> String {
+    handle.render()
+}
+
+/// Router that serves `GET /metrics` from the recorder behind `handle`.
+pub fn router(handle: PrometheusHandle) -> Router {
+    Router::new()
+        .route(METRICS_PATH, get(render))
+        .layer(Extension(handle))
+}
+
+/// Starts serving [`router`] over plain HTTP on `addr`.
+///
+/// Returns the `SocketAddr` of the server socket and the `JoinHandle` of the server task.
+///
+/// ## Panics
+/// If the server fails to bind to `addr`.
+pub async fn serve(addr: SocketAddr, handle: PrometheusHandle) -> (SocketAddr, JoinHandle<()>) {
+    let server_handle = Handle::new();
+    let task_handle = tokio::spawn({
+        let server_handle = server_handle.clone();
+        async move {
+            axum_server::bind(addr)
+                .handle(server_handle)
+                .serve(router(handle).into_make_service())
+                .await
+                .expect("Failed to serve metrics");
+        }
+    });
+
+    let bound_addr = server_handle
+        .listening()
+        .await
+        .expect("Failed to bind metrics server to a port");
+    tracing::info!("metrics available at http://{bound_addr}{METRICS_PATH}");
+
+    (bound_addr, task_handle)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use hyper::{Body, Request, StatusCode};
+    use metrics::{Key, Label, Recorder};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::telemetry::{labels::QUERY_TYPE, metrics::QUERIES_STARTED};
+
+    #[tokio::test]
+    async fn renders_prometheus_text() {
+        // use a local recorder, the global one may already be taken by other tests
+        let recorder = builder().unwrap().build_recorder();
+        let labels = vec![Label::new(QUERY_TYPE, "semihonest-ipa")];
+        recorder
+            .register_counter(&Key::from_parts(QUERIES_STARTED, labels.clone()))
+            .increment(2);
+        recorder
+            .register_histogram(&Key::from_parts(QUERY_LATENCY, labels))
+            .record(42.0);
+
+        let resp = router(recorder.handle())
+            .oneshot(Request::get(METRICS_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains(r#"queries_started{query_type="semihonest-ipa"} 2"#),
+            "{body}"
+        );
+        assert!(
+            body.contains(r#"query_latency_bucket{query_type="semihonest-ipa",le="60"} 1"#),
+            "{body}"
+        );
+    }
+}
@@ -78,6 +78,10 @@ impl Metrics {
 
         let snapshot = snapshot.into_vec();
         for (ckey, _, descr, val) in snapshot {
+            // Histograms are only consumed by the Prometheus exporter, these stats are counters only
+            if ckey.kind() != MetricKind::Counter {
+                continue;
+            }
             let (key_name, labels) = ckey.key().clone().into_parts();
             if !filter_fn(labels.as_slice()) {
                 continue;
@@ -88,10 +92,7 @@ impl Metrics {
                 this.metric_description.insert(key_name, descr);
             }
 
-            match ckey.kind() {
-                MetricKind::Counter => entry.add(&ckey, &val),
-                MetricKind::Gauge | MetricKind::Histogram => unimplemented!(),
-            }
+            entry.add(&ckey, &val);
         }
 
         this
@@ -25,17 +25,9 @@ pub trait CsvExporter {
 
 impl CsvExporter for Metrics {
     fn export<W: Write>(&self, w: &mut W) -> Result<(), Error> {
-        // first thing is to collect all the steps and metrics emitted
-        let mut steps_stats = StepsStats::default();
-        for (counter_name, details) in &self.counters {
-            if let Some(steps) = details.dimensions.get(labels::STEP) {
-                for (step, val) in steps {
-                    steps_stats.offer(step, counter_name.as_str(), *val);
-                }
-            }
-        }
+        let steps_stats = StepsStats::from(self);
 
-        // then dump them to the provided Write interface
+        // dump the stats to the provided Write interface
         // TODO: include role dimension. That requires rethinking `Metrics` implementation
         // because it does not allow such breakdown atm.
         if self.print_header {
@@ -61,13 +53,29 @@ impl CsvExporter for Metrics {
     }
 }
 
+/// Counters recorded with a step dimension, partitioned by step.
 #[derive(Default)]
-struct StepsStats<'a> {
+pub struct StepsStats<'a> {
     inner: BTreeMap<&'a str, StepStats<'a>>,
 }
 
+impl<'a> From<&'a Metrics> for StepsStats<'a> {
+    fn from(metrics: &'a Metrics) -> Self {
+        let mut steps_stats = StepsStats::default();
+        for (counter_name, details) in &metrics.counters {
+            if let Some(steps) = details.dimensions.get(labels::STEP) {
+                for (step, val) in steps {
+                    steps_stats.offer(step, counter_name.as_str(), *val);
+                }
+            }
+        }
+
+        steps_stats
+    }
+}
+
 impl<'a> StepsStats<'a> {
-    pub fn offer(&mut self, step_name: &'a str, metric: &'a str, val: u64) {
+    fn offer(&mut self, step_name: &'a str, metric: &'a str, val: u64) {
         if let Some(step_stats) = self.inner.get_mut(step_name) {
             step_stats.offer(metric, val);
         } else {
@@ -83,7 +91,7 @@ impl<'a> StepsStats<'a> {
 }
 
 #[derive(Default)]
-struct StepStats<'a> {
+pub struct StepStats<'a> {
     inner: HashMap<&'a str, u64>,
 }
 
@@ -96,7 +104,9 @@ impl<'a> StepStats<'a> {
         }
     }
 
-    fn get(&'a self, metric_name: &'a str) -> u64 {
+    /// Returns the value of the counter `metric_name` for this step, or 0 if it was not recorded.
+    #[must_use]
+    pub fn get(&'a self, metric_name: &'a str) -> u64 {
         *self.inner.get(metric_name).unwrap_or(&0)
     }
 }
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use crate::{
+    helpers::{ChannelId, Role, SendAction, SendHook, TotalRecords},
+    protocol::RecordId,
+    sync::Mutex,
+};
+
+/// What a [`Tamper`] hook does with the messages it intercepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tampering {
+    /// Changes the value of the message by clearing the lowest set bit of its first non-zero byte,
+    /// or by setting the lowest bit if all bytes are zero. Values are serialized in little-endian
+    /// order, so this makes them smaller or sets them to one, and the message remains a valid value
+    /// of its type.
+    FlipBit,
+    /// Stops sending on the channel at the first message, as a helper that gives up on the
+    /// protocol would. The peer sees the end of the stream, so receiving fails.
+    Drop,
+    /// Swaps the messages of records `2k` and `2k + 1`, holding back the first of them until the
+    /// second one is sent. The last message stays in place if the channel has an odd number of
+    /// records, or if the number of records is not known.
+    Reorder,
+}
+
+/// [`SendHook`] that tampers with the messages sent on the gates that contain a pattern.
+///
+/// By default, all messages on these gates are tampered with. They can be restricted to one peer,
+/// which makes the adversarial helper send different values to each of its peers, or to a single
+/// record.
+pub struct Tamper {
+    tampering: Tampering,
+    gate: String,
+    peer: Option<Role>,
+    record: Option<RecordId>,
+    held: Mutex<HashMap<(ChannelId, RecordId), Vec<u8>>>,
+}
+
+impl Tamper {
+    #[must_use]
+    pub fn new(tampering: Tampering, gate: &str) -> Self {
+        Self {
+            tampering,
+            gate: gate.to_owned(),
+            peer: None,
+            record: None,
+            held: Mutex::default(),
+        }
+    }
+
+    /// Only tampers with the messages sent to `peer`.
+    #[must_use]
+    pub fn to_peer(mut self, peer: Role) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Only tampers with the message for `record_id`. [`Tampering::Reorder`] swaps it with the
+    /// other record of its pair.
+    #[must_use]
+    pub fn on_record(mut self, record_id: RecordId) -> Self {
+        self.record = Some(record_id);
+        self
+    }
+
+    fn matches(&self, channel_id: &ChannelId, record_id: RecordId) -> bool {
+        let record_matches = |record: RecordId| match self.tampering {
+            Tampering::Reorder => pair_of(record) == pair_of(record_id),
+            Tampering::FlipBit | Tampering::Drop => record == record_id,
+        };
+        self.peer.map_or(true, |peer| peer == channel_id.role)
+            && self.record.map_or(true, record_matches)
+            && channel_id.gate.as_ref().contains(&self.gate)
+    }
+
+    fn reorder(
+        &self,
+        channel_id: &ChannelId,
+        record_id: RecordId,
+        total_records: TotalRecords,
+        data: &[u8],
+    ) -> SendAction {
+        let i = usize::from(record_id);
+        let partner = RecordId::from(i ^ 1);
+        let has_partner = match total_records {
+            TotalRecords::Specified(count) => (i ^ 1) < count.get(),
+            TotalRecords::Unspecified | TotalRecords::Indeterminate => false,
+        };
+        if !has_partner {
+            return SendAction::Send(vec![(record_id, data.to_vec())]);
+        }
+
+        let mut held = self.held.lock().unwrap();
+        if let Some(partner_data) = held.remove(&(channel_id.clone(), partner)) {
+            SendAction::Send(vec![(partner, data.to_vec()), (record_id, partner_data)])
+        } else {
+            held.insert((channel_id.clone(), record_id), data.to_vec());
+            SendAction::Send(Vec::new())
+        }
+    }
+}
+
+fn pair_of(record_id: RecordId) -> usize {
+    usize::from(record_id) / 2
+}
+
+impl SendHook for Tamper {
+    fn on_send(
+        &self,
+        channel_id: &ChannelId,
+        record_id: RecordId,
+        total_records: TotalRecords,
+        data: &[u8],
+    ) -> SendAction {
+        if !self.matches(channel_id, record_id) {
+            return SendAction::Send(vec![(record_id, data.to_vec())]);
+        }
+
+        match self.tampering {
+            Tampering::FlipBit => {
+                let mut data = data.to_vec();
+                match data.iter_mut().find(|b| **b != 0) {
+                    Some(b) => *b &= *b - 1,
+                    None => data[0] = 1,
+                }
+                SendAction::Send(vec![(record_id, data)])
+            }
+            Tampering::Drop => SendAction::End,
+            Tampering::Reorder => self.reorder(channel_id, record_id, total_records, data),
+        }
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::future::ready;
+
+    use futures::{
+        future::join3,
+        stream::{once, TryStreamExt},
+    };
+
+    use super::{Tamper, Tampering};
+    use crate::{
+        error::Error,
+        ff::{Field, Fp31, Fp32BitPrime, Gf3Bit, Gf8Bit},
+        helpers::{query::IpaQueryConfig, Direction, Role},
+        ipa_test_input,
+        protocol::{
+            aggregation::{sparse_aggregate, SparseAggregateInputRow},
+            context::{Context, SemiHonestContext, UpgradableContext, UpgradedContext, Validator},
+            ipa::ipa,
+            modulus_conversion::convert_bits,
+            shuffle::{shuffle, ShufflePermutations},
+            BreakdownKey, MatchKey, RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, SharedValue},
+        sync::Arc,
+        test_fixture::{input::GenericReportTestInput, Runner, TestWorld, TestWorldConfig},
+    };
+
+    fn adversarial_world(role: Role, tamper: Tamper) -> TestWorld {
+        TestWorld::new_with(TestWorldConfig::default().with_adversary(role, Arc::new(tamper)))
+    }
+
+    fn assert_aborted<T: std::fmt::Debug>(results: [Result<T, Error>; 3]) {
+        for result in results {
+            assert!(
+                matches!(result, Err(Error::MaliciousSecurityCheckFailed)),
+                "expected the malicious security check to fail, got {result:?}"
+            );
+        }
+    }
+
+    /// Makes H1 send `values` to both of its peers and returns the values that H2 and H3 receive.
+    /// A value that can't be received is `None`, and so are all the values after it.
+    async fn send_from_h1(tamper: Tamper, values: &[u128]) -> [Vec<Option<u128>>; 2] {
+        let world = adversarial_world(Role::H1, tamper);
+        let [h1, h2, h3] = world
+            .contexts()
+            .map(|ctx| ctx.narrow("values").set_total_records(values.len()));
+
+        let send = async {
+            for peer in [Role::H2, Role::H3] {
+                let channel = h1.send_channel(peer);
+                for (i, &v) in values.iter().enumerate() {
+                    channel
+                        .send(RecordId::from(i), Fp31::truncate_from(v))
+                        .await
+                        .unwrap();
+                }
+            }
+        };
+        let ((), from_h2, from_h3) = join3(
+            send,
+            receive_from_h1(h2, values.len()),
+            receive_from_h1(h3, values.len()),
+        )
+        .await;
+        [from_h2, from_h3]
+    }
+
+    async fn receive_from_h1(ctx: SemiHonestContext<'_>, count: usize) -> Vec<Option<u128>> {
+        let channel = ctx.recv_channel::<Fp31>(Role::H1);
+        let mut received = vec![None; count];
+        for (i, v) in received.iter_mut().enumerate() {
+            match channel.receive(RecordId::from(i)).await {
+                Ok(r) => *v = Some(r.as_u128()),
+                Err(_) => break,
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn tampers_with_matching_messages() {
+        const VALUES: &[u128] = &[5, 9, 0];
+        let sent = VALUES.iter().copied().map(Some).collect::<Vec<_>>();
+        let flipped = vec![Some(4), Some(8), Some(1)];
+
+        let received = send_from_h1(Tamper::new(Tampering::FlipBit, "values"), VALUES).await;
+        assert_eq!([flipped.clone(), flipped.clone()], received);
+
+        let received = send_from_h1(Tamper::new(Tampering::FlipBit, "other"), VALUES).await;
+        assert_eq!([sent.clone(), sent.clone()], received);
+
+        let tamper = Tamper::new(Tampering::FlipBit, "values").to_peer(Role::H3);
+        let received = send_from_h1(tamper, VALUES).await;
+        assert_eq!([sent.clone(), flipped], received);
+
+        let tamper = Tamper::new(Tampering::FlipBit, "values").on_record(RecordId::from(1));
+        let received = send_from_h1(tamper, VALUES).await;
+        let expected = vec![Some(5), Some(8), Some(0)];
+        assert_eq!([expected.clone(), expected], received);
+
+        let received = send_from_h1(Tamper::new(Tampering::Reorder, "values"), VALUES).await;
+        let expected = vec![Some(9), Some(5), Some(0)];
+        assert_eq!([expected.clone(), expected], received);
+
+        let tamper = Tamper::new(Tampering::Drop, "values").on_record(RecordId::from(1));
+        let received = send_from_h1(tamper, VALUES).await;
+        let expected = vec![Some(5), None, None];
+        assert_eq!([expected.clone(), expected], received);
+    }
+
+    #[tokio::test]
+    async fn modulus_conversion_aborts() {
+        for &role in Role::all() {
+            let world = adversarial_world(role, Tamper::new(Tampering::FlipBit, "xor"));
+            let match_key = thread_rng().gen::<MatchKey>();
+            let results = world
+                .malicious(match_key, |ctx, mk_share| async move {
+                    let v = ctx.validator::<Fp32BitPrime>();
+                    let bits = convert_bits(
+                        v.context().set_total_records(1),
+                        once(ready(mk_share)),
+                        0..MatchKey::BITS,
+                    )
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                    v.validate(bits).await
+                })
+                .await;
+            assert_aborted(results);
+        }
+    }
+
+    #[tokio::test]
+    async fn sparse_aggregate_aborts() {
+        type BK = Gf3Bit;
+        type CV = Gf8Bit;
+        const INPUT: &[(u128, u128)] = &[(0, 18), (3, 5), (4, 1), (7, 2), (3, 1)];
+
+        for &role in Role::all() {
+            // Only one of the peers receives the wrong value.
+            let tamper = Tamper::new(Tampering::FlipBit, "check_times_value")
+                .to_peer(role.peer(Direction::Right));
+            let world = adversarial_world(role, tamper);
+            let input = INPUT
+                .iter()
+                .map(|&(bk, value)| (BK::truncate_from(bk), CV::truncate_from(value)));
+            let results = world
+                .malicious(
+                    input,
+                    |ctx, shares: Vec<(Replicated<BK>, Replicated<CV>)>| async move {
+                        let rows = shares
+                            .into_iter()
+                            .map(
+                                |(breakdown_key, contribution_value)| SparseAggregateInputRow {
+                                    contribution_value,
+                                    breakdown_key,
+                                },
+                            )
+                            .collect::<Vec<_>>();
+                        sparse_aggregate::<_, _, _, Fp32BitPrime, CV, BK>(ctx, &rows, 1 << BK::BITS)
+                            .await
+                    },
+                )
+                .await;
+            assert_aborted(results);
+        }
+    }
+
+    #[tokio::test]
+    async fn shuffle_aborts() {
+        const COUNT: u128 = 10;
+
+        for &role in Role::all() {
+            let world = adversarial_world(role, Tamper::new(Tampering::Reorder, "shuffle"));
+            let input = (0..COUNT).map(Fp32BitPrime::truncate_from);
+            let results = world
+                .malicious(input, |ctx, shares| async move {
+                    let v = ctx.validator::<Fp32BitPrime>();
+                    let m_ctx = v.context();
+                    let rows = m_ctx.upgrade(shares).await?;
+                    let permutations =
+                        ShufflePermutations::generate(&m_ctx.narrow("permutations"), rows.len());
+                    let rows = shuffle(m_ctx.narrow("shuffle"), rows, &permutations).await?;
+                    v.validate(rows).await
+                })
+                .await;
+            assert_aborted(results);
+        }
+    }
+
+    /// Tampers with multiplications in the sort, the binary attribution circuit and the
+    /// aggregation of IPA. Each of them is checked by a validator before anything that depends on
+    /// them is revealed, so every helper sees the check fail.
+    #[tokio::test]
+    async fn ipa_aborts() {
+        const GATES: &[&str] = &[
+            "bit_permutation",
+            "compute_helper_bits",
+            "compute_equality_checks",
+            "check_times_credit",
+        ];
+        let records: Vec<GenericReportTestInput<Fp32BitPrime, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 1, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 2, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                { timestamp: 3, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 4, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                { timestamp: 5, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+            ];
+            (Fp32BitPrime, MatchKey, BreakdownKey)
+        );
+
+        for gate in GATES {
+            for &role in Role::all() {
+                let world = adversarial_world(role, Tamper::new(Tampering::FlipBit, gate));
+                let results = world
+                    .malicious(records.clone().into_iter(), |ctx, input_rows| async move {
+                        ipa::<_, _, _, _, MatchKey, BreakdownKey>(
+                            ctx,
+                            &input_rows,
+                            IpaQueryConfig::no_window(3, 3, 3),
+                        )
+                        .await
+                    })
+                    .await;
+                assert_aborted(results);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Drivers for the standalone sparse aggregation protocol, shared by tests and benchmarks.
+
+use crate::{
+    ff::{GaloisField, PrimeField, Serializable},
+    protocol::aggregation::{sparse_aggregate, SparseAggregateInputRow},
+    secret_sharing::{
+        replicated::{
+            malicious::{self, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        IntoShares,
+    },
+    test_fixture::{ipa::IpaSecurityModel, Reconstruct, Runner, TestWorld},
+};
+
+/// Aggregates `(breakdown_key, contribution_value)` pairs in the clear.
+///
+/// ## Panics
+/// If a breakdown key is not less than `num_buckets`.
+#[must_use]
+pub fn sparse_aggregate_in_the_clear(input: &[(u32, u32)], num_buckets: usize) -> Vec<u32> {
+    let mut buckets = vec![0; num_buckets];
+    for &(breakdown_key, value) in input {
+        buckets[usize::try_from(breakdown_key).unwrap()] += value;
+    }
+    buckets
+}
+
+/// Runs [`sparse_aggregate`] on the given `(breakdown_key, contribution_value)` pairs and checks
+/// the result against [`sparse_aggregate_in_the_clear`].
+///
+/// ## Panics
+/// If the protocol fails or produces a result that does not match the expected buckets.
+pub async fn test_sparse_aggregate<BK, CV, F>(
+    world: &TestWorld,
+    input: &[(u32, u32)],
+    num_buckets: usize,
+    security_model: IpaSecurityModel,
+) where
+    BK: GaloisField + IntoShares<Replicated<BK>>,
+    CV: GaloisField + IntoShares<Replicated<CV>>,
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+    malicious::AdditiveShare<F>: Serializable,
+{
+    fn rows<BK: GaloisField, CV: GaloisField>(
+        shares: Vec<(Replicated<BK>, Replicated<CV>)>,
+    ) -> Vec<SparseAggregateInputRow<CV, BK>> {
+        shares
+            .into_iter()
+            .map(
+                |(breakdown_key, contribution_value)| SparseAggregateInputRow {
+                    contribution_value,
+                    breakdown_key,
+                },
+            )
+            .collect()
+    }
+
+    let expected = sparse_aggregate_in_the_clear(input, num_buckets);
+    let input = input
+        .iter()
+        .map(|&(bk, value)| (BK::truncate_from(bk), CV::truncate_from(value)))
+        .collect::<Vec<_>>();
+
+    let result: Vec<F> = match security_model {
+        IpaSecurityModel::Malicious => world
+            .malicious(input.into_iter(), |ctx, shares| async move {
+                sparse_aggregate::<_, _, _, F, CV, BK>(ctx, &rows(shares), num_buckets)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct(),
+        IpaSecurityModel::SemiHonest => world
+            .semi_honest(input.into_iter(), |ctx, shares| async move {
+                sparse_aggregate::<_, _, _, F, CV, BK>(ctx, &rows(shares), num_buckets)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct(),
+    };
+    let result = result
+        .into_iter()
+        .map(|v| u32::try_from(v.as_u128()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(result, expected);
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::test_sparse_aggregate;
+    use crate::{
+        ff::{Fp32BitPrime, Gf3Bit, Gf8Bit},
+        test_fixture::{ipa::IpaSecurityModel, TestWorld},
+    };
+
+    #[tokio::test]
+    async fn sparse_aggregate_matches_the_clear() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let input = (0..50)
+            .map(|_| (rng.gen_range(0..6), rng.gen_range(0..10)))
+            .collect::<Vec<_>>();
+
+        let world = TestWorld::default();
+        for security_model in [IpaSecurityModel::SemiHonest, IpaSecurityModel::Malicious] {
+            test_sparse_aggregate::<Gf3Bit, Gf8Bit, Fp32BitPrime>(
+                &world,
+                &input,
+                6,
+                security_model,
+            )
+            .await;
+        }
+    }
+}
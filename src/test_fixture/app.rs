@@ -1,4 +1,4 @@
-use std::iter::zip;
+use std::{iter::zip, path::Path};
 
 use generic_array::GenericArray;
 use typenum::Unsigned;
@@ -8,10 +8,11 @@ use crate::{
     ff::Serializable,
     helpers::{
         query::{QueryConfig, QueryInput},
-        InMemoryNetwork, InMemoryTransport,
+        InMemoryNetwork, InMemoryTransport, TransportCallbacks,
     },
+    hpke::KeyRegistry,
     protocol::QueryId,
-    query::QueryStatus,
+    query::{AdmissionConfig, QueryStatus},
     secret_sharing::IntoShares,
     test_fixture::try_join3_array,
     AppSetup, HelperApp,
@@ -60,8 +61,25 @@ fn unzip_tuple_array<T, U>(input: [(T, U); 3]) -> ([T; 3], [U; 3]) {
 
 impl Default for TestApp {
     fn default() -> Self {
-        let (setup, callbacks) =
-            unzip_tuple_array([AppSetup::new(), AppSetup::new(), AppSetup::new()]);
+        Self::new([AppSetup::new(), AppSetup::new(), AppSetup::new()])
+    }
+}
+
+impl TestApp {
+    /// Creates an app whose helpers capture the traffic of every query to a file in `dir`.
+    #[must_use]
+    pub fn with_capture_dir(dir: &Path) -> Self {
+        Self::new([(); 3].map(|()| {
+            AppSetup::with_config(
+                KeyRegistry::empty(),
+                AdmissionConfig::default(),
+                Some(dir.to_owned()),
+            )
+        }))
+    }
+
+    fn new(setups: [(AppSetup, TransportCallbacks<InMemoryTransport>); 3]) -> Self {
+        let (setup, callbacks) = unzip_tuple_array(setups);
 
         let network = InMemoryNetwork::new(callbacks);
         let drivers = network
@@ -76,9 +94,7 @@ impl Default for TestApp {
 
         Self { drivers, network }
     }
-}
 
-impl TestApp {
     /// Initiates a new query on all helpers and drives it to completion.
     ///
     /// ## Errors
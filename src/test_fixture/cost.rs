@@ -0,0 +1,538 @@
+//! Analytic communication cost of the top-level protocols.
+//!
+//! [`Simulator`] measures what a query sends, but only for inputs that fit in a test. The
+//! functions here compute the same numbers from the input size and the query configuration, by
+//! counting the multiplications, reveals, reshares and validations every step of the protocol
+//! does. Records and bytes are exact for inputs of any size, unless the protocol is data
+//! dependent. Rounds are the depth of the circuit, which is within a few percent of the measured
+//! latency, as long as every helper is allowed at least as many records in flight as there are
+//! input rows (see [`GatewayConfig`]). Smaller windows add rounds that the model does not
+//! account for.
+//!
+//! The tests of each protocol check that the model stays within a tolerance of the
+//! [`Simulator`] measurements.
+//!
+//! [`Simulator`]: crate::test_fixture::simulate::Simulator
+//! [`GatewayConfig`]: crate::helpers::GatewayConfig
+
+use std::iter::successors;
+
+use generic_array::typenum::Unsigned;
+
+#[cfg(feature = "in-memory-infra")]
+use crate::test_fixture::simulate::Simulator;
+use crate::{
+    ff::{GaloisField, Gf2, Serializable},
+    helpers::query::IpaQueryConfig,
+    secret_sharing::replicated::malicious::ExtendableField,
+    test_fixture::ipa::IpaSecurityModel,
+};
+
+/// Network usage of a protocol, summed over all helpers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub records_sent: u64,
+    pub bytes_sent: u64,
+    /// Number of rounds on the critical path.
+    pub rounds: u64,
+}
+
+impl Cost {
+    pub const ZERO: Self = Self {
+        records_sent: 0,
+        bytes_sent: 0,
+        rounds: 0,
+    };
+
+    /// `records` messages of `size` bytes each, sent in parallel.
+    fn messages(records: u64, size: u64) -> Self {
+        Self {
+            records_sent: records,
+            bytes_sent: records * size,
+            rounds: 0,
+        }
+    }
+
+    fn rounds(self, rounds: u64) -> Self {
+        Self { rounds, ..self }
+    }
+
+    /// The cost of running `self` and then `next`.
+    #[must_use]
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            records_sent: self.records_sent + next.records_sent,
+            bytes_sent: self.bytes_sent + next.bytes_sent,
+            rounds: self.rounds + next.rounds,
+        }
+    }
+
+    /// The cost of running `self` and `other` at the same time.
+    #[must_use]
+    pub fn alongside(self, other: Self) -> Self {
+        Self {
+            rounds: self.rounds.max(other.rounds),
+            ..self.then(other)
+        }
+    }
+
+    /// The cost of running `self` `count` times, one after another.
+    #[must_use]
+    pub fn times(self, count: u64) -> Self {
+        Self {
+            records_sent: self.records_sent * count,
+            bytes_sent: self.bytes_sent * count,
+            rounds: self.rounds * count,
+        }
+    }
+
+    /// Network usage of everything that ran in `simulator` so far.
+    #[cfg(feature = "in-memory-infra")]
+    #[must_use]
+    pub fn measured(simulator: &Simulator) -> Self {
+        let report = simulator.report();
+        Self {
+            records_sent: report.steps.iter().map(|step| step.records_sent).sum(),
+            bytes_sent: report.bytes_sent(),
+            rounds: simulator.rounds(),
+        }
+    }
+
+    /// Whether every field of `measured` is within `tolerance` (a fraction) of `self`.
+    #[must_use]
+    pub fn is_close_to(&self, measured: &Self, tolerance: f64) -> bool {
+        #[allow(clippy::cast_precision_loss)]
+        let close = |expected: u64, measured: u64| {
+            expected.abs_diff(measured) as f64 <= expected as f64 * tolerance
+        };
+        close(self.records_sent, measured.records_sent)
+            && close(self.bytes_sent, measured.bytes_sent)
+            && close(self.rounds, measured.rounds)
+    }
+}
+
+/// Costs of the building blocks over shares of one field.
+///
+/// In the malicious setting, multiplications and reshares also send the share of `r * x`, which
+/// lives in the extended field, and every part of the protocol ends with a validation.
+#[derive(Clone, Copy)]
+struct Shares {
+    /// Bytes in a share.
+    size: u64,
+    /// Bytes in a share of `r * x`, or `None` for semi-honest shares.
+    mac_size: Option<u64>,
+    /// Number of bits in the field.
+    bits: u64,
+}
+
+impl Shares {
+    fn new<V: ExtendableField>(security: IpaSecurityModel) -> Self {
+        Self {
+            size: <V as Serializable>::Size::U64,
+            mac_size: match security {
+                IpaSecurityModel::SemiHonest => None,
+                IpaSecurityModel::Malicious => Some(<V::ExtendedField as Serializable>::Size::U64),
+            },
+            bits: u64::from(V::BITS),
+        }
+    }
+
+    fn is_malicious(&self) -> bool {
+        self.mac_size.is_some()
+    }
+
+    /// `records` messages of one share and, in the malicious setting, `mac_records` messages of
+    /// the share of `r * x`.
+    fn send(&self, records: u64, mac_records: u64) -> Cost {
+        Cost::messages(records, self.size).then(self.send_macs(mac_records))
+    }
+
+    fn send_macs(&self, records: u64) -> Cost {
+        self.mac_size
+            .map_or(Cost::ZERO, |size| Cost::messages(records, size))
+    }
+
+    /// Semi-honest shares of the extended field, which validations work with.
+    fn macs(&self) -> Option<Self> {
+        self.mac_size.map(|size| Self {
+            size,
+            mac_size: None,
+            // only the size matters to validations
+            bits: size * 8,
+        })
+    }
+
+    /// `count` multiplications in one round.
+    fn multiply(&self, count: u64) -> Cost {
+        self.send(3 * count, 3 * count).rounds(u64::from(count > 0))
+    }
+
+    /// `count` multiplications `depth` rounds deep.
+    fn multiply_in(&self, count: u64, depth: u64) -> Cost {
+        self.multiply(count)
+            .rounds(if count > 0 { depth } else { 0 })
+    }
+
+    /// Reveals `count` values. Malicious reveals send each share to both peers.
+    fn reveal(&self, count: u64) -> Cost {
+        let per_value = if self.is_malicious() { 6 } else { 3 };
+        Cost::messages(per_value * count, self.size).rounds(u64::from(count > 0))
+    }
+
+    /// Multiplies `count` semi-honest shares by `r`.
+    fn upgrade(&self, count: u64) -> Cost {
+        self.send_macs(3 * count)
+            .rounds(u64::from(self.is_malicious() && count > 0))
+    }
+
+    /// Checks all multiplications done so far, see [`Malicious::validate`]. Every helper sends its
+    /// shares of `u` and `w` to the next one, then `r` is revealed and [`check_zero`] multiplies
+    /// `u - r * w` by a random value and reveals it. Semi-honest protocols are never validated.
+    ///
+    /// [`Malicious::validate`]: crate::protocol::context::validator::Malicious
+    /// [`check_zero`]: crate::protocol::basics::check_zero
+    fn validate(&self) -> Cost {
+        self.macs().map_or(Cost::ZERO, |macs| {
+            let propagate_u_and_w = macs.send(2 * 3, 0).rounds(1);
+            propagate_u_and_w
+                .then(macs.reveal(1))
+                .then(macs.multiply(1))
+                .then(macs.reveal(1))
+        })
+    }
+
+    /// Reshares `count` values three times under random permutations. Malicious shuffles are
+    /// validated on their own.
+    fn shuffle(&self, count: u64) -> Cost {
+        self.unchecked_shuffle(count).then(self.validate())
+    }
+
+    fn unchecked_shuffle(&self, count: u64) -> Cost {
+        self.send(6 * count, 6 * count).rounds(2)
+    }
+
+    /// Converts `count` bits from boolean to arithmetic shares.
+    fn convert_bits(&self, count: u64) -> Cost {
+        if self.is_malicious() {
+            self.send(3 * count, 10 * count).rounds(3)
+        } else {
+            self.send(3 * count, 0).rounds(2)
+        }
+    }
+
+    /// Turns `count` values of `bits` bits into one-hot vectors.
+    fn bitwise_to_onehot(&self, count: u64, bits: u64) -> Cost {
+        self.multiply_in(count * ((1 << bits) - bits - 1), bits.saturating_sub(1))
+    }
+
+    /// Generates `count` random values together with their bits.
+    fn random_bits(&self, count: u64) -> Cost {
+        self.convert_bits(count * self.bits)
+            .then(self.multiply_in(count * (self.bits - 1), ceil_log2(self.bits) + 1))
+            .then(self.reveal(count))
+    }
+
+    /// Compares `count` values with a constant, given random bits for each.
+    fn greater_than_constant(&self, count: u64) -> Cost {
+        self.reveal(count)
+            .then(self.multiply_in(count * (2 * self.bits + 1), 2 * self.bits + 1))
+    }
+
+    /// Computes a prefix sum of `len` values in a binary tree, restarted at every stop bit.
+    fn prefix_sum(&self, len: u64) -> Cost {
+        tree_levels(len).fold(Cost::ZERO, |cost, step| {
+            let level = self
+                .multiply(len - step)
+                .alongside(self.multiply(len.saturating_sub(2 * step)));
+            cost.then(level)
+        })
+    }
+
+    /// Computes a prefix OR of `len` values in a binary tree, restarted at every stop bit.
+    /// `add_first` skips the OR of the first level, for inputs that never have two ones in a row.
+    fn prefix_or(&self, len: u64, add_first: bool) -> Cost {
+        tree_levels(len).fold(Cost::ZERO, |cost, step| {
+            let mut level = self.multiply(len - step);
+            if !(add_first && step == 1) {
+                level = level.then(self.multiply(len - step));
+            }
+            cost.then(level.alongside(self.multiply(len.saturating_sub(2 * step))))
+        })
+    }
+}
+
+/// The step sizes of a binary tree over `len` rows.
+fn tree_levels(len: u64) -> impl Iterator<Item = u64> {
+    successors(Some(1_u64), |step| step.checked_mul(2)).take_while(move |&step| step < len)
+}
+
+fn ceil_log2(x: u64) -> u64 {
+    u64::from(u64::BITS - x.saturating_sub(1).leading_zeros())
+}
+
+/// Cost of [`ipa`] on `query_size` rows.
+///
+/// With [`IpaQueryConfig::gf2_sort`], the number of comparisons the quicksort makes depends on
+/// the order of the input. The model uses their expected number for distinct keys, and the
+/// expected height of a random binary search tree for the number of rounds. The latter is
+/// asymptotic, and overestimates the rounds of small inputs.
+///
+/// With [`IpaQueryConfig::offline_preprocessing`], the random bits do not count towards the
+/// rounds, as they are generated while the inputs are uploaded.
+///
+/// ## Panics
+/// If `query_size` is less than 2, which IPA does not support.
+///
+/// [`ipa`]: crate::protocol::ipa::ipa
+#[must_use]
+pub fn ipa<F, MK, BK>(
+    query_size: usize,
+    config: &IpaQueryConfig,
+    security: IpaSecurityModel,
+) -> Cost
+where
+    F: ExtendableField,
+    MK: GaloisField,
+    BK: GaloisField,
+{
+    assert!(query_size > 1, "IPA needs at least two rows");
+    let n = u64::try_from(query_size).unwrap();
+    let f = Shares::new::<F>(security);
+    let g = Shares::new::<Gf2>(security);
+    let (mk_bits, bk_bits) = (u64::from(MK::BITS), u64::from(BK::BITS));
+
+    let sort = if config.gf2_sort {
+        quicksort(&g, n, mk_bits)
+    } else {
+        radix_sort(&f, n, mk_bits, u64::from(config.num_multi_bits))
+    };
+
+    let binary = g
+        .upgrade(n * (mk_bits + bk_bits))
+        .then(g.shuffle(n * (mk_bits + bk_bits)))
+        .then(g.multiply_in((n - 1) * (mk_bits - 1), ceil_log2(mk_bits)))
+        .then(g.validate());
+    let arithmetic = f.upgrade(3 * n).then(f.shuffle(3 * n));
+
+    sort.then(binary.alongside(arithmetic))
+        .then(attribution(&f, n, config))
+}
+
+/// Sorts by `bits`-bit keys, `chunk` bits at a time.
+fn radix_sort(f: &Shares, n: u64, bits: u64, chunk: u64) -> Cost {
+    let bit_permutation = |width: u64| f.bitwise_to_onehot(n, width).then(f.multiply(n));
+    let shuffle_and_reveal = f.shuffle(n).then(f.validate()).then(f.reveal(n));
+
+    let mut chunks = (0..bits)
+        .step_by(usize::try_from(chunk).unwrap())
+        .map(|start| (start + chunk).min(bits) - start);
+    let first = chunks.next().unwrap_or(0);
+    let mut cost = f.convert_bits(n * first).then(bit_permutation(first));
+    for width in chunks {
+        cost = cost
+            .then(shuffle_and_reveal)
+            .then(f.convert_bits(n * width))
+            .then(f.shuffle(n * width))
+            .then(bit_permutation(width))
+            .then(f.unchecked_shuffle(n));
+    }
+    cost.then(shuffle_and_reveal)
+}
+
+/// Sorts by `bits`-bit keys with a quicksort over boolean shares.
+fn quicksort(g: &Shares, n: u64, bits: u64) -> Cost {
+    let width = bits + ceil_log2(n);
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    let (comparisons, height) = {
+        let ln = (n as f64).ln();
+        let harmonic = ln + EULER_GAMMA + 1.0 / (2.0 * n as f64);
+        let comparisons = 2.0 * (n + 1) as f64 * harmonic - 4.0 * n as f64;
+        let height = 4.311 * ln - 1.953 * ln.max(1.0).ln();
+        (
+            comparisons.round() as u64,
+            height.round().max(ceil_log2(n) as f64) as u64,
+        )
+    };
+
+    // every comparison multiplies the bits and merges them in a tree, and every round of the
+    // quicksort is validated before its comparisons are revealed
+    let round = g
+        .multiply_in(1, 1 + ceil_log2(width))
+        .then(g.validate())
+        .then(g.reveal(1));
+    let sort = g
+        .multiply(comparisons * (3 * width - 2))
+        .then(g.reveal(comparisons))
+        .then(g.validate().times(height))
+        .rounds(height * round.rounds);
+
+    g.upgrade(n * bits)
+        .then(g.validate())
+        .then(g.shuffle(n * width))
+        .then(sort)
+}
+
+/// The Euler-Mascheroni constant, for approximating harmonic numbers.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Attributes, caps and aggregates `n` sorted rows.
+fn attribution(f: &Shares, n: u64, config: &IpaQueryConfig) -> Cost {
+    let random_bits = |count: u64| {
+        let cost = f.random_bits(count);
+        if config.offline_preprocessing {
+            cost.rounds(0)
+        } else {
+            cost
+        }
+    };
+    let compare = |count: u64| random_bits(count).then(f.greater_than_constant(count));
+
+    let stop_bits = f.convert_bits(n - 1).then(f.multiply(n - 1));
+
+    let window = config.attribution_window_seconds.map_or(Cost::ZERO, |_| {
+        f.multiply(n - 1)
+            .then(f.prefix_sum(n))
+            .then(compare(n))
+            .then(f.multiply(n))
+    });
+
+    let (accumulate, capping) = if config.per_user_credit_cap == 1 {
+        let accumulate = config
+            .attribution_window_seconds
+            .map_or(Cost::ZERO, |_| f.multiply(n - 1));
+        let capping = f
+            .multiply(n - 1)
+            .then(f.prefix_or(n - 2, true))
+            .then(f.multiply(n - 2))
+            .then(f.multiply(n - 2));
+        (accumulate, capping)
+    } else {
+        let capping = f
+            .multiply(n)
+            .then(compare(n))
+            .then(f.multiply(n))
+            .then(f.prefix_sum(n))
+            .then(compare(n))
+            .then(f.prefix_or(n, false))
+            .then(f.multiply(n - 1))
+            .then(f.multiply(n - 1))
+            .then(f.multiply(n - 1));
+        (f.prefix_sum(n), capping)
+    };
+
+    let buckets = u64::from(config.max_breakdown_key);
+    let bucket_bits = ceil_log2(buckets);
+    let aggregate = f
+        .convert_bits((n - 1) * bucket_bits)
+        .then(f.bitwise_to_onehot(n - 1, bucket_bits))
+        .then(f.multiply((n - 1) * buckets.min(1 << bucket_bits)))
+        .then(f.validate());
+
+    stop_bits
+        .then(window)
+        .then(accumulate)
+        .then(capping)
+        .then(aggregate)
+}
+
+/// Cost of [`sparse_aggregate`] on `rows` rows with `num_buckets` breakdowns.
+///
+/// ## Panics
+/// If `rows` or `num_buckets` does not fit in a `u64`.
+///
+/// [`sparse_aggregate`]: crate::protocol::aggregation::sparse_aggregate
+#[must_use]
+pub fn sparse_aggregate<F, CV, BK>(
+    rows: usize,
+    num_buckets: usize,
+    security: IpaSecurityModel,
+) -> Cost
+where
+    F: ExtendableField,
+    CV: GaloisField,
+    BK: GaloisField,
+{
+    let n = u64::try_from(rows).unwrap();
+    let f = Shares::new::<F>(security);
+    let (cv_bits, bk_bits) = (u64::from(CV::BITS), u64::from(BK::BITS));
+    let buckets = u64::try_from(num_buckets).unwrap().min(1 << bk_bits);
+
+    f.convert_bits(n * (cv_bits + bk_bits))
+        .then(f.bitwise_to_onehot(n, bk_bits))
+        .then(f.multiply(n * buckets))
+        .then(f.validate())
+}
+
+/// Cost of [`attribution_and_capping`] for `rows_per_user`, the number of rows of each user.
+/// Users with more than `max_rows_per_user` rows are truncated.
+///
+/// The protocol only runs on semi-honest shares. It processes every row after the first of each
+/// user with a fixed number of multiplications over [`Gf2`], and processes all users at once.
+///
+/// ## Panics
+/// If a size does not fit in a `u64`.
+///
+/// [`attribution_and_capping`]: crate::protocol::prf_sharding::attribution_and_capping
+#[must_use]
+pub fn attribution_and_capping<BK, TV>(
+    rows_per_user: impl IntoIterator<Item = usize>,
+    num_saturating_sum_bits: usize,
+    max_rows_per_user: usize,
+) -> Cost
+where
+    BK: GaloisField,
+    TV: GaloisField,
+{
+    let g = Shares::new::<Gf2>(IpaSecurityModel::SemiHonest);
+    let (bk_bits, tv_bits) = (u64::from(BK::BITS), u64::from(TV::BITS));
+    let sum_bits = u64::try_from(num_saturating_sum_bits).unwrap();
+
+    let (processed, deepest) = rows_per_user
+        .into_iter()
+        .map(|rows| u64::try_from(rows.min(max_rows_per_user).saturating_sub(1)).unwrap())
+        .fold((0, 0), |(total, deepest), rows| {
+            (total + rows, deepest.max(rows))
+        });
+
+    // breakdown key, attributed trigger value, saturating sum and the capped value
+    let per_row = bk_bits + 4 * tv_bits + sum_bits + 3;
+    let depth = sum_bits + 5 + tv_bits.saturating_sub(1).max(1);
+    Cost {
+        rounds: deepest * depth,
+        ..g.multiply(processed * per_row)
+    }
+}
+
+#[cfg(all(test, unit_test, feature = "in-memory-infra"))]
+mod tests {
+    use std::iter;
+
+    use super::{Cost, Shares};
+    use crate::{
+        ff::Fp32BitPrime,
+        protocol::context::{UpgradableContext, Validator},
+        test_fixture::{ipa::IpaSecurityModel, simulate::Simulator, Runner, TestWorldConfig},
+    };
+
+    #[tokio::test]
+    async fn validate() {
+        let simulator = Simulator::new(TestWorldConfig::default());
+        simulator
+            .world()
+            .malicious(iter::empty::<Fp32BitPrime>(), |ctx, shares| async move {
+                ctx.validator::<Fp32BitPrime>()
+                    .validate(shares)
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        let validate = Shares::new::<Fp32BitPrime>(IpaSecurityModel::Malicious).validate();
+        assert_eq!(15, validate.records_sent);
+        assert_eq!(Cost::measured(&simulator), validate);
+    }
+}
@@ -0,0 +1,139 @@
+//! Golden tests: checked-in snapshots of query outputs and communication costs.
+//!
+//! A [`Snapshot`] records the output shares of every helper and the number of records and bytes
+//! sent by every step of a computation on a [`TestWorld`]. With a fixed PRSS seed and fixed input
+//! shares these are deterministic, so [`assert_snapshot`] compares them with a file under
+//! `tests/golden`. Any change to a protocol, or to how much it communicates, shows up as a diff
+//! of that file in review.
+//!
+//! After an intended change, run the tests with `IPA_UPDATE_GOLDEN=1` to rewrite the files.
+
+use std::{collections::BTreeMap, env, fmt::Write, fs, iter::zip, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telemetry::{
+        metrics::{BYTES_SENT, RECORDS_SENT},
+        StepsStats,
+    },
+    test_fixture::TestWorld,
+};
+
+/// Set this environment variable to rewrite snapshots instead of checking them.
+pub const UPDATE_GOLDEN: &str = "IPA_UPDATE_GOLDEN";
+
+/// Seed to create test worlds and input shares for golden tests with.
+pub const GOLDEN_SEED: u64 = 42;
+
+/// Communication of a single step, summed over all helpers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepSnapshot {
+    pub records_sent: u64,
+    pub bytes_sent: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Serialized output shares of each helper, hex encoded.
+    pub outputs: [String; 3],
+    /// Number of steps that sent anything.
+    pub step_count: usize,
+    /// Bytes sent by all steps.
+    pub bytes_sent: u64,
+    pub steps: BTreeMap<String, StepSnapshot>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of everything that ran in `world`, whose helpers produced `outputs`.
+    /// `world` must have metrics enabled.
+    #[must_use]
+    pub fn new(world: &TestWorld, outputs: [Vec<u8>; 3]) -> Self {
+        let metrics = world.metrics_snapshot();
+        let steps = StepsStats::from(&metrics)
+            .all_steps()
+            .map(|(step, stats)| {
+                let step_snapshot = StepSnapshot {
+                    records_sent: stats.get(RECORDS_SENT),
+                    bytes_sent: stats.get(BYTES_SENT),
+                };
+                (step.to_owned(), step_snapshot)
+            })
+            .filter(|(_, step)| step.records_sent > 0)
+            .collect::<BTreeMap<_, _>>();
+
+        Self {
+            outputs: outputs.map(hex::encode),
+            step_count: steps.len(),
+            bytes_sent: steps.values().map(|step| step.bytes_sent).sum(),
+            steps,
+        }
+    }
+
+    /// Pretty-printed JSON, except that every step takes a single line. IPA runs through
+    /// thousands of steps, and this keeps the files short while leaving one line of diff per
+    /// step that changed.
+    fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        writeln!(json, "  \"outputs\": {},", to_line(&self.outputs)).unwrap();
+        writeln!(json, "  \"step_count\": {},", self.step_count).unwrap();
+        writeln!(json, "  \"bytes_sent\": {},", self.bytes_sent).unwrap();
+        json.push_str("  \"steps\": {\n");
+        for (i, (step, stats)) in self.steps.iter().enumerate() {
+            let separator = if i + 1 < self.steps.len() { "," } else { "" };
+            writeln!(json, "    {}: {}{separator}", to_line(step), to_line(stats)).unwrap();
+        }
+        json.push_str("  }\n}\n");
+        json
+    }
+}
+
+fn to_line<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn golden_file(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("json")
+}
+
+/// Compares `snapshot` with the one checked in as `tests/golden/<name>.json`, or writes it there
+/// if [`UPDATE_GOLDEN`] is set.
+///
+/// ## Panics
+/// If the snapshots differ, or if the checked-in snapshot can't be read.
+pub fn assert_snapshot(name: &str, snapshot: &Snapshot) {
+    let path = golden_file(name);
+    let actual = snapshot.to_json();
+    if env::var_os(UPDATE_GOLDEN).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "can't read {}: {e}. Run with {UPDATE_GOLDEN}=1 to create it",
+            path.display()
+        )
+    });
+    if expected != actual {
+        let expected = expected.lines().collect::<Vec<_>>();
+        let actual = actual.lines().collect::<Vec<_>>();
+        let first = zip(&expected, &actual)
+            .position(|(e, a)| e != a)
+            .unwrap_or(expected.len().min(actual.len()));
+        let context =
+            |lines: &[&str]| lines[first.saturating_sub(3)..lines.len().min(first + 5)].join("\n");
+        panic!(
+            "{} is out of date, starting at line {}.\nExpected:\n{}\nActual:\n{}\nRun with \
+             {UPDATE_GOLDEN}=1 to update it if this change is intended",
+            path.display(),
+            first + 1,
+            context(&expected),
+            context(&actual),
+        );
+    }
+}
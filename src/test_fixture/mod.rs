@@ -1,3 +1,5 @@
+#[cfg(feature = "in-memory-infra")]
+mod adversary;
 pub mod input;
 mod sharing;
 #[cfg(feature = "in-memory-infra")]
@@ -8,22 +10,33 @@ mod world;
 #[cfg(feature = "in-memory-infra")]
 mod app;
 
+#[cfg(feature = "in-memory-infra")]
+pub mod aggregation;
 #[cfg(feature = "in-memory-infra")]
 pub mod circuit;
-mod event_gen;
+pub mod cost;
+#[cfg(all(feature = "in-memory-infra", feature = "enable-serde"))]
+pub mod golden;
 pub mod ipa;
 pub mod logging;
 pub mod metrics;
+#[cfg(feature = "in-memory-infra")]
+pub mod prf_sharding;
+#[cfg(feature = "in-memory-infra")]
+pub mod simulate;
+pub mod workload;
 
 use std::fmt::Debug;
 
+#[cfg(feature = "in-memory-infra")]
+pub use adversary::{Tamper, Tampering};
 #[cfg(feature = "in-memory-infra")]
 pub use app::TestApp;
-pub use event_gen::{Config as EventGeneratorConfig, EventGenerator};
 use futures::TryFuture;
 use rand::{distributions::Standard, prelude::Distribution, rngs::mock::StepRng};
 use rand_core::{CryptoRng, RngCore};
 pub use sharing::{get_bits, into_bits, Reconstruct};
+pub use workload::{ReportFilter, WorkloadConfig, WorkloadGenerator};
 #[cfg(feature = "in-memory-infra")]
 pub use world::{Runner, TestWorld, TestWorldConfig};
 
@@ -0,0 +1,277 @@
+//! Drivers for the PRF-sharded protocols, shared by tests and benchmarks.
+//!
+//! The protocols only run in the semi-honest model: attribution works on replicated shares of
+//! `Gf2`, which malicious contexts do not produce.
+
+use std::iter::zip;
+
+use crate::{
+    ff::{Field, GaloisField, Gf2, PrimeField, Serializable},
+    protocol::prf_sharding::{
+        attribution_and_capping_and_aggregation,
+        feature_label_dot_product::{
+            compute_feature_label_dot_product,
+            PreShardedAndSortedOPRFTestInput as FeatureLabelTestInput,
+        },
+        PreShardedAndSortedOPRFTestInput,
+    },
+    secret_sharing::{
+        replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
+        IntoShares,
+    },
+    test_fixture::{ipa::TestRawDataRecord, Reconstruct, Runner, TestWorld},
+};
+
+/// Orders the records the way the PRF-sharded protocols expect them once the PRF of the match
+/// keys is revealed: the records of every user are adjacent and, for each user, in time order.
+///
+/// Records must be in time order, as [`WorkloadGenerator`] produces them.
+///
+/// [`WorkloadGenerator`]: crate::test_fixture::WorkloadGenerator
+pub fn group_by_user(records: &mut [TestRawDataRecord]) {
+    records.sort_by_key(|r| r.user_id);
+}
+
+fn chunk_by_user<T>(records: &[T], user_id: impl Fn(&T) -> u64) -> Vec<&[T]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for i in 1..=records.len() {
+        if i == records.len() || user_id(&records[i]) != user_id(&records[start]) {
+            chunks.push(&records[start..i]);
+            start = i;
+        }
+    }
+    chunks
+}
+
+/// Executes attribution, capping and aggregation of the PRF-sharded IPA protocol in the clear.
+/// Every user contributes at most `2^num_saturating_sum_bits`, and only the last
+/// `max_rows_per_user` records of every user are processed.
+///
+/// Records must be grouped by user, see [`group_by_user`].
+///
+/// ## Panics
+/// If `num_saturating_sum_bits` is 32 or more.
+#[must_use]
+pub fn prf_ipa_in_the_clear(
+    records: &[TestRawDataRecord],
+    num_saturating_sum_bits: usize,
+    max_rows_per_user: usize,
+    num_breakdowns: usize,
+) -> Vec<u32> {
+    let cap = 1_u32 << num_saturating_sum_bits;
+    let mut breakdowns = vec![0; num_breakdowns];
+    for user_records in chunk_by_user(records, |r| r.user_id) {
+        let user_records = &user_records[user_records.len().saturating_sub(max_rows_per_user)..];
+        let mut breakdown_key = None;
+        let mut sum = 0_u32;
+        for record in user_records {
+            if !record.is_trigger_report {
+                breakdown_key = Some(record.breakdown_key);
+            } else if let Some(breakdown_key) = breakdown_key {
+                let contribution = (sum + record.trigger_value).min(cap) - sum.min(cap);
+                sum += record.trigger_value;
+                breakdowns[usize::try_from(breakdown_key).unwrap()] += contribution;
+            }
+        }
+    }
+    breakdowns
+}
+
+/// Runs [`attribution_and_capping_and_aggregation`] on the given records in the semi-honest
+/// model and checks the result against [`prf_ipa_in_the_clear`].
+///
+/// Records must be grouped by user, see [`group_by_user`].
+///
+/// ## Panics
+/// If the protocol fails or produces a result that does not match the expected breakdowns.
+pub async fn test_prf_ipa<BK, TV, F>(
+    world: &TestWorld,
+    records: &[TestRawDataRecord],
+    num_saturating_sum_bits: usize,
+    max_rows_per_user: usize,
+) where
+    BK: GaloisField + IntoShares<Replicated<BK>>,
+    TV: GaloisField + IntoShares<Replicated<TV>>,
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+{
+    let expected = prf_ipa_in_the_clear(
+        records,
+        num_saturating_sum_bits,
+        max_rows_per_user,
+        1 << BK::BITS,
+    );
+    let input = records
+        .iter()
+        .map(|r| PreShardedAndSortedOPRFTestInput {
+            prf_of_match_key: r.user_id,
+            is_trigger_bit: Gf2::truncate_from(r.is_trigger_report),
+            breakdown_key: BK::truncate_from(r.breakdown_key),
+            trigger_value: TV::truncate_from(r.trigger_value),
+        })
+        .collect::<Vec<_>>();
+
+    let result: Vec<F> = world
+        .semi_honest(input.into_iter(), |ctx, input_rows| async move {
+            attribution_and_capping_and_aggregation::<_, BK, TV, F, _, Replicated<Gf2>>(
+                ctx,
+                input_rows,
+                num_saturating_sum_bits,
+                max_rows_per_user,
+            )
+            .await
+            .unwrap()
+            .breakdowns
+        })
+        .await
+        .reconstruct();
+    let result = result
+        .into_iter()
+        .map(|v| u32::try_from(v.as_u128()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(result, expected);
+}
+
+/// Computes the feature-label dot product in the clear: for every user, the feature vector of
+/// the most recent source event followed by a trigger event is added to the output, bit by bit.
+///
+/// Records must be grouped by user, see [`group_by_user`], and every record is paired with the
+/// feature vector of its event.
+///
+/// ## Panics
+/// Never, unless `FV` has more than `usize::MAX` bits.
+#[must_use]
+pub fn feature_label_dot_product_in_the_clear<FV: GaloisField>(
+    records: &[(TestRawDataRecord, FV)],
+) -> Vec<u32> {
+    let mut output = vec![0; usize::try_from(FV::BITS).unwrap()];
+    for user_records in chunk_by_user(records, |(r, _)| r.user_id) {
+        let mut seen_trigger = false;
+        for (record, feature_vector) in user_records.iter().rev() {
+            if record.is_trigger_report {
+                seen_trigger = true;
+            } else if seen_trigger {
+                let feature_vector = feature_vector.as_u128();
+                for (i, count) in output.iter_mut().enumerate() {
+                    *count += u32::from(feature_vector >> i & 1 == 1);
+                }
+                break;
+            }
+        }
+    }
+    output
+}
+
+/// Runs [`compute_feature_label_dot_product`] on the given records in the semi-honest model and
+/// checks the result against [`feature_label_dot_product_in_the_clear`].
+///
+/// Records must be grouped by user, see [`group_by_user`].
+///
+/// ## Panics
+/// If the protocol fails or produces a result that does not match the expected dot product.
+pub async fn test_feature_label_dot_product<FV, F>(
+    world: &TestWorld,
+    records: &[(TestRawDataRecord, FV)],
+) where
+    FV: GaloisField + IntoShares<Replicated<FV>>,
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+{
+    let expected = feature_label_dot_product_in_the_clear(records);
+
+    // The protocol processes the records of every user from newest to oldest.
+    let mut histogram = Vec::new();
+    let mut input = Vec::with_capacity(records.len());
+    for user_records in chunk_by_user(records, |(r, _)| r.user_id) {
+        if histogram.len() < user_records.len() {
+            histogram.resize(user_records.len(), 0);
+        }
+        for (count, (record, feature_vector)) in zip(&mut histogram, user_records.iter().rev()) {
+            *count += 1;
+            input.push(FeatureLabelTestInput {
+                prf_of_match_key: record.user_id,
+                is_trigger_bit: Gf2::truncate_from(record.is_trigger_report),
+                feature_vector: *feature_vector,
+            });
+        }
+    }
+
+    let result: Vec<F> = world
+        .semi_honest(input.into_iter(), |ctx, input_rows| {
+            let histogram = histogram.as_slice();
+            async move {
+                compute_feature_label_dot_product::<_, FV, F, Replicated<F>>(
+                    ctx, input_rows, histogram,
+                )
+                .await
+                .unwrap()
+            }
+        })
+        .await
+        .reconstruct();
+    let result = result
+        .into_iter()
+        .map(|v| u32::try_from(v.as_u128()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(result, expected);
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{group_by_user, test_feature_label_dot_product, test_prf_ipa};
+    use crate::{
+        ff::{Field, Fp32BitPrime, Gf32Bit, Gf3Bit, Gf5Bit},
+        helpers::GatewayConfig,
+        test_executor::run,
+        test_fixture::{TestWorld, TestWorldConfig, WorkloadConfig, WorkloadGenerator},
+    };
+
+    /// A world with much less active work than there are users or records at each row depth.
+    fn world() -> TestWorld {
+        TestWorld::new_with(TestWorldConfig {
+            gateway_config: GatewayConfig::new(4),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prf_ipa_matches_the_clear() {
+        run(|| async {
+            let mut records = WorkloadGenerator::new(
+                StdRng::seed_from_u64(1),
+                WorkloadConfig::uniform(5, 32, 10).with_max_match_key(1_000),
+            )
+            .generate(200);
+            group_by_user(&mut records);
+
+            test_prf_ipa::<Gf5Bit, Gf3Bit, Fp32BitPrime>(&world(), &records, 4, 6).await;
+        });
+    }
+
+    #[test]
+    fn feature_label_dot_product_matches_the_clear() {
+        run(|| async {
+            let mut rng = StdRng::seed_from_u64(2);
+            let mut records = WorkloadGenerator::new(
+                &mut rng,
+                WorkloadConfig::uniform(5, 32, 10).with_max_match_key(1_000),
+            )
+            .generate(200);
+            group_by_user(&mut records);
+            let records = records
+                .into_iter()
+                .map(|record| {
+                    let feature_vector = Gf32Bit::truncate_from(rng.gen::<u32>());
+                    (record, feature_vector)
+                })
+                .collect::<Vec<_>>();
+
+            let world = world();
+            test_feature_label_dot_product::<Gf32Bit, Fp32BitPrime>(&world, &records).await;
+        });
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
             semi_honest::AdditiveShare as Replicated,
             ReplicatedSecretSharing,
         },
-        BitDecomposed, SecretSharing,
+        BitDecomposed, SecretSharing, SharedValue,
     },
 };
 
@@ -37,8 +37,8 @@ pub trait Reconstruct<T> {
     fn reconstruct(&self) -> T;
 }
 
-impl<F: Field> Reconstruct<F> for [&Replicated<F>; 3] {
-    fn reconstruct(&self) -> F {
+impl<V: SharedValue> Reconstruct<V> for [&Replicated<V>; 3] {
+    fn reconstruct(&self) -> V {
         let s0 = &self[0];
         let s1 = &self[1];
         let s2 = &self[2];
@@ -56,8 +56,8 @@ impl<F: Field> Reconstruct<F> for [&Replicated<F>; 3] {
     }
 }
 
-impl<F: Field> Reconstruct<F> for [Replicated<F>; 3] {
-    fn reconstruct(&self) -> F {
+impl<V: SharedValue> Reconstruct<V> for [Replicated<V>; 3] {
+    fn reconstruct(&self) -> V {
         [&self[0], &self[1], &self[2]].reconstruct()
     }
 }
@@ -186,6 +186,17 @@ impl<F: ExtendableField> ValidateMalicious<F> for [BitDecomposed<MaliciousReplic
     }
 }
 
+impl<F: ExtendableField> ValidateMalicious<F> for [Vec<BitDecomposed<MaliciousReplicated<F>>>; 3] {
+    fn validate(&self, r: F::ExtendedField) {
+        assert_eq!(self[0].len(), self[1].len());
+        assert_eq!(self[0].len(), self[2].len());
+
+        for (m0, (m1, m2)) in zip(self[0].iter(), zip(self[1].iter(), self[2].iter())) {
+            [&m0[..], &m1[..], &m2[..]].validate(r);
+        }
+    }
+}
+
 impl<F: ExtendableField> ValidateMalicious<F>
     for [(
         MaliciousReplicated<F>,
@@ -0,0 +1,253 @@
+//! Predicts how long a query takes on a real network.
+//!
+//! [`Simulator`] runs a query on a [`TestWorld`] and counts the rounds on the critical path of
+//! each step from the dependencies between the messages the helpers send, see [`RoundCounter`].
+//! Together with the number of bytes each step sends, taken from the step metrics, this gives an
+//! estimate of the wall-clock time for any round trip time and bandwidth. The estimate does not
+//! include computation.
+
+use std::{collections::HashMap, num::NonZeroU64, ops::Range, time::Duration};
+
+use crate::{
+    helpers::RoundCounter,
+    sync::Arc,
+    telemetry::{
+        metrics::{BYTES_SENT, RECORDS_SENT},
+        StepsStats,
+    },
+    test_fixture::{TestWorld, TestWorldConfig},
+};
+
+/// Network the estimates are made for.
+#[derive(Clone, Copy, Debug)]
+pub struct NetworkProfile {
+    /// Round trip time between any two helpers.
+    pub rtt: Duration,
+    /// Bytes per second each helper can send.
+    pub bandwidth: NonZeroU64,
+}
+
+/// Network usage of a top-level step of the query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepCost {
+    pub step: String,
+    /// Records sent by all helpers.
+    pub records_sent: u64,
+    /// Bytes sent by all helpers.
+    pub bytes_sent: u64,
+    /// Number of rounds on the critical path of this step.
+    pub rounds: u64,
+}
+
+impl StepCost {
+    /// Estimates the time this step takes on `profile`. Every round takes half of the round trip
+    /// time, and each helper sends a third of the bytes.
+    #[must_use]
+    pub fn estimate(&self, profile: &NetworkProfile) -> Duration {
+        let latency = profile.rtt.as_nanos() * u128::from(self.rounds) / 2;
+        let transfer =
+            u128::from(self.bytes_sent / 3) * 1_000_000_000 / u128::from(profile.bandwidth.get());
+        Duration::from_nanos(u64::try_from(latency + transfer).unwrap_or(u64::MAX))
+    }
+}
+
+/// Network usage of a query, per top-level step, in the order the steps started.
+#[derive(Clone, Debug, Default)]
+pub struct SimulationReport {
+    pub steps: Vec<StepCost>,
+}
+
+impl SimulationReport {
+    #[must_use]
+    pub fn rounds(&self) -> u64 {
+        self.steps.iter().map(|step| step.rounds).sum()
+    }
+
+    #[must_use]
+    pub fn bytes_sent(&self) -> u64 {
+        self.steps.iter().map(|step| step.bytes_sent).sum()
+    }
+
+    /// Estimates the time the query takes on `profile`, assuming that its top-level steps run one
+    /// after another.
+    #[must_use]
+    pub fn estimate(&self, profile: &NetworkProfile) -> Duration {
+        self.steps.iter().map(|step| step.estimate(profile)).sum()
+    }
+}
+
+/// Runs queries on a simulated network and reports their network usage.
+pub struct Simulator {
+    world: TestWorld,
+    rounds: Arc<RoundCounter>,
+}
+
+impl Simulator {
+    /// Creates a simulator with a test world configured by `config`. Metrics are enabled in it.
+    #[must_use]
+    pub fn new(config: TestWorldConfig) -> Self {
+        let rounds = Arc::new(RoundCounter::default());
+        Self {
+            world: TestWorld::new_with(
+                config
+                    .enable_metrics()
+                    .with_round_counter(Arc::clone(&rounds)),
+            ),
+            rounds,
+        }
+    }
+
+    /// The world to run queries on.
+    #[must_use]
+    pub fn world(&self) -> &TestWorld {
+        &self.world
+    }
+
+    /// Number of rounds on the critical path of everything that ran in this simulator so far.
+    #[must_use]
+    pub fn rounds(&self) -> u64 {
+        self.rounds.rounds()
+    }
+
+    /// Reports the network usage of everything that ran in this simulator so far.
+    #[must_use]
+    pub fn report(&self) -> SimulationReport {
+        let mut steps = HashMap::<String, (StepCost, Option<Range<u64>>)>::new();
+
+        for (gate, rounds) in self.rounds.gate_rounds() {
+            let (_, step_rounds) = step_entry(&mut steps, top_level_step(gate.as_ref()));
+            let step_rounds = step_rounds.get_or_insert(rounds.clone());
+            step_rounds.start = step_rounds.start.min(rounds.start);
+            step_rounds.end = step_rounds.end.max(rounds.end);
+        }
+        for (cost, rounds) in steps.values_mut() {
+            cost.rounds = rounds.as_ref().map_or(0, |r| r.end - r.start);
+        }
+
+        let metrics = self.world.metrics_snapshot();
+        for (gate, stats) in StepsStats::from(&metrics).all_steps() {
+            let (cost, _) = step_entry(&mut steps, top_level_step(gate));
+            cost.records_sent += stats.get(RECORDS_SENT);
+            cost.bytes_sent += stats.get(BYTES_SENT);
+        }
+
+        let mut steps = steps.into_values().collect::<Vec<_>>();
+        // steps that did not use the network go last
+        steps.sort_by_key(|(cost, rounds)| {
+            (
+                rounds.is_none(),
+                rounds.as_ref().map(|r| r.start),
+                cost.step.clone(),
+            )
+        });
+        SimulationReport {
+            steps: steps.into_iter().map(|(cost, _)| cost).collect(),
+        }
+    }
+}
+
+fn step_entry<'a>(
+    steps: &'a mut HashMap<String, (StepCost, Option<Range<u64>>)>,
+    name: &str,
+) -> &'a mut (StepCost, Option<Range<u64>>) {
+    steps.entry(name.to_owned()).or_insert_with(|| {
+        let cost = StepCost {
+            step: name.to_owned(),
+            records_sent: 0,
+            bytes_sent: 0,
+            rounds: 0,
+        };
+        (cost, None)
+    })
+}
+
+/// Returns the name of the top-level step of `gate`. Gates in a test world start with
+/// `protocol/run-N/`.
+fn top_level_step(gate: &str) -> &str {
+    gate.split('/').nth(2).unwrap_or(gate)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{num::NonZeroU64, time::Duration};
+
+    use super::{NetworkProfile, Simulator, StepCost};
+    use crate::{
+        ff::{Fp31, Fp32BitPrime},
+        helpers::query::IpaQueryConfig,
+        protocol::{basics::SecureMul, context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        test_fixture::{
+            ipa::{ipa_in_the_clear, test_ipa, IpaSecurityModel},
+            Reconstruct, Runner, TestWorldConfig, WorkloadConfig, WorkloadGenerator,
+        },
+    };
+
+    #[tokio::test]
+    async fn sequential_multiplications() {
+        let simulator = Simulator::new(TestWorldConfig::default());
+        let a = thread_rng().gen::<Fp31>();
+
+        let result = simulator
+            .world()
+            .semi_honest(a, |ctx, a| async move {
+                let ctx = ctx.narrow("power").set_total_records(1);
+                let mut result = a.clone();
+                for i in 0..3 {
+                    result = result
+                        .multiply(&a, ctx.narrow(&format!("mul{i}")), RecordId::from(0))
+                        .await
+                        .unwrap();
+                }
+                result
+            })
+            .await;
+        assert_eq!(a * a * a * a, result.reconstruct());
+
+        let report = simulator.report();
+        assert_eq!(
+            vec![StepCost {
+                step: "power".to_owned(),
+                records_sent: 9,
+                bytes_sent: 9,
+                rounds: 3,
+            }],
+            report.steps
+        );
+
+        let profile = NetworkProfile {
+            rtt: Duration::from_millis(100),
+            bandwidth: NonZeroU64::new(2).unwrap(),
+        };
+        assert_eq!(Duration::from_millis(1650), report.estimate(&profile));
+    }
+
+    #[tokio::test]
+    async fn ipa() {
+        const QUERY_SIZE: usize = 20;
+        let records = WorkloadGenerator::new(thread_rng(), WorkloadConfig::uniform(5, 20, 50))
+            .generate(QUERY_SIZE);
+        let config = IpaQueryConfig::default();
+        let expected = ipa_in_the_clear(
+            &records,
+            config.per_user_credit_cap,
+            config.attribution_window_seconds,
+            config.max_breakdown_key,
+        );
+
+        let simulator = Simulator::new(TestWorldConfig::default());
+        test_ipa::<Fp32BitPrime>(
+            simulator.world(),
+            &records,
+            &expected,
+            config,
+            IpaSecurityModel::SemiHonest,
+        )
+        .await;
+
+        let report = simulator.report();
+        assert!(report.steps.len() > 1);
+        assert!(report.rounds() > 0);
+        assert!(report.steps.iter().all(|step| step.bytes_sent > 0));
+    }
+}
@@ -0,0 +1,666 @@
+//! Generates IPA inputs.
+//!
+//! [`WorkloadGenerator`] samples events from the distributions in [`WorkloadConfig`], which can be
+//! loaded from a JSON file. The defaults resemble production traffic: real people have a
+//! heavy-tailed number of events, do most of their browsing during the day, convert on a
+//! different device than the one they saw the ad on, and convert hours or days after the
+//! impression. Correctness tests usually want [`WorkloadConfig::uniform`] instead, which draws
+//! everything uniformly from a few bounds.
+//!
+//! The generated records can be written as CSV, or shared and encrypted for the helpers with
+//! [`encrypt_reports`].
+
+use std::{
+    collections::HashSet,
+    num::{NonZeroU16, NonZeroU32, NonZeroU64},
+    ops::Range,
+};
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    CryptoRng, Rng, RngCore,
+};
+
+use crate::{
+    ff::{PrimeField, Serializable},
+    hpke::PublicKeyRegistry,
+    protocol::{BreakdownKey, MatchKey},
+    report::{Epoch, InvalidReportError, KeyIdentifier, Report},
+    secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, IntoShares},
+    test_fixture::ipa::TestRawDataRecord,
+};
+
+/// Length of an epoch. Reports are assigned to epochs by their timestamps.
+pub const SECONDS_IN_EPOCH: u64 = 7 * SECONDS_IN_DAY;
+const SECONDS_IN_DAY: u64 = 24 * SECONDS_IN_HOUR;
+const SECONDS_IN_HOUR: u64 = 60 * 60;
+
+/// Match keys are 40 bits long, and 0 is reserved for conversions without impressions.
+const MAX_MATCH_KEY: u64 = (1 << 40) - 1;
+
+/// Match key of the conversions that can't be attributed to anyone.
+const EPHEMERAL_MATCH_KEY: u64 = 0;
+
+/// A value, or a range of values to pick one from uniformly, and its relative weight.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Weighted<T> {
+    pub value: T,
+    pub weight: f64,
+}
+
+impl<T> Weighted<T> {
+    fn new(value: T, weight: f64) -> Self {
+        Self { value, weight }
+    }
+}
+
+/// Number of events a person generates, across all of their devices.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "distribution", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum EventsPerUser {
+    /// Every count in `1..=max` is equally likely.
+    Uniform { max: NonZeroU32 },
+    /// Count `k` in `1..=max` has a probability proportional to `k^-exponent`, so most people
+    /// have a few events and a few people have a lot of them.
+    Zipf { exponent: f64, max: NonZeroU32 },
+}
+
+impl EventsPerUser {
+    fn weights(&self) -> Vec<f64> {
+        match *self {
+            Self::Uniform { max } => vec![1.0; usize::try_from(max.get()).unwrap()],
+            Self::Zipf { exponent, max } => (1..=max.get())
+                .map(|k| f64::from(k).powf(-exponent))
+                .collect(),
+        }
+    }
+}
+
+/// Types of reports the [`WorkloadGenerator`] outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ReportFilter {
+    #[default]
+    All,
+    TriggerOnly,
+    SourceOnly,
+}
+
+impl ReportFilter {
+    fn keeps(self, record: &TestRawDataRecord) -> bool {
+        match self {
+            Self::All => true,
+            Self::TriggerOnly => record.is_trigger_report,
+            Self::SourceOnly => !record.is_trigger_report,
+        }
+    }
+}
+
+/// Distributions the [`WorkloadGenerator`] samples from. The defaults are a rough approximation
+/// of production traffic.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct WorkloadConfig {
+    pub events_per_user: EventsPerUser,
+    /// Weights of the number of devices a person has, starting with one device.
+    pub devices_per_user: Vec<f64>,
+    /// Probability that a device is signed in. Signed in devices of a person share the same match
+    /// key, the others have a match key of their own, so events on them can't be attributed to
+    /// each other.
+    pub shared_match_key_probability: f64,
+    /// Probability that an impression leads to a conversion.
+    pub conversion_rate: f64,
+    /// Time from an impression to the conversion it leads to, in seconds.
+    pub conversion_delay: Vec<Weighted<Range<u64>>>,
+    /// Weights of the hour of the day impressions happen at, starting at midnight. There must be
+    /// 24 of them.
+    pub hourly_weights: Vec<f64>,
+    /// Number of epochs impressions are spread over. Conversions may spill into the next epoch.
+    pub epochs: NonZeroU16,
+    pub max_breakdown_key: NonZeroU32,
+    pub trigger_values: Vec<Weighted<Range<u32>>>,
+    /// Match keys are drawn from `1..=max_match_key`. Once all of them are taken, no more people
+    /// are generated, so there may be fewer events than requested.
+    pub max_match_key: NonZeroU64,
+    /// Probability that a conversion can't be attributed to anyone, because it happened on a
+    /// device the ad platform doesn't know. Such conversions have match key 0.
+    pub ephemeral_conversion_rate: f64,
+    /// Types of reports to output. The others are generated and then discarded, so filtering
+    /// doesn't change the timestamps or match keys of the reports that remain.
+    pub report_filter: ReportFilter,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            events_per_user: EventsPerUser::Zipf {
+                exponent: 2.0,
+                max: NonZeroU32::new(200).unwrap(),
+            },
+            devices_per_user: vec![0.6, 0.3, 0.1],
+            shared_match_key_probability: 0.5,
+            conversion_rate: 0.1,
+            conversion_delay: vec![
+                Weighted::new(0..SECONDS_IN_HOUR, 0.5),
+                Weighted::new(SECONDS_IN_HOUR..SECONDS_IN_DAY, 0.3),
+                Weighted::new(SECONDS_IN_DAY..SECONDS_IN_EPOCH, 0.2),
+            ],
+            hourly_weights: vec![
+                1.0, 0.6, 0.4, 0.3, 0.3, 0.4, 0.8, 1.4, 1.8, 2.0, 2.0, 2.1, 2.2, 2.1, 2.0, 2.0,
+                2.1, 2.4, 2.8, 3.0, 3.0, 2.6, 2.0, 1.4,
+            ],
+            epochs: NonZeroU16::new(1).unwrap(),
+            max_breakdown_key: NonZeroU32::new(20).unwrap(),
+            trigger_values: vec![
+                Weighted::new(1..10, 0.6),
+                Weighted::new(10..100, 0.3),
+                Weighted::new(100..1000, 0.1),
+            ],
+            max_match_key: NonZeroU64::new(MAX_MATCH_KEY).unwrap(),
+            ephemeral_conversion_rate: 0.0,
+            report_filter: ReportFilter::All,
+        }
+    }
+}
+
+impl WorkloadConfig {
+    /// Draws everything uniformly: the number of events of a person from `1..=max_events_per_user`,
+    /// breakdown keys from `0..max_breakdown_key`, trigger values from `1..max_trigger_value` and
+    /// impressions from any hour of the day. Every person has a single device and half of the
+    /// impressions lead to a conversion.
+    ///
+    /// ## Panics
+    /// If any argument is 0.
+    #[must_use]
+    pub fn uniform(
+        max_trigger_value: u32,
+        max_breakdown_key: u32,
+        max_events_per_user: u32,
+    ) -> Self {
+        Self {
+            events_per_user: EventsPerUser::Uniform {
+                max: NonZeroU32::new(max_events_per_user).unwrap(),
+            },
+            devices_per_user: vec![1.0],
+            shared_match_key_probability: 1.0,
+            conversion_rate: 0.5,
+            hourly_weights: vec![1.0; 24],
+            max_breakdown_key: NonZeroU32::new(max_breakdown_key).unwrap(),
+            trigger_values: vec![Weighted::new(1..max_trigger_value, 1.0)],
+            ..Self::default()
+        }
+    }
+
+    /// Draws match keys from `1..=max`, so that there are at most `max` people.
+    ///
+    /// ## Panics
+    /// If `max` is 0 or more than 40 bits.
+    #[must_use]
+    pub fn with_max_match_key(mut self, max: u64) -> Self {
+        assert!(max <= MAX_MATCH_KEY, "match keys are 40 bits long");
+        self.max_match_key = NonZeroU64::new(max).unwrap();
+        self
+    }
+}
+
+/// Generates source and trigger events, a person at a time, from the distributions in
+/// [`WorkloadConfig`].
+pub struct WorkloadGenerator<R: Rng> {
+    config: WorkloadConfig,
+    rng: R,
+    events_per_user: WeightedIndex<f64>,
+    devices_per_user: WeightedIndex<f64>,
+    conversion_delay: WeightedIndex<f64>,
+    hour: WeightedIndex<f64>,
+    trigger_value: WeightedIndex<f64>,
+    used: HashSet<u64>,
+}
+
+impl<R: Rng> WorkloadGenerator<R> {
+    /// ## Panics
+    /// If any of the weights in `config` are invalid, or if there aren't 24 hourly weights.
+    pub fn new(rng: R, config: WorkloadConfig) -> Self {
+        fn weights<T>(values: &[Weighted<T>]) -> WeightedIndex<f64> {
+            WeightedIndex::new(values.iter().map(|v| v.weight)).unwrap()
+        }
+
+        assert_eq!(
+            24,
+            config.hourly_weights.len(),
+            "there must be a weight for every hour of the day"
+        );
+        Self {
+            events_per_user: WeightedIndex::new(config.events_per_user.weights()).unwrap(),
+            devices_per_user: WeightedIndex::new(&config.devices_per_user).unwrap(),
+            conversion_delay: weights(&config.conversion_delay),
+            hour: WeightedIndex::new(&config.hourly_weights).unwrap(),
+            trigger_value: weights(&config.trigger_values),
+            config,
+            rng,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Generates `count` events, sorted by their timestamps. There are fewer of them if the
+    /// generator runs out of match keys.
+    pub fn generate(&mut self, count: usize) -> Vec<TestRawDataRecord> {
+        let mut records = Vec::with_capacity(count);
+        while records.len() < count && self.gen_user(&mut records) {}
+        records.truncate(count);
+        records.sort_by_key(|record| record.timestamp);
+        records
+    }
+
+    /// Adds the events of a new person to `out`. Returns `false` if there are no match keys left
+    /// for them.
+    fn gen_user(&mut self, out: &mut Vec<TestRawDataRecord>) -> bool {
+        let mut remaining = self.events_per_user.sample(&mut self.rng) + 1;
+        let Some(shared) = self.new_match_key() else {
+            return false;
+        };
+        let devices = (0..=self.devices_per_user.sample(&mut self.rng))
+            .map(|_| {
+                if self.rng.gen_bool(self.config.shared_match_key_probability) {
+                    shared
+                } else {
+                    self.new_match_key().unwrap_or(shared)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut records = Vec::with_capacity(remaining);
+        while remaining > 0 {
+            let timestamp = self.impression_time();
+            records.push(TestRawDataRecord {
+                timestamp,
+                user_id: devices[self.rng.gen_range(0..devices.len())],
+                is_trigger_report: false,
+                breakdown_key: self.rng.gen_range(0..self.config.max_breakdown_key.get()),
+                trigger_value: 0,
+            });
+            remaining -= 1;
+
+            if remaining > 0 && self.rng.gen_bool(self.config.conversion_rate) {
+                let delay = self.config.conversion_delay
+                    [self.conversion_delay.sample(&mut self.rng)]
+                .value
+                .clone();
+                let value = self.config.trigger_values[self.trigger_value.sample(&mut self.rng)]
+                    .value
+                    .clone();
+                // Only draw when needed, so that seeds keep producing the same workloads.
+                let ephemeral = self.config.ephemeral_conversion_rate > 0.0
+                    && self.rng.gen_bool(self.config.ephemeral_conversion_rate);
+                let user_id = if ephemeral {
+                    EPHEMERAL_MATCH_KEY
+                } else {
+                    devices[self.rng.gen_range(0..devices.len())]
+                };
+                records.push(TestRawDataRecord {
+                    timestamp: timestamp + self.rng.gen_range(delay),
+                    user_id,
+                    is_trigger_report: true,
+                    breakdown_key: 0,
+                    trigger_value: self.rng.gen_range(value),
+                });
+                remaining -= 1;
+            }
+        }
+
+        let filter = self.config.report_filter;
+        out.extend(records.into_iter().filter(|record| filter.keeps(record)));
+        true
+    }
+
+    fn impression_time(&mut self) -> u64 {
+        let epoch = self.rng.gen_range(0..self.config.epochs.get());
+        let day = self.rng.gen_range(0..7);
+        let hour = u64::try_from(self.hour.sample(&mut self.rng)).unwrap();
+        u64::from(epoch) * SECONDS_IN_EPOCH
+            + day * SECONDS_IN_DAY
+            + hour * SECONDS_IN_HOUR
+            + self.rng.gen_range(0..SECONDS_IN_HOUR)
+    }
+
+    fn new_match_key(&mut self) -> Option<u64> {
+        let max = self.config.max_match_key.get();
+        if u64::try_from(self.used.len()).unwrap() == max {
+            return None;
+        }
+        loop {
+            let match_key = self.rng.gen_range(1..=max);
+            if self.used.insert(match_key) {
+                break Some(match_key);
+            }
+        }
+    }
+}
+
+/// Returns the epoch of an event that happened at `timestamp`.
+///
+/// ## Panics
+/// If `timestamp` is too far in the future.
+#[must_use]
+pub fn epoch(timestamp: u64) -> Epoch {
+    Epoch::try_from(timestamp / SECONDS_IN_EPOCH).unwrap()
+}
+
+/// Secret shares `records` and encrypts the match key shares for every helper, in the format
+/// helpers accept as query input. Each report is encrypted for the epoch its timestamp falls in.
+///
+/// ## Errors
+/// If a report can't be encrypted, for example because `key_id` is not in a key registry.
+pub fn encrypt_reports<F, KR, R>(
+    records: &[TestRawDataRecord],
+    key_id: KeyIdentifier,
+    key_registries: [&KR; 3],
+    rng: &mut R,
+) -> Result<[Vec<u8>; 3], InvalidReportError>
+where
+    F: PrimeField,
+    Replicated<F>: Serializable,
+    TestRawDataRecord: IntoShares<Report<F, MatchKey, BreakdownKey>>,
+    KR: PublicKeyRegistry,
+    R: RngCore + CryptoRng,
+{
+    const ESTIMATED_AVERAGE_REPORT_SIZE: usize = 80; // TODO: confirm/adjust
+    let shares: [Vec<Report<F, MatchKey, BreakdownKey>>; 3] =
+        records.iter().cloned().share_with(rng);
+
+    let mut buffers: [_; 3] =
+        std::array::from_fn(|_| Vec::with_capacity(records.len() * ESTIMATED_AVERAGE_REPORT_SIZE));
+    for ((buf, shares), key_registry) in buffers.iter_mut().zip(shares).zip(key_registries) {
+        for (mut share, record) in shares.into_iter().zip(records) {
+            share.epoch = epoch(record.timestamp);
+            share.delimited_encrypt_to(key_id, key_registry, rng, buf)?;
+        }
+    }
+
+    Ok(buffers)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        num::{NonZeroU16, NonZeroU32},
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{
+        encrypt_reports, epoch, EventsPerUser, ReportFilter, WorkloadConfig, WorkloadGenerator,
+        SECONDS_IN_EPOCH,
+    };
+    use crate::{
+        ff::{Fp32BitPrime, GaloisField, Gf40Bit, Gf8Bit},
+        hpke::KeyRegistry,
+        report::{EncryptedReport, EventType},
+        test_fixture::{ipa::TestRawDataRecord, Reconstruct},
+    };
+
+    fn generate(config: WorkloadConfig, count: usize) -> Vec<TestRawDataRecord> {
+        WorkloadGenerator::new(StdRng::seed_from_u64(42), config).generate(count)
+    }
+
+    #[test]
+    fn sorted_and_reproducible() {
+        let records = generate(WorkloadConfig::default(), 1000);
+        assert_eq!(1000, records.len());
+        assert!(records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        let again = generate(WorkloadConfig::default(), 1000);
+        assert_eq!(format!("{records:?}"), format!("{again:?}"));
+    }
+
+    #[test]
+    fn heavy_tailed_events_per_user() {
+        let config = WorkloadConfig {
+            devices_per_user: vec![1.0],
+            shared_match_key_probability: 1.0,
+            events_per_user: EventsPerUser::Zipf {
+                exponent: 1.5,
+                max: NonZeroU32::new(1000).unwrap(),
+            },
+            ..WorkloadConfig::default()
+        };
+        let mut per_user = HashMap::<u64, u32>::new();
+        for record in generate(config, 10_000) {
+            *per_user.entry(record.user_id).or_default() += 1;
+        }
+        let mut counts = per_user.into_values().collect::<Vec<_>>();
+        counts.sort_unstable();
+
+        assert!(counts[counts.len() / 2] <= 2, "{counts:?}");
+        assert!(counts[counts.len() - 1] > 100, "{counts:?}");
+    }
+
+    #[test]
+    fn cross_device_match_keys() {
+        let config = WorkloadConfig {
+            events_per_user: EventsPerUser::Uniform {
+                max: NonZeroU32::new(30).unwrap(),
+            },
+            devices_per_user: vec![0.0, 0.0, 1.0],
+            ..WorkloadConfig::default()
+        };
+        let shared = generate(
+            WorkloadConfig {
+                shared_match_key_probability: 1.0,
+                ..config.clone()
+            },
+            1000,
+        );
+        let separate = generate(
+            WorkloadConfig {
+                shared_match_key_probability: 0.0,
+                ..config
+            },
+            1000,
+        );
+        let match_keys = |records: &[TestRawDataRecord]| {
+            records
+                .iter()
+                .map(|r| r.user_id)
+                .collect::<HashSet<_>>()
+                .len()
+        };
+
+        // Every person has 3 devices, which either share a match key or don't.
+        assert!(match_keys(&separate) > 2 * match_keys(&shared));
+    }
+
+    #[test]
+    fn conversions_follow_impressions() {
+        let config = WorkloadConfig {
+            devices_per_user: vec![1.0],
+            shared_match_key_probability: 1.0,
+            conversion_rate: 1.0,
+            ..WorkloadConfig::default()
+        };
+        let mut last_impression = HashMap::new();
+        let mut conversions = 0;
+        for record in generate(config, 1000) {
+            if record.is_trigger_report {
+                conversions += 1;
+                assert!(record.trigger_value > 0);
+                assert!(last_impression[&record.user_id] <= record.timestamp);
+            } else {
+                last_impression.insert(record.user_id, record.timestamp);
+            }
+        }
+        assert!(conversions > 300, "{conversions}");
+    }
+
+    #[test]
+    fn multiple_epochs() {
+        let config = WorkloadConfig {
+            epochs: NonZeroU16::new(3).unwrap(),
+            ..WorkloadConfig::default()
+        };
+        let records = generate(config, 1000);
+        let epochs = records
+            .iter()
+            .map(|r| epoch(r.timestamp))
+            .collect::<HashSet<_>>();
+        assert!(epochs.is_superset(&[0, 1, 2].into()), "{epochs:?}");
+        assert!(records
+            .iter()
+            .all(|r| r.is_trigger_report || r.timestamp < 3 * SECONDS_IN_EPOCH));
+    }
+
+    #[test]
+    fn runs_out_of_match_keys() {
+        let records = generate(WorkloadConfig::uniform(5, 20, 1).with_max_match_key(2), 10);
+        assert_eq!(2, records.len());
+        let match_keys = records.iter().map(|r| r.user_id).collect::<HashSet<_>>();
+        assert_eq!(HashSet::from([1, 2]), match_keys);
+    }
+
+    #[test]
+    fn filters_reports() {
+        let config = WorkloadConfig {
+            report_filter: ReportFilter::TriggerOnly,
+            ephemeral_conversion_rate: 0.5,
+            ..WorkloadConfig::default()
+        };
+        let records = generate(config, 1000);
+        assert_eq!(1000, records.len());
+        assert!(records.iter().all(|r| r.is_trigger_report));
+        let ephemeral = records.iter().filter(|r| r.user_id == 0).count();
+        assert!((300..700).contains(&ephemeral), "{ephemeral}");
+
+        let config = WorkloadConfig {
+            report_filter: ReportFilter::SourceOnly,
+            ..WorkloadConfig::default()
+        };
+        assert!(generate(config, 1000).iter().all(|r| !r.is_trigger_report));
+    }
+
+    mod proptests {
+        use std::collections::HashMap;
+
+        use proptest::{prop_oneof, proptest, strategy::Just};
+        use rand::{rngs::StdRng, SeedableRng};
+
+        use super::{ReportFilter, WorkloadConfig, WorkloadGenerator};
+
+        fn report_filter() -> impl proptest::strategy::Strategy<Value = ReportFilter> {
+            prop_oneof![
+                Just(ReportFilter::All),
+                Just(ReportFilter::TriggerOnly),
+                Just(ReportFilter::SourceOnly),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
+            fn uniform_within_bounds(
+                seed: u64,
+                max_trigger_value in 2..u32::MAX,
+                max_breakdown_key in 1..u32::MAX,
+                max_events_per_user in 1..100_u32,
+                report_filter in report_filter(),
+                count in 1_usize..2000,
+            ) {
+                let config = WorkloadConfig {
+                    report_filter,
+                    ..WorkloadConfig::uniform(max_trigger_value, max_breakdown_key, max_events_per_user)
+                        .with_max_match_key(10_000)
+                };
+                let records = WorkloadGenerator::new(StdRng::seed_from_u64(seed), config)
+                    .generate(count);
+
+                let mut events_per_user = HashMap::<_, u32>::new();
+                let mut last_ts = 0;
+                for record in records {
+                    let events = events_per_user.entry(record.user_id).or_default();
+                    *events += 1;
+                    assert!(*events <= max_events_per_user);
+                    assert!(record.timestamp >= last_ts);
+                    assert!(report_filter.keeps(&record));
+                    if record.is_trigger_report {
+                        assert_eq!(0, record.breakdown_key);
+                        assert!((1..max_trigger_value).contains(&record.trigger_value));
+                    } else {
+                        assert_eq!(0, record.trigger_value);
+                        assert!(record.breakdown_key < max_breakdown_key);
+                    }
+                    last_ts = record.timestamp;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_reports() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let config = WorkloadConfig {
+            epochs: NonZeroU16::new(2).unwrap(),
+            ..WorkloadConfig::default()
+        };
+        let records = WorkloadGenerator::new(StdRng::seed_from_u64(1), config).generate(20);
+        let key_registries = [(); 3].map(|()| KeyRegistry::random(1, &mut rng));
+
+        let buffers = encrypt_reports::<Fp32BitPrime, _, _>(
+            &records,
+            0,
+            [&key_registries[0], &key_registries[1], &key_registries[2]],
+            &mut rng,
+        )
+        .unwrap();
+
+        let reports = buffers
+            .iter()
+            .zip(&key_registries)
+            .map(|(buf, key_registry)| {
+                let mut buf = buf.as_slice();
+                let mut reports = Vec::new();
+                while !buf.is_empty() {
+                    let len = usize::from(u16::from_le_bytes([buf[0], buf[1]]));
+                    let report = EncryptedReport::<Fp32BitPrime, Gf40Bit, Gf8Bit, _>::from_bytes(
+                        &buf[2..2 + len],
+                    )
+                    .unwrap();
+                    reports.push(report.decrypt(key_registry).unwrap());
+                    buf = &buf[2 + len..];
+                }
+                reports
+            });
+        let [r0, r1, r2] = <[_; 3]>::try_from(reports.collect::<Vec<_>>()).unwrap();
+
+        assert_eq!(records.len(), r0.len());
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(epoch(record.timestamp), r0[i].epoch);
+            assert_eq!(
+                if record.is_trigger_report {
+                    EventType::Trigger
+                } else {
+                    EventType::Source
+                },
+                r0[i].event_type
+            );
+            let match_key = [&r0[i].mk_shares, &r1[i].mk_shares, &r2[i].mk_shares].reconstruct();
+            assert_eq!(u128::from(record.user_id), match_key.as_u128());
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, io::stdout, iter::zip};
+use std::{array, fmt::Debug, io::stdout, iter::zip};
 
 use async_trait::async_trait;
 use futures::{future::join_all, Future};
@@ -7,7 +7,10 @@ use rand_core::{RngCore, SeedableRng};
 use tracing::{Instrument, Level, Span};
 
 use crate::{
-    helpers::{Gateway, GatewayConfig, InMemoryNetwork, Role, RoleAssignment},
+    helpers::{
+        Gateway, GatewayConfig, InMemoryNetwork, NetworkFaults, Role, RoleAssignment, RoundCounter,
+        SendHook, TransportCallbacks,
+    },
     protocol::{
         context::{
             Context, MaliciousContext, SemiHonestContext, UpgradableContext, UpgradeContext,
@@ -54,6 +57,14 @@ pub struct TestWorldConfig {
     pub role_assignment: Option<RoleAssignment>,
     /// Seed for random generators used in PRSS
     pub seed: u64,
+    /// Helper that deviates from the protocol, by passing all messages it sends through the hook.
+    /// If `None`, all helpers are honest.
+    pub adversary: Option<(Role, Arc<dyn SendHook>)>,
+    /// Faults injected into the network between helpers. If `None`, messages are delivered
+    /// instantly and reliably.
+    pub network_faults: Option<NetworkFaults>,
+    /// Counter that the gateways of all helpers record the depth of their messages in.
+    pub round_counter: Option<Arc<RoundCounter>>,
 }
 
 impl Default for TestWorldConfig {
@@ -66,6 +77,9 @@ impl Default for TestWorldConfig {
             metrics_level: Level::DEBUG,
             role_assignment: None,
             seed: thread_rng().next_u64(),
+            adversary: None,
+            network_faults: None,
+            round_counter: None,
         }
     }
 }
@@ -82,6 +96,32 @@ impl TestWorldConfig {
         self.seed = seed;
         self
     }
+
+    /// Makes the helper with the given role adversarial. See [`Tamper`] for a hook that changes,
+    /// drops or reorders messages.
+    ///
+    /// [`Tamper`]: crate::test_fixture::Tamper
+    #[must_use]
+    pub fn with_adversary(mut self, role: Role, hook: Arc<dyn SendHook>) -> Self {
+        self.adversary = Some((role, hook));
+        self
+    }
+
+    /// Makes the network between helpers slow or unreliable. Helpers are assigned roles in the
+    /// order of their identities, unless [`Self::role_assignment`] says otherwise.
+    #[must_use]
+    pub fn with_network_faults(mut self, faults: NetworkFaults) -> Self {
+        self.network_faults = Some(faults);
+        self
+    }
+
+    /// Counts the communication rounds of everything that runs in this world, see
+    /// [`RoundCounter`].
+    #[must_use]
+    pub fn with_round_counter(mut self, counter: Arc<RoundCounter>) -> Self {
+        self.round_counter = Some(counter);
+        self
+    }
 }
 
 impl Default for TestWorld {
@@ -100,7 +140,13 @@ impl TestWorld {
 
         let metrics_handle = MetricsHandle::new(config.metrics_level);
         let participants = make_participants(&mut StdRng::seed_from_u64(config.seed));
-        let network = InMemoryNetwork::default();
+        let network = match config.network_faults {
+            Some(faults) => InMemoryNetwork::with_faults(
+                array::from_fn(|_| TransportCallbacks::default()),
+                faults,
+            ),
+            None => InMemoryNetwork::default(),
+        };
         let role_assignment = config
             .role_assignment
             .unwrap_or_else(|| RoleAssignment::new(network.helper_identities()));
@@ -116,6 +162,16 @@ impl TestWorld {
                 Arc::downgrade(transport),
             );
             let role = gateway.role();
+            let gateway = match &config.adversary {
+                Some((adversary, hook)) if *adversary == role => {
+                    gateway.with_send_hook(Arc::clone(hook))
+                }
+                _ => gateway,
+            };
+            let gateway = match &config.round_counter {
+                Some(counter) => gateway.with_round_counter(Arc::clone(counter)),
+                None => gateway,
+            };
             gateways[role] = Some(gateway);
         }
         let gateways = gateways.map(Option::unwrap);